@@ -0,0 +1,53 @@
+//! Test helper that relays its instruction to another program through CPI, so
+//! tests can exercise the insurance program's guards against composed calls.
+//!
+//! The first account is the target program. The remaining accounts and the
+//! instruction data are forwarded unchanged.
+
+use solana_program::{
+    account_info::AccountInfo,
+    entrypoint::ProgramResult,
+    instruction::{AccountMeta, Instruction},
+    program::invoke,
+    program_error::ProgramError,
+    pubkey::Pubkey,
+};
+
+solana_program::declare_id!("CpiProbe11111111111111111111111111111111111");
+
+#[cfg(not(feature = "no-entrypoint"))]
+solana_program::entrypoint!(process_instruction);
+
+pub fn process_instruction(
+    _program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    data: &[u8],
+) -> ProgramResult {
+    let (target, forwarded) = accounts
+        .split_first()
+        .ok_or(ProgramError::NotEnoughAccountKeys)?;
+    let instruction = Instruction {
+        program_id: *target.key,
+        accounts: forwarded
+            .iter()
+            .map(|account| AccountMeta {
+                pubkey: *account.key,
+                is_signer: account.is_signer,
+                is_writable: account.is_writable,
+            })
+            .collect(),
+        data: data.to_vec(),
+    };
+    invoke(&instruction, accounts)
+}
+
+/// Wrap `inner` so it reaches its program through the probe
+pub fn relay(inner: Instruction) -> Instruction {
+    let mut accounts = vec![AccountMeta::new_readonly(inner.program_id, false)];
+    accounts.extend(inner.accounts);
+    Instruction {
+        program_id: ID,
+        accounts,
+        data: inner.data,
+    }
+}
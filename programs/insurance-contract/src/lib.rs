@@ -4,59 +4,486 @@
 //! pay out based on predefined conditions and oracle data feeds.
 
 use anchor_lang::prelude::*;
-use anchor_spl::token::{self, Token, TokenAccount, Transfer};
-use pyth_sdk_solana::load_price_feed_from_account_info;
+use anchor_lang::solana_program::bpf_loader_upgradeable;
+use anchor_lang::solana_program::compute_units::sol_remaining_compute_units;
+use anchor_lang::solana_program::ed25519_program;
+use anchor_lang::solana_program::hash::{hash, hashv};
+use anchor_lang::solana_program::instruction::{
+    get_stack_height, Instruction, TRANSACTION_LEVEL_STACK_HEIGHT,
+};
+use anchor_lang::solana_program::program::invoke;
+use anchor_lang::solana_program::sysvar::instructions as sysvar_instructions;
+use anchor_lang::system_program;
+use anchor_spl::associated_token::{self, get_associated_token_address, AssociatedToken};
+use anchor_spl::token::{self, Mint, Token, TokenAccount, Transfer};
+use pyth_sdk_solana::{load_price_feed_from_account_info, Price};
+
+pub mod math;
+pub mod state;
+
+pub use math::{apply_bps, RoundingMode, BPS_DENOMINATOR};
+
+use state::{transition, PolicyEvent, TransitionRecord, TRANSITION_HISTORY_LEN};
 
 declare_id!("InsuranceContract1111111111111111111111111111");
 
+/// `msg!` for diagnostics only worth their compute while debugging. Compiled
+/// out unless the `debug-logs` feature is enabled; data clients rely on goes
+/// out through events instead.
+macro_rules! debug_msg {
+    ($($arg:tt)*) => {
+        if cfg!(feature = "debug-logs") {
+            msg!($($arg)*);
+        }
+    };
+}
+
 #[program]
 pub mod insurance_contract {
     use super::*;
 
     /// Initialize a new insurance contract
-    pub fn initialize(
+    pub fn initialize(ctx: Context<Initialize>, bump: u8, params: InitializeParams) -> Result<()> {
+        let insurance_policy = &mut ctx.accounts.insurance_policy;
+        let clock = Clock::get()?;
+
+        ctx.accounts.config.check_policy_duration(params.expiry_timestamp, clock.unix_timestamp)?;
+        enforce_mint_limits(
+            &ctx.accounts.premium_mint_limits,
+            &ctx.accounts.payout_mint_limits,
+            &params,
+        )?;
+        insurance_policy.initialize_numbered(
+            &mut ctx.accounts.config,
+            ctx.accounts.authority.key(),
+            ctx.accounts.policy_holder.key(),
+            bump,
+            &params,
+            clock.unix_timestamp,
+        )?;
+        escrow_on_policy(
+            ctx.accounts.authority.to_account_info(),
+            insurance_policy.to_account_info(),
+            ctx.accounts.system_program.to_account_info(),
+            params.keeper_reward_lamports,
+        )?;
+
+        emit!(insurance_policy.initialized_event(insurance_policy.key()));
+        msg!("Insurance policy initialized: {}", insurance_policy.key());
+        Ok(())
+    }
+
+    /// Initialize a policy covering a group of members, each entitled to a
+    /// weighted slice of the payout. `merkle_root` commits to the members as
+    /// `group_leaf(index, member, weight)` leaves; members claim with a proof
+    /// through `claim_group_payout` once the policy triggers.
+    pub fn initialize_group(
+        ctx: Context<InitializeGroup>,
+        bump: u8,
+        params: InitializeParams,
+        merkle_root: [u8; 32],
+        total_weight: u64,
+        member_count: u32,
+    ) -> Result<()> {
+        let insurance_policy = &mut ctx.accounts.insurance_policy;
+        let clock = Clock::get()?;
+
+        // Members are paid their slice directly, so per-holder payout features do not apply
+        require!(
+            total_weight > 0
+                && (1..=MAX_GROUP_MEMBERS).contains(&member_count)
+                && params.payout_vesting_seconds == 0
+                && params.retention_amount.is_none()
+                && params.payout_peg_oracle_address.is_none(),
+            InsuranceError::InvalidPolicyParameters
+        );
+        ctx.accounts.config.check_policy_duration(params.expiry_timestamp, clock.unix_timestamp)?;
+        enforce_mint_limits(
+            &ctx.accounts.premium_mint_limits,
+            &ctx.accounts.payout_mint_limits,
+            &params,
+        )?;
+        insurance_policy.initialize_numbered(
+            &mut ctx.accounts.config,
+            ctx.accounts.authority.key(),
+            ctx.accounts.policy_holder.key(),
+            bump,
+            &params,
+            clock.unix_timestamp,
+        )?;
+        insurance_policy.group_merkle_root = Some(merkle_root);
+        insurance_policy.group_total_weight = total_weight;
+        escrow_on_policy(
+            ctx.accounts.authority.to_account_info(),
+            insurance_policy.to_account_info(),
+            ctx.accounts.system_program.to_account_info(),
+            params.keeper_reward_lamports,
+        )?;
+
+        let group_claims = &mut ctx.accounts.group_claims;
+        group_claims.policy = insurance_policy.key();
+        group_claims.member_count = member_count;
+        group_claims.bitmap = vec![0; GroupClaims::bitmap_len(member_count)];
+        group_claims.bump = ctx.bumps.group_claims;
+
+        emit!(insurance_policy.initialized_event(insurance_policy.key()));
+        msg!(
+            "Group policy for {} members initialized: {}",
+            member_count,
+            insurance_policy.key()
+        );
+        Ok(())
+    }
+
+    /// Initialize a stablecoin depeg policy from a peg price and tolerance rather
+    /// than a raw threshold; pays if the price stays below the tolerance band
+    /// for `min_duration_seconds`
+    pub fn initialize_depeg_policy(
         ctx: Context<Initialize>,
         bump: u8,
-        oracle_address: Pubkey,
-        trigger_threshold: i64,
-        coverage_amount: u64,
-        premium_amount: u64,
-        expiry_timestamp: i64,
+        peg_price: i64,
+        peg_exponent: i32,
+        depeg_bps: u16,
+        min_duration_seconds: i64,
+        params: InitializeParams,
     ) -> Result<()> {
         let insurance_policy = &mut ctx.accounts.insurance_policy;
         let clock = Clock::get()?;
 
-        insurance_policy.authority = ctx.accounts.authority.key();
-        insurance_policy.policy_holder = ctx.accounts.policy_holder.key();
-        insurance_policy.oracle_address = oracle_address;
-        insurance_policy.trigger_threshold = trigger_threshold;
-        insurance_policy.coverage_amount = coverage_amount;
-        insurance_policy.premium_amount = premium_amount;
-        insurance_policy.expiry_timestamp = expiry_timestamp;
-        insurance_policy.created_timestamp = clock.unix_timestamp;
-        insurance_policy.status = PolicyStatus::Active;
-        insurance_policy.bump = bump;
+        let params = params.into_depeg(peg_price, peg_exponent, depeg_bps, min_duration_seconds)?;
+        ctx.accounts.config.check_policy_duration(params.expiry_timestamp, clock.unix_timestamp)?;
+        enforce_mint_limits(
+            &ctx.accounts.premium_mint_limits,
+            &ctx.accounts.payout_mint_limits,
+            &params,
+        )?;
+        insurance_policy.initialize_numbered(
+            &mut ctx.accounts.config,
+            ctx.accounts.authority.key(),
+            ctx.accounts.policy_holder.key(),
+            bump,
+            &params,
+            clock.unix_timestamp,
+        )?;
+        insurance_policy.product_kind = ProductKind::Depeg;
+        escrow_on_policy(
+            ctx.accounts.authority.to_account_info(),
+            insurance_policy.to_account_info(),
+            ctx.accounts.system_program.to_account_info(),
+            params.keeper_reward_lamports,
+        )?;
 
-        msg!("Insurance policy initialized: {}", insurance_policy.key());
+        emit!(insurance_policy.initialized_event(insurance_policy.key()));
+        msg!("Depeg policy initialized: {}", insurance_policy.key());
+        Ok(())
+    }
+
+    /// Initialize one policy per entry from a shared template in a single
+    /// transaction. Policy PDAs are passed as remaining accounts in entry order.
+    /// Every entry is validated before the first account is created, and any
+    /// later failure reverts the whole transaction, so a batch lands all or nothing.
+    pub fn initialize_batch<'info>(
+        ctx: Context<'_, '_, '_, 'info, InitializeBatch<'info>>,
+        count: u8,
+        template: InitializeParams,
+        entries: Vec<BatchPolicyEntry>,
+    ) -> Result<()> {
+        require!(
+            count as usize == entries.len() && ctx.remaining_accounts.len() == entries.len(),
+            InsuranceError::InvalidBatch
+        );
+        let authority = ctx.accounts.authority.key();
+        let clock = Clock::get()?;
+        ctx.accounts.config.check_policy_duration(template.expiry_timestamp, clock.unix_timestamp)?;
+        enforce_batch_mint_limits(
+            &ctx.accounts.premium_mint_limits,
+            &ctx.accounts.payout_mint_limits,
+            &template,
+            &entries,
+        )?;
+        let policies = build_policy_batch(&authority, &template, &entries, clock.unix_timestamp, ctx.program_id)?;
+
+        let created = policies.len();
+        for ((address, mut policy), account) in policies.into_iter().zip(ctx.remaining_accounts) {
+            require_keys_eq!(account.key(), address, InsuranceError::InvalidBatch);
+            require!(account.data_is_empty(), InsuranceError::InvalidBatch);
+            let seeds = &[
+                b"insurance_policy".as_ref(),
+                authority.as_ref(),
+                policy.policy_holder.as_ref(),
+                &[policy.bump],
+            ];
+            create_policy_account(
+                ctx.accounts.authority.to_account_info(),
+                account.clone(),
+                ctx.accounts.system_program.to_account_info(),
+                &seeds[..],
+            )?;
+            ctx.accounts.config.assign_policy_number(&mut policy)?;
+            policy.try_serialize(&mut &mut account.try_borrow_mut_data()?[..])?;
+            escrow_on_policy(
+                ctx.accounts.authority.to_account_info(),
+                account.clone(),
+                ctx.accounts.system_program.to_account_info(),
+                template.keeper_reward_lamports,
+            )?;
+            emit!(policy.initialized_event(address));
+            msg!("Insurance policy initialized: {}", address);
+        }
+
+        msg!("Initialized {} policies in batch", created);
         Ok(())
     }
 
-    /// Purchase insurance policy by paying premium
+    /// Purchase insurance policy by paying premium. A lender financing the
+    /// premium passes itself as `premium_payer` with its token account; it
+    /// must sign, and cancellation refunds go back to it.
     pub fn purchase_policy(ctx: Context<PurchasePolicy>) -> Result<()> {
+        let clock = Clock::get()?;
+        ctx.accounts.config.require_not_paused()?;
+        ctx.accounts.config.require_direct_invocation(&ctx.accounts.instructions_sysvar)?;
+        let loyalty_discount_bps = ctx
+            .accounts
+            .config
+            .loyalty_discount_bps(ctx.accounts.holder_stats.claim_free_policies);
+
+        // A lender financing the premium co-signs and pays from its own account
+        let (funding_account, funding_authority) = match (
+            ctx.accounts.premium_payer.as_ref(),
+            ctx.accounts.premium_payer_token_account.as_ref(),
+        ) {
+            (Some(payer), Some(account)) => {
+                require_keys_eq!(
+                    account.owner,
+                    payer.key(),
+                    InsuranceError::WrongTokenAccountOwner
+                );
+                (account, payer.to_account_info())
+            }
+            (None, None) => (
+                &ctx.accounts.policy_holder_token_account,
+                ctx.accounts.policy_holder.to_account_info(),
+            ),
+            _ => return err!(InsuranceError::WrongTokenAccountOwner),
+        };
+
+        complete_purchase(
+            &mut ctx.accounts.insurance_policy,
+            &mut ctx.accounts.pool_state,
+            &ctx.accounts.config,
+            &ctx.accounts.oracle_account,
+            ctx.accounts.denominator_oracle_account.as_ref(),
+            funding_account,
+            funding_authority,
+            &ctx.accounts.insurance_pool_token_account,
+            ctx.accounts.fee_recipient_token_account.as_ref(),
+            ctx.accounts.referrer_token_account.as_ref(),
+            ctx.accounts.premium_conversion_oracle_account.as_ref(),
+            &ctx.accounts.token_program,
+            loyalty_discount_bps,
+            clock.unix_timestamp,
+        )?;
+        if let Some(payer) = ctx.accounts.premium_payer.as_ref() {
+            ctx.accounts.insurance_policy.record_premium_payer(payer.key());
+        }
+
+        let premium_paid = ctx.accounts.insurance_policy.installment_amount(0);
+        let holder_stats = &mut ctx.accounts.holder_stats;
+        holder_stats.holder = ctx.accounts.policy_holder.key();
+        holder_stats.bump = ctx.bumps.holder_stats;
+        holder_stats.record_purchase(premium_paid);
+
+        msg!("Policy purchased: {}", ctx.accounts.insurance_policy.key());
+        Ok(())
+    }
+
+    /// Purchase a policy on the holder's behalf, funded by a separate payer.
+    /// The holder must co-sign unless the policy allows third-party purchase.
+    /// Cancellation refunds go back to the payer.
+    pub fn purchase_policy_for(ctx: Context<PurchasePolicyFor>) -> Result<()> {
+        let clock = Clock::get()?;
+        ctx.accounts.config.require_not_paused()?;
+        ctx.accounts.config.require_direct_invocation(&ctx.accounts.instructions_sysvar)?;
+        require!(
+            ctx.accounts.policy_holder.is_signer
+                || ctx.accounts.insurance_policy.allow_third_party_purchase,
+            InsuranceError::Unauthorized
+        );
+
+        complete_purchase(
+            &mut ctx.accounts.insurance_policy,
+            &mut ctx.accounts.pool_state,
+            &ctx.accounts.config,
+            &ctx.accounts.oracle_account,
+            ctx.accounts.denominator_oracle_account.as_ref(),
+            &ctx.accounts.payer_token_account,
+            ctx.accounts.payer.to_account_info(),
+            &ctx.accounts.insurance_pool_token_account,
+            ctx.accounts.fee_recipient_token_account.as_ref(),
+            None,
+            ctx.accounts.premium_conversion_oracle_account.as_ref(),
+            &ctx.accounts.token_program,
+            0,
+            clock.unix_timestamp,
+        )?;
+        ctx.accounts.insurance_policy.record_premium_payer(ctx.accounts.payer.key());
+
+        msg!(
+            "Policy {} purchased for {} by {}",
+            ctx.accounts.insurance_policy.key(),
+            ctx.accounts.policy_holder.key(),
+            ctx.accounts.payer.key()
+        );
+        Ok(())
+    }
+
+    /// Purchase a wSOL-denominated policy with native SOL. The first installment
+    /// is wrapped into a temporary wSOL account which is closed again before
+    /// returning, so its rent deposit goes straight back to the holder.
+    pub fn purchase_policy_with_sol(ctx: Context<PurchasePolicyWithSol>) -> Result<()> {
+        let clock = Clock::get()?;
+        ctx.accounts.config.require_not_paused()?;
+        ctx.accounts.config.require_direct_invocation(&ctx.accounts.instructions_sysvar)?;
+
+        let amount = ctx.accounts.insurance_policy.installment_amount(0);
+        let cpi_accounts = system_program::Transfer {
+            from: ctx.accounts.policy_holder.to_account_info(),
+            to: ctx.accounts.wrapped_sol_account.to_account_info(),
+        };
+        let cpi_program = ctx.accounts.system_program.to_account_info();
+        system_program::transfer(CpiContext::new(cpi_program, cpi_accounts), amount)?;
+        let cpi_accounts = token::SyncNative {
+            account: ctx.accounts.wrapped_sol_account.to_account_info(),
+        };
+        token::sync_native(CpiContext::new(ctx.accounts.token_program.to_account_info(), cpi_accounts))?;
+
+        complete_purchase(
+            &mut ctx.accounts.insurance_policy,
+            &mut ctx.accounts.pool_state,
+            &ctx.accounts.config,
+            &ctx.accounts.oracle_account,
+            ctx.accounts.denominator_oracle_account.as_ref(),
+            &ctx.accounts.wrapped_sol_account,
+            ctx.accounts.policy_holder.to_account_info(),
+            &ctx.accounts.insurance_pool_token_account,
+            ctx.accounts.fee_recipient_token_account.as_ref(),
+            None,
+            // The amount was wrapped before conversion, so converted premiums can't be paid in SOL
+            None,
+            &ctx.accounts.token_program,
+            0,
+            clock.unix_timestamp,
+        )?;
+
+        // The wrapped balance is fully spent; closing returns only the rent deposit
+        let cpi_accounts = token::CloseAccount {
+            account: ctx.accounts.wrapped_sol_account.to_account_info(),
+            destination: ctx.accounts.policy_holder.to_account_info(),
+            authority: ctx.accounts.policy_holder.to_account_info(),
+        };
+        token::close_account(CpiContext::new(ctx.accounts.token_program.to_account_info(), cpi_accounts))?;
+
+        msg!("Policy purchased with SOL: {}", ctx.accounts.insurance_policy.key());
+        Ok(())
+    }
+
+    /// Renew an auto-renewing policy that reached expiry untriggered, pulling a
+    /// fresh premium through the pool's delegation on the holder's account.
+    /// Callable by anyone; a holder who cannot pay lapses instead.
+    pub fn process_renewal(ctx: Context<ProcessRenewal>) -> Result<()> {
+        let insurance_policy = &mut ctx.accounts.insurance_policy;
+        let clock = Clock::get()?;
+
+        let holder_account = &ctx.accounts.policy_holder_token_account;
+        let renewal = insurance_policy.renewal_premium(
+            clock.unix_timestamp,
+            holder_account.delegate.into(),
+            holder_account.delegated_amount,
+            holder_account.amount,
+            &ctx.accounts.pool_state.key(),
+        )?;
+        let Some(amount) = renewal else {
+            lapse_policy_account(
+                insurance_policy,
+                &mut ctx.accounts.pool_state,
+                &ctx.accounts.authority,
+                ctx.accounts.caller.key(),
+                clock.unix_timestamp,
+            )?;
+            return Ok(());
+        };
+
+        let pool_state = &mut ctx.accounts.pool_state;
+        let seeds = &[
+            b"pool".as_ref(),
+            pool_state.mint.as_ref(),
+            &[pool_state.bump],
+        ];
+        let signer = &[&seeds[..]];
+
+        // The pool PDA signs as the delegate of the holder's account
+        let cpi_accounts = Transfer {
+            from: ctx.accounts.policy_holder_token_account.to_account_info(),
+            to: ctx.accounts.insurance_pool_token_account.to_account_info(),
+            authority: pool_state.to_account_info(),
+        };
+        let cpi_program = ctx.accounts.token_program.to_account_info();
+        let cpi_ctx = CpiContext::new_with_signer(cpi_program, cpi_accounts, signer);
+
+        token::transfer(cpi_ctx, amount)?;
+
+        pool_state.total_capital = pool_state
+            .total_capital
+            .checked_add(amount)
+            .ok_or(InsuranceError::MathOverflow)?;
+        insurance_policy.renew(clock.unix_timestamp, ctx.accounts.caller.key())?;
+
+        msg!(
+            "Policy {} renewed until {}",
+            insurance_policy.key(),
+            insurance_policy.expiry_timestamp
+        );
+        Ok(())
+    }
+
+    /// Pay the next premium installment of an installment plan
+    pub fn pay_installment(ctx: Context<PayInstallment>) -> Result<()> {
         let insurance_policy = &mut ctx.accounts.insurance_policy;
         let clock = Clock::get()?;
 
-        // Check if policy is still active and not expired
+        insurance_policy.require_unexpired(clock.unix_timestamp)?;
         require!(
-            insurance_policy.status == PolicyStatus::Active,
-            InsuranceError::PolicyNotActive
+            insurance_policy.status == PolicyStatus::Purchased,
+            InsuranceError::PolicyNotPurchased
         );
         require!(
-            clock.unix_timestamp < insurance_policy.expiry_timestamp,
-            InsuranceError::PolicyExpired
+            insurance_policy.installments_paid < insurance_policy.installment_count,
+            InsuranceError::NoInstallmentDue
         );
 
-        // Transfer premium from policy holder to insurance pool
+        // A payment arriving after the grace period cannot revive the policy
+        if insurance_policy.is_installment_lapsed(clock.unix_timestamp) {
+            lapse_policy_account(
+                insurance_policy,
+                &mut ctx.accounts.pool_state,
+                &ctx.accounts.authority,
+                ctx.accounts.policy_holder.key(),
+                clock.unix_timestamp,
+            )?;
+            return Ok(());
+        }
+
+        let amount = insurance_policy.installment_amount(insurance_policy.installments_paid);
+        let protocol_fee = transfer_protocol_fee(
+            &ctx.accounts.config,
+            amount,
+            &ctx.accounts.policy_holder_token_account,
+            ctx.accounts.policy_holder.to_account_info(),
+            ctx.accounts.fee_recipient_token_account.as_ref(),
+            &ctx.accounts.token_program,
+            &[],
+        )?;
+        let pool_share = amount - protocol_fee;
         let cpi_accounts = Transfer {
             from: ctx.accounts.policy_holder_token_account.to_account_info(),
             to: ctx.accounts.insurance_pool_token_account.to_account_info(),
@@ -64,380 +491,15365 @@ pub mod insurance_contract {
         };
         let cpi_program = ctx.accounts.token_program.to_account_info();
         let cpi_ctx = CpiContext::new(cpi_program, cpi_accounts);
-        
-        token::transfer(cpi_ctx, insurance_policy.premium_amount)?;
 
-        insurance_policy.status = PolicyStatus::Purchased;
-        insurance_policy.purchased_timestamp = Some(clock.unix_timestamp);
+        token::transfer(cpi_ctx, pool_share)?;
+
+        let pool_state = &mut ctx.accounts.pool_state;
+        pool_state.total_capital = pool_state
+            .total_capital
+            .checked_add(pool_share)
+            .ok_or(InsuranceError::MathOverflow)?;
+        insurance_policy.record_installment_paid(protocol_fee);
+
+        msg!(
+            "Installment {}/{} paid for policy: {}",
+            insurance_policy.installments_paid,
+            insurance_policy.installment_count,
+            insurance_policy.key()
+        );
+        Ok(())
+    }
+
+    /// Pull the due installment from the holder's token account using the
+    /// delegation they granted the pool PDA with SPL `approve`. Callable by the
+    /// authority or any keeper, but never before the installment is due.
+    pub fn collect_premium(ctx: Context<CollectPremium>) -> Result<()> {
+        let insurance_policy = &mut ctx.accounts.insurance_policy;
+        let clock = Clock::get()?;
+
+        insurance_policy.require_unexpired(clock.unix_timestamp)?;
+
+        // A missed installment past the grace period lapses the policy as usual
+        if insurance_policy.is_installment_lapsed(clock.unix_timestamp) {
+            lapse_policy_account(
+                insurance_policy,
+                &mut ctx.accounts.pool_state,
+                &ctx.accounts.authority,
+                ctx.accounts.caller.key(),
+                clock.unix_timestamp,
+            )?;
+            return Ok(());
+        }
+
+        let holder_account = &ctx.accounts.policy_holder_token_account;
+        let amount = insurance_policy.collectible_installment(
+            clock.unix_timestamp,
+            holder_account.delegate.into(),
+            holder_account.delegated_amount,
+            &ctx.accounts.pool_state.key(),
+        )?;
+
+        let pool_state = &mut ctx.accounts.pool_state;
+        let seeds = &[
+            b"pool".as_ref(),
+            pool_state.mint.as_ref(),
+            &[pool_state.bump],
+        ];
+        let signer = &[&seeds[..]];
+
+        // The pool PDA signs as the delegate of the holder's account, for the
+        // protocol fee and the pool's share alike
+        let protocol_fee = transfer_protocol_fee(
+            &ctx.accounts.config,
+            amount,
+            &ctx.accounts.policy_holder_token_account,
+            pool_state.to_account_info(),
+            ctx.accounts.fee_recipient_token_account.as_ref(),
+            &ctx.accounts.token_program,
+            signer,
+        )?;
+        let pool_share = amount - protocol_fee;
+        let cpi_accounts = Transfer {
+            from: ctx.accounts.policy_holder_token_account.to_account_info(),
+            to: ctx.accounts.insurance_pool_token_account.to_account_info(),
+            authority: pool_state.to_account_info(),
+        };
+        let cpi_program = ctx.accounts.token_program.to_account_info();
+        let cpi_ctx = CpiContext::new_with_signer(cpi_program, cpi_accounts, signer);
+
+        token::transfer(cpi_ctx, pool_share)?;
 
-        msg!("Policy purchased: {}", insurance_policy.key());
+        pool_state.total_capital = pool_state
+            .total_capital
+            .checked_add(pool_share)
+            .ok_or(InsuranceError::MathOverflow)?;
+        insurance_policy.record_installment_paid(protocol_fee);
+
+        msg!(
+            "Installment {}/{} collected for policy: {}",
+            insurance_policy.installments_paid,
+            insurance_policy.installment_count,
+            insurance_policy.key()
+        );
         Ok(())
     }
 
+    /// Lapse a policy whose installment is overdue beyond its grace period,
+    /// releasing its coverage reservation. Callable by anyone.
+    pub fn lapse_policy(ctx: Context<LapsePolicy>) -> Result<()> {
+        let insurance_policy = &mut ctx.accounts.insurance_policy;
+        let clock = Clock::get()?;
+
+        require!(
+            insurance_policy.is_installment_lapsed(clock.unix_timestamp),
+            InsuranceError::InstallmentNotOverdue
+        );
+        lapse_policy_account(
+            insurance_policy,
+            &mut ctx.accounts.pool_state,
+            &ctx.accounts.authority,
+            ctx.accounts.caller.key(),
+            clock.unix_timestamp,
+        )
+    }
+
     /// Check oracle conditions and trigger payout if conditions are met
     pub fn check_trigger_conditions(ctx: Context<CheckTriggerConditions>) -> Result<()> {
+        ctx.accounts.config.require_not_paused()?;
+        ctx.accounts.config.require_direct_invocation(&ctx.accounts.instructions_sysvar)?;
         let insurance_policy = &mut ctx.accounts.insurance_policy;
         let clock = Clock::get()?;
 
-        // Check if policy is purchased and not expired
+        // Keepers may race; only the first trigger is recorded and emitted
+        if insurance_policy.status == PolicyStatus::TriggeredPayout {
+            debug_msg!("Payout already triggered for policy: {}", insurance_policy.key());
+            return Ok(());
+        }
+
+        // A policy past expiry is closed out and the check succeeds
+        if close_if_expired(insurance_policy, &mut ctx.accounts.pool_state, clock.unix_timestamp) {
+            return Ok(());
+        }
+        insurance_policy.require_unexpired(clock.unix_timestamp)?;
         require!(
             insurance_policy.status == PolicyStatus::Purchased,
             InsuranceError::PolicyNotPurchased
         );
-        require!(
-            clock.unix_timestamp < insurance_policy.expiry_timestamp,
-            InsuranceError::PolicyExpired
+        insurance_policy.check_evaluation_due(clock.unix_timestamp)?;
+        insurance_policy.check_interval_elapsed(clock.unix_timestamp)?;
+
+        // Coverage ends once an installment is overdue beyond the grace period
+        if insurance_policy.is_installment_lapsed(clock.unix_timestamp) {
+            lapse_policy_account(
+                insurance_policy,
+                &mut ctx.accounts.pool_state,
+                &ctx.accounts.authority.to_account_info(),
+                ctx.accounts.authority.key(),
+                clock.unix_timestamp,
+            )?;
+            return Ok(());
+        }
+
+        // Load oracle price data, falling back to the secondary feed if the primary is unusable
+        let primary = load_oracle_price(
+            &insurance_policy.oracle_kind,
+            &ctx.accounts.oracle_account,
+            clock.unix_timestamp,
+            insurance_policy.max_price_age_seconds,
         );
+        let fallback = match (
+            insurance_policy.fallback_oracle_address,
+            ctx.accounts.fallback_oracle_account.as_ref(),
+        ) {
+            (Some(expected), Some(account)) => {
+                require_keys_eq!(account.key(), expected, InsuranceError::OracleMismatch);
+                Some(load_oracle_price(
+                    &insurance_policy.oracle_kind,
+                    account,
+                    clock.unix_timestamp,
+                    insurance_policy.max_price_age_seconds,
+                ))
+            }
+            // A configured fallback must be passed, so a stale primary cannot stand alone
+            (Some(_), None) | (None, Some(_)) => return err!(InsuranceError::OracleMismatch),
+            (None, None) => None,
+        };
+        // Backups are passed as remaining accounts in the configured order
+        let mut backups = Vec::new();
+        for (index, expected) in insurance_policy.configured_backup_oracles().enumerate() {
+            let account = ctx
+                .remaining_accounts
+                .get(index)
+                .ok_or(InsuranceError::OracleMismatch)?;
+            require_keys_eq!(account.key(), expected, InsuranceError::OracleMismatch);
+            backups.push(load_oracle_price(
+                &insurance_policy.oracle_kind,
+                account,
+                clock.unix_timestamp,
+                insurance_policy.max_price_age_seconds,
+            ));
+        }
+        // A check that finds no usable feed still succeeds, so why it stopped is
+        // kept on the policy as `last_block_reason` for clients to read
+        let Some((current_price, source)) =
+            insurance_policy.note_block(select_oracle_price(primary, fallback, backups))?
+        else {
+            return Ok(());
+        };
+        insurance_policy.last_oracle_source = source;
+        // A flash-crash tick ends the check instead of being evaluated
+        let reading = insurance_policy
+            .apply_cross_rate(
+                current_price,
+                ctx.accounts.denominator_oracle_account.as_ref(),
+                clock.unix_timestamp,
+            )
+            .and_then(|price| {
+                let observed = insurance_policy.check_price_band(&price)?;
+                insurance_policy.check_published_after_purchase(&price)?;
+                Ok((price, observed))
+            });
+        // The reading at the policy's exponent is reused by the evaluation below
+        let Some((current_price, observed_price)) = insurance_policy.note_block(reading)? else {
+            return Ok(());
+        };
+        // Only a check that reached a usable price counts against the check interval
+        insurance_policy.record_check(clock.unix_timestamp)?;
+
+        // Volatility is measured over recorded history when the policy keeps one,
+        // and from the reading's confidence interval otherwise
+        let volatility_bps = match ctx.accounts.price_history.as_ref() {
+            Some(history) => {
+                require!(
+                    insurance_policy.price_history == Some(history.key()),
+                    InsuranceError::InvalidPolicyParameters
+                );
+                // Compute budget consumed by the calculation, for benchmarking buffer sizes
+                debug_msg!("Compute units before volatility: {}", sol_remaining_compute_units());
+                let volatility = history.realized_volatility_bps(
+                    insurance_policy.volatility_window_seconds,
+                    insurance_policy.min_samples as usize,
+                )?;
+                debug_msg!("Compute units after volatility: {}", sol_remaining_compute_units());
+                Some(volatility)
+            }
+            None if insurance_policy.price_history.is_none() => {
+                Some(confidence_bps(&current_price)?)
+            }
+            None => None,
+        };
 
-        // Load oracle price data
-        let oracle_account_info = &ctx.accounts.oracle_account;
-        let price_feed = load_price_feed_from_account_info(oracle_account_info)?;
-        let current_price = price_feed.get_current_price().unwrap();
+        // Spread conditions compare against a second feed, loaded and staleness-checked on its own
+        let reference_price = match insurance_policy.spread_oracle_address {
+            Some(expected) => {
+                let account = ctx
+                    .accounts
+                    .spread_oracle_account
+                    .as_ref()
+                    .ok_or(InsuranceError::OracleMismatch)?;
+                require_keys_eq!(account.key(), expected, InsuranceError::OracleMismatch);
+                Some(load_spread_reference(
+                    &insurance_policy.spread_oracle_kind,
+                    account,
+                    insurance_policy.price_expo,
+                    clock.unix_timestamp,
+                    insurance_policy.max_price_age_seconds,
+                )?)
+            }
+            None => None,
+        };
+
+        // Quorum feeds follow the backups in the remaining accounts; unusable feeds do not agree
+        if insurance_policy.required_agreements > 0 {
+            let offset = insurance_policy.configured_backup_oracles().count();
+            let mut readings = Vec::new();
+            for (index, expected) in insurance_policy.configured_quorum_oracles().enumerate() {
+                let account = ctx
+                    .remaining_accounts
+                    .get(offset + index)
+                    .ok_or(InsuranceError::OracleMismatch)?;
+                require_keys_eq!(account.key(), expected, InsuranceError::OracleMismatch);
+                if let Ok(reading) = load_oracle_price(
+                    &insurance_policy.oracle_kind,
+                    account,
+                    clock.unix_timestamp,
+                    insurance_policy.max_price_age_seconds,
+                ) {
+                    readings.push(reading);
+                }
+            }
+            insurance_policy.last_quorum_agreements =
+                insurance_policy.count_agreements(&readings, volatility_bps, reference_price)?;
+            debug_msg!(
+                "Quorum agreements: {}/{}",
+                insurance_policy.last_quorum_agreements,
+                insurance_policy.required_agreements
+            );
+        }
+
+        // Categorical conditions are read from their own boolean feed
+        if let Some(expected) = insurance_policy.condition_oracle {
+            let account = ctx
+                .accounts
+                .condition_oracle_account
+                .as_ref()
+                .ok_or(InsuranceError::OracleMismatch)?;
+            require_keys_eq!(account.key(), expected, InsuranceError::OracleMismatch);
+            insurance_policy.last_condition_flag = load_condition_flag(
+                account,
+                clock.unix_timestamp,
+                insurance_policy.max_price_age_seconds,
+            )?;
+            debug_msg!("Condition feed: {}", insurance_policy.last_condition_flag);
+        }
 
-        msg!("Current oracle price: {}", current_price.price);
-        msg!("Trigger threshold: {}", insurance_policy.trigger_threshold);
+        debug_msg!("Current oracle price: {}", current_price.price);
+        debug_msg!("Trigger threshold: {}", insurance_policy.trigger_threshold);
 
         // Check if trigger conditions are met
-        let trigger_met = match insurance_policy.trigger_condition_type() {
-            TriggerConditionType::PriceAbove => current_price.price > insurance_policy.trigger_threshold,
-            TriggerConditionType::PriceBelow => current_price.price < insurance_policy.trigger_threshold,
-            TriggerConditionType::VolatilityAbove => {
-                // Simplified volatility check - in production, would use historical data
-                let price_confidence = current_price.conf as i64;
-                (price_confidence * 100 / current_price.price) > insurance_policy.trigger_threshold
+        let policy_key = insurance_policy.key();
+        if let Some(evaluation) = insurance_policy.record_normalized_observation(
+            policy_key,
+            &current_price,
+            observed_price,
+            volatility_bps,
+            reference_price,
+            clock.unix_timestamp,
+        )? {
+            if evaluation.triggered {
+                ctx.accounts.pool_state.add_obligation(insurance_policy.coverage_amount);
+                debug_msg!("Trigger conditions met! Payout triggered for policy: {}", policy_key);
+
+                // The keeper stakes a deposit on the trigger holding up
+                let config = &ctx.accounts.config;
+                escrow_on_policy(
+                    ctx.accounts.authority.to_account_info(),
+                    insurance_policy.to_account_info(),
+                    ctx.accounts.system_program.to_account_info(),
+                    config.keeper_deposit_lamports,
+                )?;
+                insurance_policy.escrow_keeper_deposit(
+                    config.keeper_deposit_lamports,
+                    clock.unix_timestamp,
+                    config.keeper_deposit_window_seconds,
+                );
+            } else {
+                insurance_policy.schedule_recheck(clock.unix_timestamp);
+                debug_msg!("Trigger conditions not met for policy: {}", policy_key);
             }
+            emit!(evaluation);
+        }
+
+        // Only a check that cleared the interval gate gets here, so racing keepers
+        // cannot collect the per-check reward twice. Pools that require keeper
+        // stake only pay keepers holding an active registration.
+        let eligible = KeeperRegistration::eligible_for_fee(
+            ctx.accounts.keeper_registration.as_deref(),
+            ctx.accounts.pool_state.keeper_stake_required,
+        );
+        let reward = if eligible { insurance_policy.take_check_reward() } else { 0 };
+        if reward > 0 {
+            let policy_info = insurance_policy.to_account_info();
+            **policy_info.try_borrow_mut_lamports()? -= reward;
+            **ctx.accounts.authority.try_borrow_mut_lamports()? += reward;
+            debug_msg!("Keeper reward paid: {}", reward);
+        }
+
+        Ok(())
+    }
+
+    /// Evaluate a reporter-signed observation for a `SignedReport` policy. The
+    /// transaction must carry an ed25519-program instruction immediately before
+    /// this one, signed by the reporter over `signed_report_message(..)`.
+    pub fn submit_signed_observation(
+        ctx: Context<SubmitSignedObservation>,
+        observed_value: i64,
+        observation_timestamp: i64,
+    ) -> Result<()> {
+        let insurance_policy = &mut ctx.accounts.insurance_policy;
+        let clock = Clock::get()?;
+
+        // Keepers may race; only the first trigger is recorded and emitted
+        if insurance_policy.status == PolicyStatus::TriggeredPayout {
+            msg!("Payout already triggered for policy: {}", insurance_policy.key());
+            return Ok(());
+        }
+
+        let reporter = match insurance_policy.oracle_kind {
+            OracleKind::SignedReport { reporter } => reporter,
+            _ => return err!(InsuranceError::InvalidOracleData),
         };
+        if close_if_expired(insurance_policy, &mut ctx.accounts.pool_state, clock.unix_timestamp) {
+            return Ok(());
+        }
+        insurance_policy.require_unexpired(clock.unix_timestamp)?;
 
-        if trigger_met {
-            // Trigger payout
-            insurance_policy.status = PolicyStatus::TriggeredPayout;
-            insurance_policy.triggered_timestamp = Some(clock.unix_timestamp);
-            insurance_policy.trigger_price = Some(current_price.price);
+        // Coverage ends once an installment is overdue beyond the grace period
+        if insurance_policy.is_installment_lapsed(clock.unix_timestamp) {
+            lapse_policy_account(
+                insurance_policy,
+                &mut ctx.accounts.pool_state,
+                &ctx.accounts.authority,
+                ctx.accounts.submitter.key(),
+                clock.unix_timestamp,
+            )?;
+            return Ok(());
+        }
 
-            msg!("Trigger conditions met! Payout triggered for policy: {}", insurance_policy.key());
-        } else {
-            msg!("Trigger conditions not met for policy: {}", insurance_policy.key());
+        // Replay protection: each accepted report must be newer than the last
+        require!(
+            observation_timestamp > insurance_policy.last_report_timestamp,
+            InsuranceError::StaleObservation
+        );
+        require!(
+            observation_timestamp <= clock.unix_timestamp
+                && clock.unix_timestamp - observation_timestamp
+                    <= insurance_policy.max_price_age_seconds as i64,
+            InsuranceError::StaleOracleData
+        );
+
+        let instructions_sysvar = &ctx.accounts.instructions_sysvar;
+        let current_index = sysvar_instructions::load_current_index_checked(instructions_sysvar)?;
+        require!(current_index > 0, InsuranceError::InvalidSignedReport);
+        let signature_ix = sysvar_instructions::load_instruction_at_checked(
+            (current_index - 1) as usize,
+            instructions_sysvar,
+        )?;
+        let message = signed_report_message(&insurance_policy.key(), observed_value, observation_timestamp);
+        verify_ed25519_instruction(&signature_ix, &reporter, &message)?;
+
+        insurance_policy.last_report_timestamp = observation_timestamp;
+        // Reporters sign values already expressed at the policy's exponent
+        let observation = Price {
+            price: observed_value,
+            conf: 0,
+            expo: insurance_policy.price_expo,
+            publish_time: observation_timestamp,
+        };
+
+        msg!("Signed observation: {}", observed_value);
+        let policy_key = insurance_policy.key();
+        if let Some(evaluation) = insurance_policy.record_observation(
+            policy_key,
+            &observation,
+            None,
+            None,
+            clock.unix_timestamp,
+        )? {
+            if evaluation.triggered {
+                ctx.accounts.pool_state.add_obligation(insurance_policy.coverage_amount);
+                msg!("Trigger conditions met! Payout triggered for policy: {}", policy_key);
+            } else {
+                msg!("Trigger conditions not met for policy: {}", policy_key);
+            }
+            emit!(evaluation);
         }
 
         Ok(())
     }
 
-    /// Execute payout to policy holder
-    pub fn execute_payout(ctx: Context<ExecutePayout>) -> Result<()> {
+    /// Declare the insured event directly, bypassing the oracle (admin function)
+    pub fn manual_trigger(ctx: Context<ManualTrigger>, reason_code: u16) -> Result<()> {
         let insurance_policy = &mut ctx.accounts.insurance_policy;
         let clock = Clock::get()?;
 
-        // Check if payout was triggered
+        insurance_policy.declare_manual_trigger(
+            &ctx.accounts.authority.key(),
+            reason_code,
+            clock.unix_timestamp,
+        )?;
+        ctx.accounts.pool_state.add_obligation(insurance_policy.coverage_amount);
+
+        msg!("Policy manually triggered ({}): {}", reason_code, insurance_policy.key());
+        Ok(())
+    }
+
+    /// Hold a triggered payout for council review (policy authority)
+    pub fn dispute_trigger(ctx: Context<DisputeTrigger>) -> Result<()> {
+        let insurance_policy = &mut ctx.accounts.insurance_policy;
+        let clock = Clock::get()?;
         require!(
             insurance_policy.status == PolicyStatus::TriggeredPayout,
             InsuranceError::PayoutNotTriggered
         );
+        insurance_policy.apply_event(
+            PolicyEvent::Dispute,
+            clock.unix_timestamp,
+            ctx.accounts.authority.key(),
+        )?;
 
-        // Transfer coverage amount from insurance pool to policy holder
-        let seeds = &[
-            b"insurance_policy".as_ref(),
-            insurance_policy.authority.as_ref(),
-            insurance_policy.policy_holder.as_ref(),
-            &[insurance_policy.bump],
-        ];
-        let signer = &[&seeds[..]];
+        // A disputed trigger costs the keeper its deposit
+        let forfeit = insurance_policy.forfeit_keeper_deposit();
+        if forfeit > 0 {
+            let policy_info = insurance_policy.to_account_info();
+            **policy_info.try_borrow_mut_lamports()? -= forfeit;
+            let pool_state = &mut ctx.accounts.pool_state;
+            **pool_state.to_account_info().try_borrow_mut_lamports()? += forfeit;
+            pool_state.record_forfeited_deposit(forfeit)?;
+            msg!("Keeper deposit forfeited to pool: {}", forfeit);
+        }
 
-        let cpi_accounts = Transfer {
-            from: ctx.accounts.insurance_pool_token_account.to_account_info(),
-            to: ctx.accounts.policy_holder_token_account.to_account_info(),
-            authority: ctx.accounts.insurance_policy.to_account_info(),
-        };
-        let cpi_program = ctx.accounts.token_program.to_account_info();
-        let cpi_ctx = CpiContext::new_with_signer(cpi_program, cpi_accounts, signer);
-        
-        token::transfer(cpi_ctx, insurance_policy.coverage_amount)?;
+        msg!("Trigger disputed for policy: {}", insurance_policy.key());
+        Ok(())
+    }
 
-        insurance_policy.status = PolicyStatus::PaidOut;
-        insurance_policy.payout_timestamp = Some(clock.unix_timestamp);
+    /// Anchor a piece of evidence for a disputed trigger. The policy authority
+    /// submits as `EvidenceRole::Authority` and the holder as `EvidenceRole::Holder`,
+    /// each up to `MAX_EVIDENCE_PER_PARTY` times.
+    pub fn submit_evidence(
+        ctx: Context<SubmitEvidence>,
+        uri: String,
+        hash: [u8; 32],
+        role: EvidenceRole,
+    ) -> Result<()> {
+        require!(uri.len() <= MAX_EVIDENCE_URI_LEN, InsuranceError::UriTooLong);
+        let insurance_policy = &mut ctx.accounts.insurance_policy;
+        let submitter = ctx.accounts.submitter.key();
+        let index = insurance_policy.record_evidence(role, &submitter)?;
+        let clock = Clock::get()?;
 
-        msg!("Payout executed for policy: {}", insurance_policy.key());
+        ctx.accounts.evidence.set_inner(Evidence {
+            policy: insurance_policy.key(),
+            submitter,
+            role,
+            index,
+            uri: uri.clone(),
+            hash,
+            submitted_timestamp: clock.unix_timestamp,
+            bump: ctx.bumps.evidence,
+        });
+
+        emit!(EvidenceSubmitted {
+            policy: insurance_policy.key(),
+            submitter,
+            role,
+            index,
+            uri,
+            hash,
+        });
+        msg!("Evidence {} submitted for policy: {}", index, insurance_policy.key());
         Ok(())
     }
 
-    /// Cancel policy and refund premium (if not yet triggered)
-    pub fn cancel_policy(ctx: Context<CancelPolicy>) -> Result<()> {
+    /// Execute payout to policy holder
+    pub fn execute_payout(ctx: Context<ExecutePayout>) -> Result<()> {
+        ctx.accounts.config.require_not_paused()?;
         let insurance_policy = &mut ctx.accounts.insurance_policy;
         let clock = Clock::get()?;
 
-        // Check if policy can be cancelled
+        // Check if payout was triggered
         require!(
-            insurance_policy.status == PolicyStatus::Purchased,
-            InsuranceError::PolicyCannotBeCancelled
+            insurance_policy.status == PolicyStatus::TriggeredPayout,
+            InsuranceError::PayoutNotTriggered
         );
+        insurance_policy.check_payout_releasable(clock.unix_timestamp)?;
+        insurance_policy.record_block(BlockReason::Clear);
+        // Group members claim their own slices
         require!(
-            clock.unix_timestamp < insurance_policy.expiry_timestamp,
-            InsuranceError::PolicyExpired
+            insurance_policy.group_merkle_root.is_none(),
+            InsuranceError::InvalidPayoutAccount
         );
 
-        // Calculate refund amount (could implement fee deduction)
-        let refund_amount = insurance_policy.premium_amount;
+        // USD coverage is paid in however many tokens it takes at the current peg
+        let owed = match insurance_policy.payout_peg_oracle_address {
+            Some(expected) => {
+                let account = ctx
+                    .accounts
+                    .payout_peg_oracle_account
+                    .as_ref()
+                    .ok_or(InsuranceError::OracleMismatch)?;
+                require_keys_eq!(account.key(), expected, InsuranceError::OracleMismatch);
+                let peg = load_oracle_price(
+                    &insurance_policy.payout_peg_oracle_kind,
+                    account,
+                    clock.unix_timestamp,
+                    insurance_policy.max_price_age_seconds,
+                )?;
+                insurance_policy.pegged_coverage(&FixedPrice::from_oracle(&peg))?
+            }
+            None => insurance_policy.effective_coverage()?,
+        };
+        if let Some(holder_stats) = ctx.accounts.holder_stats.as_mut() {
+            holder_stats.record_claim();
+        }
+        settle_keeper_deposit(
+            insurance_policy,
+            ctx.accounts.authority.to_account_info(),
+            &mut ctx.accounts.pool_state,
+            clock.unix_timestamp,
+        )?;
+
+        // Vested payouts transfer nothing now; the holder claims as the schedule releases
+        if insurance_policy.payout_vesting_seconds > 0 {
+            let total = insurance_policy.begin_vesting_of(
+                owed,
+                clock.unix_timestamp,
+                ctx.accounts.authority.key(),
+            )?;
+            let pool_state = &mut ctx.accounts.pool_state;
+            pool_state.retire(insurance_policy.coverage_amount);
+            pool_state.settle_obligation(insurance_policy.coverage_amount);
+            pool_state.add_outstanding_claim(total);
+
+            debug_msg!(
+                "Payout of {} vesting over {}s for policy: {}",
+                total,
+                insurance_policy.payout_vesting_seconds,
+                insurance_policy.key()
+            );
+            return Ok(());
+        }
+
+        // Anything above the retention comes from the reinsurer, who must cover it in full
+        let (retained, reinsured) = insurance_policy.split_retention(owed);
+        if reinsured > 0 {
+            let (Some(reinsurance_pool), Some(reinsurance_vault)) =
+                (&ctx.accounts.reinsurance_pool, &ctx.accounts.reinsurance_vault)
+            else {
+                return err!(InsuranceError::InvalidPayoutAccount);
+            };
+            require_keys_eq!(
+                reinsurance_vault.key(),
+                reinsurance_pool.vault,
+                InsuranceError::InvalidPayoutAccount
+            );
+            reinsurance_pool.check_cover(reinsurance_vault.amount, reinsured)?;
+        }
 
-        // Transfer refund from insurance pool to policy holder
+        // Pay what the vault holds now; any shortfall stays owed to the holder
+        let payable = insurance_policy.begin_payout_of(
+            owed,
+            ctx.accounts.insurance_pool_token_account.amount.min(retained) + reinsured,
+            clock.unix_timestamp,
+            ctx.accounts.authority.key(),
+        )?;
+        if payable < owed {
+            insurance_policy.record_block(BlockReason::InsufficientLiquidity);
+        }
+        let primary_paid = payable - reinsured;
+        let pool_state = &mut ctx.accounts.pool_state;
+        pool_state.record_payout(primary_paid, clock.unix_timestamp)?;
         let seeds = &[
-            b"insurance_policy".as_ref(),
-            insurance_policy.authority.as_ref(),
-            insurance_policy.policy_holder.as_ref(),
-            &[insurance_policy.bump],
+            b"pool".as_ref(),
+            pool_state.mint.as_ref(),
+            &[pool_state.bump],
         ];
         let signer = &[&seeds[..]];
 
-        let cpi_accounts = Transfer {
-            from: ctx.accounts.insurance_pool_token_account.to_account_info(),
-            to: ctx.accounts.policy_holder_token_account.to_account_info(),
-            authority: ctx.accounts.insurance_policy.to_account_info(),
-        };
-        let cpi_program = ctx.accounts.token_program.to_account_info();
-        let cpi_ctx = CpiContext::new_with_signer(cpi_program, cpi_accounts, signer);
-        
-        token::transfer(cpi_ctx, refund_amount)?;
+        if payable > 0 && insurance_policy.beneficiaries.is_empty() {
+            let destination = &ctx.accounts.policy_holder_token_account;
+            match insurance_policy.classify_payout_destination(&destination.key())? {
+                // Fresh wallets get their ATA created on the fly, with the cranker paying rent
+                PayoutDestination::AssociatedTokenAccount => {
+                    if destination.data_is_empty() {
+                        let cpi_accounts = associated_token::Create {
+                            payer: ctx.accounts.authority.to_account_info(),
+                            associated_token: destination.to_account_info(),
+                            authority: ctx.accounts.policy_holder.to_account_info(),
+                            mint: ctx.accounts.payout_mint.to_account_info(),
+                            system_program: ctx.accounts.system_program.to_account_info(),
+                            token_program: ctx.accounts.token_program.to_account_info(),
+                        };
+                        let cpi_program = ctx.accounts.associated_token_program.to_account_info();
+                        associated_token::create(CpiContext::new(cpi_program, cpi_accounts))?;
+                    }
+                }
+                PayoutDestination::Custom => {
+                    require_keys_eq!(*destination.owner, token::ID, InsuranceError::InvalidPayoutAccount);
+                    let token_account = TokenAccount::try_deserialize(&mut &destination.data.borrow()[..])?;
+                    require_keys_eq!(
+                        token_account.owner,
+                        insurance_policy.policy_holder,
+                        InsuranceError::WrongTokenAccountOwner
+                    );
+                    require_keys_eq!(token_account.mint, insurance_policy.payout_mint, InsuranceError::WrongMint);
+                }
+            }
 
-        insurance_policy.status = PolicyStatus::Cancelled;
-        insurance_policy.cancelled_timestamp = Some(clock.unix_timestamp);
+            let cpi_accounts = Transfer {
+                from: ctx.accounts.insurance_pool_token_account.to_account_info(),
+                to: ctx.accounts.policy_holder_token_account.to_account_info(),
+                authority: pool_state.to_account_info(),
+            };
+            let cpi_program = ctx.accounts.token_program.to_account_info();
+            let cpi_ctx = CpiContext::new_with_signer(cpi_program, cpi_accounts, signer);
 
-        msg!("Policy cancelled: {}", insurance_policy.key());
+            token::transfer(cpi_ctx, primary_paid)?;
+
+            if reinsured > 0 {
+                let (Some(reinsurance_pool), Some(reinsurance_vault)) =
+                    (ctx.accounts.reinsurance_pool.as_mut(), &ctx.accounts.reinsurance_vault)
+                else {
+                    return err!(InsuranceError::InvalidPayoutAccount);
+                };
+                let pool_key = reinsurance_pool.pool;
+                let reinsurance_seeds = &[
+                    b"reinsurance_pool".as_ref(),
+                    pool_key.as_ref(),
+                    &[reinsurance_pool.bump],
+                ];
+                let reinsurance_signer = &[&reinsurance_seeds[..]];
+                let cpi_accounts = Transfer {
+                    from: reinsurance_vault.to_account_info(),
+                    to: ctx.accounts.policy_holder_token_account.to_account_info(),
+                    authority: reinsurance_pool.to_account_info(),
+                };
+                let cpi_program = ctx.accounts.token_program.to_account_info();
+                let cpi_ctx =
+                    CpiContext::new_with_signer(cpi_program, cpi_accounts, reinsurance_signer);
+
+                token::transfer(cpi_ctx, reinsured)?;
+                reinsurance_pool.record_claim(reinsured);
+                insurance_policy.reinsured_paid = reinsured;
+            }
+        } else if payable > 0 {
+            pay_beneficiaries(
+                insurance_policy,
+                payable,
+                ctx.remaining_accounts,
+                &ctx.accounts.insurance_pool_token_account,
+                pool_state,
+                &ctx.accounts.token_program,
+            )?;
+        }
+
+        pool_state.retire(insurance_policy.coverage_amount);
+        pool_state.settle_obligation(insurance_policy.coverage_amount);
+        pool_state.add_outstanding_claim(insurance_policy.outstanding_claim);
+        pool_state.debit_payout(primary_paid);
+        insurance_policy.payout_timestamp = Some(clock.unix_timestamp);
+
+        // Notify a composing protocol; a failed callback fails the whole payout
+        if let Some(callback_program) = insurance_policy.payout_callback_program {
+            let callback_account = ctx
+                .accounts
+                .payout_callback_program
+                .as_ref()
+                .ok_or(InsuranceError::InvalidPayoutCallback)?;
+            require_keys_eq!(
+                callback_account.key(),
+                callback_program,
+                InsuranceError::InvalidPayoutCallback
+            );
+            let policy_info = insurance_policy.to_account_info();
+            let ix = payout_callback_instruction(callback_program, policy_info.key(), payable);
+            invoke(&ix, &[policy_info, callback_account.clone()])?;
+        }
+
+        let policy_key = insurance_policy.key();
+        if let Some(event) = insurance_policy.finalized_event(policy_key) {
+            emit!(event);
+        }
+        emit!(PayoutExecuted {
+            policy: policy_key,
+            paid: payable,
+            outstanding: insurance_policy.outstanding_claim,
+            timestamp: clock.unix_timestamp,
+        });
         Ok(())
     }
 
-    /// Update oracle address (admin function)
-    pub fn update_oracle(ctx: Context<UpdateOracle>, new_oracle_address: Pubkey) -> Result<()> {
+    /// Pay many triggered policies in one transaction after a market-wide event.
+    /// `remaining_accounts` holds (policy, holder token account) pairs.
+    ///
+    /// Policies are paid strictly in the order given, each in full. The first
+    /// claim the vault (or the pool's drawdown cap) cannot cover ends the batch,
+    /// so a later, smaller claim never gets ahead of it. Policies that cannot be
+    /// paid here are skipped rather than failing the batch. Returns one
+    /// `BatchPayoutResult` per pair, in input order. Holder loyalty records are
+    /// not updated by batched payouts.
+    pub fn execute_payouts_batch<'info>(
+        ctx: Context<'_, '_, 'info, 'info, ExecutePayoutsBatch<'info>>,
+    ) -> Result<Vec<BatchPayoutResult>> {
+        ctx.accounts.config.require_not_paused()?;
+        require!(
+            !ctx.remaining_accounts.is_empty() && ctx.remaining_accounts.len() % 2 == 0,
+            InsuranceError::InvalidBatch
+        );
+        let clock = Clock::get()?;
+        let authority = ctx.accounts.authority.key();
+        let pool_key = ctx.accounts.pool_state.key();
+        let pool_mint = ctx.accounts.pool_state.mint;
+
+        let mut policies = Vec::new();
+        let mut claims = Vec::new();
+        let mut seen = Vec::new();
+        for pair in ctx.remaining_accounts.chunks(2) {
+            let (policy_info, destination) = (&pair[0], &pair[1]);
+            // A policy listed twice is only considered the first time
+            let duplicate = seen.contains(policy_info.key);
+            seen.push(policy_info.key());
+            let policy = match Account::<InsurancePolicy>::try_from(policy_info) {
+                Ok(policy) if !duplicate && policy_info.is_writable => policy,
+                _ => {
+                    policies.push(None);
+                    claims.push(Err(BatchPayoutResult::Ineligible));
+                    continue;
+                }
+            };
+            let claim = policy
+                .batch_claim(&authority, &pool_key, &pool_mint, clock.unix_timestamp)
+                .and_then(|owed| {
+                    check_batch_destination(&policy, destination)?;
+                    Ok(owed)
+                });
+            policies.push(Some(policy));
+            claims.push(claim);
+        }
+
+        let results = ctx.accounts.pool_state.allocate_batch_payouts(
+            &claims,
+            ctx.accounts.insurance_pool_token_account.amount,
+            clock.unix_timestamp,
+        );
+
+        let pool_state = &mut ctx.accounts.pool_state;
+        let seeds = &[
+            b"pool".as_ref(),
+            pool_state.mint.as_ref(),
+            &[pool_state.bump],
+        ];
+        let signer = &[&seeds[..]];
+        for (index, policy) in policies.into_iter().enumerate() {
+            let (Some(mut policy), Ok(owed)) = (policy, claims[index]) else {
+                continue;
+            };
+            if results[index] != BatchPayoutResult::Paid {
+                continue;
+            }
+            settle_keeper_deposit(
+                &mut policy,
+                ctx.accounts.authority.to_account_info(),
+                pool_state,
+                clock.unix_timestamp,
+            )?;
+            policy.begin_payout_of(owed, owed, clock.unix_timestamp, authority)?;
+
+            let cpi_accounts = Transfer {
+                from: ctx.accounts.insurance_pool_token_account.to_account_info(),
+                to: ctx.remaining_accounts[2 * index + 1].clone(),
+                authority: pool_state.to_account_info(),
+            };
+            let cpi_program = ctx.accounts.token_program.to_account_info();
+            token::transfer(CpiContext::new_with_signer(cpi_program, cpi_accounts, signer), owed)?;
+
+            pool_state.retire(policy.coverage_amount);
+            pool_state.settle_obligation(policy.coverage_amount);
+            pool_state.debit_payout(owed);
+            policy.payout_timestamp = Some(clock.unix_timestamp);
+            if let Some(event) = policy.finalized_event(policy.key()) {
+                emit!(event);
+            }
+            policy.exit(&crate::ID)?;
+        }
+
+        let paid = results.iter().filter(|result| **result == BatchPayoutResult::Paid).count();
+        msg!("Batch payout: {} of {} policies paid", paid, results.len());
+        Ok(results)
+    }
+
+    /// Pay a wSOL-denominated payout straight to the holder's wallet as SOL.
+    /// The payout passes through a temporary wSOL account, funded with rent by
+    /// the authority, which is closed into the policy account; the policy then
+    /// forwards the payout to the holder and the rent back to the authority.
+    pub fn execute_payout_unwrap(ctx: Context<ExecutePayoutUnwrap>) -> Result<()> {
+        ctx.accounts.config.require_not_paused()?;
+        let insurance_policy = &mut ctx.accounts.insurance_policy;
+        let clock = Clock::get()?;
+
+        // Split, vested, reinsured, pegged and callback payouts keep going
+        // through `execute_payout`
+        require!(insurance_policy.has_plain_payout(), InsuranceError::InvalidPayoutAccount);
+        insurance_policy.check_payout_releasable(clock.unix_timestamp)?;
+        if let Some(holder_stats) = ctx.accounts.holder_stats.as_mut() {
+            holder_stats.record_claim();
+        }
+        settle_keeper_deposit(
+            insurance_policy,
+            ctx.accounts.authority.to_account_info(),
+            &mut ctx.accounts.pool_state,
+            clock.unix_timestamp,
+        )?;
+
+        let payable = insurance_policy.begin_payout(
+            ctx.accounts.insurance_pool_token_account.amount,
+            clock.unix_timestamp,
+            ctx.accounts.authority.key(),
+        )?;
+        let pool_state = &mut ctx.accounts.pool_state;
+        pool_state.record_payout(payable, clock.unix_timestamp)?;
+        let seeds = &[
+            b"pool".as_ref(),
+            pool_state.mint.as_ref(),
+            &[pool_state.bump],
+        ];
+        let signer = &[&seeds[..]];
+
+        let cpi_accounts = Transfer {
+            from: ctx.accounts.insurance_pool_token_account.to_account_info(),
+            to: ctx.accounts.wrapped_sol_account.to_account_info(),
+            authority: pool_state.to_account_info(),
+        };
+        let cpi_program = ctx.accounts.token_program.to_account_info();
+        token::transfer(CpiContext::new_with_signer(cpi_program, cpi_accounts, signer), payable)?;
+
+        let closed_lamports = ctx.accounts.wrapped_sol_account.to_account_info().lamports();
+        let cpi_accounts = token::CloseAccount {
+            account: ctx.accounts.wrapped_sol_account.to_account_info(),
+            destination: insurance_policy.to_account_info(),
+            authority: pool_state.to_account_info(),
+        };
+        let cpi_program = ctx.accounts.token_program.to_account_info();
+        token::close_account(CpiContext::new_with_signer(cpi_program, cpi_accounts, signer))?;
+
+        let rent_refund = wrapped_sol_rent_refund(closed_lamports, payable)?;
+        let policy_info = insurance_policy.to_account_info();
+        **policy_info.try_borrow_mut_lamports()? -= closed_lamports;
+        **ctx.accounts.policy_holder.try_borrow_mut_lamports()? += payable;
+        **ctx.accounts.authority.try_borrow_mut_lamports()? += rent_refund;
+
+        pool_state.retire(insurance_policy.coverage_amount);
+        pool_state.settle_obligation(insurance_policy.coverage_amount);
+        pool_state.add_outstanding_claim(insurance_policy.outstanding_claim);
+        pool_state.debit_payout(payable);
+        insurance_policy.payout_timestamp = Some(clock.unix_timestamp);
+
+        if let Some(event) = insurance_policy.finalized_event(insurance_policy.key()) {
+            emit!(event);
+        }
+        msg!(
+            "Payout of {} lamports unwrapped to {} for policy: {}",
+            payable,
+            insurance_policy.policy_holder,
+            insurance_policy.key()
+        );
+        Ok(())
+    }
+
+    /// Transfer the portion of a vesting payout released since the last claim
+    pub fn claim_vested_payout(ctx: Context<ClaimVestedPayout>) -> Result<()> {
+        let insurance_policy = &mut ctx.accounts.insurance_policy;
+        let clock = Clock::get()?;
+
+        let amount = insurance_policy.claim_vested(
+            clock.unix_timestamp,
+            ctx.accounts.policy_holder.key(),
+        )?;
+        let pool_state = &mut ctx.accounts.pool_state;
+        pool_state.record_payout(amount, clock.unix_timestamp)?;
+        let seeds = &[
+            b"pool".as_ref(),
+            pool_state.mint.as_ref(),
+            &[pool_state.bump],
+        ];
+        let signer = &[&seeds[..]];
+
+        let cpi_accounts = Transfer {
+            from: ctx.accounts.insurance_pool_token_account.to_account_info(),
+            to: ctx.accounts.policy_holder_token_account.to_account_info(),
+            authority: pool_state.to_account_info(),
+        };
+        let cpi_program = ctx.accounts.token_program.to_account_info();
+        let cpi_ctx = CpiContext::new_with_signer(cpi_program, cpi_accounts, signer);
+
+        token::transfer(cpi_ctx, amount)?;
+
+        pool_state.settle_outstanding_claim(amount);
+        pool_state.debit_payout(amount);
+        if insurance_policy.status == PolicyStatus::PaidOut {
+            insurance_policy.payout_timestamp = Some(clock.unix_timestamp);
+        }
+
+        if let Some(event) = insurance_policy.finalized_event(insurance_policy.key()) {
+            emit!(event);
+        }
+        msg!(
+            "Vested payout claimed for policy {}: {} ({} of {})",
+            insurance_policy.key(),
+            amount,
+            insurance_policy.vested_claimed,
+            insurance_policy.vesting_total
+        );
+        Ok(())
+    }
+
+    /// Allow or forbid payouts to a token account other than the holder's
+    /// canonical ATA for the payout mint (policy holder)
+    pub fn set_custom_payout_account_allowed(ctx: Context<SetCustomPayoutAccountAllowed>, allowed: bool) -> Result<()> {
+        let insurance_policy = &mut ctx.accounts.insurance_policy;
+        insurance_policy.allow_custom_payout_account = allowed;
+
+        msg!("Custom payout account allowed for policy {}: {}", insurance_policy.key(), allowed);
+        Ok(())
+    }
+
+    /// Replace the payout split among beneficiaries; only possible before a trigger
+    pub fn set_beneficiaries(ctx: Context<SetBeneficiaries>, beneficiaries: Vec<Beneficiary>) -> Result<()> {
+        let insurance_policy = &mut ctx.accounts.insurance_policy;
+        insurance_policy.replace_beneficiaries(beneficiaries)?;
+
+        msg!(
+            "Beneficiaries set for policy {}: {}",
+            insurance_policy.key(),
+            insurance_policy.beneficiaries.len()
+        );
+        Ok(())
+    }
+
+    /// Hand the policy to a new holder, e.g. after a secondary-market sale.
+    /// Beneficiary entries naming the old holder follow the policy.
+    pub fn transfer_policy(ctx: Context<TransferPolicy>, new_holder: Pubkey) -> Result<()> {
+        let insurance_policy = &mut ctx.accounts.insurance_policy;
+        let previous_holder = insurance_policy.transfer_to(new_holder)?;
+
+        emit!(PolicyTransferred {
+            policy: insurance_policy.key(),
+            previous_holder,
+            new_holder,
+        });
+        msg!("Policy {} transferred from {} to {}", insurance_policy.key(), previous_holder, new_holder);
+        Ok(())
+    }
+
+    /// Split a purchased policy in two: the source keeps `retained_coverage` and
+    /// a new child policy takes `split_coverage`, each with its share of the
+    /// premium and identical trigger terms. The pool's reservation is unchanged.
+    pub fn split_policy(
+        ctx: Context<SplitPolicy>,
+        retained_coverage: u64,
+        split_coverage: u64,
+    ) -> Result<()> {
+        let parent_key = ctx.accounts.insurance_policy.key();
+        let mut child = ctx
+            .accounts
+            .insurance_policy
+            .split(parent_key, retained_coverage, split_coverage)?;
+        child.bump = ctx.bumps.child_policy;
+        ctx.accounts.config.assign_policy_number(&mut child)?;
+        ctx.accounts.pool_state.open_policy();
+
+        let child_policy = &mut ctx.accounts.child_policy;
+        child_policy.set_inner(child);
+        emit!(child_policy.initialized_event(child_policy.key()));
+        msg!(
+            "Policy {} split: {} retained, {} moved to {}",
+            parent_key,
+            retained_coverage,
+            split_coverage,
+            child_policy.key()
+        );
+        Ok(())
+    }
+
+    /// Pay a group member their weighted slice of a triggered group policy. The
+    /// member proves their leaf against the policy's merkle root, and each leaf
+    /// can be claimed once. The last claim settles the policy.
+    pub fn claim_group_payout(
+        ctx: Context<ClaimGroupPayout>,
+        index: u32,
+        weight: u64,
+        proof: Vec<[u8; 32]>,
+    ) -> Result<()> {
+        ctx.accounts.config.require_not_paused()?;
+        let insurance_policy = &mut ctx.accounts.insurance_policy;
+        let clock = Clock::get()?;
+        insurance_policy.check_payout_releasable(clock.unix_timestamp)?;
+
+        let member = ctx.accounts.member.key();
+        let amount = insurance_policy.verify_group_claim(index, &member, weight, &proof)?;
+        let group_claims = &mut ctx.accounts.group_claims;
+        group_claims.mark_claimed(index)?;
+
+        let pool_state = &mut ctx.accounts.pool_state;
+        pool_state.record_payout(amount, clock.unix_timestamp)?;
+        let seeds = &[
+            b"pool".as_ref(),
+            pool_state.mint.as_ref(),
+            &[pool_state.bump],
+        ];
+        let signer = &[&seeds[..]];
+        let cpi_accounts = Transfer {
+            from: ctx.accounts.insurance_pool_token_account.to_account_info(),
+            to: ctx.accounts.member_token_account.to_account_info(),
+            authority: pool_state.to_account_info(),
+        };
+        let cpi_program = ctx.accounts.token_program.to_account_info();
+        token::transfer(CpiContext::new_with_signer(cpi_program, cpi_accounts, signer), amount)?;
+        pool_state.debit_payout(amount);
+
+        if group_claims.all_claimed() {
+            insurance_policy.apply_event(PolicyEvent::Payout, clock.unix_timestamp, member)?;
+            insurance_policy.payout_timestamp = Some(clock.unix_timestamp);
+            pool_state.retire(insurance_policy.coverage_amount);
+            pool_state.settle_obligation(insurance_policy.coverage_amount);
+            settle_keeper_deposit(
+                insurance_policy,
+                ctx.accounts.authority.to_account_info(),
+                pool_state,
+                clock.unix_timestamp,
+            )?;
+        }
+
+        msg!("Group member {} claimed {} from policy: {}", member, amount, insurance_policy.key());
+        Ok(())
+    }
+
+    /// Pay down a partially paid claim from the topped-up vault. Policies with
+    /// beneficiaries pass one destination per beneficiary as remaining accounts,
+    /// in order, and the payment is split as `execute_payout` splits it.
+    pub fn settle_outstanding_claim(ctx: Context<SettleOutstandingClaim>) -> Result<()> {
+        let insurance_policy = &mut ctx.accounts.insurance_policy;
+        let clock = Clock::get()?;
+
+        let payable = insurance_policy.settle_claim(
+            ctx.accounts.insurance_pool_token_account.amount,
+            clock.unix_timestamp,
+            ctx.accounts.authority.key(),
+        )?;
+        let pool_state = &mut ctx.accounts.pool_state;
+        pool_state.record_payout(payable, clock.unix_timestamp)?;
+        if insurance_policy.beneficiaries.is_empty() {
+            let seeds = &[
+                b"pool".as_ref(),
+                pool_state.mint.as_ref(),
+                &[pool_state.bump],
+            ];
+            let signer = &[&seeds[..]];
+
+            let cpi_accounts = Transfer {
+                from: ctx.accounts.insurance_pool_token_account.to_account_info(),
+                to: ctx.accounts.policy_holder_token_account.to_account_info(),
+                authority: pool_state.to_account_info(),
+            };
+            let cpi_program = ctx.accounts.token_program.to_account_info();
+            let cpi_ctx = CpiContext::new_with_signer(cpi_program, cpi_accounts, signer);
+
+            token::transfer(cpi_ctx, payable)?;
+        } else {
+            pay_beneficiaries(
+                insurance_policy,
+                payable,
+                ctx.remaining_accounts,
+                &ctx.accounts.insurance_pool_token_account,
+                pool_state,
+                &ctx.accounts.token_program,
+            )?;
+        }
+
+        pool_state.settle_outstanding_claim(payable);
+        pool_state.debit_payout(payable);
+
+        if let Some(event) = insurance_policy.finalized_event(insurance_policy.key()) {
+            emit!(event);
+        }
+        msg!(
+            "Outstanding claim settled for policy {}: {} paid, {} remaining",
+            insurance_policy.key(),
+            payable,
+            insurance_policy.outstanding_claim
+        );
+        Ok(())
+    }
+
+    /// Cancel policy and refund premium (if not yet triggered)
+    pub fn cancel_policy(ctx: Context<CancelPolicy>) -> Result<()> {
         let insurance_policy = &mut ctx.accounts.insurance_policy;
+        let clock = Clock::get()?;
+
+        // Check if policy can be cancelled
+        insurance_policy.require_unexpired(clock.unix_timestamp)?;
+        require!(
+            insurance_policy.status == PolicyStatus::Purchased,
+            InsuranceError::PolicyCannotBeCancelled
+        );
+
+        // Full refund inside the cooling-off window, otherwise net of the cancellation fee
+        let refund_amount = insurance_policy.cancellation_refund(clock.unix_timestamp)?;
+
+        // Transfer refund from insurance pool to whoever paid the premium
+        let pool_state = &mut ctx.accounts.pool_state;
+        let seeds = &[
+            b"pool".as_ref(),
+            pool_state.mint.as_ref(),
+            &[pool_state.bump],
+        ];
+        let signer = &[&seeds[..]];
+
+        let cpi_accounts = Transfer {
+            from: ctx.accounts.insurance_pool_token_account.to_account_info(),
+            to: ctx.accounts.refund_token_account.to_account_info(),
+            authority: pool_state.to_account_info(),
+        };
+        let cpi_program = ctx.accounts.token_program.to_account_info();
+        let cpi_ctx = CpiContext::new_with_signer(cpi_program, cpi_accounts, signer);
         
-        insurance_policy.oracle_address = new_oracle_address;
+        token::transfer(cpi_ctx, refund_amount)?;
+
+        pool_state.retire(insurance_policy.coverage_amount);
+        pool_state.total_capital = pool_state.total_capital.saturating_sub(refund_amount);
+        insurance_policy.apply_event(
+            PolicyEvent::Cancel,
+            clock.unix_timestamp,
+            ctx.accounts.policy_holder.key(),
+        )?;
+        insurance_policy.cancelled_timestamp = Some(clock.unix_timestamp);
+        return_keeper_reward(insurance_policy, &ctx.accounts.authority)?;
+
+        msg!("Policy cancelled: {}", insurance_policy.key());
+        if let Some(event) = insurance_policy.finalized_event(insurance_policy.key()) {
+            emit!(event);
+        }
+        Ok(())
+    }
+
+    /// Lower the policy's coverage and refund the corresponding share of premium
+    pub fn reduce_coverage(ctx: Context<ReduceCoverage>, new_coverage_amount: u64) -> Result<()> {
+        let insurance_policy = &mut ctx.accounts.insurance_policy;
+        let clock = Clock::get()?;
+
+        insurance_policy.require_unexpired(clock.unix_timestamp)?;
+
+        let released_coverage = insurance_policy.coverage_amount.saturating_sub(new_coverage_amount);
+        let refund_amount = insurance_policy.reduce_coverage(new_coverage_amount)?;
+
+        let pool_state = &mut ctx.accounts.pool_state;
+        let seeds = &[
+            b"pool".as_ref(),
+            pool_state.mint.as_ref(),
+            &[pool_state.bump],
+        ];
+        let signer = &[&seeds[..]];
+
+        let cpi_accounts = Transfer {
+            from: ctx.accounts.insurance_pool_token_account.to_account_info(),
+            to: ctx.accounts.refund_token_account.to_account_info(),
+            authority: pool_state.to_account_info(),
+        };
+        let cpi_program = ctx.accounts.token_program.to_account_info();
+        let cpi_ctx = CpiContext::new_with_signer(cpi_program, cpi_accounts, signer);
         
-        msg!("Oracle address updated for policy: {}", insurance_policy.key());
+        token::transfer(cpi_ctx, refund_amount)?;
+
+        pool_state.release(released_coverage);
+        pool_state.total_capital = pool_state.total_capital.saturating_sub(refund_amount);
+
+        msg!("Coverage reduced to {} for policy: {}", new_coverage_amount, insurance_policy.key());
+        Ok(())
+    }
+
+    /// Raise the policy's coverage, charging the holder the premium for the added
+    /// coverage over the remaining coverage window
+    pub fn increase_coverage(
+        ctx: Context<IncreaseCoverage>,
+        new_coverage_amount: u64,
+    ) -> Result<()> {
+        ctx.accounts.config.require_not_paused()?;
+        let insurance_policy = &mut ctx.accounts.insurance_policy;
+        let clock = Clock::get()?;
+
+        let added_coverage = new_coverage_amount.saturating_sub(insurance_policy.coverage_amount);
+        let additional_premium =
+            insurance_policy.increase_coverage(new_coverage_amount, clock.unix_timestamp)?;
+
+        // The pool must be able to back the higher coverage before any premium moves
+        let pool_state = &mut ctx.accounts.pool_state;
+        pool_state.commit(added_coverage)?;
+
+        let cpi_accounts = Transfer {
+            from: ctx.accounts.policy_holder_token_account.to_account_info(),
+            to: ctx.accounts.insurance_pool_token_account.to_account_info(),
+            authority: ctx.accounts.policy_holder.to_account_info(),
+        };
+        let cpi_program = ctx.accounts.token_program.to_account_info();
+        token::transfer(CpiContext::new(cpi_program, cpi_accounts), additional_premium)?;
+        pool_state.total_capital = pool_state
+            .total_capital
+            .checked_add(additional_premium)
+            .ok_or(InsuranceError::MathOverflow)?;
+
+        msg!(
+            "Coverage increased to {} for policy: {} (additional premium {})",
+            new_coverage_amount,
+            insurance_policy.key(),
+            additional_premium
+        );
+        Ok(())
+    }
+
+    /// Mark a policy past its expiry as expired and release its reserved coverage.
+    /// A policy a trigger check already expired only has its keeper reward returned.
+    pub fn expire_policy(ctx: Context<ExpirePolicy>) -> Result<()> {
+        let insurance_policy = &mut ctx.accounts.insurance_policy;
+        let clock = Clock::get()?;
+
+        let newly_expired = insurance_policy.status != PolicyStatus::Expired;
+        if newly_expired && insurance_policy.expire(clock.unix_timestamp)? {
+            ctx.accounts.pool_state.retire(insurance_policy.coverage_amount);
+        }
+
+        return_keeper_reward(insurance_policy, &ctx.accounts.authority)?;
+
+        if newly_expired {
+            msg!("Policy expired: {}", insurance_policy.key());
+            if let Some(event) = insurance_policy.finalized_event(insurance_policy.key()) {
+                emit!(event);
+            }
+        }
+        Ok(())
+    }
+
+    /// Expire many policies of one pool past their expiry, passed as (policy,
+    /// authority) pairs in `remaining_accounts`. Each releases its reserved
+    /// coverage and returns its unspent keeper reward to the authority, as
+    /// `expire_policy` does, and earns the cranker `SWEEP_FEE_LAMPORTS` out of
+    /// the pool account's lamports above rent. Policies that are not expired,
+    /// not active or purchased, or not backed by this pool are skipped.
+    pub fn sweep_expired<'info>(
+        ctx: Context<'_, '_, 'info, 'info, SweepExpired<'info>>,
+    ) -> Result<()> {
+        let pairs = ctx.remaining_accounts.len() / 2;
+        require!(
+            ctx.remaining_accounts.len() % 2 == 0 && (1..=MAX_SWEEP_POLICIES).contains(&pairs),
+            InsuranceError::InvalidBatch
+        );
+        let clock = Clock::get()?;
+        let pool_key = ctx.accounts.pool_state.key();
+        let pool_mint = ctx.accounts.pool_state.mint;
+
+        let mut expired = 0;
+        for pair in ctx.remaining_accounts.chunks(2) {
+            let (policy_info, authority_info) = (&pair[0], &pair[1]);
+            let Ok(mut policy) = Account::<InsurancePolicy>::try_from(policy_info) else {
+                continue;
+            };
+            let eligible = policy_info.is_writable
+                && authority_info.is_writable
+                && policy.version == CURRENT_POLICY_VERSION
+                && policy.authority == authority_info.key()
+                && policy.is_backed_by(&pool_key, &pool_mint);
+            // A policy listed twice was written back as expired the first time
+            let pool_state = &mut ctx.accounts.pool_state;
+            if !eligible || !pool_state.close_expired(&mut policy, clock.unix_timestamp) {
+                continue;
+            }
+
+            return_keeper_reward(&mut policy, authority_info)?;
+            msg!("Policy expired: {}", policy.key());
+            if let Some(event) = policy.finalized_event(policy.key()) {
+                emit!(event);
+            }
+            policy.exit(&crate::ID)?;
+            expired += 1;
+        }
+
+        let pool_info = ctx.accounts.pool_state.to_account_info();
+        // Forfeited deposits are the pool authority's to withdraw, not the cranker's
+        let reserved = Rent::get()?
+            .minimum_balance(pool_info.data_len())
+            .saturating_add(ctx.accounts.pool_state.forfeited_deposits);
+        let fee = sweep_fee(expired, pool_info.lamports().saturating_sub(reserved));
+        if fee > 0 {
+            **pool_info.try_borrow_mut_lamports()? -= fee;
+            **ctx.accounts.cranker.try_borrow_mut_lamports()? += fee;
+        }
+        msg!("Swept {} expired policies, cranker fee: {}", expired, fee);
+        Ok(())
+    }
+
+    /// Read-only view of the policy's lifecycle timestamps and status, returned
+    /// to the caller as return data
+    pub fn get_policy_timeline(ctx: Context<GetPolicyTimeline>) -> Result<PolicyTimeline> {
+        let insurance_policy = &ctx.accounts.insurance_policy;
+        let mut timeline = insurance_policy.timeline();
+        // Report expiry as soon as it applies, not only once `expire_policy` has run
+        timeline.status = insurance_policy.status_at(Clock::get()?.unix_timestamp);
+        Ok(timeline)
+    }
+
+    /// Read-only view of the pool's capital, committed coverage, active policy
+    /// count and utilization, returned to the caller as return data
+    pub fn get_pool_metrics(ctx: Context<GetPoolMetrics>) -> Result<PoolMetrics> {
+        Ok(ctx.accounts.pool_state.metrics())
+    }
+
+    /// Rough probability of payout from the current oracle price (view, no state change)
+    pub fn estimate_payout_odds(ctx: Context<EstimatePayoutOdds>) -> Result<PayoutOdds> {
+        let insurance_policy = &ctx.accounts.insurance_policy;
+        let clock = Clock::get()?;
+        let price = load_oracle_price(
+            &insurance_policy.oracle_kind,
+            &ctx.accounts.oracle_account,
+            clock.unix_timestamp,
+            insurance_policy.max_price_age_seconds,
+        )?;
+        insurance_policy.estimate_payout_odds(&price)
+    }
+
+    /// Suggest a premium for `coverage_amount` against a trigger at
+    /// `trigger_threshold` (in `price_expo`), priced from the feed's current
+    /// reading; see `suggested_premium` for the model, scaled by the pool's
+    /// utilization multiplier. Read-only: the quote is handed back through the
+    /// instruction's return data.
+    pub fn quote_premium(
+        ctx: Context<QuotePremium>,
+        coverage_amount: u64,
+        trigger_condition: TriggerConditionType,
+        trigger_threshold: i64,
+        price_expo: i32,
+        oracle_kind: OracleKind,
+    ) -> Result<u64> {
+        let clock = Clock::get()?;
+        let price = load_oracle_price(
+            &oracle_kind,
+            &ctx.accounts.oracle_account,
+            clock.unix_timestamp,
+            DEFAULT_MAX_PRICE_AGE_SECONDS,
+        )?;
+        let price = FixedPrice::from_oracle(&price).normalize_to(price_expo)?.mantissa;
+        let premium =
+            suggested_premium(coverage_amount, trigger_condition, trigger_threshold, price)?;
+        let multiplier_bps = ctx.accounts.pool_state.premium_multiplier_bps(coverage_amount)?;
+        apply_bps(premium, multiplier_bps, RoundingMode::Ceil)
+    }
+
+    /// Define a reusable product whose bounds constrain every policy written from it
+    pub fn create_product(ctx: Context<CreateProduct>, product_id: u64, terms: ProductTerms) -> Result<()> {
+        require_role(&ctx.accounts.roles, Roles::UNDERWRITER)?;
+        terms.validate()?;
+
+        let product = &mut ctx.accounts.product;
+        product.authority = ctx.accounts.authority.key();
+        product.product_id = product_id;
+        product.terms = terms;
+        product.bump = ctx.bumps.product;
+
+        msg!("Product created: {}", product.key());
+        Ok(())
+    }
+
+    /// Replace a product's bounds; policies already written keep their own copy of the terms
+    pub fn update_product(ctx: Context<UpdateProduct>, terms: ProductTerms) -> Result<()> {
+        require_role(&ctx.accounts.roles, Roles::UNDERWRITER)?;
+        terms.validate()?;
+
+        let product = &mut ctx.accounts.product;
+        product.terms = terms;
+
+        msg!("Product updated: {}", product.key());
+        Ok(())
+    }
+
+    /// Initialize a policy from a product, checking every per-policy value against its bounds
+    pub fn initialize_from_product(
+        ctx: Context<InitializeFromProduct>,
+        bump: u8,
+        params: InitializeParams,
+    ) -> Result<()> {
+        let insurance_policy = &mut ctx.accounts.insurance_policy;
+        let clock = Clock::get()?;
+
+        let params = ctx.accounts.product.terms.apply(params, clock.unix_timestamp)?;
+        ctx.accounts.config.check_policy_duration(params.expiry_timestamp, clock.unix_timestamp)?;
+        enforce_mint_limits(
+            &ctx.accounts.premium_mint_limits,
+            &ctx.accounts.payout_mint_limits,
+            &params,
+        )?;
+        insurance_policy.initialize_numbered(
+            &mut ctx.accounts.config,
+            ctx.accounts.authority.key(),
+            ctx.accounts.policy_holder.key(),
+            bump,
+            &params,
+            clock.unix_timestamp,
+        )?;
+        insurance_policy.product = Some(ctx.accounts.product.key());
+        escrow_on_policy(
+            ctx.accounts.authority.to_account_info(),
+            insurance_policy.to_account_info(),
+            ctx.accounts.system_program.to_account_info(),
+            params.keeper_reward_lamports,
+        )?;
+
+        emit!(insurance_policy.initialized_event(insurance_policy.key()));
+        msg!("Insurance policy initialized from product: {}", insurance_policy.key());
         Ok(())
     }
-}
 
-#[derive(Accounts)]
-#[instruction(bump: u8)]
-pub struct Initialize<'info> {
-    #[account(mut)]
-    pub authority: Signer<'info>,
-    
-    /// CHECK: This is the policy holder address
-    pub policy_holder: AccountInfo<'info>,
-    
-    #[account(
-        init,
-        payer = authority,
-        space = InsurancePolicy::LEN,
-        seeds = [b"insurance_policy", authority.key().as_ref(), policy_holder.key().as_ref()],
-        bump
-    )]
-    pub insurance_policy: Account<'info, InsurancePolicy>,
-    
-    pub system_program: Program<'info, System>,
-}
+    /// Create the protocol-wide config; only the program's upgrade authority may,
+    /// and it becomes the config admin able to grant the first roles
+    pub fn initialize_config(ctx: Context<InitializeConfig>) -> Result<()> {
+        let config = &mut ctx.accounts.config;
+        config.admin = ctx.accounts.admin.key();
+        config.paused = false;
+        config.bump = ctx.bumps.config;
+
+        msg!("Config initialized with admin: {}", config.admin);
+        Ok(())
+    }
+
+    /// Hand the config admin seat to another key (ADMIN role)
+    pub fn update_config(ctx: Context<UpdateConfig>, new_admin: Pubkey) -> Result<()> {
+        require_role(&ctx.accounts.roles, Roles::ADMIN)?;
+        ctx.accounts.config.admin = new_admin;
+        log_admin_action(
+            &ctx.accounts.audit_log,
+            ctx.accounts.config.audit_log,
+            AuditAction::ConfigUpdate,
+            ctx.accounts.admin.key(),
+            new_admin,
+            0,
+        )?;
+
+        msg!("Config admin updated: {}", new_admin);
+        Ok(())
+    }
+
+    /// Set the protocol fee skimmed from premiums (ADMIN role). Where the fee is
+    /// sent only changes through the council's `UpdateTreasury` action.
+    pub fn update_protocol_config(ctx: Context<UpdateConfig>, protocol_fee_bps: u16) -> Result<()> {
+        require_role(&ctx.accounts.roles, Roles::ADMIN)?;
+        require!(
+            protocol_fee_bps <= MAX_PROTOCOL_FEE_BPS,
+            InsuranceError::InvalidPolicyParameters
+        );
+        let config = &mut ctx.accounts.config;
+        config.protocol_fee_bps = protocol_fee_bps;
+        let fee_recipient = config.fee_recipient;
+        log_admin_action(
+            &ctx.accounts.audit_log,
+            ctx.accounts.config.audit_log,
+            AuditAction::ConfigUpdate,
+            ctx.accounts.admin.key(),
+            fee_recipient,
+            protocol_fee_bps as u64,
+        )?;
+
+        msg!("Protocol fee set to {} bps", protocol_fee_bps);
+        Ok(())
+    }
+
+    /// Cap how far out a new policy's expiry may be; 0 lifts the cap (ADMIN role)
+    pub fn set_max_policy_duration(
+        ctx: Context<UpdateConfig>,
+        max_policy_duration_seconds: i64,
+    ) -> Result<()> {
+        require_role(&ctx.accounts.roles, Roles::ADMIN)?;
+        require!(max_policy_duration_seconds >= 0, InsuranceError::InvalidPolicyParameters);
+        ctx.accounts.config.max_policy_duration_seconds = max_policy_duration_seconds;
+        log_admin_action(
+            &ctx.accounts.audit_log,
+            ctx.accounts.config.audit_log,
+            AuditAction::ConfigUpdate,
+            ctx.accounts.admin.key(),
+            ctx.accounts.config.key(),
+            max_policy_duration_seconds as u64,
+        )?;
+
+        msg!("Max policy duration set to {} seconds", max_policy_duration_seconds);
+        Ok(())
+    }
+
+    /// Set the cut of premiums paid to referring integrators (ADMIN role)
+    pub fn set_referral_fee(ctx: Context<UpdateConfig>, referral_fee_bps: u16) -> Result<()> {
+        require_role(&ctx.accounts.roles, Roles::ADMIN)?;
+        require!(
+            referral_fee_bps <= MAX_REFERRAL_FEE_BPS,
+            InsuranceError::InvalidPolicyParameters
+        );
+        ctx.accounts.config.referral_fee_bps = referral_fee_bps;
+        log_admin_action(
+            &ctx.accounts.audit_log,
+            ctx.accounts.config.audit_log,
+            AuditAction::ConfigUpdate,
+            ctx.accounts.admin.key(),
+            ctx.accounts.config.key(),
+            referral_fee_bps as u64,
+        )?;
+
+        msg!("Referral fee set to {} bps", referral_fee_bps);
+        Ok(())
+    }
+
+    /// Set the deposit keepers escrow when their check triggers a payout, and how
+    /// long after the trigger a successful payout still refunds it (ADMIN role)
+    pub fn set_keeper_deposit(
+        ctx: Context<UpdateConfig>,
+        keeper_deposit_lamports: u64,
+        keeper_deposit_window_seconds: i64,
+    ) -> Result<()> {
+        require_role(&ctx.accounts.roles, Roles::ADMIN)?;
+        require!(keeper_deposit_window_seconds >= 0, InsuranceError::InvalidPolicyParameters);
+        let config = &mut ctx.accounts.config;
+        config.keeper_deposit_lamports = keeper_deposit_lamports;
+        config.keeper_deposit_window_seconds = keeper_deposit_window_seconds;
+        log_admin_action(
+            &ctx.accounts.audit_log,
+            ctx.accounts.config.audit_log,
+            AuditAction::ConfigUpdate,
+            ctx.accounts.admin.key(),
+            ctx.accounts.config.key(),
+            keeper_deposit_lamports,
+        )?;
+
+        msg!(
+            "Keeper deposit set to {} lamports, refundable for {} seconds",
+            keeper_deposit_lamports,
+            keeper_deposit_window_seconds
+        );
+        Ok(())
+    }
+
+    /// Let other programs CPI into purchases and trigger checks (ADMIN role)
+    pub fn set_allow_cpi(ctx: Context<UpdateConfig>, allow_cpi: bool) -> Result<()> {
+        require_role(&ctx.accounts.roles, Roles::ADMIN)?;
+        ctx.accounts.config.allow_cpi = allow_cpi;
+        log_admin_action(
+            &ctx.accounts.audit_log,
+            ctx.accounts.config.audit_log,
+            AuditAction::ConfigUpdate,
+            ctx.accounts.admin.key(),
+            ctx.accounts.config.key(),
+            allow_cpi as u64,
+        )?;
+
+        msg!("CPI into purchases and trigger checks allowed: {}", allow_cpi);
+        Ok(())
+    }
+
+    /// Set the smallest premium and coverage policies may be written for in a
+    /// mint, in its base units (ADMIN role)
+    pub fn set_mint_limits(
+        ctx: Context<SetMintLimits>,
+        min_premium_amount: u64,
+        min_coverage_amount: u64,
+    ) -> Result<()> {
+        require_role(&ctx.accounts.roles, Roles::ADMIN)?;
+        let mint_limits = &mut ctx.accounts.mint_limits;
+        mint_limits.mint = ctx.accounts.mint.key();
+        mint_limits.min_premium_amount = min_premium_amount;
+        mint_limits.min_coverage_amount = min_coverage_amount;
+        mint_limits.bump = ctx.bumps.mint_limits;
+        log_admin_action(
+            &ctx.accounts.audit_log,
+            ctx.accounts.config.audit_log,
+            AuditAction::ConfigUpdate,
+            ctx.accounts.admin.key(),
+            mint_limits.mint,
+            min_premium_amount,
+        )?;
+
+        msg!(
+            "Mint limits for {}: premium >= {}, coverage >= {}",
+            mint_limits.mint,
+            min_premium_amount,
+            min_coverage_amount
+        );
+        Ok(())
+    }
+
+    /// Replace the claim-free loyalty discount schedule (ADMIN role)
+    pub fn set_loyalty_schedule(ctx: Context<UpdateConfig>, tiers: Vec<LoyaltyTier>) -> Result<()> {
+        require_role(&ctx.accounts.roles, Roles::ADMIN)?;
+        ctx.accounts.config.set_loyalty_schedule(&tiers)?;
+        log_admin_action(
+            &ctx.accounts.audit_log,
+            ctx.accounts.config.audit_log,
+            AuditAction::ConfigUpdate,
+            ctx.accounts.admin.key(),
+            ctx.accounts.config.key(),
+            tiers.len() as u64,
+        )?;
+
+        msg!("Loyalty schedule set with {} tiers", tiers.len());
+        Ok(())
+    }
+
+    /// Add role flags to a grantee, creating their role account if needed
+    pub fn grant_role(ctx: Context<GrantRole>, grantee: Pubkey, flags: u8) -> Result<()> {
+        require!(
+            ctx.accounts.config.can_grant(
+                &ctx.accounts.granter.key(),
+                ctx.accounts.granter_roles.as_deref(),
+            ),
+            InsuranceError::Unauthorized
+        );
+        require!(flags != 0 && flags & !Roles::ALL == 0, InsuranceError::InvalidRole);
+
+        let roles = &mut ctx.accounts.grantee_roles;
+        roles.grantee = grantee;
+        roles.bump = ctx.bumps.grantee_roles;
+        roles.grant(flags);
+
+        msg!("Roles {:#04x} granted to {}", flags, grantee);
+        Ok(())
+    }
+
+    /// Remove role flags from a grantee; effective for the next instruction
+    pub fn revoke_role(ctx: Context<RevokeRole>, grantee: Pubkey, flags: u8) -> Result<()> {
+        require!(
+            ctx.accounts.config.can_grant(
+                &ctx.accounts.granter.key(),
+                ctx.accounts.granter_roles.as_deref(),
+            ),
+            InsuranceError::Unauthorized
+        );
+
+        ctx.accounts.grantee_roles.revoke(flags);
+
+        msg!("Roles {:#04x} revoked from {}", flags, grantee);
+        Ok(())
+    }
+
+    /// Seat the admin council (ADMIN role). Only possible while no council is
+    /// seated; afterwards the council governs itself through proposals.
+    pub fn configure_council(
+        ctx: Context<ConfigureCouncil>,
+        members: Vec<Pubkey>,
+        approval_threshold: u8,
+        proposal_ttl_seconds: i64,
+    ) -> Result<()> {
+        require_role(&ctx.accounts.roles, Roles::ADMIN)?;
+        let config = &mut ctx.accounts.config;
+        require!(config.council.is_empty(), InsuranceError::Unauthorized);
+        require!(
+            !members.is_empty()
+                && members.len() <= MAX_COUNCIL_MEMBERS
+                && approval_threshold as usize >= 1
+                && approval_threshold as usize <= members.len()
+                && proposal_ttl_seconds > 0,
+            InsuranceError::InvalidPolicyParameters
+        );
+
+        config.council = members;
+        config.approval_threshold = approval_threshold;
+        config.proposal_ttl_seconds = proposal_ttl_seconds;
+
+        msg!("Council seated with threshold {}", approval_threshold);
+        Ok(())
+    }
+
+    /// Propose a privileged action; the proposing council member's approval is counted
+    pub fn create_admin_action(ctx: Context<CreateAdminAction>, action: AdminAction) -> Result<()> {
+        let config = &mut ctx.accounts.config;
+        let clock = Clock::get()?;
+        let proposer = ctx.accounts.proposer.key();
+        require!(config.is_council_member(&proposer), InsuranceError::Unauthorized);
+
+        let proposal = &mut ctx.accounts.proposal;
+        proposal.id = config.next_proposal_id;
+        proposal.proposer = proposer;
+        proposal.action = action;
+        proposal.approvals = vec![proposer];
+        proposal.created_timestamp = clock.unix_timestamp;
+        proposal.expires_timestamp = clock
+            .unix_timestamp
+            .checked_add(config.proposal_ttl_seconds)
+            .ok_or(InsuranceError::MathOverflow)?;
+        proposal.bump = ctx.bumps.proposal;
+        config.next_proposal_id += 1;
+
+        msg!("Admin action {} proposed", proposal.id);
+        Ok(())
+    }
+
+    /// Add a council member's approval to a pending proposal
+    pub fn approve_admin_action(ctx: Context<ApproveAdminAction>) -> Result<()> {
+        let clock = Clock::get()?;
+        let proposal = &mut ctx.accounts.proposal;
+        proposal.approve(&ctx.accounts.config, ctx.accounts.approver.key(), clock.unix_timestamp)?;
+
+        msg!("Admin action {} approved ({})", proposal.id, proposal.approvals.len());
+        Ok(())
+    }
+
+    /// Run an approved proposal and close it
+    pub fn execute_admin_action(ctx: Context<ExecuteAdminAction>) -> Result<()> {
+        let clock = Clock::get()?;
+        let proposal = &ctx.accounts.proposal;
+        proposal.ensure_executable(&ctx.accounts.config, clock.unix_timestamp)?;
+
+        let executor = ctx.accounts.executor.key();
+        match proposal.action.clone() {
+            AdminAction::Pause { paused } => {
+                ctx.accounts.config.paused = paused;
+                log_admin_action(
+                    &ctx.accounts.audit_log,
+                    ctx.accounts.config.audit_log,
+                    AuditAction::ConfigUpdate,
+                    executor,
+                    ctx.accounts.config.key(),
+                    paused as u64,
+                )?;
+            }
+            AdminAction::UpdateTreasury { fee_recipient } => {
+                ctx.accounts.config.fee_recipient = fee_recipient;
+                log_admin_action(
+                    &ctx.accounts.audit_log,
+                    ctx.accounts.config.audit_log,
+                    AuditAction::ConfigUpdate,
+                    executor,
+                    fee_recipient,
+                    0,
+                )?;
+            }
+            AdminAction::ResolveDispute { policy, uphold_trigger, winning_evidence } => {
+                let insurance_policy = ctx
+                    .accounts
+                    .insurance_policy
+                    .as_mut()
+                    .ok_or(InsuranceError::MissingActionAccount)?;
+                require_keys_eq!(insurance_policy.key(), policy, InsuranceError::MissingActionAccount);
+                let pool_state = ctx
+                    .accounts
+                    .pool_state
+                    .as_mut()
+                    .ok_or(InsuranceError::MissingActionAccount)?;
+                require!(
+                    insurance_policy.is_backed_by(&pool_state.key(), &pool_state.mint),
+                    InsuranceError::MissingActionAccount
+                );
+                insurance_policy.record_dispute_ruling(uphold_trigger, winning_evidence)?;
+                insurance_policy.resolve_dispute(uphold_trigger, clock.unix_timestamp, executor)?;
+                // A rejected trigger no longer counts against the pool
+                if !uphold_trigger {
+                    pool_state.settle_obligation(insurance_policy.coverage_amount);
+                }
+                log_admin_action(
+                    &ctx.accounts.audit_log,
+                    pool_state.audit_log,
+                    AuditAction::DisputeResolution,
+                    executor,
+                    policy,
+                    uphold_trigger as u64,
+                )?;
+            }
+            AdminAction::EmergencyWithdraw { pool, amount, destination } => {
+                let pool_state = ctx
+                    .accounts
+                    .pool_state
+                    .as_mut()
+                    .ok_or(InsuranceError::MissingActionAccount)?;
+                require_keys_eq!(pool_state.key(), pool, InsuranceError::MissingActionAccount);
+                // Approval only queues the withdrawal; it still waits out the
+                // timelock and runs through `execute_emergency_withdraw`
+                require!(
+                    amount <= pool_state.withdrawable_capital(),
+                    InsuranceError::OutstandingPayoutObligations
+                );
+                let executable_at = pool_state.request_emergency_withdraw(
+                    amount,
+                    destination,
+                    clock.unix_timestamp,
+                )?;
+                emit!(EmergencyWithdrawRequested {
+                    pool,
+                    amount,
+                    destination,
+                    executable_at,
+                });
+                log_admin_action(
+                    &ctx.accounts.audit_log,
+                    pool_state.audit_log,
+                    AuditAction::EmergencyWithdraw,
+                    executor,
+                    destination,
+                    amount,
+                )?;
+            }
+        }
+
+        msg!("Admin action {} executed", proposal.id);
+        Ok(())
+    }
+
+    /// Register the audit log for a pool, or for protocol-wide changes when no
+    /// pool is passed (ADMIN role). The log account is allocated by the caller,
+    /// as it is too large to create through CPI, and each scope takes one log.
+    pub fn init_audit_log(ctx: Context<InitAuditLog>) -> Result<()> {
+        require_role(&ctx.accounts.roles, Roles::ADMIN)?;
+        let log_key = ctx.accounts.audit_log.key();
+        let scope = match ctx.accounts.pool_state.as_mut() {
+            Some(pool_state) => {
+                require_keys_eq!(
+                    pool_state.audit_log,
+                    Pubkey::default(),
+                    InsuranceError::AuditLogMismatch
+                );
+                pool_state.audit_log = log_key;
+                pool_state.key()
+            }
+            None => {
+                let config = &mut ctx.accounts.config;
+                require_keys_eq!(
+                    config.audit_log,
+                    Pubkey::default(),
+                    InsuranceError::AuditLogMismatch
+                );
+                config.audit_log = log_key;
+                config.key()
+            }
+        };
+        ctx.accounts.audit_log.load_init()?.scope = scope;
+
+        msg!("Audit log {} registered for {}", log_key, scope);
+        Ok(())
+    }
+
+    /// Read-only view of the newest `count` audit entries, oldest first,
+    /// returned to the caller as return data
+    pub fn read_audit_log(ctx: Context<ReadAuditLog>, count: u8) -> Result<Vec<AuditEntry>> {
+        require!(
+            (count as usize) <= MAX_AUDIT_ENTRIES_PER_READ,
+            InsuranceError::InvalidPolicyParameters
+        );
+        Ok(ctx.accounts.audit_log.load()?.latest(count as usize))
+    }
+
+    /// Create the insurer's pool state backing all of its policies
+    pub fn initialize_pool(
+        ctx: Context<InitializePool>,
+        reserve_ratio_bps: u16,
+        drawdown_window_seconds: i64,
+        max_drawdown_per_window: u64,
+    ) -> Result<()> {
+        require!(reserve_ratio_bps > 0, InsuranceError::InvalidPolicyParameters);
+        require!(
+            max_drawdown_per_window == 0 || drawdown_window_seconds > 0,
+            InsuranceError::InvalidPolicyParameters
+        );
+
+        let pool_state = &mut ctx.accounts.pool_state;
+        pool_state.authority = ctx.accounts.authority.key();
+        pool_state.mint = ctx.accounts.mint.key();
+        pool_state.vault = ctx.accounts.insurance_pool_token_account.key();
+        pool_state.payout_vault = ctx
+            .accounts
+            .payout_vault_token_account
+            .as_ref()
+            .map_or(pool_state.vault, |vault| vault.key());
+        pool_state.reserve_ratio_bps = reserve_ratio_bps;
+        pool_state.drawdown_window_seconds = drawdown_window_seconds;
+        pool_state.max_drawdown_per_window = max_drawdown_per_window;
+        pool_state.window_start_timestamp = Clock::get()?.unix_timestamp;
+        pool_state.bump = ctx.bumps.pool_state;
+
+        msg!("Pool initialized: {}", pool_state.key());
+        Ok(())
+    }
+
+    /// Add underwriting capital to the pool
+    pub fn deposit_capital(ctx: Context<DepositCapital>, amount: u64) -> Result<()> {
+        let cpi_accounts = Transfer {
+            from: ctx.accounts.authority_token_account.to_account_info(),
+            to: ctx.accounts.insurance_pool_token_account.to_account_info(),
+            authority: ctx.accounts.authority.to_account_info(),
+        };
+        let cpi_program = ctx.accounts.token_program.to_account_info();
+        let cpi_ctx = CpiContext::new(cpi_program, cpi_accounts);
+
+        token::transfer(cpi_ctx, amount)?;
+
+        let vault_key = ctx.accounts.insurance_pool_token_account.key();
+        ctx.accounts.pool_state.credit_capital(vault_key, amount)?;
+
+        msg!("Capital deposited into pool: {}", amount);
+        Ok(())
+    }
+
+    /// Queue a capital withdrawal, paid by `process_withdrawal_queue` once enough
+    /// capital is free of coverage backing. The queued amount stops backing new
+    /// policies straight away.
+    pub fn request_withdrawal(ctx: Context<RequestWithdrawal>, amount: u64) -> Result<()> {
+        let pool_state = &mut ctx.accounts.pool_state;
+        let index = pool_state.enqueue_withdrawal(amount)?;
+
+        let request = &mut ctx.accounts.withdrawal_request;
+        request.pool = pool_state.key();
+        request.owner = ctx.accounts.authority.key();
+        request.index = index;
+        request.amount = amount;
+        request.requested_at = Clock::get()?.unix_timestamp;
+        request.bump = ctx.bumps.withdrawal_request;
+
+        msg!("Withdrawal of {} queued at position {}", amount, index);
+        Ok(())
+    }
+
+    /// Withdraw a queued request; it is closed unpaid when the queue reaches it
+    pub fn cancel_withdrawal(ctx: Context<CancelWithdrawal>) -> Result<()> {
+        let request = &mut ctx.accounts.withdrawal_request;
+        request.cancelled = true;
+        ctx.accounts.pool_state.cancel_withdrawal(request.amount);
+
+        msg!("Withdrawal request {} cancelled", request.index);
+        Ok(())
+    }
+
+    /// Pay the request at the head of the withdrawal queue and close it, refunding
+    /// its rent to the owner. Anyone may crank it once liquidity frees up.
+    pub fn process_withdrawal_queue(ctx: Context<ProcessWithdrawalQueue>) -> Result<()> {
+        let request = &ctx.accounts.withdrawal_request;
+        let pool_state = &mut ctx.accounts.pool_state;
+        if request.cancelled {
+            pool_state.withdrawal_queue_head += 1;
+            msg!("Cancelled withdrawal request {} closed", request.index);
+            return Ok(());
+        }
+
+        let vault_balance = ctx.accounts.insurance_pool_token_account.amount;
+        pool_state.fill_withdrawal(request.amount, vault_balance)?;
+        let seeds = &[
+            b"pool".as_ref(),
+            pool_state.mint.as_ref(),
+            &[pool_state.bump],
+        ];
+        let signer = &[&seeds[..]];
+        let cpi_accounts = Transfer {
+            from: ctx.accounts.insurance_pool_token_account.to_account_info(),
+            to: ctx.accounts.owner_token_account.to_account_info(),
+            authority: pool_state.to_account_info(),
+        };
+        let cpi_program = ctx.accounts.token_program.to_account_info();
+        let cpi_ctx = CpiContext::new_with_signer(cpi_program, cpi_accounts, signer);
+        token::transfer(cpi_ctx, request.amount)?;
+
+        msg!("Withdrawal request {} paid: {}", request.index, request.amount);
+        Ok(())
+    }
+
+    /// Configure the utilization curve that scales premiums as the pool fills up
+    pub fn set_utilization_curve(
+        ctx: Context<SetUtilizationCurve>,
+        kink_bps: u16,
+        slope_bps: u32,
+        steep_slope_bps: u32,
+    ) -> Result<()> {
+        require!(kink_bps as u64 <= BPS_DENOMINATOR, InsuranceError::InvalidPolicyParameters);
+
+        let pool_state = &mut ctx.accounts.pool_state;
+        pool_state.utilization_kink_bps = kink_bps;
+        pool_state.utilization_slope_bps = slope_bps;
+        pool_state.utilization_steep_slope_bps = steep_slope_bps;
+
+        msg!(
+            "Utilization curve set: kink {} bps, slopes {}/{} bps",
+            kink_bps,
+            slope_bps,
+            steep_slope_bps
+        );
+        Ok(())
+    }
+
+    /// Set the stake, in the pool's mint, keepers must post to earn check rewards,
+    /// and how long a deregistered keeper waits for it back; a zero stake pays
+    /// any keeper (pool authority)
+    pub fn set_keeper_staking(
+        ctx: Context<SetKeeperStaking>,
+        keeper_stake_required: u64,
+        keeper_cooldown_seconds: i64,
+    ) -> Result<()> {
+        require!(keeper_cooldown_seconds >= 0, InsuranceError::InvalidPolicyParameters);
+
+        let pool_state = &mut ctx.accounts.pool_state;
+        pool_state.keeper_stake_required = keeper_stake_required;
+        pool_state.keeper_cooldown_seconds = keeper_cooldown_seconds;
+
+        msg!(
+            "Keeper stake set to {} with a {}s cooldown",
+            keeper_stake_required,
+            keeper_cooldown_seconds
+        );
+        Ok(())
+    }
+
+    /// Stake the pool's token as a keeper. The stake sits in a vault owned by
+    /// the registration PDA until the keeper deregisters or is slashed.
+    pub fn register_keeper(ctx: Context<RegisterKeeper>, amount: u64) -> Result<()> {
+        require!(
+            amount >= ctx.accounts.pool_state.keeper_stake_required,
+            InsuranceError::InsufficientKeeperStake
+        );
+        let cpi_accounts = Transfer {
+            from: ctx.accounts.keeper_token_account.to_account_info(),
+            to: ctx.accounts.stake_vault.to_account_info(),
+            authority: ctx.accounts.keeper.to_account_info(),
+        };
+        let cpi_program = ctx.accounts.token_program.to_account_info();
+        token::transfer(CpiContext::new(cpi_program, cpi_accounts), amount)?;
+
+        let registration = &mut ctx.accounts.keeper_registration;
+        registration.keeper = ctx.accounts.keeper.key();
+        registration.pool = ctx.accounts.pool_state.key();
+        registration.vault = ctx.accounts.stake_vault.key();
+        registration.stake = amount;
+        registration.unbonding_at = None;
+        registration.bump = ctx.bumps.keeper_registration;
+
+        msg!("Keeper registered with stake {}: {}", amount, registration.keeper);
+        Ok(())
+    }
+
+    /// Stop earning check rewards and start the cooldown after which
+    /// `withdraw_keeper_stake` returns the stake
+    pub fn deregister_keeper(ctx: Context<DeregisterKeeper>) -> Result<()> {
+        let clock = Clock::get()?;
+        let cooldown = ctx.accounts.pool_state.keeper_cooldown_seconds;
+        let registration = &mut ctx.accounts.keeper_registration;
+        let withdrawable_at = registration.begin_unbonding(clock.unix_timestamp, cooldown)?;
+
+        msg!("Keeper deregistered, stake withdrawable at {}", withdrawable_at);
+        Ok(())
+    }
+
+    /// Return a deregistered keeper's remaining stake once the cooldown has
+    /// elapsed, closing the registration
+    pub fn withdraw_keeper_stake(ctx: Context<WithdrawKeeperStake>) -> Result<()> {
+        let clock = Clock::get()?;
+        let registration = &ctx.accounts.keeper_registration;
+        registration.check_withdrawable(clock.unix_timestamp)?;
+
+        let pool = registration.pool;
+        let keeper = registration.keeper;
+        let seeds = &[b"keeper".as_ref(), pool.as_ref(), keeper.as_ref(), &[registration.bump]];
+        let signer = &[&seeds[..]];
+        let cpi_accounts = Transfer {
+            from: ctx.accounts.stake_vault.to_account_info(),
+            to: ctx.accounts.keeper_token_account.to_account_info(),
+            authority: registration.to_account_info(),
+        };
+        let cpi_program = ctx.accounts.token_program.to_account_info();
+        token::transfer(
+            CpiContext::new_with_signer(cpi_program, cpi_accounts, signer),
+            registration.stake,
+        )?;
+
+        msg!("Keeper stake of {} returned to {}", registration.stake, keeper);
+        Ok(())
+    }
+
+    /// Seize up to `amount` of a keeper's stake into the pool, e.g. for
+    /// submitting manipulated signed-report observations (ADMIN role)
+    pub fn slash_keeper(ctx: Context<SlashKeeper>, amount: u64) -> Result<()> {
+        require_role(&ctx.accounts.roles, Roles::ADMIN)?;
+        let registration = &mut ctx.accounts.keeper_registration;
+        let slashed = registration.slash(amount);
+
+        let pool = registration.pool;
+        let keeper = registration.keeper;
+        let seeds = &[b"keeper".as_ref(), pool.as_ref(), keeper.as_ref(), &[registration.bump]];
+        let signer = &[&seeds[..]];
+        let cpi_accounts = Transfer {
+            from: ctx.accounts.stake_vault.to_account_info(),
+            to: ctx.accounts.insurance_pool_token_account.to_account_info(),
+            authority: registration.to_account_info(),
+        };
+        let cpi_program = ctx.accounts.token_program.to_account_info();
+        token::transfer(CpiContext::new_with_signer(cpi_program, cpi_accounts, signer), slashed)?;
+
+        let pool_state = &mut ctx.accounts.pool_state;
+        pool_state.total_capital = pool_state.total_capital.saturating_add(slashed);
+
+        msg!("Keeper {} slashed by {}", keeper, slashed);
+        Ok(())
+    }
+
+    /// Open excess-of-loss cover on the pool, funded by `reinsurer` and paid
+    /// `premium_share_bps` of each reinsured policy's premium
+    pub fn create_reinsurance_pool(
+        ctx: Context<CreateReinsurancePool>,
+        reinsurer: Pubkey,
+        premium_share_bps: u16,
+    ) -> Result<()> {
+        require!(
+            premium_share_bps <= MAX_REINSURANCE_PREMIUM_SHARE_BPS,
+            InsuranceError::InvalidPolicyParameters
+        );
+
+        let reinsurance_pool = &mut ctx.accounts.reinsurance_pool;
+        reinsurance_pool.pool = ctx.accounts.pool_state.key();
+        reinsurance_pool.reinsurer = reinsurer;
+        reinsurance_pool.vault = ctx.accounts.reinsurance_vault.key();
+        reinsurance_pool.premium_share_bps = premium_share_bps;
+        reinsurance_pool.bump = ctx.bumps.reinsurance_pool;
+
+        msg!("Reinsurance pool created: {}", reinsurance_pool.key());
+        Ok(())
+    }
+
+    /// Add reinsurer capital to the reinsurance vault
+    pub fn deposit_reinsurance(ctx: Context<DepositReinsurance>, amount: u64) -> Result<()> {
+        let cpi_accounts = Transfer {
+            from: ctx.accounts.reinsurer_token_account.to_account_info(),
+            to: ctx.accounts.reinsurance_vault.to_account_info(),
+            authority: ctx.accounts.reinsurer.to_account_info(),
+        };
+        let cpi_program = ctx.accounts.token_program.to_account_info();
+        token::transfer(CpiContext::new(cpi_program, cpi_accounts), amount)?;
+
+        let reinsurance_pool = &mut ctx.accounts.reinsurance_pool;
+        reinsurance_pool.total_capital = reinsurance_pool
+            .total_capital
+            .checked_add(amount)
+            .ok_or(InsuranceError::MathOverflow)?;
+
+        msg!("Reinsurance capital deposited: {}", amount);
+        Ok(())
+    }
+
+    /// Return reinsurer capital. Claims above retention fail with
+    /// `ReinsuranceShortfall` once the vault can no longer cover them.
+    pub fn withdraw_reinsurance(ctx: Context<WithdrawReinsurance>, amount: u64) -> Result<()> {
+        let reinsurance_pool = &mut ctx.accounts.reinsurance_pool;
+        reinsurance_pool.withdraw(amount)?;
+
+        let seeds = &[
+            b"reinsurance_pool".as_ref(),
+            reinsurance_pool.pool.as_ref(),
+            &[reinsurance_pool.bump],
+        ];
+        let signer = &[&seeds[..]];
+        let cpi_accounts = Transfer {
+            from: ctx.accounts.reinsurance_vault.to_account_info(),
+            to: ctx.accounts.reinsurer_token_account.to_account_info(),
+            authority: reinsurance_pool.to_account_info(),
+        };
+        let cpi_program = ctx.accounts.token_program.to_account_info();
+        token::transfer(CpiContext::new_with_signer(cpi_program, cpi_accounts, signer), amount)?;
+
+        msg!("Reinsurance capital withdrawn: {}", amount);
+        Ok(())
+    }
+
+    /// Pay the reinsurer its share of a settled reinsured policy's premium out
+    /// of the premium vault. Anyone may crank it; each policy pays once.
+    pub fn sweep_reinsurance_premium(ctx: Context<SweepReinsurancePremium>) -> Result<()> {
+        let share = ctx
+            .accounts
+            .insurance_policy
+            .take_reinsurance_premium(&ctx.accounts.reinsurance_pool)?;
+
+        let pool_state = &mut ctx.accounts.pool_state;
+        if share > 0 {
+            let seeds = &[
+                b"pool".as_ref(),
+                pool_state.mint.as_ref(),
+                &[pool_state.bump],
+            ];
+            let signer = &[&seeds[..]];
+            let cpi_accounts = Transfer {
+                from: ctx.accounts.insurance_pool_token_account.to_account_info(),
+                to: ctx.accounts.reinsurer_token_account.to_account_info(),
+                authority: pool_state.to_account_info(),
+            };
+            let cpi_program = ctx.accounts.token_program.to_account_info();
+            token::transfer(CpiContext::new_with_signer(cpi_program, cpi_accounts, signer), share)?;
+        }
+        pool_state.total_capital = pool_state.total_capital.saturating_sub(share);
+        let reinsurance_pool = &mut ctx.accounts.reinsurance_pool;
+        reinsurance_pool.premiums_earned = reinsurance_pool.premiums_earned.saturating_add(share);
+
+        msg!(
+            "Reinsurance premium of {} swept for policy: {}",
+            share,
+            ctx.accounts.insurance_policy.key()
+        );
+        Ok(())
+    }
+
+    /// Announce a withdrawal from the premium vault, executable once the
+    /// timelock has elapsed. Only one request may be pending at a time.
+    pub fn request_emergency_withdraw(
+        ctx: Context<RequestEmergencyWithdraw>,
+        amount: u64,
+        destination: Pubkey,
+    ) -> Result<()> {
+        let clock = Clock::get()?;
+        let pool_state = &mut ctx.accounts.pool_state;
+        let executable_at = pool_state.request_emergency_withdraw(amount, destination, clock.unix_timestamp)?;
+
+        emit!(EmergencyWithdrawRequested {
+            pool: pool_state.key(),
+            amount,
+            destination,
+            executable_at,
+        });
+        msg!("EMERGENCY WITHDRAW REQUESTED: {} to {}, executable at {}", amount, destination, executable_at);
+        Ok(())
+    }
+
+    /// Perform a previously requested withdrawal after its timelock
+    pub fn execute_emergency_withdraw(ctx: Context<ExecuteEmergencyWithdraw>, amount: u64) -> Result<()> {
+        let clock = Clock::get()?;
+        let pool_state = &mut ctx.accounts.pool_state;
+        pool_state.check_emergency_withdraw(
+            amount,
+            ctx.accounts.destination_token_account.key(),
+            ctx.accounts.insurance_pool_token_account.amount,
+            clock.unix_timestamp,
+        )?;
+
+        let seeds = &[
+            b"pool".as_ref(),
+            pool_state.mint.as_ref(),
+            &[pool_state.bump],
+        ];
+        let signer = &[&seeds[..]];
+
+        let cpi_accounts = Transfer {
+            from: ctx.accounts.insurance_pool_token_account.to_account_info(),
+            to: ctx.accounts.destination_token_account.to_account_info(),
+            authority: pool_state.to_account_info(),
+        };
+        let cpi_program = ctx.accounts.token_program.to_account_info();
+        let cpi_ctx = CpiContext::new_with_signer(cpi_program, cpi_accounts, signer);
+
+        token::transfer(cpi_ctx, amount)?;
+
+        pool_state.total_capital = pool_state.total_capital.saturating_sub(amount);
+        pool_state.clear_emergency_withdraw();
+        log_admin_action(
+            &ctx.accounts.audit_log,
+            pool_state.audit_log,
+            AuditAction::EmergencyWithdraw,
+            ctx.accounts.authority.key(),
+            ctx.accounts.destination_token_account.key(),
+            amount,
+        )?;
+
+        msg!("Emergency withdraw executed: {}", amount);
+        Ok(())
+    }
+
+    /// Withdraw a pending emergency withdrawal request
+    pub fn cancel_emergency_withdraw(ctx: Context<CancelEmergencyWithdraw>) -> Result<()> {
+        let pool_state = &mut ctx.accounts.pool_state;
+        require!(
+            pool_state.emergency_withdraw_executable_at.is_some(),
+            InsuranceError::NoEmergencyWithdrawPending
+        );
+        pool_state.clear_emergency_withdraw();
+
+        emit!(EmergencyWithdrawCancelled { pool: pool_state.key() });
+        msg!("Emergency withdraw cancelled");
+        Ok(())
+    }
+
+    /// Send rounding dust left in the vault to the fee recipient once no
+    /// coverage is in force and nothing is owed to claimants
+    pub fn sweep_dust(ctx: Context<SweepDust>) -> Result<()> {
+        let pool_state = &ctx.accounts.pool_state;
+        let dust = pool_state.sweepable_dust(ctx.accounts.insurance_pool_token_account.amount)?;
+        if dust == 0 {
+            msg!("No dust to sweep");
+            return Ok(());
+        }
+
+        let seeds = &[
+            b"pool".as_ref(),
+            pool_state.mint.as_ref(),
+            &[pool_state.bump],
+        ];
+        let signer = &[&seeds[..]];
+
+        let cpi_accounts = Transfer {
+            from: ctx.accounts.insurance_pool_token_account.to_account_info(),
+            to: ctx.accounts.fee_recipient_token_account.to_account_info(),
+            authority: pool_state.to_account_info(),
+        };
+        let cpi_program = ctx.accounts.token_program.to_account_info();
+        let cpi_ctx = CpiContext::new_with_signer(cpi_program, cpi_accounts, signer);
+
+        token::transfer(cpi_ctx, dust)?;
+
+        msg!("Dust swept to fee recipient: {}", dust);
+        Ok(())
+    }
+
+    /// Pay the keeper deposits forfeited to the pool out to its authority
+    pub fn withdraw_forfeited_deposits(ctx: Context<WithdrawForfeitedDeposits>) -> Result<()> {
+        let pool_state = &mut ctx.accounts.pool_state;
+        let amount = pool_state.take_forfeited_deposits();
+        if amount == 0 {
+            msg!("No forfeited deposits to withdraw");
+            return Ok(());
+        }
+        **pool_state.to_account_info().try_borrow_mut_lamports()? -= amount;
+        **ctx.accounts.authority.try_borrow_mut_lamports()? += amount;
+
+        msg!("Forfeited deposits withdrawn: {}", amount);
+        Ok(())
+    }
+
+    /// Upgrade a policy written with an older account layout to the current schema.
+    ///
+    /// Pools used to live at `[b"pool_state", authority]` and back every policy of
+    /// that authority; they are now keyed by premium mint at `[b"pool", mint]`. A
+    /// single-pool deployment migrates by creating the mint's pool with
+    /// `initialize_pool`, moving its capital across with `emergency_withdraw` and
+    /// `deposit_capital`, and then migrating each policy here, which points it
+    /// at the pool for `settlement_mint`.
+    pub fn migrate_policy(
+        ctx: Context<MigratePolicy>,
+        price_expo: i32,
+        settlement_mint: Pubkey,
+    ) -> Result<()> {
+        require!(
+            price_expo.unsigned_abs() <= MAX_PRICE_EXPO_MAGNITUDE,
+            InsuranceError::InvalidPolicyParameters
+        );
+        let policy_info = ctx.accounts.insurance_policy.to_account_info();
+
+        let mut migrated = {
+            let data = policy_info.try_borrow_data()?;
+            require!(
+                data.len() < InsurancePolicy::LEN,
+                InsuranceError::PolicyAlreadyMigrated
+            );
+            InsurancePolicy::from_legacy_bytes(&data)?
+        };
+        require_keys_eq!(
+            migrated.authority,
+            ctx.accounts.authority.key(),
+            ErrorCode::ConstraintHasOne
+        );
+        // v1 and v2 compared raw feed mantissas, so the authority states the exponent
+        // the existing threshold was written against
+        migrated.price_expo = price_expo;
+        // v1 and v2 premiums and payouts shared the pool vault's mint
+        migrated.premium_mint = settlement_mint;
+        migrated.payout_mint = settlement_mint;
+        migrated.pool = pool_address(&settlement_mint);
+
+        // Authority covers the rent for the larger layout
+        let rent_shortfall = Rent::get()?
+            .minimum_balance(InsurancePolicy::LEN)
+            .saturating_sub(policy_info.lamports());
+        if rent_shortfall > 0 {
+            let cpi_accounts = system_program::Transfer {
+                from: ctx.accounts.authority.to_account_info(),
+                to: policy_info.clone(),
+            };
+            let cpi_ctx = CpiContext::new(ctx.accounts.system_program.to_account_info(), cpi_accounts);
+            system_program::transfer(cpi_ctx, rent_shortfall)?;
+        }
+
+        policy_info.realloc(InsurancePolicy::LEN, true)?;
+        let mut data = policy_info.try_borrow_mut_data()?;
+        migrated.try_serialize(&mut &mut data[..])?;
+
+        msg!("Policy migrated to version {}: {}", CURRENT_POLICY_VERSION, policy_info.key());
+        Ok(())
+    }
+
+    /// Point the policy at a new oracle account (admin function). The account
+    /// must be a feed of the policy's `OracleKind` with a fresh price right now.
+    pub fn update_oracle(ctx: Context<UpdateOracle>) -> Result<()> {
+        require_role(&ctx.accounts.roles, Roles::ORACLE_MANAGER)?;
+        let insurance_policy = &mut ctx.accounts.insurance_policy;
+        let new_oracle = &ctx.accounts.new_oracle_account;
+        let clock = Clock::get()?;
+
+        validate_oracle_account(
+            &insurance_policy.oracle_kind,
+            new_oracle,
+            clock.unix_timestamp,
+            insurance_policy.max_price_age_seconds,
+        )?;
+
+        let old_oracle = insurance_policy.oracle_address;
+        insurance_policy.oracle_address = new_oracle.key();
+        log_admin_action(
+            &ctx.accounts.audit_log,
+            ctx.accounts.pool_state.audit_log,
+            AuditAction::OracleSwap,
+            ctx.accounts.oracle_manager.key(),
+            insurance_policy.key(),
+            0,
+        )?;
+
+        emit!(OracleUpdated {
+            policy: insurance_policy.key(),
+            old_oracle,
+            new_oracle: new_oracle.key(),
+        });
+        msg!("Oracle address updated for policy: {}", insurance_policy.key());
+        Ok(())
+    }
+
+    /// Replace the link to the policy's legal terms (admin function, before purchase)
+    pub fn set_metadata_uri(ctx: Context<SetMetadataUri>, metadata_uri: Vec<u8>) -> Result<()> {
+        let insurance_policy = &mut ctx.accounts.insurance_policy;
+        insurance_policy.metadata_uri = parse_metadata_uri(&metadata_uri)?;
+
+        msg!("Metadata URI updated for policy: {}", insurance_policy.key());
+        Ok(())
+    }
+
+    /// Replace the policy-admin system's claim number (admin function, before purchase)
+    pub fn set_external_id(ctx: Context<SetExternalId>, external_id: u128) -> Result<()> {
+        let insurance_policy = &mut ctx.accounts.insurance_policy;
+        insurance_policy.external_id = external_id;
+
+        msg!("External id updated for policy {}: {}", insurance_policy.key(), external_id);
+        Ok(())
+    }
+
+    /// Re-price the trigger of a quoted policy (admin function, before purchase)
+    pub fn update_threshold(ctx: Context<UpdateThreshold>, trigger_threshold: i64) -> Result<()> {
+        let insurance_policy = &mut ctx.accounts.insurance_policy;
+        let clock = Clock::get()?;
+        insurance_policy.update_threshold(trigger_threshold, clock.unix_timestamp)?;
+
+        msg!(
+            "Trigger threshold updated for policy {}: {}",
+            insurance_policy.key(),
+            trigger_threshold
+        );
+        Ok(())
+    }
+
+    /// Create the price history buffer for a policy, sized for `capacity`
+    /// samples (at most `MAX_PRICE_HISTORY_CAPACITY`)
+    pub fn init_history(
+        ctx: Context<InitHistory>,
+        min_interval_seconds: i64,
+        capacity: u32,
+    ) -> Result<()> {
+        let insurance_policy = &mut ctx.accounts.insurance_policy;
+        ctx.accounts.price_history.set_inner(PriceHistory::new(
+            insurance_policy.key(),
+            min_interval_seconds,
+            capacity as usize,
+        )?);
+        insurance_policy.price_history = Some(ctx.accounts.price_history.key());
+
+        msg!(
+            "Price history of {} samples initialized for policy: {}",
+            capacity,
+            insurance_policy.key()
+        );
+        Ok(())
+    }
+
+    /// Append the current oracle price to a policy's history (permissionless)
+    pub fn record_observation(ctx: Context<RecordObservation>) -> Result<()> {
+        let insurance_policy = &ctx.accounts.insurance_policy;
+        let clock = Clock::get()?;
+
+        let price = load_oracle_price(
+            &insurance_policy.oracle_kind,
+            &ctx.accounts.oracle_account,
+            clock.unix_timestamp,
+            insurance_policy.max_price_age_seconds,
+        )?;
+        let history = &mut ctx.accounts.price_history;
+        history.push(price.publish_time, insurance_policy.normalize_price(&price)?)?;
+
+        msg!("Observation recorded: {} at {}", price.price, price.publish_time);
+        Ok(())
+    }
+}
+
+/// Move lamports from the authority onto the policy account, where keeper
+/// rewards and trigger deposits are held until they are paid out or released
+fn escrow_on_policy<'info>(
+    authority: AccountInfo<'info>,
+    insurance_policy: AccountInfo<'info>,
+    system_program: AccountInfo<'info>,
+    amount: u64,
+) -> Result<()> {
+    if amount == 0 {
+        return Ok(());
+    }
+    let cpi_accounts = system_program::Transfer {
+        from: authority,
+        to: insurance_policy,
+    };
+    system_program::transfer(CpiContext::new(system_program, cpi_accounts), amount)
+}
+
+/// Reject parameters below the minimums configured for the premium and payout
+/// mints. Either limits account may be uninitialized, in which case that mint
+/// has no minimum.
+fn enforce_mint_limits(
+    premium_mint_limits: &AccountInfo,
+    payout_mint_limits: &AccountInfo,
+    params: &InitializeParams,
+) -> Result<()> {
+    let payout_mint = params.payout_mint.unwrap_or(params.premium_mint);
+    if let Some(limits) = MintLimits::load(premium_mint_limits, &params.premium_mint)? {
+        limits.check_premium(params.premium_amount)?;
+    }
+    if let Some(limits) = MintLimits::load(payout_mint_limits, &payout_mint)? {
+        limits.check_coverage(params.coverage_amount)?;
+    }
+    Ok(())
+}
+
+/// `enforce_mint_limits` for every entry of a batch written from `template`,
+/// loading each mint's limits once
+fn enforce_batch_mint_limits(
+    premium_mint_limits: &AccountInfo,
+    payout_mint_limits: &AccountInfo,
+    template: &InitializeParams,
+    entries: &[BatchPolicyEntry],
+) -> Result<()> {
+    let payout_mint = template.payout_mint.unwrap_or(template.premium_mint);
+    let premium_limits = MintLimits::load(premium_mint_limits, &template.premium_mint)?;
+    let payout_limits = MintLimits::load(payout_mint_limits, &payout_mint)?;
+    for entry in entries {
+        if let Some(limits) = &premium_limits {
+            limits.check_premium(entry.premium_amount)?;
+        }
+        if let Some(limits) = &payout_limits {
+            limits.check_coverage(entry.coverage_amount)?;
+        }
+    }
+    Ok(())
+}
+
+/// Check that a batch payout destination is the policy holder's token account
+/// for the payout mint. Batches do not create token accounts.
+fn check_batch_destination(
+    policy: &InsurancePolicy,
+    destination: &AccountInfo,
+) -> std::result::Result<(), BatchPayoutResult> {
+    let valid = *destination.owner == token::ID
+        && destination.is_writable
+        && TokenAccount::try_deserialize(&mut &destination.data.borrow()[..]).is_ok_and(
+            |account| account.owner == policy.policy_holder && account.mint == policy.payout_mint,
+        );
+    if valid {
+        Ok(())
+    } else {
+        Err(BatchPayoutResult::InvalidDestination)
+    }
+}
+
+/// Append a privileged action to `audit_log`, which must be the log registered
+/// (`registered`) for the pool or config the action touched
+fn log_admin_action(
+    audit_log: &AccountLoader<AuditLog>,
+    registered: Pubkey,
+    action: AuditAction,
+    actor: Pubkey,
+    target: Pubkey,
+    amount: u64,
+) -> Result<()> {
+    require_keys_eq!(audit_log.key(), registered, InsuranceError::AuditLogMismatch);
+    let now = Clock::get()?.unix_timestamp;
+    audit_log.load_mut()?.record(action, actor, target, amount, now);
+    Ok(())
+}
+
+/// Return a triggering keeper's deposit once the payout it led to has gone out,
+/// or hand it to the pool if the payout came after the refund window
+fn settle_keeper_deposit<'info>(
+    insurance_policy: &mut Account<'info, InsurancePolicy>,
+    keeper: AccountInfo<'info>,
+    pool_state: &mut Account<'info, PoolState>,
+    now: i64,
+) -> Result<()> {
+    let (refund, forfeit) = insurance_policy.release_keeper_deposit(now);
+    let policy_info = insurance_policy.to_account_info();
+    **policy_info.try_borrow_mut_lamports()? -= refund + forfeit;
+    **keeper.try_borrow_mut_lamports()? += refund;
+    **pool_state.to_account_info().try_borrow_mut_lamports()? += forfeit;
+    pool_state.record_forfeited_deposit(forfeit)?;
+    if refund > 0 {
+        msg!("Keeper deposit returned: {}", refund);
+    }
+    if forfeit > 0 {
+        msg!("Keeper deposit forfeited to pool after the refund window: {}", forfeit);
+    }
+    Ok(())
+}
+
+/// Create a policy account at its PDA the way Anchor's `init` does, keeping any
+/// lamports already sent to the address and topping them up to rent exemption
+fn create_policy_account<'info>(
+    payer: AccountInfo<'info>,
+    account: AccountInfo<'info>,
+    system_program: AccountInfo<'info>,
+    seeds: &[&[u8]],
+) -> Result<()> {
+    let space = InsurancePolicy::LEN;
+    let rent = Rent::get()?.minimum_balance(space);
+    let signer = &[seeds];
+    if account.lamports() == 0 {
+        let cpi_accounts = system_program::CreateAccount { from: payer, to: account };
+        let cpi_ctx = CpiContext::new_with_signer(system_program, cpi_accounts, signer);
+        return system_program::create_account(cpi_ctx, rent, space as u64, &crate::ID);
+    }
+
+    let shortfall = rent.saturating_sub(account.lamports());
+    if shortfall > 0 {
+        let cpi_accounts = system_program::Transfer { from: payer, to: account.clone() };
+        system_program::transfer(CpiContext::new(system_program.clone(), cpi_accounts), shortfall)?;
+    }
+    let cpi_accounts = system_program::Allocate { account_to_allocate: account.clone() };
+    system_program::allocate(
+        CpiContext::new_with_signer(system_program.clone(), cpi_accounts, signer),
+        space as u64,
+    )?;
+    let cpi_accounts = system_program::Assign { account_to_assign: account };
+    system_program::assign(CpiContext::new_with_signer(system_program, cpi_accounts, signer), &crate::ID)
+}
+
+/// Closing a temporary wSOL account releases the unwrapped amount plus its
+/// rent deposit; returns the deposit owed back to whoever funded the account
+pub fn wrapped_sol_rent_refund(closed_lamports: u64, unwrapped_amount: u64) -> Result<u64> {
+    closed_lamports
+        .checked_sub(unwrapped_amount)
+        .ok_or(error!(InsuranceError::MathOverflow))
+}
+
+/// Send the protocol fee on a premium payment from `from` to the fee recipient
+/// and return it; the caller moves the rest into the pool. `signer` carries the
+/// pool's seeds when it pulls the payment as the holder's delegate.
+fn transfer_protocol_fee<'info>(
+    config: &GlobalConfig,
+    payment: u64,
+    from: &Account<'info, TokenAccount>,
+    authority: AccountInfo<'info>,
+    fee_recipient_token_account: Option<&Account<'info, TokenAccount>>,
+    token_program: &Program<'info, Token>,
+    signer: &[&[&[u8]]],
+) -> Result<u64> {
+    let protocol_fee = config.protocol_fee(payment)?;
+    if protocol_fee > 0 {
+        let fee_account = fee_recipient_token_account.ok_or(InsuranceError::InvalidFeeRecipient)?;
+        let cpi_accounts = Transfer {
+            from: from.to_account_info(),
+            to: fee_account.to_account_info(),
+            authority,
+        };
+        let cpi_program = token_program.to_account_info();
+        let cpi_ctx = CpiContext::new_with_signer(cpi_program, cpi_accounts, signer);
+        token::transfer(cpi_ctx, protocol_fee)?;
+    }
+    Ok(protocol_fee)
+}
+
+/// Shared by `purchase_policy` and `purchase_policy_for`: record the purchase
+/// price, reserve the coverage, and move the first installment from
+/// `funding_account` into the pool less the protocol fee and any referral cut
+#[allow(clippy::too_many_arguments)]
+fn complete_purchase<'info>(
+    insurance_policy: &mut Account<'info, InsurancePolicy>,
+    pool_state: &mut Account<'info, PoolState>,
+    config: &GlobalConfig,
+    oracle_account: &AccountInfo<'info>,
+    denominator_oracle_account: Option<&AccountInfo<'info>>,
+    funding_account: &Account<'info, TokenAccount>,
+    funding_authority: AccountInfo<'info>,
+    insurance_pool_token_account: &Account<'info, TokenAccount>,
+    fee_recipient_token_account: Option<&Account<'info, TokenAccount>>,
+    referrer_token_account: Option<&Account<'info, TokenAccount>>,
+    conversion_oracle_account: Option<&AccountInfo<'info>>,
+    token_program: &Program<'info, Token>,
+    loyalty_discount_bps: u16,
+    now: i64,
+) -> Result<()> {
+    // Check if policy is still active and not expired
+    insurance_policy.require_unexpired(now)?;
+    require!(
+        insurance_policy.status == PolicyStatus::Active,
+        InsuranceError::PolicyNotActive
+    );
+    insurance_policy.reprice_premium(conversion_oracle_account, now)?;
+    // Priced against the pool's utilization now, not when the policy was initialized
+    let multiplier_bps = pool_state.premium_multiplier_bps(insurance_policy.coverage_amount)?;
+    insurance_policy.apply_utilization_multiplier(multiplier_bps)?;
+    insurance_policy.apply_loyalty_discount(loyalty_discount_bps)?;
+
+    // Record the market state the coverage was sold against. Signed reports have
+    // no feed account to read; their first value arrives with an observation.
+    if !matches!(insurance_policy.oracle_kind, OracleKind::SignedReport { .. }) {
+        let purchase_price = load_oracle_price(
+            &insurance_policy.oracle_kind,
+            oracle_account,
+            now,
+            insurance_policy.max_price_age_seconds,
+        )?;
+        let purchase_price =
+            insurance_policy.apply_cross_rate(purchase_price, denominator_oracle_account, now)?;
+        insurance_policy.price_at_purchase = insurance_policy.normalize_price(&purchase_price)?;
+        insurance_policy.price_at_purchase_publish_time = purchase_price.publish_time;
+        // Relative conditions are meaningless against a zero baseline
+        require!(
+            !insurance_policy.trigger_condition.is_relative()
+                || insurance_policy.price_at_purchase != 0,
+            InsuranceError::InvalidOracleData
+        );
+        insurance_policy.check_not_in_the_money(&purchase_price)?;
+    }
+
+    // Reserve the coverage against pool capital before taking the premium
+    pool_state.commit(insurance_policy.coverage_amount)?;
+    pool_state.open_policy();
+
+    // The protocol fee is skimmed from the premium (or first installment) before it reaches the pool
+    let first_installment = insurance_policy.installment_amount(0);
+    let protocol_fee = transfer_protocol_fee(
+        config,
+        first_installment,
+        funding_account,
+        funding_authority.clone(),
+        fee_recipient_token_account,
+        token_program,
+        &[],
+    )?;
+    insurance_policy.protocol_fee_paid = protocol_fee;
+    let referrer = referrer_token_account.map(|account| account.owner);
+    let referral_fee = insurance_policy.referral_fee(config, referrer, first_installment)?;
+    let pool_share = first_installment - protocol_fee - referral_fee;
+    if let (Some(referrer_account), true) = (referrer_token_account, referral_fee > 0) {
+        let cpi_accounts = Transfer {
+            from: funding_account.to_account_info(),
+            to: referrer_account.to_account_info(),
+            authority: funding_authority.clone(),
+        };
+        let cpi_ctx = CpiContext::new(token_program.to_account_info(), cpi_accounts);
+        token::transfer(cpi_ctx, referral_fee)?;
+        insurance_policy.referrer = Some(referrer_account.owner);
+        insurance_policy.referral_fee_paid = referral_fee;
+    }
+
+    // Transfer the rest into the insurance pool
+    let cpi_accounts = Transfer {
+        from: funding_account.to_account_info(),
+        to: insurance_pool_token_account.to_account_info(),
+        authority: funding_authority.clone(),
+    };
+    let cpi_ctx = CpiContext::new(token_program.to_account_info(), cpi_accounts);
+
+    token::transfer(cpi_ctx, pool_share)?;
+    pool_state.total_capital = pool_state
+        .total_capital
+        .checked_add(pool_share)
+        .ok_or(InsuranceError::MathOverflow)?;
+
+    insurance_policy.apply_event(PolicyEvent::Purchase, now, funding_authority.key())?;
+    insurance_policy.purchased_timestamp = Some(now);
+    insurance_policy.installments_paid = 1;
+    insurance_policy.next_installment_due = insurance_policy.next_due_after(now);
+
+    emit!(PolicyPurchased {
+        policy: insurance_policy.key(),
+        policy_number: insurance_policy.policy_number,
+        policy_holder: insurance_policy.policy_holder,
+        payer: funding_authority.key(),
+        external_id: insurance_policy.external_id,
+        metadata_uri: insurance_policy.metadata_uri.clone(),
+    });
+    Ok(())
+}
+
+/// Close out a policy a trigger check finds past expiry: record `Expired` and
+/// retire its reservation. Returns whether it expired, in which case the check
+/// returns `Ok` so the transition persists instead of being reverted.
+fn close_if_expired(
+    insurance_policy: &mut Account<InsurancePolicy>,
+    pool_state: &mut PoolState,
+    now: i64,
+) -> bool {
+    if !pool_state.close_expired(insurance_policy, now) {
+        return false;
+    }
+    msg!("Policy expired: {}", insurance_policy.key());
+    if let Some(event) = insurance_policy.finalized_event(insurance_policy.key()) {
+        emit!(event);
+    }
+    true
+}
+
+/// Move a policy to `Lapsed`, retire its reservation and return its unspent
+/// keeper reward to `authority`
+fn lapse_policy_account<'info>(
+    insurance_policy: &mut Account<'info, InsurancePolicy>,
+    pool_state: &mut PoolState,
+    authority: &AccountInfo<'info>,
+    actor: Pubkey,
+    now: i64,
+) -> Result<()> {
+    insurance_policy.apply_event(PolicyEvent::Lapse, now, actor)?;
+    pool_state.retire(insurance_policy.coverage_amount);
+    return_keeper_reward(insurance_policy, authority)?;
+    msg!("Policy lapsed: {}", insurance_policy.key());
+    if let Some(event) = insurance_policy.finalized_event(insurance_policy.key()) {
+        emit!(event);
+    }
+    Ok(())
+}
+
+/// Hand the keeper reward escrow a policy still holds back to `authority` once
+/// it reaches a terminal state no keeper can earn the reward from
+fn return_keeper_reward<'info>(
+    insurance_policy: &mut Account<'info, InsurancePolicy>,
+    authority: &AccountInfo<'info>,
+) -> Result<()> {
+    let unspent_reward = insurance_policy.take_keeper_reward();
+    if unspent_reward > 0 {
+        let policy_info = insurance_policy.to_account_info();
+        **policy_info.try_borrow_mut_lamports()? -= unspent_reward;
+        **authority.try_borrow_mut_lamports()? += unspent_reward;
+        msg!("Unspent keeper reward returned: {}", unspent_reward);
+    }
+    Ok(())
+}
+
+/// Pay `amount` from the pool's `vault` split among the policy's beneficiaries,
+/// one destination token account per beneficiary in `destinations`, in order
+fn pay_beneficiaries<'info>(
+    insurance_policy: &InsurancePolicy,
+    amount: u64,
+    destinations: &[AccountInfo<'info>],
+    vault: &Account<'info, TokenAccount>,
+    pool_state: &Account<'info, PoolState>,
+    token_program: &Program<'info, Token>,
+) -> Result<()> {
+    require!(
+        destinations.len() == insurance_policy.beneficiaries.len(),
+        InsuranceError::InvalidBeneficiaryAccount
+    );
+    let seeds = &[
+        b"pool".as_ref(),
+        pool_state.mint.as_ref(),
+        &[pool_state.bump],
+    ];
+    let signer = &[&seeds[..]];
+
+    let shares = insurance_policy.split_payout(amount)?;
+    for (index, (share, destination)) in shares.into_iter().zip(destinations).enumerate() {
+        require_keys_eq!(*destination.owner, token::ID, InsuranceError::InvalidBeneficiaryAccount);
+        let token_account = TokenAccount::try_deserialize(&mut &destination.data.borrow()[..])?;
+        insurance_policy.check_beneficiary_destination(
+            index,
+            &token_account.owner,
+            &token_account.mint,
+        )?;
+
+        let cpi_accounts = Transfer {
+            from: vault.to_account_info(),
+            to: destination.clone(),
+            authority: pool_state.to_account_info(),
+        };
+        let cpi_ctx =
+            CpiContext::new_with_signer(token_program.to_account_info(), cpi_accounts, signer);
+        token::transfer(cpi_ctx, share)?;
+    }
+    Ok(())
+}
+
+/// Most policies `initialize_batch` creates at once, so a batch stays within a
+/// single transaction's account and compute limits
+pub const MAX_BATCH_POLICIES: usize = 8;
+
+/// Most policies one `sweep_expired` call takes
+pub const MAX_SWEEP_POLICIES: usize = 10;
+
+/// Lamports the pool pays a cranker per policy `sweep_expired` expires
+pub const SWEEP_FEE_LAMPORTS: u64 = 10_000;
+
+/// Cranker fee for `expired` swept policies, capped at the pool's spare lamports
+pub fn sweep_fee(expired: u64, spare_lamports: u64) -> u64 {
+    expired.saturating_mul(SWEEP_FEE_LAMPORTS).min(spare_lamports)
+}
+
+/// Per-holder terms for `initialize_batch`; everything else comes from the template
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, PartialEq, Eq, Debug)]
+pub struct BatchPolicyEntry {
+    pub policy_holder: Pubkey,
+    pub coverage_amount: u64,
+    pub premium_amount: u64,
+}
+
+/// Build every policy of a batch with its PDA address. Fails as a whole if the
+/// batch is empty or too large, a holder appears twice, or any entry's terms are
+/// invalid, so nothing is created for a batch that cannot fully succeed.
+pub fn build_policy_batch(
+    authority: &Pubkey,
+    template: &InitializeParams,
+    entries: &[BatchPolicyEntry],
+    now: i64,
+    program_id: &Pubkey,
+) -> Result<Vec<(Pubkey, InsurancePolicy)>> {
+    require!(
+        !entries.is_empty() && entries.len() <= MAX_BATCH_POLICIES,
+        InsuranceError::InvalidBatch
+    );
+    let mut policies = Vec::with_capacity(entries.len());
+    for (index, entry) in entries.iter().enumerate() {
+        require!(
+            entries[..index].iter().all(|earlier| earlier.policy_holder != entry.policy_holder),
+            InsuranceError::InvalidBatch
+        );
+        let (address, bump) = Pubkey::find_program_address(
+            &[b"insurance_policy", authority.as_ref(), entry.policy_holder.as_ref()],
+            program_id,
+        );
+        let params = InitializeParams {
+            coverage_amount: entry.coverage_amount,
+            premium_amount: entry.premium_amount,
+            ..template.clone()
+        };
+        let mut policy = InsurancePolicy::default();
+        policy.initialize_from_params(*authority, entry.policy_holder, bump, &params, now)?;
+        policies.push((address, policy));
+    }
+    Ok(policies)
+}
+
+/// Per-policy terms supplied at `initialize`
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Default)]
+pub struct InitializeParams {
+    pub oracle_address: Pubkey,
+    pub trigger_condition: TriggerConditionType,
+    /// Absolute price level, or bps of the baseline price for relative conditions
+    pub trigger_threshold: i64,
+    pub coverage_amount: u64,
+    pub premium_amount: u64,
+    pub expiry_timestamp: i64,
+    /// Number of premium installments; 1 means the premium is paid up front
+    pub installment_count: u8,
+    pub installment_interval_seconds: i64,
+    /// How long a missed installment may stay unpaid before the policy can be
+    /// lapsed; defaults to `INSTALLMENT_GRACE_PERIOD_SECONDS`
+    pub lapse_grace_seconds: Option<i64>,
+    /// Roll the policy into another term of the same length at expiry, paid by
+    /// `process_renewal` from the holder's delegated token account
+    pub auto_renew: bool,
+    /// Largest claim the primary pool pays; anything above is paid by the pool's
+    /// reinsurer. `None` leaves the policy fully retained.
+    pub retention_amount: Option<u64>,
+    /// Window after purchase in which cancellation refunds the full premium
+    pub cooling_off_seconds: i64,
+    pub cancellation_fee_bps: u16,
+    /// Cancellation is rejected until this long after purchase
+    pub min_holding_period_seconds: i64,
+    /// Statutory window after purchase in which cancellation is always allowed
+    /// and refunds in full, overriding the fee and the holding period
+    pub free_look_seconds: i64,
+    /// Whether coverage ramps up with installments paid
+    pub coverage_mode: CoverageMode,
+    /// Whether coverage shrinks over the coverage window
+    pub coverage_schedule: CoverageSchedule,
+    /// Payouts release linearly over this period; 0 pays out immediately
+    pub payout_vesting_seconds: i64,
+    /// Payout split; empty pays everything to the policy holder
+    pub beneficiaries: Vec<Beneficiary>,
+    /// Insured event window; defaults to creation time and expiry respectively
+    pub coverage_start_timestamp: Option<i64>,
+    pub coverage_end_timestamp: Option<i64>,
+    /// Oldest oracle publish time accepted at purchase and trigger checks
+    pub max_price_age_seconds: u64,
+    /// Program notified via CPI whenever this policy pays out
+    pub payout_callback_program: Option<Pubkey>,
+    pub oracle_kind: OracleKind,
+    /// Secondary feed of the same kind, used when the primary is stale or unreadable
+    pub fallback_oracle_address: Option<Pubkey>,
+    /// Further feeds tried in order after the fallback; unused slots are the default key
+    pub backup_oracles: [Pubkey; MAX_BACKUP_ORACLES],
+    /// Independent feeds that must agree before triggering; unused slots are the default key
+    pub quorum_oracles: [Pubkey; MAX_QUORUM_ORACLES],
+    /// How many `quorum_oracles` must satisfy the condition; 0 disables the quorum
+    pub required_agreements: u8,
+    /// Lets a sponsor buy the policy via `purchase_policy_for` without the holder co-signing
+    pub allow_third_party_purchase: bool,
+    /// Lamports the authority escrows on the policy to reward keepers
+    pub keeper_reward_lamports: u64,
+    /// Paid from the escrow to the signer of each accepted trigger check
+    pub keeper_reward_per_check_lamports: u64,
+    /// Minimum spacing between trigger checks; 0 allows back-to-back checks
+    pub min_check_interval_seconds: i64,
+    /// Largest move between consecutive observations accepted as a trigger; 0 disables
+    pub max_single_observation_move_bps: u16,
+    /// Minimum wait before a suspect observation can be confirmed
+    pub confirmation_delay_seconds: i64,
+    /// Plausible price range at the policy exponent; readings outside it are
+    /// rejected as bad ticks. A zero `sane_price_max` disables the band.
+    pub sane_price_min: i64,
+    pub sane_price_max: i64,
+    /// UTF-8 link to the policy's legal terms, at most `MAX_METADATA_URI_LEN` bytes
+    pub metadata_uri: Vec<u8>,
+    /// Claim number assigned by the off-chain policy-admin system
+    pub external_id: u128,
+    /// Lookback and minimum recorded samples for `VolatilityAbove`
+    pub volatility_window_seconds: i64,
+    pub min_samples: u16,
+    /// Second leg for `SpreadAbove`; the threshold is in bps of this feed's price
+    pub spread_oracle_address: Option<Pubkey>,
+    pub spread_oracle_kind: OracleKind,
+    /// Decimal exponent of `trigger_threshold`; observations are rescaled to it
+    pub price_expo: i32,
+    /// How long the condition must hold continuously before the policy triggers
+    pub min_trigger_duration_seconds: i64,
+    /// Margin, in bps of the threshold, a reading must clear it by to flip the
+    /// previous evaluation's outcome; 0 compares against the bare threshold
+    pub hysteresis_bps: u16,
+    /// Mint premiums and refunds are paid in
+    pub premium_mint: Pubkey,
+    /// Mint payouts settle in; defaults to the premium mint
+    pub payout_mint: Option<Pubkey>,
+    /// USD price feed for the payout token. When set, coverage is a USD amount
+    /// and payouts grow to preserve it if the token trades below $1.
+    pub payout_peg_oracle_address: Option<Pubkey>,
+    pub payout_peg_oracle_kind: OracleKind,
+    /// Feed pricing one coverage (payout) mint token in premium mint tokens. When
+    /// set, `premium_amount` is quoted in the coverage mint and converted into
+    /// the premium mint at purchase.
+    pub premium_conversion_oracle: Option<Pubkey>,
+    pub premium_conversion_oracle_kind: OracleKind,
+    /// Premium mint decimals minus coverage mint decimals
+    pub premium_decimals_shift: i8,
+    /// Accepted conversion rates at `CONVERSION_RATE_EXPO`, inclusive
+    pub premium_conversion_rate_min: i64,
+    pub premium_conversion_rate_max: i64,
+    /// Boolean feed (see `ConditionFeed`) that must read `true` alongside the
+    /// price trigger, e.g. "hurricane made landfall"
+    pub condition_oracle: Option<Pubkey>,
+    /// Sell coverage even when the trigger already holds at purchase
+    pub allow_in_the_money_purchase: bool,
+    /// Hold on payouts after the trigger for fraud review, separate from disputes
+    pub payout_delay_seconds: i64,
+    /// Wait after a check that did not trigger before the next may evaluate;
+    /// spaces oracle reads for slow markets, on top of `min_check_interval_seconds`
+    pub recheck_interval_seconds: i64,
+    /// Trigger on the cross rate `numerator / denominator` of two USD feeds
+    /// (e.g. SOL/USD over ETH/USD for SOL/ETH). Set both or neither; the
+    /// numerator must be `oracle_address`.
+    pub numerator_oracle: Option<Pubkey>,
+    pub denominator_oracle: Option<Pubkey>,
+}
+
+impl InitializeParams {
+    /// Configure these terms as a depeg policy: `PriceBelow` at
+    /// `peg * (10000 - depeg_bps) / 10000`, held for `min_duration_seconds`.
+    /// The duration must span at least one oracle heartbeat (the staleness bound).
+    pub fn into_depeg(
+        self,
+        peg_price: i64,
+        peg_exponent: i32,
+        depeg_bps: u16,
+        min_duration_seconds: i64,
+    ) -> Result<Self> {
+        require!(peg_price > 0, InsuranceError::InvalidPolicyParameters);
+        require!(
+            depeg_bps > 0 && (depeg_bps as u64) < BPS_DENOMINATOR,
+            InsuranceError::InvalidPolicyParameters
+        );
+        require!(
+            min_duration_seconds >= self.max_price_age_seconds as i64,
+            InsuranceError::InvalidPolicyParameters
+        );
+
+        let threshold = apply_bps(
+            peg_price as u64,
+            BPS_DENOMINATOR - depeg_bps as u64,
+            RoundingMode::Floor,
+        )?;
+        Ok(InitializeParams {
+            trigger_condition: TriggerConditionType::PriceBelow,
+            trigger_threshold: threshold as i64,
+            price_expo: peg_exponent,
+            min_trigger_duration_seconds: min_duration_seconds,
+            ..self
+        })
+    }
+
+    pub fn validate(&self, now: i64) -> Result<()> {
+        InsurancePolicy::validate_parameters(
+            now,
+            self.trigger_condition,
+            self.trigger_threshold,
+            self.expiry_timestamp,
+        )?;
+        InsurancePolicy::validate_installment_plan(
+            now,
+            self.expiry_timestamp,
+            self.installment_count,
+            self.installment_interval_seconds,
+        )?;
+        require!(self.cooling_off_seconds >= 0, InsuranceError::InvalidPolicyParameters);
+        require!(self.min_holding_period_seconds >= 0, InsuranceError::InvalidPolicyParameters);
+        require!(self.free_look_seconds >= 0, InsuranceError::InvalidPolicyParameters);
+        require!(self.min_check_interval_seconds >= 0, InsuranceError::InvalidPolicyParameters);
+        require!(
+            self.lapse_grace_seconds.map_or(true, |grace| grace >= 0),
+            InsuranceError::InvalidPolicyParameters
+        );
+        require!(self.payout_vesting_seconds >= 0, InsuranceError::InvalidPolicyParameters);
+        validate_beneficiaries(&self.beneficiaries)?;
+        // Vested tranches are claimed by the holder alone and are never split
+        require!(
+            self.beneficiaries.is_empty() || self.payout_vesting_seconds == 0,
+            InsuranceError::InvalidPolicyParameters
+        );
+        // The reinsurer pays the holder directly, so split and vested payouts can't be reinsured
+        if let Some(retention) = self.retention_amount {
+            require!(
+                retention < self.coverage_amount
+                    && self.beneficiaries.is_empty()
+                    && self.payout_vesting_seconds == 0,
+                InsuranceError::InvalidPolicyParameters
+            );
+        }
+        require!(
+            self.cancellation_fee_bps <= BPS_DENOMINATOR as u16,
+            InsuranceError::InvalidPolicyParameters
+        );
+        require!(self.max_price_age_seconds > 0, InsuranceError::InvalidPolicyParameters);
+        require!(self.confirmation_delay_seconds >= 0, InsuranceError::InvalidPolicyParameters);
+        require!(self.payout_delay_seconds >= 0, InsuranceError::InvalidPolicyParameters);
+        require!(self.recheck_interval_seconds >= 0, InsuranceError::InvalidPolicyParameters);
+        match (self.numerator_oracle, self.denominator_oracle) {
+            (None, None) => {}
+            // Quorum feeds would be compared as plain prices, not cross rates
+            (Some(numerator), Some(denominator)) => require!(
+                numerator == self.oracle_address
+                    && denominator != numerator
+                    && self.required_agreements == 0,
+                InsuranceError::InvalidPolicyParameters
+            ),
+            _ => return err!(InsuranceError::InvalidPolicyParameters),
+        }
+        require!(
+            self.sane_price_max == 0 || self.sane_price_min <= self.sane_price_max,
+            InsuranceError::InvalidPolicyParameters
+        );
+        if self.premium_conversion_oracle.is_some() {
+            require!(
+                self.premium_conversion_rate_min > 0
+                    && self.premium_conversion_rate_min <= self.premium_conversion_rate_max
+                    && (self.premium_decimals_shift.unsigned_abs() as u32)
+                        <= MAX_PRICE_EXPO_MAGNITUDE,
+                InsuranceError::InvalidPolicyParameters
+            );
+        }
+        parse_metadata_uri(&self.metadata_uri)?;
+        if self.trigger_condition == TriggerConditionType::VolatilityAbove {
+            require!(
+                self.volatility_window_seconds > 0 && self.min_samples >= 2,
+                InsuranceError::InvalidTriggerConfiguration
+            );
+        }
+        require!(
+            (self.trigger_condition == TriggerConditionType::SpreadAbove)
+                == self.spread_oracle_address.is_some(),
+            InsuranceError::InvalidTriggerConfiguration
+        );
+        require!(
+            !matches!(self.spread_oracle_kind, OracleKind::SignedReport { .. }),
+            InsuranceError::InvalidPolicyParameters
+        );
+        // Signed-report policies record no purchase price to measure a move from
+        require!(
+            !(matches!(self.oracle_kind, OracleKind::SignedReport { .. })
+                && self.trigger_condition.is_relative()),
+            InsuranceError::InvalidTriggerConfiguration
+        );
+        require!(
+            !matches!(self.payout_peg_oracle_kind, OracleKind::SignedReport { .. }),
+            InsuranceError::InvalidPolicyParameters
+        );
+        require!(
+            self.price_expo.unsigned_abs() <= MAX_PRICE_EXPO_MAGNITUDE,
+            InsuranceError::InvalidPolicyParameters
+        );
+        require!(self.min_trigger_duration_seconds >= 0, InsuranceError::InvalidPolicyParameters);
+        require!(
+            self.hysteresis_bps as u64 <= BPS_DENOMINATOR,
+            InsuranceError::InvalidTriggerConfiguration
+        );
+        // Configured backups must be contiguous so they line up with the remaining accounts
+        let configured = self.backup_oracles.iter().take_while(|key| **key != Pubkey::default()).count();
+        require!(
+            self.backup_oracles[configured..].iter().all(|key| *key == Pubkey::default()),
+            InsuranceError::InvalidTriggerConfiguration
+        );
+        let quorum = self.quorum_oracles.iter().take_while(|key| **key != Pubkey::default()).count();
+        require!(
+            self.quorum_oracles[quorum..].iter().all(|key| *key == Pubkey::default())
+                && self.required_agreements as usize <= quorum,
+            InsuranceError::InvalidTriggerConfiguration
+        );
+        let coverage_start = self.coverage_start_timestamp.unwrap_or(now);
+        let coverage_end = self.coverage_end_timestamp.unwrap_or(self.expiry_timestamp);
+        require!(
+            now <= coverage_start
+                && coverage_start < coverage_end
+                && coverage_end <= self.expiry_timestamp,
+            InsuranceError::InvalidPolicyParameters
+        );
+        Ok(())
+    }
+}
+
+#[derive(Accounts)]
+#[instruction(bump: u8)]
+pub struct Initialize<'info> {
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    
+    /// CHECK: This is the policy holder address
+    pub policy_holder: AccountInfo<'info>,
+    
+    #[account(
+        init,
+        payer = authority,
+        space = 8 + InsurancePolicy::INIT_SPACE,
+        seeds = [b"insurance_policy", authority.key().as_ref(), policy_holder.key().as_ref()],
+        bump
+    )]
+    pub insurance_policy: Account<'info, InsurancePolicy>,
+    
+    #[account(mut, seeds = [b"config"], bump = config.bump)]
+    pub config: Account<'info, GlobalConfig>,
+    
+    pub system_program: Program<'info, System>,
+    
+    /// CHECK: `MintLimits` address of the premium mint, possibly uninitialized;
+    /// validated in the instruction
+    pub premium_mint_limits: UncheckedAccount<'info>,
+    
+    /// CHECK: `MintLimits` address of the payout mint, possibly uninitialized;
+    /// validated in the instruction
+    pub payout_mint_limits: UncheckedAccount<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(
+    bump: u8,
+    params: InitializeParams,
+    merkle_root: [u8; 32],
+    total_weight: u64,
+    member_count: u32
+)]
+pub struct InitializeGroup<'info> {
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    
+    /// CHECK: The group's holder, e.g. the DAO treasury paying the premium
+    pub policy_holder: AccountInfo<'info>,
+    
+    #[account(
+        init,
+        payer = authority,
+        space = 8 + InsurancePolicy::INIT_SPACE,
+        seeds = [b"insurance_policy", authority.key().as_ref(), policy_holder.key().as_ref()],
+        bump
+    )]
+    pub insurance_policy: Account<'info, InsurancePolicy>,
+    
+    #[account(
+        init,
+        payer = authority,
+        space = GroupClaims::space(member_count),
+        seeds = [b"group_claims", insurance_policy.key().as_ref()],
+        bump
+    )]
+    pub group_claims: Account<'info, GroupClaims>,
+    
+    #[account(mut, seeds = [b"config"], bump = config.bump)]
+    pub config: Account<'info, GlobalConfig>,
+    
+    pub system_program: Program<'info, System>,
+    
+    /// CHECK: `MintLimits` address of the premium mint, possibly uninitialized;
+    /// validated in the instruction
+    pub premium_mint_limits: UncheckedAccount<'info>,
+    
+    /// CHECK: `MintLimits` address of the payout mint, possibly uninitialized;
+    /// validated in the instruction
+    pub payout_mint_limits: UncheckedAccount<'info>,
+}
+
+#[derive(Accounts)]
+pub struct ClaimGroupPayout<'info> {
+    pub member: Signer<'info>,
+    
+    #[account(
+        mut,
+        constraint = insurance_policy.status == PolicyStatus::TriggeredPayout @ InsuranceError::PayoutNotTriggered,
+        constraint = insurance_policy.version == CURRENT_POLICY_VERSION @ InsuranceError::PolicyNeedsMigration
+    )]
+    pub insurance_policy: Account<'info, InsurancePolicy>,
+    
+    #[account(
+        mut,
+        seeds = [b"group_claims", insurance_policy.key().as_ref()],
+        bump = group_claims.bump
+    )]
+    pub group_claims: Account<'info, GroupClaims>,
+    
+    #[account(
+        mut,
+        seeds = [b"pool", pool_state.mint.as_ref()],
+        bump = pool_state.bump,
+        constraint = insurance_policy.is_backed_by(&pool_state.key(), &pool_state.mint) @ InsuranceError::PoolMismatch
+    )]
+    pub pool_state: Account<'info, PoolState>,
+    
+    #[account(
+        mut,
+        constraint = member_token_account.owner == member.key() @ InsuranceError::WrongTokenAccountOwner,
+        constraint = member_token_account.mint == insurance_policy.payout_mint @ InsuranceError::WrongMint
+    )]
+    pub member_token_account: Account<'info, TokenAccount>,
+    
+    #[account(mut, address = pool_state.payout_vault)]
+    pub insurance_pool_token_account: Account<'info, TokenAccount>,
+    
+    /// CHECK: Policy authority; refunded any keeper deposit when the last slice is claimed
+    #[account(mut, address = insurance_policy.authority)]
+    pub authority: AccountInfo<'info>,
+    
+    #[account(seeds = [b"config"], bump = config.bump)]
+    pub config: Account<'info, GlobalConfig>,
+    
+    pub token_program: Program<'info, Token>,
+}
+
+#[derive(Accounts)]
+pub struct InitializeBatch<'info> {
+    /// Pays rent for every policy in the batch
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    
+    #[account(mut, seeds = [b"config"], bump = config.bump)]
+    pub config: Account<'info, GlobalConfig>,
+    
+    pub system_program: Program<'info, System>,
+    
+    /// CHECK: `MintLimits` address of the premium mint, possibly uninitialized;
+    /// validated in the instruction
+    pub premium_mint_limits: UncheckedAccount<'info>,
+    
+    /// CHECK: `MintLimits` address of the payout mint, possibly uninitialized;
+    /// validated in the instruction
+    pub payout_mint_limits: UncheckedAccount<'info>,
+}
+
+#[derive(Accounts)]
+pub struct PurchasePolicy<'info> {
+    #[account(mut)]
+    pub policy_holder: Signer<'info>,
+    
+    #[account(
+        mut,
+        has_one = policy_holder,
+        constraint = insurance_policy.status == PolicyStatus::Active,
+        constraint = insurance_policy.version == CURRENT_POLICY_VERSION @ InsuranceError::PolicyNeedsMigration
+    )]
+    pub insurance_policy: Account<'info, InsurancePolicy>,
+    
+    #[account(
+        mut,
+        seeds = [b"pool", pool_state.mint.as_ref()],
+        bump = pool_state.bump,
+        constraint = insurance_policy.is_backed_by(&pool_state.key(), &pool_state.mint) @ InsuranceError::PoolMismatch
+    )]
+    pub pool_state: Account<'info, PoolState>,
+    
+    /// CHECK: This is the oracle account that provides price data
+    #[account(address = insurance_policy.oracle_address @ InsuranceError::OracleMismatch)]
+    pub oracle_account: AccountInfo<'info>,
+    
+    /// CHECK: Required by cross-rate policies; validated against
+    /// `insurance_policy.denominator_oracle` in the instruction
+    pub denominator_oracle_account: Option<AccountInfo<'info>>,
+    
+    #[account(
+        mut,
+        constraint = policy_holder_token_account.mint == insurance_policy.premium_mint @ InsuranceError::WrongMint,
+        constraint = policy_holder_token_account.delegate.is_none() @ InsuranceError::DelegatedTokenAccount
+    )]
+    pub policy_holder_token_account: Account<'info, TokenAccount>,
+    
+    #[account(
+        mut,
+        address = pool_state.vault,
+        constraint = insurance_pool_token_account.mint == insurance_policy.premium_mint @ InsuranceError::WrongMint
+    )]
+    pub insurance_pool_token_account: Account<'info, TokenAccount>,
+    
+    #[account(seeds = [b"config"], bump = config.bump)]
+    pub config: Account<'info, GlobalConfig>,
+    
+    /// Required when the protocol fee is nonzero
+    #[account(
+        mut,
+        constraint = fee_recipient_token_account.owner == config.fee_recipient @ InsuranceError::InvalidFeeRecipient,
+        constraint = fee_recipient_token_account.mint == insurance_policy.premium_mint @ InsuranceError::WrongMint
+    )]
+    pub fee_recipient_token_account: Option<Account<'info, TokenAccount>>,
+    
+    /// Integrator that originated the sale; its owner is recorded as the referrer
+    #[account(
+        mut,
+        constraint = referrer_token_account.mint == insurance_policy.premium_mint @ InsuranceError::WrongMint
+    )]
+    pub referrer_token_account: Option<Account<'info, TokenAccount>>,
+    
+    #[account(
+        init_if_needed,
+        payer = policy_holder,
+        space = 8 + HolderStats::INIT_SPACE,
+        seeds = [b"holder_stats", policy_holder.key().as_ref()],
+        bump
+    )]
+    pub holder_stats: Account<'info, HolderStats>,
+    
+    pub token_program: Program<'info, Token>,
+    
+    pub system_program: Program<'info, System>,
+
+    /// CHECK: Required when the policy converts its premium; validated against
+    /// `insurance_policy.premium_conversion_oracle` in the instruction
+    pub premium_conversion_oracle_account: Option<AccountInfo<'info>>,
+    
+    /// CHECK: Instructions sysvar, used to reject calls made through CPI
+    #[account(address = sysvar_instructions::ID)]
+    pub instructions_sysvar: AccountInfo<'info>,
+    
+    /// Lender financing the premium in place of the holder; pass with its token account
+    pub premium_payer: Option<Signer<'info>>,
+    
+    #[account(
+        mut,
+        constraint = premium_payer_token_account.mint == insurance_policy.premium_mint @ InsuranceError::WrongMint,
+        constraint = premium_payer_token_account.delegate.is_none() @ InsuranceError::DelegatedTokenAccount
+    )]
+    pub premium_payer_token_account: Option<Account<'info, TokenAccount>>,
+}
+
+#[derive(Accounts)]
+pub struct PurchasePolicyFor<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    
+    /// CHECK: The insured; must sign unless `allow_third_party_purchase` is set
+    pub policy_holder: AccountInfo<'info>,
+    
+    #[account(
+        mut,
+        has_one = policy_holder,
+        constraint = insurance_policy.status == PolicyStatus::Active,
+        constraint = insurance_policy.version == CURRENT_POLICY_VERSION @ InsuranceError::PolicyNeedsMigration
+    )]
+    pub insurance_policy: Account<'info, InsurancePolicy>,
+    
+    #[account(
+        mut,
+        seeds = [b"pool", pool_state.mint.as_ref()],
+        bump = pool_state.bump,
+        constraint = insurance_policy.is_backed_by(&pool_state.key(), &pool_state.mint) @ InsuranceError::PoolMismatch
+    )]
+    pub pool_state: Account<'info, PoolState>,
+    
+    /// CHECK: This is the oracle account that provides price data
+    #[account(address = insurance_policy.oracle_address @ InsuranceError::OracleMismatch)]
+    pub oracle_account: AccountInfo<'info>,
+    
+    /// CHECK: Required by cross-rate policies; validated against
+    /// `insurance_policy.denominator_oracle` in the instruction
+    pub denominator_oracle_account: Option<AccountInfo<'info>>,
+    
+    #[account(
+        mut,
+        constraint = payer_token_account.owner == payer.key() @ InsuranceError::WrongTokenAccountOwner,
+        constraint = payer_token_account.mint == insurance_policy.premium_mint @ InsuranceError::WrongMint,
+        constraint = payer_token_account.delegate.is_none() @ InsuranceError::DelegatedTokenAccount
+    )]
+    pub payer_token_account: Account<'info, TokenAccount>,
+    
+    #[account(
+        mut,
+        address = pool_state.vault,
+        constraint = insurance_pool_token_account.mint == insurance_policy.premium_mint @ InsuranceError::WrongMint
+    )]
+    pub insurance_pool_token_account: Account<'info, TokenAccount>,
+    
+    #[account(seeds = [b"config"], bump = config.bump)]
+    pub config: Account<'info, GlobalConfig>,
+    
+    /// Required when the protocol fee is nonzero
+    #[account(
+        mut,
+        constraint = fee_recipient_token_account.owner == config.fee_recipient @ InsuranceError::InvalidFeeRecipient,
+        constraint = fee_recipient_token_account.mint == insurance_policy.premium_mint @ InsuranceError::WrongMint
+    )]
+    pub fee_recipient_token_account: Option<Account<'info, TokenAccount>>,
+    
+    pub token_program: Program<'info, Token>,
+
+    /// CHECK: Required when the policy converts its premium; validated against
+    /// `insurance_policy.premium_conversion_oracle` in the instruction
+    pub premium_conversion_oracle_account: Option<AccountInfo<'info>>,
+    
+    /// CHECK: Instructions sysvar, used to reject calls made through CPI
+    #[account(address = sysvar_instructions::ID)]
+    pub instructions_sysvar: AccountInfo<'info>,
+}
+
+#[derive(Accounts)]
+pub struct PurchasePolicyWithSol<'info> {
+    #[account(mut)]
+    pub policy_holder: Signer<'info>,
+    
+    #[account(
+        mut,
+        has_one = policy_holder,
+        constraint = insurance_policy.status == PolicyStatus::Active,
+        constraint = insurance_policy.premium_mint == native_mint.key() @ InsuranceError::WrongMint,
+        constraint = insurance_policy.version == CURRENT_POLICY_VERSION @ InsuranceError::PolicyNeedsMigration
+    )]
+    pub insurance_policy: Account<'info, InsurancePolicy>,
+    
+    #[account(
+        mut,
+        seeds = [b"pool", pool_state.mint.as_ref()],
+        bump = pool_state.bump,
+        constraint = insurance_policy.is_backed_by(&pool_state.key(), &pool_state.mint) @ InsuranceError::PoolMismatch
+    )]
+    pub pool_state: Account<'info, PoolState>,
+    
+    /// CHECK: This is the oracle account that provides price data
+    #[account(address = insurance_policy.oracle_address @ InsuranceError::OracleMismatch)]
+    pub oracle_account: AccountInfo<'info>,
+    
+    /// CHECK: Required by cross-rate policies; validated against
+    /// `insurance_policy.denominator_oracle` in the instruction
+    pub denominator_oracle_account: Option<AccountInfo<'info>>,
+    
+    /// Temporary account holding the wrapped premium; closed before the instruction returns
+    #[account(
+        init,
+        payer = policy_holder,
+        seeds = [b"wsol", insurance_policy.key().as_ref()],
+        bump,
+        token::mint = native_mint,
+        token::authority = policy_holder
+    )]
+    pub wrapped_sol_account: Account<'info, TokenAccount>,
+    
+    #[account(address = token::spl_token::native_mint::ID @ InsuranceError::WrongMint)]
+    pub native_mint: Account<'info, Mint>,
+    
+    #[account(
+        mut,
+        address = pool_state.vault,
+        constraint = insurance_pool_token_account.mint == insurance_policy.premium_mint @ InsuranceError::WrongMint
+    )]
+    pub insurance_pool_token_account: Account<'info, TokenAccount>,
+    
+    #[account(seeds = [b"config"], bump = config.bump)]
+    pub config: Account<'info, GlobalConfig>,
+    
+    /// Required when the protocol fee is nonzero
+    #[account(
+        mut,
+        constraint = fee_recipient_token_account.owner == config.fee_recipient @ InsuranceError::InvalidFeeRecipient,
+        constraint = fee_recipient_token_account.mint == insurance_policy.premium_mint @ InsuranceError::WrongMint
+    )]
+    pub fee_recipient_token_account: Option<Account<'info, TokenAccount>>,
+    
+    pub token_program: Program<'info, Token>,
+    
+    pub system_program: Program<'info, System>,
+    
+    /// CHECK: Instructions sysvar, used to reject calls made through CPI
+    #[account(address = sysvar_instructions::ID)]
+    pub instructions_sysvar: AccountInfo<'info>,
+}
+
+#[derive(Accounts)]
+pub struct PayInstallment<'info> {
+    #[account(mut)]
+    pub policy_holder: Signer<'info>,
+    
+    #[account(
+        mut,
+        has_one = policy_holder,
+        constraint = insurance_policy.status == PolicyStatus::Purchased,
+        constraint = insurance_policy.version == CURRENT_POLICY_VERSION @ InsuranceError::PolicyNeedsMigration
+    )]
+    pub insurance_policy: Account<'info, InsurancePolicy>,
+    
+    #[account(
+        mut,
+        seeds = [b"pool", pool_state.mint.as_ref()],
+        bump = pool_state.bump,
+        constraint = insurance_policy.is_backed_by(&pool_state.key(), &pool_state.mint) @ InsuranceError::PoolMismatch
+    )]
+    pub pool_state: Account<'info, PoolState>,
+    
+    #[account(
+        mut,
+        constraint = policy_holder_token_account.mint == insurance_policy.premium_mint @ InsuranceError::WrongMint
+    )]
+    pub policy_holder_token_account: Account<'info, TokenAccount>,
+    
+    #[account(
+        mut,
+        address = pool_state.vault,
+        constraint = insurance_pool_token_account.mint == insurance_policy.premium_mint @ InsuranceError::WrongMint
+    )]
+    pub insurance_pool_token_account: Account<'info, TokenAccount>,
+    
+    #[account(seeds = [b"config"], bump = config.bump)]
+    pub config: Account<'info, GlobalConfig>,
+    
+    /// Required when the protocol fee is nonzero
+    #[account(
+        mut,
+        constraint = fee_recipient_token_account.owner == config.fee_recipient @ InsuranceError::InvalidFeeRecipient,
+        constraint = fee_recipient_token_account.mint == insurance_policy.premium_mint @ InsuranceError::WrongMint
+    )]
+    pub fee_recipient_token_account: Option<Account<'info, TokenAccount>>,
+    
+    pub token_program: Program<'info, Token>,
+    
+    /// CHECK: Receives any unspent keeper reward if the policy lapses
+    #[account(mut, address = insurance_policy.authority)]
+    pub authority: AccountInfo<'info>,
+}
+
+#[derive(Accounts)]
+pub struct CollectPremium<'info> {
+    /// The authority or a keeper; the pull only succeeds once the installment is due
+    pub caller: Signer<'info>,
+    
+    #[account(
+        mut,
+        constraint = insurance_policy.status == PolicyStatus::Purchased @ InsuranceError::PolicyNotPurchased,
+        constraint = insurance_policy.version == CURRENT_POLICY_VERSION @ InsuranceError::PolicyNeedsMigration
+    )]
+    pub insurance_policy: Account<'info, InsurancePolicy>,
+    
+    #[account(
+        mut,
+        seeds = [b"pool", pool_state.mint.as_ref()],
+        bump = pool_state.bump,
+        constraint = insurance_policy.is_backed_by(&pool_state.key(), &pool_state.mint) @ InsuranceError::PoolMismatch
+    )]
+    pub pool_state: Account<'info, PoolState>,
+    
+    #[account(
+        mut,
+        constraint = policy_holder_token_account.owner == insurance_policy.policy_holder @ InsuranceError::WrongTokenAccountOwner,
+        constraint = policy_holder_token_account.mint == insurance_policy.premium_mint @ InsuranceError::WrongMint
+    )]
+    pub policy_holder_token_account: Account<'info, TokenAccount>,
+    
+    #[account(
+        mut,
+        address = pool_state.vault,
+        constraint = insurance_pool_token_account.mint == insurance_policy.premium_mint @ InsuranceError::WrongMint
+    )]
+    pub insurance_pool_token_account: Account<'info, TokenAccount>,
+    
+    #[account(seeds = [b"config"], bump = config.bump)]
+    pub config: Account<'info, GlobalConfig>,
+    
+    /// Required when the protocol fee is nonzero
+    #[account(
+        mut,
+        constraint = fee_recipient_token_account.owner == config.fee_recipient @ InsuranceError::InvalidFeeRecipient,
+        constraint = fee_recipient_token_account.mint == insurance_policy.premium_mint @ InsuranceError::WrongMint
+    )]
+    pub fee_recipient_token_account: Option<Account<'info, TokenAccount>>,
+    
+    pub token_program: Program<'info, Token>,
+    
+    /// CHECK: Receives any unspent keeper reward if the policy lapses
+    #[account(mut, address = insurance_policy.authority)]
+    pub authority: AccountInfo<'info>,
+}
+
+#[derive(Accounts)]
+pub struct ProcessRenewal<'info> {
+    /// The authority or a keeper; renewal only succeeds once the policy has expired
+    pub caller: Signer<'info>,
+    
+    #[account(
+        mut,
+        constraint = insurance_policy.status == PolicyStatus::Purchased @ InsuranceError::PolicyNotPurchased,
+        constraint = insurance_policy.version == CURRENT_POLICY_VERSION @ InsuranceError::PolicyNeedsMigration
+    )]
+    pub insurance_policy: Account<'info, InsurancePolicy>,
+    
+    #[account(
+        mut,
+        seeds = [b"pool", pool_state.mint.as_ref()],
+        bump = pool_state.bump,
+        constraint = insurance_policy.is_backed_by(&pool_state.key(), &pool_state.mint) @ InsuranceError::PoolMismatch
+    )]
+    pub pool_state: Account<'info, PoolState>,
+    
+    #[account(
+        mut,
+        constraint = policy_holder_token_account.owner == insurance_policy.policy_holder @ InsuranceError::WrongTokenAccountOwner,
+        constraint = policy_holder_token_account.mint == insurance_policy.premium_mint @ InsuranceError::WrongMint
+    )]
+    pub policy_holder_token_account: Account<'info, TokenAccount>,
+    
+    #[account(
+        mut,
+        address = pool_state.vault,
+        constraint = insurance_pool_token_account.mint == insurance_policy.premium_mint @ InsuranceError::WrongMint
+    )]
+    pub insurance_pool_token_account: Account<'info, TokenAccount>,
+    
+    pub token_program: Program<'info, Token>,
+    
+    /// CHECK: Receives any unspent keeper reward if the policy lapses
+    #[account(mut, address = insurance_policy.authority)]
+    pub authority: AccountInfo<'info>,
+}
+
+#[derive(Accounts)]
+pub struct CheckTriggerConditions<'info> {
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    
+    #[account(
+        mut,
+        has_one = authority,
+        constraint = (insurance_policy.status == PolicyStatus::Purchased
+            || insurance_policy.status == PolicyStatus::TriggeredPayout) @ InsuranceError::PolicyNotPurchased,
+        constraint = insurance_policy.version == CURRENT_POLICY_VERSION @ InsuranceError::PolicyNeedsMigration
+    )]
+    pub insurance_policy: Account<'info, InsurancePolicy>,
+    
+    #[account(
+        mut,
+        seeds = [b"pool", pool_state.mint.as_ref()],
+        bump = pool_state.bump,
+        constraint = insurance_policy.is_backed_by(&pool_state.key(), &pool_state.mint) @ InsuranceError::PoolMismatch
+    )]
+    pub pool_state: Account<'info, PoolState>,
+    
+    /// CHECK: This is the oracle account that provides price data
+    #[account(address = insurance_policy.oracle_address @ InsuranceError::OracleMismatch)]
+    pub oracle_account: AccountInfo<'info>,
+    
+    /// CHECK: Required by cross-rate policies; validated against
+    /// `insurance_policy.denominator_oracle` in the instruction
+    pub denominator_oracle_account: Option<AccountInfo<'info>>,
+    
+    /// CHECK: Validated against `insurance_policy.fallback_oracle_address` in the instruction
+    pub fallback_oracle_account: Option<AccountInfo<'info>>,
+    
+    /// Required for `VolatilityAbove`; validated against `insurance_policy.price_history`
+    pub price_history: Option<Account<'info, PriceHistory>>,
+    
+    /// CHECK: Required for `SpreadAbove`; validated against `insurance_policy.spread_oracle_address`
+    pub spread_oracle_account: Option<AccountInfo<'info>>,
+    
+    /// CHECK: Required when the policy has a condition feed; validated against
+    /// `insurance_policy.condition_oracle` and parsed as a `ConditionFeed`
+    pub condition_oracle_account: Option<AccountInfo<'info>>,
+    
+    #[account(seeds = [b"config"], bump = config.bump)]
+    pub config: Account<'info, GlobalConfig>,
+    
+    pub system_program: Program<'info, System>,
+    
+    /// The signer's stake; required to earn the check reward when the pool sets
+    /// `keeper_stake_required`
+    #[account(
+        seeds = [b"keeper", pool_state.key().as_ref(), authority.key().as_ref()],
+        bump = keeper_registration.bump
+    )]
+    pub keeper_registration: Option<Account<'info, KeeperRegistration>>,
+    
+    /// CHECK: Instructions sysvar, used to reject calls made through CPI
+    #[account(address = sysvar_instructions::ID)]
+    pub instructions_sysvar: AccountInfo<'info>,
+}
+
+#[derive(Accounts)]
+pub struct ManualTrigger<'info> {
+    pub authority: Signer<'info>,
+    
+    #[account(
+        mut,
+        has_one = authority @ InsuranceError::Unauthorized,
+        constraint = insurance_policy.version == CURRENT_POLICY_VERSION @ InsuranceError::PolicyNeedsMigration
+    )]
+    pub insurance_policy: Account<'info, InsurancePolicy>,
+    
+    #[account(
+        mut,
+        seeds = [b"pool", pool_state.mint.as_ref()],
+        bump = pool_state.bump,
+        constraint = insurance_policy.is_backed_by(&pool_state.key(), &pool_state.mint) @ InsuranceError::PoolMismatch
+    )]
+    pub pool_state: Account<'info, PoolState>,
+}
+
+#[derive(Accounts)]
+pub struct DisputeTrigger<'info> {
+    pub authority: Signer<'info>,
+    
+    #[account(
+        mut,
+        has_one = authority @ InsuranceError::Unauthorized,
+        constraint = insurance_policy.version == CURRENT_POLICY_VERSION @ InsuranceError::PolicyNeedsMigration
+    )]
+    pub insurance_policy: Account<'info, InsurancePolicy>,
+    
+    /// Receives the triggering keeper's deposit
+    #[account(
+        mut,
+        seeds = [b"pool", pool_state.mint.as_ref()],
+        bump = pool_state.bump,
+        constraint = insurance_policy.is_backed_by(&pool_state.key(), &pool_state.mint) @ InsuranceError::PoolMismatch
+    )]
+    pub pool_state: Account<'info, PoolState>,
+}
+
+#[derive(Accounts)]
+#[instruction(uri: String, hash: [u8; 32], role: EvidenceRole)]
+pub struct SubmitEvidence<'info> {
+    #[account(mut)]
+    pub submitter: Signer<'info>,
+    
+    #[account(
+        mut,
+        constraint = insurance_policy.status == PolicyStatus::Disputed @ InsuranceError::PolicyNotDisputed,
+        constraint = insurance_policy.version == CURRENT_POLICY_VERSION @ InsuranceError::PolicyNeedsMigration
+    )]
+    pub insurance_policy: Account<'info, InsurancePolicy>,
+    
+    #[account(
+        init,
+        payer = submitter,
+        space = 8 + Evidence::INIT_SPACE,
+        seeds = [
+            b"evidence",
+            insurance_policy.key().as_ref(),
+            submitter.key().as_ref(),
+            &[insurance_policy.evidence_count(role)],
+        ],
+        bump
+    )]
+    pub evidence: Account<'info, Evidence>,
+    
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct SubmitSignedObservation<'info> {
+    pub submitter: Signer<'info>,
+    
+    #[account(
+        mut,
+        constraint = (insurance_policy.status == PolicyStatus::Purchased
+            || insurance_policy.status == PolicyStatus::TriggeredPayout) @ InsuranceError::PolicyNotPurchased,
+        constraint = insurance_policy.version == CURRENT_POLICY_VERSION @ InsuranceError::PolicyNeedsMigration
+    )]
+    pub insurance_policy: Account<'info, InsurancePolicy>,
+    
+    #[account(
+        mut,
+        seeds = [b"pool", pool_state.mint.as_ref()],
+        bump = pool_state.bump,
+        constraint = insurance_policy.is_backed_by(&pool_state.key(), &pool_state.mint) @ InsuranceError::PoolMismatch
+    )]
+    pub pool_state: Account<'info, PoolState>,
+    
+    /// CHECK: Instructions sysvar, used to inspect the preceding ed25519 instruction
+    #[account(address = sysvar_instructions::ID)]
+    pub instructions_sysvar: AccountInfo<'info>,
+    
+    /// CHECK: Receives any unspent keeper reward if the policy lapses
+    #[account(mut, address = insurance_policy.authority)]
+    pub authority: AccountInfo<'info>,
+}
+
+#[derive(Accounts)]
+pub struct ExecutePayout<'info> {
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    
+    #[account(
+        mut,
+        has_one = authority,
+        constraint = insurance_policy.status == PolicyStatus::TriggeredPayout,
+        constraint = insurance_policy.version == CURRENT_POLICY_VERSION @ InsuranceError::PolicyNeedsMigration
+    )]
+    pub insurance_policy: Account<'info, InsurancePolicy>,
+    
+    #[account(
+        mut,
+        seeds = [b"pool", pool_state.mint.as_ref()],
+        bump = pool_state.bump,
+        constraint = insurance_policy.is_backed_by(&pool_state.key(), &pool_state.mint) @ InsuranceError::PoolMismatch
+    )]
+    pub pool_state: Account<'info, PoolState>,
+    
+    /// CHECK: Receives the payout
+    #[account(address = insurance_policy.policy_holder)]
+    pub policy_holder: AccountInfo<'info>,
+    
+    /// CHECK: The holder's ATA for the payout mint, created if missing; any token
+    /// account of theirs when `allow_custom_payout_account` is set. Validated in the instruction.
+    #[account(mut)]
+    pub policy_holder_token_account: UncheckedAccount<'info>,
+    
+    #[account(address = insurance_policy.payout_mint @ InsuranceError::WrongMint)]
+    pub payout_mint: Account<'info, Mint>,
+    
+    #[account(
+        mut,
+        address = pool_state.payout_vault,
+        constraint = insurance_pool_token_account.mint == insurance_policy.payout_mint @ InsuranceError::WrongMint
+    )]
+    pub insurance_pool_token_account: Account<'info, TokenAccount>,
+    
+    pub token_program: Program<'info, Token>,
+    
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    
+    pub system_program: Program<'info, System>,
+    
+    /// CHECK: Must match `insurance_policy.payout_callback_program`; only required when one is set
+    pub payout_callback_program: Option<AccountInfo<'info>>,
+    
+    /// CHECK: Required when the policy has a payout peg feed; validated against
+    /// `insurance_policy.payout_peg_oracle_address` in the instruction
+    pub payout_peg_oracle_account: Option<AccountInfo<'info>>,
+    
+    #[account(seeds = [b"config"], bump = config.bump)]
+    pub config: Account<'info, GlobalConfig>,
+    
+    /// Holder's purchase record, if they have one; a paid claim resets their loyalty tier
+    #[account(
+        mut,
+        seeds = [b"holder_stats", insurance_policy.policy_holder.as_ref()],
+        bump = holder_stats.bump
+    )]
+    pub holder_stats: Option<Account<'info, HolderStats>>,
+    
+    /// Required when the claim exceeds the policy's `retention_amount`
+    #[account(
+        mut,
+        seeds = [b"reinsurance_pool", pool_state.key().as_ref()],
+        bump = reinsurance_pool.bump
+    )]
+    pub reinsurance_pool: Option<Account<'info, ReinsurancePool>>,
+    
+    /// Checked against `reinsurance_pool.vault` in the instruction
+    #[account(mut)]
+    pub reinsurance_vault: Option<Account<'info, TokenAccount>>,
+}
+
+#[derive(Accounts)]
+pub struct ExecutePayoutsBatch<'info> {
+    /// Authority of every policy paid; refunded their keepers' deposits
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    
+    #[account(mut, seeds = [b"pool", pool_state.mint.as_ref()], bump = pool_state.bump)]
+    pub pool_state: Account<'info, PoolState>,
+    
+    #[account(mut, address = pool_state.payout_vault)]
+    pub insurance_pool_token_account: Account<'info, TokenAccount>,
+    
+    #[account(seeds = [b"config"], bump = config.bump)]
+    pub config: Account<'info, GlobalConfig>,
+    
+    pub token_program: Program<'info, Token>,
+}
+
+#[derive(Accounts)]
+pub struct SweepExpired<'info> {
+    /// Paid `SWEEP_FEE_LAMPORTS` per policy expired
+    #[account(mut)]
+    pub cranker: Signer<'info>,
+    
+    #[account(mut, seeds = [b"pool", pool_state.mint.as_ref()], bump = pool_state.bump)]
+    pub pool_state: Account<'info, PoolState>,
+}
+
+#[derive(Accounts)]
+pub struct ExecutePayoutUnwrap<'info> {
+    /// Funds the temporary wSOL account's rent, refunded in the same instruction
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    
+    #[account(
+        mut,
+        has_one = authority,
+        constraint = insurance_policy.status == PolicyStatus::TriggeredPayout @ InsuranceError::PayoutNotTriggered,
+        constraint = insurance_policy.payout_mint == native_mint.key() @ InsuranceError::WrongMint,
+        constraint = insurance_policy.version == CURRENT_POLICY_VERSION @ InsuranceError::PolicyNeedsMigration
+    )]
+    pub insurance_policy: Account<'info, InsurancePolicy>,
+    
+    #[account(
+        mut,
+        seeds = [b"pool", pool_state.mint.as_ref()],
+        bump = pool_state.bump,
+        constraint = insurance_policy.is_backed_by(&pool_state.key(), &pool_state.mint) @ InsuranceError::PoolMismatch
+    )]
+    pub pool_state: Account<'info, PoolState>,
+    
+    /// Receives the payout as lamports
+    #[account(mut, address = insurance_policy.policy_holder)]
+    pub policy_holder: SystemAccount<'info>,
+    
+    /// Temporary account the payout is unwrapped through; closed before the instruction returns
+    #[account(
+        init,
+        payer = authority,
+        seeds = [b"wsol", insurance_policy.key().as_ref()],
+        bump,
+        token::mint = native_mint,
+        token::authority = pool_state
+    )]
+    pub wrapped_sol_account: Account<'info, TokenAccount>,
+    
+    #[account(address = token::spl_token::native_mint::ID @ InsuranceError::WrongMint)]
+    pub native_mint: Account<'info, Mint>,
+    
+    #[account(
+        mut,
+        address = pool_state.payout_vault,
+        constraint = insurance_pool_token_account.mint == insurance_policy.payout_mint @ InsuranceError::WrongMint
+    )]
+    pub insurance_pool_token_account: Account<'info, TokenAccount>,
+    
+    pub token_program: Program<'info, Token>,
+    
+    pub system_program: Program<'info, System>,
+    
+    #[account(seeds = [b"config"], bump = config.bump)]
+    pub config: Account<'info, GlobalConfig>,
+    
+    /// Holder's purchase record, if they have one; a paid claim resets their loyalty tier
+    #[account(
+        mut,
+        seeds = [b"holder_stats", insurance_policy.policy_holder.as_ref()],
+        bump = holder_stats.bump
+    )]
+    pub holder_stats: Option<Account<'info, HolderStats>>,
+}
+
+#[derive(Accounts)]
+pub struct SetCustomPayoutAccountAllowed<'info> {
+    pub policy_holder: Signer<'info>,
+    
+    #[account(
+        mut,
+        has_one = policy_holder,
+        constraint = insurance_policy.version == CURRENT_POLICY_VERSION @ InsuranceError::PolicyNeedsMigration
+    )]
+    pub insurance_policy: Account<'info, InsurancePolicy>,
+}
+
+#[derive(Accounts)]
+pub struct SetBeneficiaries<'info> {
+    pub policy_holder: Signer<'info>,
+    
+    #[account(
+        mut,
+        has_one = policy_holder,
+        constraint = insurance_policy.version == CURRENT_POLICY_VERSION @ InsuranceError::PolicyNeedsMigration
+    )]
+    pub insurance_policy: Account<'info, InsurancePolicy>,
+}
+
+#[derive(Accounts)]
+pub struct TransferPolicy<'info> {
+    pub policy_holder: Signer<'info>,
+    
+    #[account(
+        mut,
+        has_one = policy_holder,
+        constraint = insurance_policy.version == CURRENT_POLICY_VERSION @ InsuranceError::PolicyNeedsMigration
+    )]
+    pub insurance_policy: Account<'info, InsurancePolicy>,
+}
+
+#[derive(Accounts)]
+pub struct SplitPolicy<'info> {
+    #[account(mut)]
+    pub policy_holder: Signer<'info>,
+    
+    #[account(
+        mut,
+        has_one = policy_holder,
+        constraint = insurance_policy.version == CURRENT_POLICY_VERSION @ InsuranceError::PolicyNeedsMigration
+    )]
+    pub insurance_policy: Account<'info, InsurancePolicy>,
+    
+    #[account(
+        init,
+        payer = policy_holder,
+        space = 8 + InsurancePolicy::INIT_SPACE,
+        seeds = [
+            b"policy_split",
+            insurance_policy.key().as_ref(),
+            &insurance_policy.split_count.to_le_bytes()
+        ],
+        bump
+    )]
+    pub child_policy: Account<'info, InsurancePolicy>,
+    
+    /// Counts the child among its active policies
+    #[account(
+        mut,
+        seeds = [b"pool", pool_state.mint.as_ref()],
+        bump = pool_state.bump,
+        constraint = insurance_policy.is_backed_by(&pool_state.key(), &pool_state.mint) @ InsuranceError::PoolMismatch
+    )]
+    pub pool_state: Account<'info, PoolState>,
+    
+    #[account(mut, seeds = [b"config"], bump = config.bump)]
+    pub config: Account<'info, GlobalConfig>,
+    
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct ClaimVestedPayout<'info> {
+    pub policy_holder: Signer<'info>,
+    
+    #[account(
+        mut,
+        has_one = policy_holder,
+        constraint = insurance_policy.version == CURRENT_POLICY_VERSION @ InsuranceError::PolicyNeedsMigration
+    )]
+    pub insurance_policy: Account<'info, InsurancePolicy>,
+    
+    #[account(
+        mut,
+        seeds = [b"pool", pool_state.mint.as_ref()],
+        bump = pool_state.bump,
+        constraint = insurance_policy.is_backed_by(&pool_state.key(), &pool_state.mint) @ InsuranceError::PoolMismatch
+    )]
+    pub pool_state: Account<'info, PoolState>,
+    
+    #[account(
+        mut,
+        constraint = policy_holder_token_account.mint == insurance_policy.payout_mint @ InsuranceError::WrongMint
+    )]
+    pub policy_holder_token_account: Account<'info, TokenAccount>,
+    
+    #[account(
+        mut,
+        address = pool_state.payout_vault,
+        constraint = insurance_pool_token_account.mint == insurance_policy.payout_mint @ InsuranceError::WrongMint
+    )]
+    pub insurance_pool_token_account: Account<'info, TokenAccount>,
+    
+    pub token_program: Program<'info, Token>,
+}
+
+#[derive(Accounts)]
+pub struct SettleOutstandingClaim<'info> {
+    pub authority: Signer<'info>,
+    
+    #[account(
+        mut,
+        has_one = authority,
+        constraint = insurance_policy.status == PolicyStatus::PartiallyPaid @ InsuranceError::NoOutstandingClaim,
+        constraint = insurance_policy.version == CURRENT_POLICY_VERSION @ InsuranceError::PolicyNeedsMigration
+    )]
+    pub insurance_policy: Account<'info, InsurancePolicy>,
+    
+    #[account(
+        mut,
+        seeds = [b"pool", pool_state.mint.as_ref()],
+        bump = pool_state.bump,
+        constraint = insurance_policy.is_backed_by(&pool_state.key(), &pool_state.mint) @ InsuranceError::PoolMismatch
+    )]
+    pub pool_state: Account<'info, PoolState>,
+    
+    #[account(
+        mut,
+        constraint = policy_holder_token_account.owner == insurance_policy.policy_holder @ InsuranceError::WrongTokenAccountOwner,
+        constraint = policy_holder_token_account.mint == insurance_policy.payout_mint @ InsuranceError::WrongMint
+    )]
+    pub policy_holder_token_account: Account<'info, TokenAccount>,
+    
+    #[account(
+        mut,
+        address = pool_state.payout_vault,
+        constraint = insurance_pool_token_account.mint == insurance_policy.payout_mint @ InsuranceError::WrongMint
+    )]
+    pub insurance_pool_token_account: Account<'info, TokenAccount>,
+    
+    pub token_program: Program<'info, Token>,
+}
+
+#[derive(Accounts)]
+pub struct CancelPolicy<'info> {
+    #[account(mut)]
+    pub policy_holder: Signer<'info>,
+    
+    #[account(
+        mut,
+        has_one = policy_holder,
+        constraint = insurance_policy.status == PolicyStatus::Purchased,
+        constraint = insurance_policy.version == CURRENT_POLICY_VERSION @ InsuranceError::PolicyNeedsMigration
+    )]
+    pub insurance_policy: Account<'info, InsurancePolicy>,
+    
+    #[account(
+        mut,
+        seeds = [b"pool", pool_state.mint.as_ref()],
+        bump = pool_state.bump,
+        constraint = insurance_policy.is_backed_by(&pool_state.key(), &pool_state.mint) @ InsuranceError::PoolMismatch
+    )]
+    pub pool_state: Account<'info, PoolState>,
+    
+    /// Belongs to whoever paid the premium: the holder, or the sponsor of a
+    /// `purchase_policy_for` purchase
+    #[account(
+        mut,
+        constraint = refund_token_account.owner == insurance_policy.refund_recipient() @ InsuranceError::WrongTokenAccountOwner,
+        constraint = refund_token_account.mint == insurance_policy.premium_mint @ InsuranceError::WrongMint
+    )]
+    pub refund_token_account: Account<'info, TokenAccount>,
+    
+    #[account(
+        mut,
+        address = pool_state.vault,
+        constraint = insurance_pool_token_account.mint == insurance_policy.premium_mint @ InsuranceError::WrongMint
+    )]
+    pub insurance_pool_token_account: Account<'info, TokenAccount>,
+    
+    pub token_program: Program<'info, Token>,
+    
+    /// CHECK: Receives any unspent keeper reward on cancellation
+    #[account(mut, address = insurance_policy.authority)]
+    pub authority: AccountInfo<'info>,
+}
+
+#[derive(Accounts)]
+pub struct ReduceCoverage<'info> {
+    pub policy_holder: Signer<'info>,
+    
+    #[account(
+        mut,
+        has_one = policy_holder,
+        constraint = insurance_policy.status == PolicyStatus::Purchased @ InsuranceError::PolicyNotPurchased,
+        constraint = insurance_policy.version == CURRENT_POLICY_VERSION @ InsuranceError::PolicyNeedsMigration
+    )]
+    pub insurance_policy: Account<'info, InsurancePolicy>,
+    
+    #[account(
+        mut,
+        seeds = [b"pool", pool_state.mint.as_ref()],
+        bump = pool_state.bump,
+        constraint = insurance_policy.is_backed_by(&pool_state.key(), &pool_state.mint) @ InsuranceError::PoolMismatch
+    )]
+    pub pool_state: Account<'info, PoolState>,
+    
+    /// Belongs to whoever paid the premium: the holder, or the sponsor of a
+    /// `purchase_policy_for` purchase
+    #[account(
+        mut,
+        constraint = refund_token_account.owner == insurance_policy.refund_recipient() @ InsuranceError::WrongTokenAccountOwner,
+        constraint = refund_token_account.mint == insurance_policy.premium_mint @ InsuranceError::WrongMint
+    )]
+    pub refund_token_account: Account<'info, TokenAccount>,
+    
+    #[account(
+        mut,
+        address = pool_state.vault,
+        constraint = insurance_pool_token_account.mint == insurance_policy.premium_mint @ InsuranceError::WrongMint
+    )]
+    pub insurance_pool_token_account: Account<'info, TokenAccount>,
+    
+    pub token_program: Program<'info, Token>,
+}
+
+#[derive(Accounts)]
+pub struct IncreaseCoverage<'info> {
+    pub policy_holder: Signer<'info>,
+    
+    #[account(
+        mut,
+        has_one = policy_holder,
+        constraint = insurance_policy.status == PolicyStatus::Purchased @ InsuranceError::PolicyNotPurchased,
+        constraint = insurance_policy.version == CURRENT_POLICY_VERSION @ InsuranceError::PolicyNeedsMigration
+    )]
+    pub insurance_policy: Account<'info, InsurancePolicy>,
+    
+    #[account(
+        mut,
+        seeds = [b"pool", pool_state.mint.as_ref()],
+        bump = pool_state.bump,
+        constraint = insurance_policy.is_backed_by(&pool_state.key(), &pool_state.mint) @ InsuranceError::PoolMismatch
+    )]
+    pub pool_state: Account<'info, PoolState>,
+    
+    #[account(
+        mut,
+        constraint = policy_holder_token_account.owner == policy_holder.key() @ InsuranceError::WrongTokenAccountOwner,
+        constraint = policy_holder_token_account.mint == insurance_policy.premium_mint @ InsuranceError::WrongMint
+    )]
+    pub policy_holder_token_account: Account<'info, TokenAccount>,
+    
+    #[account(
+        mut,
+        address = pool_state.vault,
+        constraint = insurance_pool_token_account.mint == insurance_policy.premium_mint @ InsuranceError::WrongMint
+    )]
+    pub insurance_pool_token_account: Account<'info, TokenAccount>,
+    
+    #[account(seeds = [b"config"], bump = config.bump)]
+    pub config: Account<'info, GlobalConfig>,
+    
+    pub token_program: Program<'info, Token>,
+}
+
+#[derive(Accounts)]
+pub struct ExpirePolicy<'info> {
+    #[account(
+        mut,
+        constraint = (insurance_policy.status == PolicyStatus::Active
+            || insurance_policy.status == PolicyStatus::Purchased
+            || insurance_policy.status == PolicyStatus::Expired) @ InsuranceError::PolicyNotActive,
+        constraint = insurance_policy.version == CURRENT_POLICY_VERSION @ InsuranceError::PolicyNeedsMigration
+    )]
+    pub insurance_policy: Account<'info, InsurancePolicy>,
+    
+    #[account(
+        mut,
+        seeds = [b"pool", pool_state.mint.as_ref()],
+        bump = pool_state.bump,
+        constraint = insurance_policy.is_backed_by(&pool_state.key(), &pool_state.mint) @ InsuranceError::PoolMismatch
+    )]
+    pub pool_state: Account<'info, PoolState>,
+    
+    /// CHECK: Receives any unspent keeper reward
+    #[account(mut, address = insurance_policy.authority)]
+    pub authority: AccountInfo<'info>,
+}
+
+#[derive(Accounts)]
+pub struct GetPolicyTimeline<'info> {
+    pub insurance_policy: Account<'info, InsurancePolicy>,
+}
+
+#[derive(Accounts)]
+pub struct GetPoolMetrics<'info> {
+    #[account(seeds = [b"pool", pool_state.mint.as_ref()], bump = pool_state.bump)]
+    pub pool_state: Account<'info, PoolState>,
+}
+
+#[derive(Accounts)]
+pub struct EstimatePayoutOdds<'info> {
+    pub insurance_policy: Account<'info, InsurancePolicy>,
+    
+    /// CHECK: This is the oracle account that provides price data
+    #[account(address = insurance_policy.oracle_address @ InsuranceError::OracleMismatch)]
+    pub oracle_account: AccountInfo<'info>,
+}
+
+#[derive(Accounts)]
+pub struct QuotePremium<'info> {
+    /// CHECK: Parsed according to the `oracle_kind` passed with the instruction
+    pub oracle_account: AccountInfo<'info>,
+    
+    /// Pool the policy would be written against; its utilization scales the quote
+    pub pool_state: Account<'info, PoolState>,
+}
+
+#[derive(Accounts)]
+#[instruction(product_id: u64)]
+pub struct CreateProduct<'info> {
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    
+    #[account(
+        init,
+        payer = authority,
+        space = 8 + ProductConfig::INIT_SPACE,
+        seeds = [b"product", authority.key().as_ref(), &product_id.to_le_bytes()],
+        bump
+    )]
+    pub product: Account<'info, ProductConfig>,
+    
+    #[account(seeds = [b"role", authority.key().as_ref()], bump = roles.bump)]
+    pub roles: Account<'info, Roles>,
+    
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct InitializeConfig<'info> {
+    #[account(mut)]
+    pub admin: Signer<'info>,
+    
+    #[account(
+        init,
+        payer = admin,
+        space = 8 + GlobalConfig::INIT_SPACE,
+        seeds = [b"config"],
+        bump
+    )]
+    pub config: Account<'info, GlobalConfig>,
+    
+    /// The program's data account, naming its upgrade authority
+    #[account(
+        seeds = [crate::ID.as_ref()],
+        bump,
+        seeds::program = bpf_loader_upgradeable::ID,
+        constraint = program_data.upgrade_authority_address == Some(admin.key()) @ InsuranceError::Unauthorized
+    )]
+    pub program_data: Account<'info, ProgramData>,
+    
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct UpdateConfig<'info> {
+    pub admin: Signer<'info>,
+    
+    #[account(seeds = [b"role", admin.key().as_ref()], bump = roles.bump)]
+    pub roles: Account<'info, Roles>,
+    
+    #[account(mut, seeds = [b"config"], bump = config.bump)]
+    pub config: Account<'info, GlobalConfig>,    
+    /// Audit log registered on the config
+    #[account(mut)]
+    pub audit_log: AccountLoader<'info, AuditLog>,
+}
+
+#[derive(Accounts)]
+pub struct InitAuditLog<'info> {
+    pub admin: Signer<'info>,
+    
+    #[account(seeds = [b"role", admin.key().as_ref()], bump = roles.bump)]
+    pub roles: Account<'info, Roles>,
+    
+    #[account(mut, seeds = [b"config"], bump = config.bump)]
+    pub config: Account<'info, GlobalConfig>,
+    
+    /// Pool the log is for; protocol-wide when omitted
+    #[account(mut, seeds = [b"pool", pool_state.mint.as_ref()], bump = pool_state.bump)]
+    pub pool_state: Option<Account<'info, PoolState>>,
+    
+    #[account(zero)]
+    pub audit_log: AccountLoader<'info, AuditLog>,
+}
+
+#[derive(Accounts)]
+pub struct ReadAuditLog<'info> {
+    pub audit_log: AccountLoader<'info, AuditLog>,
+}
+
+#[derive(Accounts)]
+pub struct ConfigureCouncil<'info> {
+    pub admin: Signer<'info>,
+    
+    #[account(seeds = [b"role", admin.key().as_ref()], bump = roles.bump)]
+    pub roles: Account<'info, Roles>,
+    
+    #[account(mut, seeds = [b"config"], bump = config.bump)]
+    pub config: Account<'info, GlobalConfig>,
+}
+
+#[derive(Accounts)]
+pub struct CreateAdminAction<'info> {
+    #[account(mut)]
+    pub proposer: Signer<'info>,
+    
+    #[account(mut, seeds = [b"config"], bump = config.bump)]
+    pub config: Account<'info, GlobalConfig>,
+    
+    #[account(
+        init,
+        payer = proposer,
+        space = 8 + AdminProposal::INIT_SPACE,
+        seeds = [b"admin_action", &config.next_proposal_id.to_le_bytes()],
+        bump
+    )]
+    pub proposal: Account<'info, AdminProposal>,
+    
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct ApproveAdminAction<'info> {
+    pub approver: Signer<'info>,
+    
+    #[account(seeds = [b"config"], bump = config.bump)]
+    pub config: Account<'info, GlobalConfig>,
+    
+    #[account(mut, seeds = [b"admin_action", &proposal.id.to_le_bytes()], bump = proposal.bump)]
+    pub proposal: Account<'info, AdminProposal>,
+}
+
+#[derive(Accounts)]
+pub struct ExecuteAdminAction<'info> {
+    pub executor: Signer<'info>,
+    
+    #[account(mut, seeds = [b"config"], bump = config.bump)]
+    pub config: Account<'info, GlobalConfig>,
+    
+    #[account(
+        mut,
+        seeds = [b"admin_action", &proposal.id.to_le_bytes()],
+        bump = proposal.bump,
+        close = proposer
+    )]
+    pub proposal: Account<'info, AdminProposal>,
+    
+    /// CHECK: Receives the proposal's rent
+    #[account(mut, address = proposal.proposer)]
+    pub proposer: AccountInfo<'info>,
+    
+    /// Required by `ResolveDispute`, together with the policy's pool
+    #[account(mut)]
+    pub insurance_policy: Option<Account<'info, InsurancePolicy>>,
+    
+    /// Required by `EmergencyWithdraw`
+    #[account(mut)]
+    pub pool_state: Option<Account<'info, PoolState>>,
+    
+    /// Audit log of the pool the action touches, or the config's for protocol-wide actions
+    #[account(mut)]
+    pub audit_log: AccountLoader<'info, AuditLog>,
+}
+
+#[derive(Accounts)]
+#[instruction(grantee: Pubkey)]
+pub struct GrantRole<'info> {
+    #[account(mut)]
+    pub granter: Signer<'info>,
+    
+    #[account(seeds = [b"config"], bump = config.bump)]
+    pub config: Account<'info, GlobalConfig>,
+    
+    /// Omitted when the config admin grants without holding a role account
+    #[account(seeds = [b"role", granter.key().as_ref()], bump = granter_roles.bump)]
+    pub granter_roles: Option<Account<'info, Roles>>,
+    
+    #[account(
+        init_if_needed,
+        payer = granter,
+        space = 8 + Roles::INIT_SPACE,
+        seeds = [b"role", grantee.as_ref()],
+        bump
+    )]
+    pub grantee_roles: Account<'info, Roles>,
+    
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct SetMintLimits<'info> {
+    #[account(mut)]
+    pub admin: Signer<'info>,
+    
+    #[account(seeds = [b"role", admin.key().as_ref()], bump = roles.bump)]
+    pub roles: Account<'info, Roles>,
+    
+    pub mint: Account<'info, Mint>,
+    
+    #[account(
+        init_if_needed,
+        payer = admin,
+        space = 8 + MintLimits::INIT_SPACE,
+        seeds = [b"mint_limits", mint.key().as_ref()],
+        bump
+    )]
+    pub mint_limits: Account<'info, MintLimits>,
+    
+    pub system_program: Program<'info, System>,
+    
+    #[account(seeds = [b"config"], bump = config.bump)]
+    pub config: Account<'info, GlobalConfig>,    
+    /// Audit log registered on the config
+    #[account(mut)]
+    pub audit_log: AccountLoader<'info, AuditLog>,
+}
+
+#[derive(Accounts)]
+#[instruction(grantee: Pubkey)]
+pub struct RevokeRole<'info> {
+    pub granter: Signer<'info>,
+    
+    #[account(seeds = [b"config"], bump = config.bump)]
+    pub config: Account<'info, GlobalConfig>,
+    
+    /// Omitted when the config admin revokes without holding a role account
+    #[account(seeds = [b"role", granter.key().as_ref()], bump = granter_roles.bump)]
+    pub granter_roles: Option<Account<'info, Roles>>,
+    
+    #[account(mut, seeds = [b"role", grantee.as_ref()], bump = grantee_roles.bump)]
+    pub grantee_roles: Account<'info, Roles>,
+}
+
+#[derive(Accounts)]
+pub struct UpdateProduct<'info> {
+    pub authority: Signer<'info>,
+    
+    #[account(mut, has_one = authority @ InsuranceError::Unauthorized)]
+    pub product: Account<'info, ProductConfig>,
+    
+    #[account(seeds = [b"role", authority.key().as_ref()], bump = roles.bump)]
+    pub roles: Account<'info, Roles>,
+}
+
+#[derive(Accounts)]
+#[instruction(bump: u8, params: InitializeParams)]
+pub struct InitializeFromProduct<'info> {
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    
+    /// CHECK: This is the policy holder address
+    pub policy_holder: AccountInfo<'info>,
+    
+    #[account(has_one = authority @ InsuranceError::Unauthorized)]
+    pub product: Account<'info, ProductConfig>,
+    
+    #[account(
+        init,
+        payer = authority,
+        space = 8 + InsurancePolicy::INIT_SPACE,
+        seeds = [b"insurance_policy", authority.key().as_ref(), policy_holder.key().as_ref()],
+        bump
+    )]
+    pub insurance_policy: Account<'info, InsurancePolicy>,
+    
+    #[account(mut, seeds = [b"config"], bump = config.bump)]
+    pub config: Account<'info, GlobalConfig>,
+    
+    pub system_program: Program<'info, System>,
+    
+    /// CHECK: `MintLimits` address of the premium mint, possibly uninitialized;
+    /// validated in the instruction
+    pub premium_mint_limits: UncheckedAccount<'info>,
+    
+    /// CHECK: `MintLimits` address of the payout mint, possibly uninitialized;
+    /// validated in the instruction
+    pub payout_mint_limits: UncheckedAccount<'info>,
+}
+
+#[derive(Accounts)]
+pub struct InitializePool<'info> {
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    
+    /// Premium mint; one pool exists per mint
+    pub mint: Account<'info, Mint>,
+    
+    #[account(
+        init,
+        payer = authority,
+        space = 8 + PoolState::INIT_SPACE,
+        seeds = [b"pool", mint.key().as_ref()],
+        bump
+    )]
+    pub pool_state: Account<'info, PoolState>,
+    
+    /// Pool vault; premiums and capital are held here under the pool PDA's authority
+    #[account(
+        constraint = insurance_pool_token_account.owner == pool_state.key(),
+        constraint = insurance_pool_token_account.mint == mint.key() @ InsuranceError::WrongMint
+    )]
+    pub insurance_pool_token_account: Account<'info, TokenAccount>,
+    
+    /// Vault payouts are made from when they settle in a different mint than premiums
+    #[account(constraint = payout_vault_token_account.owner == pool_state.key())]
+    pub payout_vault_token_account: Option<Account<'info, TokenAccount>>,
+    
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct DepositCapital<'info> {
+    pub authority: Signer<'info>,
+    
+    #[account(
+        mut,
+        has_one = authority,
+        seeds = [b"pool", pool_state.mint.as_ref()],
+        bump = pool_state.bump
+    )]
+    pub pool_state: Account<'info, PoolState>,
+    
+    #[account(mut)]
+    pub authority_token_account: Account<'info, TokenAccount>,
+    
+    /// Either the premium vault or the payout vault
+    #[account(
+        mut,
+        constraint = insurance_pool_token_account.key() == pool_state.vault
+            || insurance_pool_token_account.key() == pool_state.payout_vault @ InsuranceError::WrongMint
+    )]
+    pub insurance_pool_token_account: Account<'info, TokenAccount>,
+    
+    pub token_program: Program<'info, Token>,
+}
+
+#[derive(Accounts)]
+pub struct RequestWithdrawal<'info> {
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    
+    #[account(
+        mut,
+        has_one = authority,
+        seeds = [b"pool", pool_state.mint.as_ref()],
+        bump = pool_state.bump
+    )]
+    pub pool_state: Account<'info, PoolState>,
+    
+    #[account(
+        init,
+        payer = authority,
+        space = 8 + WithdrawalRequest::INIT_SPACE,
+        seeds = [
+            b"withdrawal_request",
+            pool_state.key().as_ref(),
+            &pool_state.withdrawal_queue_tail.to_le_bytes()
+        ],
+        bump
+    )]
+    pub withdrawal_request: Account<'info, WithdrawalRequest>,
+    
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct CancelWithdrawal<'info> {
+    pub owner: Signer<'info>,
+    
+    #[account(
+        mut,
+        has_one = owner,
+        constraint = !withdrawal_request.cancelled @ InsuranceError::InvalidPolicyParameters
+    )]
+    pub withdrawal_request: Account<'info, WithdrawalRequest>,
+    
+    #[account(mut, address = withdrawal_request.pool)]
+    pub pool_state: Account<'info, PoolState>,
+}
+
+#[derive(Accounts)]
+pub struct ProcessWithdrawalQueue<'info> {
+    #[account(
+        mut,
+        seeds = [b"pool", pool_state.mint.as_ref()],
+        bump = pool_state.bump
+    )]
+    pub pool_state: Account<'info, PoolState>,
+    
+    /// Only the head of the queue can be processed
+    #[account(
+        mut,
+        close = owner,
+        seeds = [
+            b"withdrawal_request",
+            pool_state.key().as_ref(),
+            &pool_state.withdrawal_queue_head.to_le_bytes()
+        ],
+        bump = withdrawal_request.bump
+    )]
+    pub withdrawal_request: Account<'info, WithdrawalRequest>,
+    
+    /// CHECK: Receives the request account's rent
+    #[account(mut, address = withdrawal_request.owner)]
+    pub owner: AccountInfo<'info>,
+    
+    #[account(
+        mut,
+        constraint = owner_token_account.owner == withdrawal_request.owner @ InsuranceError::WrongTokenAccountOwner,
+        constraint = owner_token_account.mint == insurance_pool_token_account.mint @ InsuranceError::WrongMint
+    )]
+    pub owner_token_account: Account<'info, TokenAccount>,
+    
+    #[account(mut, address = pool_state.vault)]
+    pub insurance_pool_token_account: Account<'info, TokenAccount>,
+    
+    pub token_program: Program<'info, Token>,
+}
+
+#[derive(Accounts)]
+pub struct SetUtilizationCurve<'info> {
+    pub authority: Signer<'info>,
+    
+    #[account(
+        mut,
+        has_one = authority,
+        seeds = [b"pool", pool_state.mint.as_ref()],
+        bump = pool_state.bump
+    )]
+    pub pool_state: Account<'info, PoolState>,
+}
+
+#[derive(Accounts)]
+pub struct SetKeeperStaking<'info> {
+    pub authority: Signer<'info>,
+    
+    #[account(
+        mut,
+        has_one = authority,
+        seeds = [b"pool", pool_state.mint.as_ref()],
+        bump = pool_state.bump
+    )]
+    pub pool_state: Account<'info, PoolState>,
+}
+
+#[derive(Accounts)]
+pub struct RegisterKeeper<'info> {
+    #[account(mut)]
+    pub keeper: Signer<'info>,
+    
+    #[account(seeds = [b"pool", pool_state.mint.as_ref()], bump = pool_state.bump)]
+    pub pool_state: Account<'info, PoolState>,
+    
+    #[account(
+        init,
+        payer = keeper,
+        space = 8 + KeeperRegistration::INIT_SPACE,
+        seeds = [b"keeper", pool_state.key().as_ref(), keeper.key().as_ref()],
+        bump
+    )]
+    pub keeper_registration: Account<'info, KeeperRegistration>,
+    
+    #[account(mut)]
+    pub keeper_token_account: Account<'info, TokenAccount>,
+    
+    /// Holds the stake under the registration PDA's authority, in the pool's mint
+    #[account(
+        mut,
+        constraint = stake_vault.owner == keeper_registration.key(),
+        constraint = stake_vault.mint == pool_state.mint @ InsuranceError::WrongMint
+    )]
+    pub stake_vault: Account<'info, TokenAccount>,
+    
+    pub token_program: Program<'info, Token>,
+    
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct DeregisterKeeper<'info> {
+    pub keeper: Signer<'info>,
+    
+    #[account(address = keeper_registration.pool)]
+    pub pool_state: Account<'info, PoolState>,
+    
+    #[account(
+        mut,
+        has_one = keeper,
+        seeds = [b"keeper", keeper_registration.pool.as_ref(), keeper.key().as_ref()],
+        bump = keeper_registration.bump
+    )]
+    pub keeper_registration: Account<'info, KeeperRegistration>,
+}
+
+#[derive(Accounts)]
+pub struct WithdrawKeeperStake<'info> {
+    #[account(mut)]
+    pub keeper: Signer<'info>,
+    
+    #[account(
+        mut,
+        has_one = keeper,
+        close = keeper,
+        seeds = [b"keeper", keeper_registration.pool.as_ref(), keeper.key().as_ref()],
+        bump = keeper_registration.bump
+    )]
+    pub keeper_registration: Account<'info, KeeperRegistration>,
+    
+    #[account(mut)]
+    pub keeper_token_account: Account<'info, TokenAccount>,
+    
+    #[account(mut, address = keeper_registration.vault)]
+    pub stake_vault: Account<'info, TokenAccount>,
+    
+    pub token_program: Program<'info, Token>,
+}
+
+#[derive(Accounts)]
+pub struct SlashKeeper<'info> {
+    pub admin: Signer<'info>,
+    
+    #[account(seeds = [b"role", admin.key().as_ref()], bump = roles.bump)]
+    pub roles: Account<'info, Roles>,
+    
+    #[account(
+        mut,
+        seeds = [b"keeper", keeper_registration.pool.as_ref(), keeper_registration.keeper.as_ref()],
+        bump = keeper_registration.bump
+    )]
+    pub keeper_registration: Account<'info, KeeperRegistration>,
+    
+    #[account(mut, address = keeper_registration.pool)]
+    pub pool_state: Account<'info, PoolState>,
+    
+    #[account(mut, address = keeper_registration.vault)]
+    pub stake_vault: Account<'info, TokenAccount>,
+    
+    #[account(mut, address = pool_state.vault)]
+    pub insurance_pool_token_account: Account<'info, TokenAccount>,
+    
+    pub token_program: Program<'info, Token>,
+}
+
+#[derive(Accounts)]
+pub struct CreateReinsurancePool<'info> {
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    
+    #[account(
+        has_one = authority,
+        seeds = [b"pool", pool_state.mint.as_ref()],
+        bump = pool_state.bump
+    )]
+    pub pool_state: Account<'info, PoolState>,
+    
+    #[account(
+        init,
+        payer = authority,
+        space = 8 + ReinsurancePool::INIT_SPACE,
+        seeds = [b"reinsurance_pool", pool_state.key().as_ref()],
+        bump
+    )]
+    pub reinsurance_pool: Account<'info, ReinsurancePool>,
+    
+    /// Holds reinsurer capital under the reinsurance PDA's authority, in the
+    /// mint claims are paid in
+    #[account(
+        constraint = reinsurance_vault.owner == reinsurance_pool.key(),
+        constraint = reinsurance_vault.mint == payout_vault.mint @ InsuranceError::WrongMint
+    )]
+    pub reinsurance_vault: Account<'info, TokenAccount>,
+    
+    #[account(address = pool_state.payout_vault)]
+    pub payout_vault: Account<'info, TokenAccount>,
+    
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct DepositReinsurance<'info> {
+    pub reinsurer: Signer<'info>,
+    
+    #[account(
+        mut,
+        has_one = reinsurer,
+        seeds = [b"reinsurance_pool", reinsurance_pool.pool.as_ref()],
+        bump = reinsurance_pool.bump
+    )]
+    pub reinsurance_pool: Account<'info, ReinsurancePool>,
+    
+    #[account(mut)]
+    pub reinsurer_token_account: Account<'info, TokenAccount>,
+    
+    #[account(mut, address = reinsurance_pool.vault)]
+    pub reinsurance_vault: Account<'info, TokenAccount>,
+    
+    pub token_program: Program<'info, Token>,
+}
+
+#[derive(Accounts)]
+pub struct WithdrawReinsurance<'info> {
+    pub reinsurer: Signer<'info>,
+    
+    #[account(
+        mut,
+        has_one = reinsurer,
+        seeds = [b"reinsurance_pool", reinsurance_pool.pool.as_ref()],
+        bump = reinsurance_pool.bump
+    )]
+    pub reinsurance_pool: Account<'info, ReinsurancePool>,
+    
+    #[account(mut)]
+    pub reinsurer_token_account: Account<'info, TokenAccount>,
+    
+    #[account(mut, address = reinsurance_pool.vault)]
+    pub reinsurance_vault: Account<'info, TokenAccount>,
+    
+    pub token_program: Program<'info, Token>,
+}
+
+#[derive(Accounts)]
+pub struct SweepReinsurancePremium<'info> {
+    #[account(
+        mut,
+        constraint = insurance_policy.version == CURRENT_POLICY_VERSION @ InsuranceError::PolicyNeedsMigration
+    )]
+    pub insurance_policy: Account<'info, InsurancePolicy>,
+    
+    #[account(
+        mut,
+        seeds = [b"pool", pool_state.mint.as_ref()],
+        bump = pool_state.bump,
+        constraint = insurance_policy.is_backed_by(&pool_state.key(), &pool_state.mint) @ InsuranceError::PoolMismatch
+    )]
+    pub pool_state: Account<'info, PoolState>,
+    
+    #[account(
+        mut,
+        seeds = [b"reinsurance_pool", pool_state.key().as_ref()],
+        bump = reinsurance_pool.bump
+    )]
+    pub reinsurance_pool: Account<'info, ReinsurancePool>,
+    
+    #[account(mut, address = pool_state.vault)]
+    pub insurance_pool_token_account: Account<'info, TokenAccount>,
+    
+    #[account(
+        mut,
+        constraint = reinsurer_token_account.owner == reinsurance_pool.reinsurer @ InsuranceError::WrongTokenAccountOwner,
+        constraint = reinsurer_token_account.mint == insurance_pool_token_account.mint @ InsuranceError::WrongMint
+    )]
+    pub reinsurer_token_account: Account<'info, TokenAccount>,
+    
+    pub token_program: Program<'info, Token>,
+}
+
+#[derive(Accounts)]
+pub struct RequestEmergencyWithdraw<'info> {
+    pub authority: Signer<'info>,
+    
+    #[account(
+        mut,
+        has_one = authority,
+        seeds = [b"pool", pool_state.mint.as_ref()],
+        bump = pool_state.bump
+    )]
+    pub pool_state: Account<'info, PoolState>,
+}
+
+#[derive(Accounts)]
+pub struct ExecuteEmergencyWithdraw<'info> {
+    pub authority: Signer<'info>,
+    
+    #[account(
+        mut,
+        has_one = authority,
+        seeds = [b"pool", pool_state.mint.as_ref()],
+        bump = pool_state.bump
+    )]
+    pub pool_state: Account<'info, PoolState>,
+    
+    #[account(mut, address = pool_state.vault)]
+    pub insurance_pool_token_account: Account<'info, TokenAccount>,
+    
+    #[account(mut)]
+    pub destination_token_account: Account<'info, TokenAccount>,
+    
+    pub token_program: Program<'info, Token>,
+    
+    /// Audit log registered on the pool
+    #[account(mut)]
+    pub audit_log: AccountLoader<'info, AuditLog>,
+}
+
+#[derive(Accounts)]
+pub struct CancelEmergencyWithdraw<'info> {
+    pub authority: Signer<'info>,
+    
+    #[account(
+        mut,
+        has_one = authority,
+        seeds = [b"pool", pool_state.mint.as_ref()],
+        bump = pool_state.bump
+    )]
+    pub pool_state: Account<'info, PoolState>,
+}
+
+#[derive(Accounts)]
+pub struct LapsePolicy<'info> {
+    /// Anyone may lapse a delinquent policy
+    pub caller: Signer<'info>,
+    
+    #[account(
+        mut,
+        constraint = insurance_policy.status == PolicyStatus::Purchased @ InsuranceError::PolicyNotPurchased,
+        constraint = insurance_policy.version == CURRENT_POLICY_VERSION @ InsuranceError::PolicyNeedsMigration
+    )]
+    pub insurance_policy: Account<'info, InsurancePolicy>,
+    
+    #[account(
+        mut,
+        seeds = [b"pool", pool_state.mint.as_ref()],
+        bump = pool_state.bump,
+        constraint = insurance_policy.is_backed_by(&pool_state.key(), &pool_state.mint) @ InsuranceError::PoolMismatch
+    )]
+    pub pool_state: Account<'info, PoolState>,
+    
+    /// CHECK: Receives any unspent keeper reward if the policy lapses
+    #[account(mut, address = insurance_policy.authority)]
+    pub authority: AccountInfo<'info>,
+}
+
+#[derive(Accounts)]
+pub struct SweepDust<'info> {
+    pub authority: Signer<'info>,
+    
+    #[account(
+        has_one = authority,
+        seeds = [b"pool", pool_state.mint.as_ref()],
+        bump = pool_state.bump
+    )]
+    pub pool_state: Account<'info, PoolState>,
+    
+    #[account(mut, address = pool_state.vault)]
+    pub insurance_pool_token_account: Account<'info, TokenAccount>,
+    
+    #[account(seeds = [b"config"], bump = config.bump)]
+    pub config: Account<'info, GlobalConfig>,
+    
+    #[account(
+        mut,
+        constraint = fee_recipient_token_account.owner == config.fee_recipient @ InsuranceError::InvalidFeeRecipient,
+        constraint = fee_recipient_token_account.mint == insurance_pool_token_account.mint @ InsuranceError::WrongMint
+    )]
+    pub fee_recipient_token_account: Account<'info, TokenAccount>,
+    
+    pub token_program: Program<'info, Token>,
+}
+
+#[derive(Accounts)]
+pub struct WithdrawForfeitedDeposits<'info> {
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    
+    #[account(
+        mut,
+        has_one = authority,
+        seeds = [b"pool", pool_state.mint.as_ref()],
+        bump = pool_state.bump
+    )]
+    pub pool_state: Account<'info, PoolState>,
+}
+
+#[derive(Accounts)]
+pub struct UpdateOracle<'info> {
+    pub oracle_manager: Signer<'info>,
+    
+    #[account(seeds = [b"role", oracle_manager.key().as_ref()], bump = roles.bump)]
+    pub roles: Account<'info, Roles>,
+    
+    #[account(
+        mut,
+        constraint = insurance_policy.version == CURRENT_POLICY_VERSION @ InsuranceError::PolicyNeedsMigration
+    )]
+    pub insurance_policy: Account<'info, InsurancePolicy>,
+    
+    /// CHECK: Owner, layout and freshness are validated in the instruction
+    pub new_oracle_account: AccountInfo<'info>,
+    
+    #[account(
+        seeds = [b"pool", pool_state.mint.as_ref()],
+        bump = pool_state.bump,
+        constraint = insurance_policy.is_backed_by(&pool_state.key(), &pool_state.mint) @ InsuranceError::PoolMismatch
+    )]
+    pub pool_state: Account<'info, PoolState>,
+    
+    /// Audit log registered on the pool
+    #[account(mut)]
+    pub audit_log: AccountLoader<'info, AuditLog>,
+}
+
+#[derive(Accounts)]
+pub struct SetMetadataUri<'info> {
+    pub authority: Signer<'info>,
+    
+    #[account(
+        mut,
+        has_one = authority,
+        constraint = insurance_policy.status == PolicyStatus::Active @ InsuranceError::PolicyNotActive,
+        constraint = insurance_policy.version == CURRENT_POLICY_VERSION @ InsuranceError::PolicyNeedsMigration
+    )]
+    pub insurance_policy: Account<'info, InsurancePolicy>,
+}
+
+#[derive(Accounts)]
+pub struct SetExternalId<'info> {
+    pub authority: Signer<'info>,
+    
+    #[account(
+        mut,
+        has_one = authority,
+        constraint = insurance_policy.status == PolicyStatus::Active @ InsuranceError::PolicyNotActive,
+        constraint = insurance_policy.version == CURRENT_POLICY_VERSION @ InsuranceError::PolicyNeedsMigration
+    )]
+    pub insurance_policy: Account<'info, InsurancePolicy>,
+}
+
+#[derive(Accounts)]
+pub struct UpdateThreshold<'info> {
+    pub authority: Signer<'info>,
+    
+    #[account(
+        mut,
+        has_one = authority,
+        constraint = insurance_policy.version == CURRENT_POLICY_VERSION @ InsuranceError::PolicyNeedsMigration
+    )]
+    pub insurance_policy: Account<'info, InsurancePolicy>,
+}
+
+#[derive(Accounts)]
+#[instruction(min_interval_seconds: i64, capacity: u32)]
+pub struct InitHistory<'info> {
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    
+    #[account(
+        mut,
+        has_one = authority,
+        constraint = insurance_policy.price_history.is_none() @ InsuranceError::InvalidPolicyParameters,
+        constraint = insurance_policy.version == CURRENT_POLICY_VERSION @ InsuranceError::PolicyNeedsMigration
+    )]
+    pub insurance_policy: Account<'info, InsurancePolicy>,
+    
+    #[account(
+        init,
+        payer = authority,
+        space = PriceHistory::space(capacity as usize),
+        seeds = [b"price_history", insurance_policy.key().as_ref()],
+        bump
+    )]
+    pub price_history: Account<'info, PriceHistory>,
+    
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct RecordObservation<'info> {
+    #[account(
+        constraint = insurance_policy.version == CURRENT_POLICY_VERSION @ InsuranceError::PolicyNeedsMigration
+    )]
+    pub insurance_policy: Account<'info, InsurancePolicy>,
+    
+    #[account(
+        mut,
+        seeds = [b"price_history", insurance_policy.key().as_ref()],
+        bump,
+        constraint = insurance_policy.price_history == Some(price_history.key()) @ InsuranceError::InvalidPolicyParameters
+    )]
+    pub price_history: Account<'info, PriceHistory>,
+    
+    /// CHECK: This is the oracle account that provides price data
+    #[account(address = insurance_policy.oracle_address @ InsuranceError::OracleMismatch)]
+    pub oracle_account: AccountInfo<'info>,
+}
+
+#[derive(Accounts)]
+pub struct MigratePolicy<'info> {
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    
+    /// CHECK: Legacy layouts cannot be deserialized as `InsurancePolicy`; ownership is
+    /// checked here and the data is parsed manually in the instruction
+    #[account(mut, owner = crate::ID)]
+    pub insurance_policy: AccountInfo<'info>,
+    
+    pub system_program: Program<'info, System>,
+}
+
+/// A single parametric policy. Stays a borsh account: its `Option` fields,
+/// `metadata_uri` and `beneficiaries` encode to variable lengths, so the
+/// offset of every later field depends on the data and no fixed zero-copy
+/// layout could read the same bytes.
+#[account]
+#[derive(Default, InitSpace)]
+pub struct InsurancePolicy {
+    pub authority: Pubkey,
+    pub policy_holder: Pubkey,
+    pub oracle_address: Pubkey,
+    pub trigger_threshold: i64,
+    pub coverage_amount: u64,
+    pub premium_amount: u64,
+    pub expiry_timestamp: i64,
+    pub created_timestamp: i64,
+    pub purchased_timestamp: Option<i64>,
+    pub triggered_timestamp: Option<i64>,
+    pub payout_timestamp: Option<i64>,
+    pub cancelled_timestamp: Option<i64>,
+    pub trigger_price: Option<i64>,
+    pub status: PolicyStatus,
+    pub bump: u8,
+    pub installment_count: u8,
+    pub installment_interval_seconds: i64,
+    pub installments_paid: u8,
+    pub next_installment_due: Option<i64>,
+    pub version: u8,
+    pub cooling_off_seconds: i64,
+    pub cancellation_fee_bps: u16,
+    pub first_adverse_observation: Option<i64>,
+    pub coverage_start_timestamp: i64,
+    pub coverage_end_timestamp: i64,
+    pub max_price_age_seconds: u64,
+    pub price_at_purchase: i64,
+    pub price_at_purchase_publish_time: i64,
+    pub payout_callback_program: Option<Pubkey>,
+    pub oracle_kind: OracleKind,
+    pub last_report_timestamp: i64,
+    pub trigger_condition: TriggerConditionType,
+    pub fallback_oracle_address: Option<Pubkey>,
+    pub last_oracle_source: OracleSource,
+    pub trigger_source: Option<OracleSource>,
+    pub max_single_observation_move_bps: u16,
+    pub confirmation_delay_seconds: i64,
+    pub last_observed_price: Option<i64>,
+    pub last_observed_timestamp: i64,
+    pub suspect_observation: bool,
+    pub price_history: Option<Pubkey>,
+    #[max_len(MAX_METADATA_URI_LEN)]
+    pub metadata_uri: String,
+    pub external_id: u128,
+    /// Sequential, human-friendly number from `GlobalConfig::policy_count`; the first policy is 1
+    pub policy_number: u64,
+    pub volatility_window_seconds: i64,
+    pub min_samples: u16,
+    pub manually_triggered: bool,
+    pub manual_trigger_reason: u16,
+    pub spread_oracle_address: Option<Pubkey>,
+    pub spread_oracle_kind: OracleKind,
+    pub trigger_reference_price: Option<i64>,
+    pub price_expo: i32,
+    pub min_trigger_duration_seconds: i64,
+    pub condition_met_since: Option<i64>,
+    pub product_kind: ProductKind,
+    pub product: Option<Pubkey>,
+    pub backup_oracles: [Pubkey; MAX_BACKUP_ORACLES],
+    pub premium_mint: Pubkey,
+    pub payout_mint: Pubkey,
+    pub min_holding_period_seconds: i64,
+    /// Coverage still owed after a payout the vault could not fully fund
+    pub outstanding_claim: u64,
+    pub coverage_mode: CoverageMode,
+    pub payout_vesting_seconds: i64,
+    /// Vesting schedule, set once a vested payout starts
+    pub payout_started_at: Option<i64>,
+    pub vesting_total: u64,
+    pub vested_claimed: u64,
+    #[max_len(MAX_BENEFICIARIES)]
+    pub beneficiaries: Vec<Beneficiary>,
+    pub quorum_oracles: [Pubkey; MAX_QUORUM_ORACLES],
+    pub required_agreements: u8,
+    /// Quorum feeds that satisfied the condition at the latest check
+    pub last_quorum_agreements: u8,
+    pub allow_third_party_purchase: bool,
+    /// Sponsor that funded the premium; `None` when the holder paid
+    pub premium_payer: Option<Pubkey>,
+    /// Keeper reward lamports held on this account above rent, not yet paid out
+    pub keeper_reward_reserved: u64,
+    /// Holder opt-in to payouts into a token account other than their ATA
+    pub allow_custom_payout_account: bool,
+    /// Window after purchase with a guaranteed full-refund cancellation
+    pub free_look_seconds: i64,
+    pub hysteresis_bps: u16,
+    /// Outcome of the latest condition evaluation, before the quorum check
+    pub last_condition_met: Option<bool>,
+    pub payout_peg_oracle_address: Option<Pubkey>,
+    pub payout_peg_oracle_kind: OracleKind,
+    pub keeper_reward_per_check: u64,
+    pub min_check_interval_seconds: i64,
+    pub last_checked_timestamp: Option<i64>,
+    /// `None` on policies that predate the field uses the default grace period
+    pub lapse_grace_seconds: Option<i64>,
+    /// Latest status changes, a ring buffer indexed by `transition_count`
+    pub transition_log: [TransitionRecord; TRANSITION_HISTORY_LEN],
+    /// Transitions ever applied, including those overwritten in `transition_log`
+    pub transition_count: u64,
+    /// Premium before any loyalty discount, recorded at purchase; `premium_amount`
+    /// is the net figure the holder actually pays
+    pub gross_premium_amount: u64,
+    pub loyalty_discount_bps: u16,
+    /// Integrator paid a cut of the first premium payment, and how much
+    pub referrer: Option<Pubkey>,
+    pub referral_fee_paid: u64,
+    /// Protocol fee skimmed from this term's premium payments; never refunded
+    pub protocol_fee_paid: u64,
+    pub auto_renew: bool,
+    /// Length of the original term; each renewal extends expiry by this much
+    pub term_seconds: i64,
+    pub renewal_count: u32,
+    pub retention_amount: Option<u64>,
+    /// Part of the payout drawn from the reinsurance vault
+    pub reinsured_paid: u64,
+    pub reinsurance_premium_swept: bool,
+    pub sane_price_min: i64,
+    pub sane_price_max: i64,
+    pub coverage_schedule: CoverageSchedule,
+    /// Policy this one was carved out of by `split_policy`
+    pub parent_policy: Option<Pubkey>,
+    /// Children split off so far; seeds the next child's address
+    pub split_count: u32,
+    /// Pool that collects this policy's premiums and pays its claims
+    pub pool: Pubkey,
+    pub premium_conversion_oracle: Option<Pubkey>,
+    pub premium_conversion_oracle_kind: OracleKind,
+    pub premium_decimals_shift: i8,
+    pub premium_conversion_rate_min: i64,
+    pub premium_conversion_rate_max: i64,
+    /// Rate, at `CONVERSION_RATE_EXPO`, the premium was converted at on purchase
+    pub premium_conversion_rate: Option<i64>,
+    /// Lamports the triggering keeper escrowed on this policy
+    pub keeper_deposit: u64,
+    /// Last moment a successful payout refunds `keeper_deposit`
+    pub keeper_deposit_deadline: i64,
+    pub condition_oracle: Option<Pubkey>,
+    /// Value `condition_oracle` read at the latest trigger check
+    pub last_condition_flag: bool,
+    pub allow_in_the_money_purchase: bool,
+    pub payout_delay_seconds: i64,
+    pub recheck_interval_seconds: i64,
+    /// Earliest time the next trigger check may evaluate, pushed out by
+    /// `recheck_interval_seconds` after each check that did not trigger
+    pub next_eligible_check_timestamp: i64,
+    /// Root over `group_leaf` entries for group policies; `None` for a single holder
+    pub group_merkle_root: Option<[u8; 32]>,
+    pub group_total_weight: u64,
+    /// `Evidence` records submitted per `EvidenceRole`, authority first
+    pub evidence_counts: [u8; 2],
+    /// Prevailing party's evidence cited when the last dispute was resolved
+    pub winning_evidence_index: Option<u8>,
+    pub numerator_oracle: Option<Pubkey>,
+    pub denominator_oracle: Option<Pubkey>,
+    /// `BlockReason` the latest trigger check or payout stopped short for;
+    /// `Clear` once one goes through
+    pub last_block_reason: u8,
+}
+
+/// Why the latest trigger check or payout on a policy did not go through, kept
+/// as `InsurancePolicy::last_block_reason` so clients can show a message
+/// without re-deriving the program's checks
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+#[repr(u8)]
+pub enum BlockReason {
+    Clear,
+    StaleOracle,
+    InvalidOracle,
+    ConditionNotMet,
+    InsufficientLiquidity,
+}
+
+impl BlockReason {
+    /// Reason for an oracle-side rejection that only blocks for now; `None` for
+    /// errors the caller must see, such as accounts, status or calling too soon
+    pub fn from_error(error: &Error) -> Option<Self> {
+        [
+            (InsuranceError::StaleOracleData, BlockReason::StaleOracle),
+            (InsuranceError::InvalidOracleData, BlockReason::InvalidOracle),
+        ]
+        .into_iter()
+        .find(|(code, _)| *error == Error::from(*code))
+        .map(|(_, reason)| reason)
+    }
+}
+
+/// Exponent premium conversion rates are normalized to before the sanity band
+/// is applied and the premium is converted
+pub const CONVERSION_RATE_EXPO: i32 = -8;
+
+/// Staleness bound applied to policies migrated from layouts that predate it
+pub const DEFAULT_MAX_PRICE_AGE_SECONDS: u64 = 60;
+
+/// Longest metadata URI, in bytes, a policy account has room for
+pub const MAX_METADATA_URI_LEN: usize = 200;
+
+/// Validate a metadata URI supplied as raw bytes
+pub fn parse_metadata_uri(bytes: &[u8]) -> Result<String> {
+    require!(bytes.len() <= MAX_METADATA_URI_LEN, InsuranceError::UriTooLong);
+    String::from_utf8(bytes.to_vec()).map_err(|_| error!(InsuranceError::InvalidMetadataUri))
+}
+
+/// Largest decimal exponent, either sign, a policy may express prices in
+pub const MAX_PRICE_EXPO_MAGNITUDE: u32 = 18;
+
+/// Plausible range for a payout token's USD price, in bps of $1. Outside it the
+/// feed is more likely broken than the token, and paying out would be a guess.
+pub const MIN_PEG_PRICE_BPS: i64 = 5_000;
+pub const MAX_PEG_PRICE_BPS: i64 = 15_000;
+
+/// Schema version written by `initialize`; bump whenever `InsurancePolicy` changes layout.
+/// Each superseded layout keeps a struct below and a `from_vN_bytes` arm in `from_legacy_bytes`.
+pub const CURRENT_POLICY_VERSION: u8 = 3;
+
+/// Policy layout prior to schema versioning (version 1)
+#[derive(AnchorSerialize, AnchorDeserialize, Clone)]
+pub struct InsurancePolicyV1 {
+    pub authority: Pubkey,
+    pub policy_holder: Pubkey,
+    pub oracle_address: Pubkey,
+    pub trigger_threshold: i64,
+    pub coverage_amount: u64,
+    pub premium_amount: u64,
+    pub expiry_timestamp: i64,
+    pub created_timestamp: i64,
+    pub purchased_timestamp: Option<i64>,
+    pub triggered_timestamp: Option<i64>,
+    pub payout_timestamp: Option<i64>,
+    pub cancelled_timestamp: Option<i64>,
+    pub trigger_price: Option<i64>,
+    pub status: PolicyStatus,
+    pub bump: u8,
+}
+
+impl InsurancePolicyV1 {
+    pub const LEN: usize = 191;
+}
+
+/// Policy layout at schema version 2: version 1 plus installment plans
+#[derive(AnchorSerialize, AnchorDeserialize, Clone)]
+pub struct InsurancePolicyV2 {
+    pub v1: InsurancePolicyV1,
+    pub installment_count: u8,
+    pub installment_interval_seconds: i64,
+    pub installments_paid: u8,
+    pub next_installment_due: Option<i64>,
+    pub version: u8,
+}
+
+impl InsurancePolicyV2 {
+    pub const LEN: usize = InsurancePolicyV1::LEN + 1 + 8 + 1 + 9 + 1;
+}
+
+/// How long after expiry an auto-renewing policy is reserved for `process_renewal`
+/// before anyone may expire it
+pub const RENEWAL_WINDOW_SECONDS: i64 = 24 * 60 * 60;
+
+/// How long a missed installment may remain unpaid before the policy lapses
+pub const INSTALLMENT_GRACE_PERIOD_SECONDS: i64 = 3 * 24 * 60 * 60;
+
+impl InsurancePolicy {
+    /// Account size including the discriminator
+    pub const LEN: usize = 8 + InsurancePolicy::INIT_SPACE;
+
+    /// Initialize from `params`, then take the next policy number from `config`
+    /// once the terms have been accepted
+    pub fn initialize_numbered(
+        &mut self,
+        config: &mut GlobalConfig,
+        authority: Pubkey,
+        policy_holder: Pubkey,
+        bump: u8,
+        params: &InitializeParams,
+        now: i64,
+    ) -> Result<()> {
+        self.initialize_from_params(authority, policy_holder, bump, params, now)?;
+        config.assign_policy_number(self)?;
+        Ok(())
+    }
+
+    /// Write a freshly created policy from validated terms
+    pub fn initialize_from_params(
+        &mut self,
+        authority: Pubkey,
+        policy_holder: Pubkey,
+        bump: u8,
+        params: &InitializeParams,
+        now: i64,
+    ) -> Result<()> {
+        params.validate(now)?;
+
+
+        self.authority = authority;
+        self.policy_holder = policy_holder;
+        self.oracle_address = params.oracle_address;
+        self.trigger_threshold = params.trigger_threshold;
+        self.trigger_condition = params.trigger_condition;
+        self.coverage_amount = params.coverage_amount;
+        self.premium_amount = params.premium_amount;
+        self.pool = pool_address(&params.premium_mint);
+        self.expiry_timestamp = params.expiry_timestamp;
+        self.created_timestamp = now;
+        self.apply_event(PolicyEvent::Initialize, now, authority)?;
+        self.bump = bump;
+        self.installment_count = params.installment_count;
+        self.installment_interval_seconds = params.installment_interval_seconds;
+        self.version = CURRENT_POLICY_VERSION;
+        self.cooling_off_seconds = params.cooling_off_seconds;
+        self.cancellation_fee_bps = params.cancellation_fee_bps;
+        self.coverage_start_timestamp = params.coverage_start_timestamp.unwrap_or(now);
+        self.coverage_end_timestamp =
+            params.coverage_end_timestamp.unwrap_or(params.expiry_timestamp);
+        self.max_price_age_seconds = params.max_price_age_seconds;
+        self.payout_callback_program = params.payout_callback_program;
+        self.oracle_kind = params.oracle_kind.clone();
+        self.fallback_oracle_address = params.fallback_oracle_address;
+        self.backup_oracles = params.backup_oracles;
+        self.max_single_observation_move_bps = params.max_single_observation_move_bps;
+        self.confirmation_delay_seconds = params.confirmation_delay_seconds;
+        self.sane_price_min = params.sane_price_min;
+        self.sane_price_max = params.sane_price_max;
+        self.premium_conversion_oracle = params.premium_conversion_oracle;
+        self.premium_conversion_oracle_kind = params.premium_conversion_oracle_kind.clone();
+        self.premium_decimals_shift = params.premium_decimals_shift;
+        self.premium_conversion_rate_min = params.premium_conversion_rate_min;
+        self.premium_conversion_rate_max = params.premium_conversion_rate_max;
+        self.condition_oracle = params.condition_oracle;
+        self.allow_in_the_money_purchase = params.allow_in_the_money_purchase;
+        self.payout_delay_seconds = params.payout_delay_seconds;
+        self.recheck_interval_seconds = params.recheck_interval_seconds;
+        self.numerator_oracle = params.numerator_oracle;
+        self.denominator_oracle = params.denominator_oracle;
+        self.metadata_uri = parse_metadata_uri(&params.metadata_uri)?;
+        self.external_id = params.external_id;
+        self.volatility_window_seconds = params.volatility_window_seconds;
+        self.min_samples = params.min_samples;
+        self.spread_oracle_address = params.spread_oracle_address;
+        self.spread_oracle_kind = params.spread_oracle_kind.clone();
+        self.price_expo = params.price_expo;
+        self.min_trigger_duration_seconds = params.min_trigger_duration_seconds;
+        self.hysteresis_bps = params.hysteresis_bps;
+        self.payout_peg_oracle_address = params.payout_peg_oracle_address;
+        self.payout_peg_oracle_kind = params.payout_peg_oracle_kind.clone();
+        self.premium_mint = params.premium_mint;
+        self.payout_mint = params.payout_mint.unwrap_or(params.premium_mint);
+        self.min_holding_period_seconds = params.min_holding_period_seconds;
+        self.coverage_mode = params.coverage_mode;
+        self.coverage_schedule = params.coverage_schedule;
+        self.payout_vesting_seconds = params.payout_vesting_seconds;
+        self.beneficiaries = params.beneficiaries.clone();
+        self.quorum_oracles = params.quorum_oracles;
+        self.required_agreements = params.required_agreements;
+        self.allow_third_party_purchase = params.allow_third_party_purchase;
+        self.keeper_reward_reserved = params.keeper_reward_lamports;
+        self.keeper_reward_per_check = params.keeper_reward_per_check_lamports;
+        self.min_check_interval_seconds = params.min_check_interval_seconds;
+        self.lapse_grace_seconds = Some(params.lapse_grace_seconds.unwrap_or(INSTALLMENT_GRACE_PERIOD_SECONDS));
+        self.auto_renew = params.auto_renew;
+        self.retention_amount = params.retention_amount;
+        self.term_seconds = params.expiry_timestamp - now;
+        self.free_look_seconds = params.free_look_seconds;
+        Ok(())
+    }
+
+    /// Replace the trigger threshold while the policy is still on offer
+    pub fn update_threshold(&mut self, trigger_threshold: i64, now: i64) -> Result<()> {
+        require!(self.status == PolicyStatus::Active, InsuranceError::PolicyNotActive);
+        Self::validate_parameters(now, self.trigger_condition, trigger_threshold, self.expiry_timestamp)?;
+        self.trigger_threshold = trigger_threshold;
+        Ok(())
+    }
+
+    /// Reject parameter sets that would produce a policy which can never pay out
+    pub fn validate_parameters(
+        now: i64,
+        trigger_condition: TriggerConditionType,
+        trigger_threshold: i64,
+        expiry_timestamp: i64,
+    ) -> Result<()> {
+        let threshold_ok = match trigger_condition {
+            // Absolute price levels may legitimately be zero or negative
+            TriggerConditionType::PriceAbove | TriggerConditionType::PriceBelow => true,
+            // Volatility is a share of price in bps and cannot exceed 100%
+            TriggerConditionType::VolatilityAbove => {
+                trigger_threshold > 0 && trigger_threshold <= BPS_DENOMINATOR as i64
+            }
+            // A relative drop can be at most 100% of the baseline
+            TriggerConditionType::RelativeBelow => {
+                trigger_threshold > 0 && trigger_threshold <= BPS_DENOMINATOR as i64
+            }
+            TriggerConditionType::RelativeAbove => trigger_threshold > 0,
+            TriggerConditionType::SpreadAbove => trigger_threshold > 0,
+        };
+        require!(threshold_ok, InsuranceError::InvalidTriggerConfiguration);
+        require!(expiry_timestamp > now, InsuranceError::InvalidPolicyParameters);
+        Ok(())
+    }
+
+    /// Decode an account written by any earlier schema version (discriminator
+    /// included) into the current layout. Legacy layouts carry no reliable
+    /// version byte, so the account's size tells them apart.
+    pub fn from_legacy_bytes(data: &[u8]) -> Result<Self> {
+        match data.len() {
+            InsurancePolicyV1::LEN => Self::from_v1_bytes(data),
+            InsurancePolicyV2::LEN => Self::from_v2_bytes(data),
+            _ => err!(ErrorCode::AccountDidNotDeserialize),
+        }
+    }
+
+    /// Decode a version 1 account (discriminator included) into the current layout.
+    /// Fields that did not exist in v1 take their zero value, except that the
+    /// premium is treated as a single up-front installment.
+    pub fn from_v1_bytes(data: &[u8]) -> Result<Self> {
+        require!(
+            data.len() >= 8 && data[..8] == InsurancePolicy::DISCRIMINATOR,
+            ErrorCode::AccountDiscriminatorMismatch
+        );
+        let v1 = InsurancePolicyV1::deserialize(&mut &data[8..])
+            .map_err(|_| error!(ErrorCode::AccountDidNotDeserialize))?;
+        Ok(Self::from_v1(v1))
+    }
+
+    /// Decode a version 2 account (discriminator included) into the current layout,
+    /// keeping its installment plan. Later fields take their zero value.
+    pub fn from_v2_bytes(data: &[u8]) -> Result<Self> {
+        require!(
+            data.len() >= 8 && data[..8] == InsurancePolicy::DISCRIMINATOR,
+            ErrorCode::AccountDiscriminatorMismatch
+        );
+        let v2 = InsurancePolicyV2::deserialize(&mut &data[8..])
+            .map_err(|_| error!(ErrorCode::AccountDidNotDeserialize))?;
+        Ok(Self {
+            installment_count: v2.installment_count,
+            installment_interval_seconds: v2.installment_interval_seconds,
+            installments_paid: v2.installments_paid,
+            next_installment_due: v2.next_installment_due,
+            ..Self::from_v1(v2.v1)
+        })
+    }
+
+    fn from_v1(v1: InsurancePolicyV1) -> Self {
+        Self {
+            installment_count: 1,
+            installments_paid: u8::from(v1.purchased_timestamp.is_some()),
+            authority: v1.authority,
+            policy_holder: v1.policy_holder,
+            oracle_address: v1.oracle_address,
+            trigger_threshold: v1.trigger_threshold,
+            coverage_amount: v1.coverage_amount,
+            premium_amount: v1.premium_amount,
+            expiry_timestamp: v1.expiry_timestamp,
+            created_timestamp: v1.created_timestamp,
+            purchased_timestamp: v1.purchased_timestamp,
+            triggered_timestamp: v1.triggered_timestamp,
+            payout_timestamp: v1.payout_timestamp,
+            cancelled_timestamp: v1.cancelled_timestamp,
+            trigger_price: v1.trigger_price,
+            trigger_condition: TriggerConditionType::from_legacy_threshold(v1.trigger_threshold),
+            coverage_start_timestamp: v1.created_timestamp,
+            coverage_end_timestamp: v1.expiry_timestamp,
+            max_price_age_seconds: DEFAULT_MAX_PRICE_AGE_SECONDS,
+            status: v1.status,
+            bump: v1.bump,
+            version: CURRENT_POLICY_VERSION,
+            ..Default::default()
+        }
+    }
+
+    /// Installment plans must fit entirely within the policy term
+    pub fn validate_installment_plan(
+        now: i64,
+        expiry_timestamp: i64,
+        installment_count: u8,
+        installment_interval_seconds: i64,
+    ) -> Result<()> {
+        require!(installment_count >= 1, InsuranceError::InvalidPolicyParameters);
+        if installment_count > 1 {
+            require!(installment_interval_seconds > 0, InsuranceError::InvalidPolicyParameters);
+            let last_due = (installment_count as i64 - 1)
+                .checked_mul(installment_interval_seconds)
+                .and_then(|offset| now.checked_add(offset))
+                .ok_or(InsuranceError::InvalidPolicyParameters)?;
+            require!(last_due < expiry_timestamp, InsuranceError::InvalidPolicyParameters);
+        }
+        Ok(())
+    }
+
+    /// Amount due for the installment at `index` (0-based); the final installment
+    /// absorbs the rounding remainder so the plan sums to exactly `premium_amount`
+    pub fn installment_amount(&self, index: u8) -> u64 {
+        let count = self.installment_count.max(1) as u64;
+        let base = self.premium_amount / count;
+        if index as u64 + 1 == count {
+            self.premium_amount - base * (count - 1)
+        } else {
+            base
+        }
+    }
+
+    /// Scale the premium by the pool's utilization multiplier at purchase. Rounds
+    /// up for the pool.
+    pub fn apply_utilization_multiplier(&mut self, multiplier_bps: u64) -> Result<()> {
+        self.premium_amount = apply_bps(self.premium_amount, multiplier_bps, RoundingMode::Ceil)?;
+        Ok(())
+    }
+
+    /// Knock `discount_bps` off the premium before the first payment, keeping the
+    /// undiscounted figure in `gross_premium_amount`. The discount rounds down so
+    /// the holder never pays less than the schedule allows.
+    pub fn apply_loyalty_discount(&mut self, discount_bps: u16) -> Result<()> {
+        let discount = apply_bps(self.premium_amount, discount_bps as u64, RoundingMode::Floor)?;
+        self.gross_premium_amount = self.premium_amount;
+        self.premium_amount -= discount;
+        self.loyalty_discount_bps = discount_bps;
+        Ok(())
+    }
+
+    /// Referral cut of `premium` owed to `referrer`; nothing without a referrer
+    /// or while the protocol's referral rate is zero. Holders cannot refer themselves.
+    pub fn referral_fee(
+        &self,
+        config: &GlobalConfig,
+        referrer: Option<Pubkey>,
+        premium: u64,
+    ) -> Result<u64> {
+        let Some(referrer) = referrer else {
+            return Ok(0);
+        };
+        require_keys_neq!(referrer, self.policy_holder, InsuranceError::SelfReferral);
+        apply_bps(premium, config.referral_fee_bps as u64, RoundingMode::Floor)
+    }
+
+    /// Convert a premium quoted in the coverage mint into the premium mint at the
+    /// conversion feed's current rate, if the policy has one. Stale feeds and
+    /// rates outside the sanity band are rejected. Rounds up for the pool.
+    pub fn reprice_premium(
+        &mut self,
+        conversion_oracle_account: Option<&AccountInfo>,
+        now: i64,
+    ) -> Result<()> {
+        let Some(expected) = self.premium_conversion_oracle else {
+            return Ok(());
+        };
+        let account = conversion_oracle_account.ok_or(InsuranceError::OracleMismatch)?;
+        require_keys_eq!(account.key(), expected, InsuranceError::OracleMismatch);
+        let price = load_oracle_price(
+            &self.premium_conversion_oracle_kind,
+            account,
+            now,
+            self.max_price_age_seconds,
+        )?;
+
+        let rate = FixedPrice::from_oracle(&price).normalize_to(CONVERSION_RATE_EXPO)?.mantissa;
+        require!(
+            (self.premium_conversion_rate_min..=self.premium_conversion_rate_max).contains(&rate),
+            InsuranceError::InvalidOracleData
+        );
+        let exponent = CONVERSION_RATE_EXPO + self.premium_decimals_shift as i32;
+        let factor = 10u128
+            .checked_pow(exponent.unsigned_abs())
+            .ok_or(InsuranceError::MathOverflow)?;
+        let premium = self.premium_amount as u128;
+        let units = u128::try_from(rate).map_err(|_| error!(InsuranceError::InvalidOracleData))?;
+        let converted = if exponent >= 0 {
+            let scaled_rate = math::mul_div(units, factor, 1, RoundingMode::Floor)?;
+            math::mul_div(premium, scaled_rate, 1, RoundingMode::Floor)?
+        } else {
+            math::mul_div(premium, units, factor, RoundingMode::Ceil)?
+        };
+        self.premium_amount =
+            u64::try_from(converted).map_err(|_| error!(InsuranceError::MathOverflow))?;
+        self.premium_conversion_rate = Some(rate);
+        Ok(())
+    }
+
+    /// Whether the pool at `pool` in `mint` is the one this policy was written against
+    pub fn is_backed_by(&self, pool: &Pubkey, mint: &Pubkey) -> bool {
+        self.pool == *pool && self.premium_mint == *mint
+    }
+
+    /// Total premium collected so far
+    pub fn premium_paid(&self) -> u64 {
+        (0..self.installments_paid)
+            .map(|index| self.installment_amount(index))
+            .sum()
+    }
+
+    /// Split a claim into the part the primary pool pays and the part above
+    /// `retention_amount` that the reinsurer pays
+    pub fn split_retention(&self, owed: u64) -> (u64, u64) {
+        match self.retention_amount {
+            Some(retention) => (owed.min(retention), owed.saturating_sub(retention)),
+            None => (owed, 0),
+        }
+    }
+
+    /// Reinsurer's share of the premium on a settled reinsured policy. Marks the
+    /// policy swept, so each policy pays the share at most once.
+    pub fn take_reinsurance_premium(&mut self, reinsurance_pool: &ReinsurancePool) -> Result<u64> {
+        require!(
+            self.retention_amount.is_some() && !self.reinsurance_premium_swept,
+            InsuranceError::InvalidPolicyParameters
+        );
+        // Cancelled policies refunded their premium, so there is nothing to share
+        require!(
+            self.status.is_terminal() && self.status != PolicyStatus::Cancelled,
+            InsuranceError::PolicyNotExpired
+        );
+        self.reinsurance_premium_swept = true;
+        reinsurance_pool.premium_share(self.premium_paid())
+    }
+
+    /// Lower coverage to `new_coverage_amount`, scaling the premium in proportion,
+    /// and return the refund owed on installments already paid. The reduced
+    /// premium rounds up so the pool keeps any fractional token.
+    pub fn reduce_coverage(&mut self, new_coverage_amount: u64) -> Result<u64> {
+        require!(
+            new_coverage_amount > 0 && new_coverage_amount < self.coverage_amount,
+            InsuranceError::InvalidPolicyParameters
+        );
+
+        let new_premium = math::prorate(
+            self.premium_amount,
+            new_coverage_amount,
+            self.coverage_amount,
+            RoundingMode::Ceil,
+        )?;
+
+        let paid_before = self.premium_paid();
+        self.premium_amount = new_premium;
+        self.coverage_amount = new_coverage_amount;
+        Ok(paid_before.saturating_sub(self.premium_paid()))
+    }
+
+    /// Raise coverage to `new_coverage_amount` and return the premium owed for the
+    /// added coverage, prorated over what is left of the coverage window and
+    /// rounded up. Only fully paid plans can grow, so the addition is owed now.
+    pub fn increase_coverage(&mut self, new_coverage_amount: u64, now: i64) -> Result<u64> {
+        require!(
+            new_coverage_amount > self.coverage_amount
+                && self.installments_paid >= self.installment_count.max(1),
+            InsuranceError::InvalidPolicyParameters
+        );
+        require!(
+            now < self.expiry_timestamp && now < self.coverage_end_timestamp,
+            InsuranceError::PolicyExpired
+        );
+
+        let added = (new_coverage_amount - self.coverage_amount) as u128;
+        let window =
+            self.coverage_end_timestamp.saturating_sub(self.coverage_start_timestamp).max(1);
+        let remaining = self.coverage_end_timestamp.saturating_sub(now).min(window);
+        let additional = math::mul_div(
+            self.premium_amount as u128,
+            added * remaining as u128,
+            self.coverage_amount as u128 * window as u128,
+            RoundingMode::Ceil,
+        )?;
+        let additional =
+            u64::try_from(additional).map_err(|_| error!(InsuranceError::MathOverflow))?;
+
+        self.premium_amount = self
+            .premium_amount
+            .checked_add(additional)
+            .ok_or(InsuranceError::MathOverflow)?;
+        self.coverage_amount = new_coverage_amount;
+        Ok(additional)
+    }
+
+    /// Carve `split_coverage` out of this policy into a child on the same terms,
+    /// keeping `retained_coverage`. The premium splits in the same proportion,
+    /// the child's part rounding down so the two still sum to the original.
+    /// The keeper reward escrow stays with this policy's account.
+    pub fn split(
+        &mut self,
+        parent: Pubkey,
+        retained_coverage: u64,
+        split_coverage: u64,
+    ) -> Result<InsurancePolicy> {
+        require!(self.status == PolicyStatus::Purchased, InsuranceError::PolicyNotPurchased);
+        require!(
+            retained_coverage > 0
+                && split_coverage > 0
+                && retained_coverage.checked_add(split_coverage) == Some(self.coverage_amount),
+            InsuranceError::InvalidPolicyParameters
+        );
+        let carve = |amount: u64| {
+            math::prorate(amount, split_coverage, self.coverage_amount, RoundingMode::Floor)
+        };
+        let split_premium = carve(self.premium_amount)?;
+        let split_gross_premium = carve(self.gross_premium_amount)?;
+        let split_protocol_fee = carve(self.protocol_fee_paid)?;
+
+        let mut child = self.clone();
+        child.coverage_amount = split_coverage;
+        child.premium_amount = split_premium;
+        child.gross_premium_amount = split_gross_premium;
+        child.protocol_fee_paid = split_protocol_fee;
+        child.keeper_reward_reserved = 0;
+        child.referrer = None;
+        child.referral_fee_paid = 0;
+        child.parent_policy = Some(parent);
+        child.split_count = 0;
+
+        self.coverage_amount = retained_coverage;
+        self.premium_amount -= split_premium;
+        self.gross_premium_amount -= split_gross_premium;
+        self.protocol_fee_paid -= split_protocol_fee;
+        self.split_count = self.split_count.checked_add(1).ok_or(InsuranceError::MathOverflow)?;
+        Ok(child)
+    }
+
+    /// Move the policy to `new_holder` and return the previous holder. The
+    /// account address keeps the original holder in its seeds; nothing
+    /// re-derives it after initialization.
+    pub fn transfer_to(&mut self, new_holder: Pubkey) -> Result<Pubkey> {
+        require!(!self.status.is_terminal(), InsuranceError::PolicyNotTransferable);
+        require!(
+            new_holder != Pubkey::default() && new_holder != self.policy_holder,
+            InsuranceError::PolicyNotTransferable
+        );
+
+        let previous_holder = self.policy_holder;
+        for entry in self.beneficiaries.iter_mut() {
+            if entry.beneficiary == previous_holder {
+                entry.beneficiary = new_holder;
+            }
+        }
+        self.policy_holder = new_holder;
+        Ok(previous_holder)
+    }
+
+    /// Replace the payout split; only possible before a trigger. The reinsurer
+    /// pays the holder directly and vested tranches are claimed by the holder
+    /// alone, so reinsured and vesting policies cannot take beneficiaries.
+    pub fn replace_beneficiaries(&mut self, beneficiaries: Vec<Beneficiary>) -> Result<()> {
+        require!(
+            self.status == PolicyStatus::Active || self.status == PolicyStatus::Purchased,
+            InsuranceError::PolicyNotActive
+        );
+        validate_beneficiaries(&beneficiaries)?;
+        require!(
+            beneficiaries.is_empty()
+                || (self.retention_amount.is_none() && self.payout_vesting_seconds == 0),
+            InsuranceError::InvalidPolicyParameters
+        );
+        self.beneficiaries = beneficiaries;
+        Ok(())
+    }
+
+    /// Reject oracle observations published outside the insured event window.
+    /// Both bounds are inclusive.
+    pub fn check_coverage_window(&self, publish_time: i64) -> Result<()> {
+        require!(
+            publish_time >= self.coverage_start_timestamp
+                && publish_time <= self.coverage_end_timestamp,
+            InsuranceError::OutsideCoverageWindow
+        );
+        Ok(())
+    }
+
+    /// Backup feeds in the order they are tried
+    pub fn configured_backup_oracles(&self) -> impl Iterator<Item = Pubkey> + '_ {
+        self.backup_oracles
+            .iter()
+            .copied()
+            .take_while(|key| *key != Pubkey::default())
+    }
+
+    pub fn configured_quorum_oracles(&self) -> impl Iterator<Item = Pubkey> + '_ {
+        self.quorum_oracles
+            .iter()
+            .copied()
+            .take_while(|key| *key != Pubkey::default())
+    }
+
+    /// Number of quorum readings under which the trigger condition holds
+    pub fn count_agreements(
+        &self,
+        readings: &[Price],
+        volatility_bps: Option<u64>,
+        reference_price: Option<i64>,
+    ) -> Result<u8> {
+        let mut agreements = 0u8;
+        for reading in readings {
+            let observed = self.normalize_price(reading)?;
+            if self.trigger_condition.is_met(
+                self.trigger_threshold,
+                observed,
+                volatility_bps,
+                self.price_at_purchase,
+                reference_price,
+            )? {
+                agreements += 1;
+            }
+        }
+        Ok(agreements)
+    }
+
+    /// Express an oracle reading at the policy's exponent so it is comparable
+    /// with `trigger_threshold`, whatever exponent the feed publishes at
+    pub fn normalize_price(&self, price: &Price) -> Result<i64> {
+        Ok(FixedPrice::from_oracle(price).normalize_to(self.price_expo)?.mantissa)
+    }
+
+    /// Refuse to sell coverage whose price trigger already holds at `price`, which
+    /// would be a guaranteed payout, unless the policy allows it. Volatility and
+    /// spread conditions need inputs not available at purchase and are not checked.
+    pub fn check_not_in_the_money(&self, price: &Price) -> Result<()> {
+        if self.allow_in_the_money_purchase
+            || matches!(
+                self.trigger_condition,
+                TriggerConditionType::VolatilityAbove | TriggerConditionType::SpreadAbove
+            )
+        {
+            return Ok(());
+        }
+        let observed = self.normalize_price(price)?;
+        let met = self.trigger_condition.is_met(
+            self.trigger_threshold,
+            observed,
+            None,
+            self.price_at_purchase,
+            None,
+        )?;
+        require!(!met, InsuranceError::ConditionAlreadyMet);
+        Ok(())
+    }
+
+    /// Only readings published after the purchase may trigger, so a cached
+    /// pre-purchase print cannot be replayed against new coverage
+    pub fn check_published_after_purchase(&self, price: &Price) -> Result<()> {
+        if let Some(purchased) = self.purchased_timestamp {
+            require!(price.publish_time > purchased, InsuranceError::StaleOracleData);
+        }
+        Ok(())
+    }
+
+    /// Reject a reading outside `[sane_price_min, sane_price_max]`, however
+    /// fresh. Returns the reading at the policy's exponent for the evaluation.
+    pub fn check_price_band(&self, price: &Price) -> Result<i64> {
+        let observed = self.normalize_price(price)?;
+        if self.sane_price_max != 0
+            && !(self.sane_price_min..=self.sane_price_max).contains(&observed)
+        {
+            debug_msg!(
+                "Price {} outside sanity band [{}, {}]",
+                observed,
+                self.sane_price_min,
+                self.sane_price_max
+            );
+            return err!(InsuranceError::InvalidOracleData);
+        }
+        Ok(observed)
+    }
+
+    /// Evaluate an oracle observation and move to `TriggeredPayout` if the
+    /// trigger condition holds. Returns whether the policy triggered.
+    pub fn apply_observation(
+        &mut self,
+        price: &Price,
+        volatility_bps: Option<u64>,
+        reference_price: Option<i64>,
+        now: i64,
+    ) -> Result<bool> {
+        let observed = self.normalize_price(price)?;
+        self.apply_normalized_observation(
+            price.publish_time,
+            observed,
+            volatility_bps,
+            reference_price,
+            now,
+        )
+    }
+
+    /// `apply_observation` for a reading the caller already put through
+    /// `normalize_price`, published at `publish_time`
+    pub fn apply_normalized_observation(
+        &mut self,
+        publish_time: i64,
+        observed: i64,
+        volatility_bps: Option<u64>,
+        reference_price: Option<i64>,
+        now: i64,
+    ) -> Result<bool> {
+        // Only observations published inside the insured event window count
+        self.check_coverage_window(publish_time)?;
+
+        // With a quorum configured, enough independent feeds must also agree, and
+        // with a condition feed the categorical event must have happened
+        let condition_met = self.evaluate_with_hysteresis(observed, volatility_bps, reference_price)?
+            && self.last_quorum_agreements >= self.required_agreements
+            && (self.condition_oracle.is_none() || self.last_condition_flag);
+        if condition_met && self.first_adverse_observation.is_none() {
+            // Closes the cooling-off refund window, even while the sustain and
+            // breaker gates still hold the trigger back
+            self.first_adverse_observation = Some(now);
+        }
+        let sustained = self.condition_sustained(condition_met, publish_time);
+        let trigger_met = self.passes_circuit_breaker(observed, now) && sustained;
+
+        if trigger_met {
+            self.apply_event(PolicyEvent::Trigger, now, self.last_oracle_key())?;
+            self.triggered_timestamp = Some(now);
+            self.trigger_price = Some(observed);
+            self.trigger_source = Some(self.last_oracle_source);
+            self.trigger_reference_price = reference_price;
+        }
+
+        Ok(trigger_met)
+    }
+
+    /// Evaluate the trigger condition against the threshold shifted by
+    /// `hysteresis_bps`: tightened while the last evaluation did not hold, and
+    /// loosened while it did, so noise around the threshold cannot flip the
+    /// outcome. The first evaluation uses the bare threshold.
+    pub fn evaluate_with_hysteresis(
+        &mut self,
+        observed: i64,
+        volatility_bps: Option<u64>,
+        reference_price: Option<i64>,
+    ) -> Result<bool> {
+        let margin = apply_bps(
+            self.trigger_threshold.unsigned_abs(),
+            self.hysteresis_bps as u64,
+            RoundingMode::Floor,
+        )? as i64;
+        let threshold = match self.last_condition_met {
+            None => self.trigger_threshold,
+            Some(false) => self.trigger_condition.shift_threshold(self.trigger_threshold, margin),
+            Some(true) => self.trigger_condition.shift_threshold(self.trigger_threshold, -margin),
+        };
+        let met = self.trigger_condition.is_met(
+            threshold,
+            observed,
+            volatility_bps,
+            self.price_at_purchase,
+            reference_price,
+        )?;
+        self.last_condition_met = Some(met);
+        Ok(met)
+    }
+
+    /// Track how long the condition has held, measured in oracle publish time,
+    /// and report whether it has held for `min_trigger_duration_seconds`.
+    /// Any observation where the condition does not hold restarts the clock.
+    pub fn condition_sustained(&mut self, condition_met: bool, publish_time: i64) -> bool {
+        if !condition_met {
+            self.condition_met_since = None;
+            return false;
+        }
+        let since = *self.condition_met_since.get_or_insert(publish_time);
+        publish_time - since >= self.min_trigger_duration_seconds
+    }
+
+    /// Record `price` as the latest observation and report whether it may trigger.
+    /// A move larger than `max_single_observation_move_bps` from the previous
+    /// observation is marked suspect and only accepted once a consistent
+    /// observation arrives `confirmation_delay_seconds` later. With the breaker
+    /// enabled the first observation only establishes the baseline.
+    pub fn passes_circuit_breaker(&mut self, price: i64, now: i64) -> bool {
+        if self.max_single_observation_move_bps == 0 {
+            return true;
+        }
+
+        let previous = match self.last_observed_price {
+            Some(previous) => previous,
+            None => {
+                self.last_observed_price = Some(price);
+                self.last_observed_timestamp = now;
+                return false;
+            }
+        };
+
+        let moved = (price as i128 - previous as i128).abs() * BPS_DENOMINATOR as i128;
+        let limit = (previous as i128).abs() * self.max_single_observation_move_bps as i128;
+        if moved > limit {
+            // Spike (or reversion from one): restart the confirmation clock
+            self.last_observed_price = Some(price);
+            self.last_observed_timestamp = now;
+            self.suspect_observation = true;
+            return false;
+        }
+
+        if self.suspect_observation {
+            if now - self.last_observed_timestamp < self.confirmation_delay_seconds {
+                // Keep the suspect timestamp so the delay is measured from the spike
+                self.last_observed_price = Some(price);
+                return false;
+            }
+            self.suspect_observation = false;
+        }
+
+        self.last_observed_price = Some(price);
+        self.last_observed_timestamp = now;
+        true
+    }
+
+    /// Evaluate an observation unless the policy has already triggered, returning
+    /// the event to emit. Repeated calls after a trigger are no-ops so racing
+    /// keepers cannot re-emit or overwrite the original trigger.
+    pub fn record_observation(
+        &mut self,
+        policy: Pubkey,
+        price: &Price,
+        volatility_bps: Option<u64>,
+        reference_price: Option<i64>,
+        now: i64,
+    ) -> Result<Option<TriggerEvaluated>> {
+        let observed = self.normalize_price(price)?;
+        self.record_normalized_observation(
+            policy,
+            price,
+            observed,
+            volatility_bps,
+            reference_price,
+            now,
+        )
+    }
+
+    /// `record_observation` for a reading the caller already put through
+    /// `normalize_price`
+    pub fn record_normalized_observation(
+        &mut self,
+        policy: Pubkey,
+        price: &Price,
+        observed: i64,
+        volatility_bps: Option<u64>,
+        reference_price: Option<i64>,
+        now: i64,
+    ) -> Result<Option<TriggerEvaluated>> {
+        if self.status == PolicyStatus::TriggeredPayout {
+            return Ok(None);
+        }
+        let triggered = self.apply_normalized_observation(
+            price.publish_time,
+            observed,
+            volatility_bps,
+            reference_price,
+            now,
+        )?;
+        self.record_block(if triggered {
+            BlockReason::Clear
+        } else {
+            BlockReason::ConditionNotMet
+        });
+        Ok(Some(TriggerEvaluated {
+            policy,
+            price: price.price,
+            publish_time: price.publish_time,
+            triggered,
+            timestamp: now,
+        }))
+    }
+
+    /// Move a purchased policy to `TriggeredPayout` on the authority's declaration.
+    /// `reason_code` is product-defined (e.g. a catastrophe category).
+    pub fn declare_manual_trigger(&mut self, caller: &Pubkey, reason_code: u16, now: i64) -> Result<()> {
+        require_keys_eq!(*caller, self.authority, InsuranceError::Unauthorized);
+        self.require_unexpired(now)?;
+        require!(self.status == PolicyStatus::Purchased, InsuranceError::PolicyNotPurchased);
+
+        self.apply_event(PolicyEvent::Trigger, now, *caller)?;
+        self.triggered_timestamp = Some(now);
+        self.manually_triggered = true;
+        self.manual_trigger_reason = reason_code;
+        Ok(())
+    }
+
+    pub fn timeline(&self) -> PolicyTimeline {
+        PolicyTimeline {
+            status: self.status.clone(),
+            created_timestamp: self.created_timestamp,
+            purchased_timestamp: self.purchased_timestamp,
+            triggered_timestamp: self.triggered_timestamp,
+            payout_timestamp: self.payout_timestamp,
+            cancelled_timestamp: self.cancelled_timestamp,
+            expiry_timestamp: self.expiry_timestamp,
+        }
+    }
+
+    /// Estimate the odds of payout from one oracle reading; see `PayoutOdds`
+    /// for the model. Relative conditions on a policy that has not been
+    /// purchased yet use the reading itself as the baseline.
+    pub fn estimate_payout_odds(&self, price: &Price) -> Result<PayoutOdds> {
+        let observed = self.normalize_price(price)?;
+        let confidence = i64::try_from(price.conf).map_err(|_| error!(InsuranceError::PriceOverflow))?;
+        let confidence = FixedPrice::new(confidence, price.expo).normalize_to(self.price_expo)?.mantissa;
+        let baseline = if self.price_at_purchase != 0 {
+            self.price_at_purchase
+        } else {
+            observed
+        };
+
+        // Distance still to travel before the condition holds, at the policy exponent
+        let (price_i, threshold, base) = (observed as i128, self.trigger_threshold as i128, baseline as i128);
+        let denominator = BPS_DENOMINATOR as i128;
+        let distance = match self.trigger_condition {
+            TriggerConditionType::PriceAbove => threshold - price_i,
+            TriggerConditionType::PriceBelow => price_i - threshold,
+            TriggerConditionType::RelativeBelow => {
+                (price_i * denominator - (base * denominator - base.abs() * threshold)) / denominator
+            }
+            TriggerConditionType::RelativeAbove => {
+                (base * denominator + base.abs() * threshold - price_i * denominator) / denominator
+            }
+            TriggerConditionType::VolatilityAbove | TriggerConditionType::SpreadAbove => {
+                return err!(InsuranceError::OddsUnavailable)
+            }
+        };
+        let met = self
+            .trigger_condition
+            .is_met(self.trigger_threshold, observed, None, baseline, None)?;
+
+        let distance_conf_bps = if met {
+            0
+        } else if confidence == 0 {
+            u64::MAX
+        } else {
+            let scaled = distance.max(0) * denominator / confidence as i128;
+            u64::try_from(scaled).unwrap_or(u64::MAX)
+        };
+
+        Ok(PayoutOdds {
+            odds_bps: if met { BPS_DENOMINATOR as u16 } else { odds_from_distance(distance_conf_bps) },
+            distance_conf_bps,
+            price: observed,
+            confidence,
+            publish_time: price.publish_time,
+        })
+    }
+
+    /// Coverage the holder is entitled to at payout. Under `ProRata` it scales
+    /// with installments paid, and under `LinearDecay` with the coverage window
+    /// left when the policy triggered, both rounding down.
+    pub fn effective_coverage(&self) -> Result<u64> {
+        let coverage = match self.coverage_mode {
+            CoverageMode::Full => self.coverage_amount,
+            CoverageMode::ProRata => {
+                let count = self.installment_count.max(1) as u64;
+                let paid = (self.installments_paid as u64).min(count);
+                math::prorate(self.coverage_amount, paid, count, RoundingMode::Floor)?
+            }
+        };
+        // Measured at the trigger so a late payout crank cannot shrink the claim
+        match self.triggered_timestamp {
+            Some(triggered) => self.coverage_schedule.scale(
+                coverage,
+                self.coverage_start_timestamp,
+                self.coverage_end_timestamp,
+                triggered,
+            ),
+            None => Ok(coverage),
+        }
+    }
+
+    /// Each beneficiary's share of `amount`, in order. Shares round down and the
+    /// final entry absorbs the remainder so the parts sum to `amount` exactly.
+    pub fn split_payout(&self, amount: u64) -> Result<Vec<u64>> {
+        let mut remaining = amount;
+        let mut parts = Vec::with_capacity(self.beneficiaries.len());
+        for (index, beneficiary) in self.beneficiaries.iter().enumerate() {
+            let part = if index + 1 == self.beneficiaries.len() {
+                remaining
+            } else {
+                apply_bps(amount, beneficiary.share_bps as u64, RoundingMode::Floor)?
+            };
+            remaining = remaining.checked_sub(part).ok_or(InsuranceError::MathOverflow)?;
+            parts.push(part);
+        }
+        Ok(parts)
+    }
+
+    /// Destination token account for beneficiary `index` must be theirs and in the payout mint
+    pub fn check_beneficiary_destination(&self, index: usize, owner: &Pubkey, mint: &Pubkey) -> Result<()> {
+        let beneficiary = self
+            .beneficiaries
+            .get(index)
+            .ok_or(InsuranceError::InvalidBeneficiaryAccount)?;
+        require_keys_eq!(*owner, beneficiary.beneficiary, InsuranceError::InvalidBeneficiaryAccount);
+        require_keys_eq!(*mint, self.payout_mint, InsuranceError::WrongMint);
+        Ok(())
+    }
+
+    /// Effective coverage, read as USD, in payout tokens at `peg` (the token's
+    /// USD price). Below $1 the amount grows so the holder still receives the
+    /// USD value, rounding up; above $1 it stays at face value. Readings outside
+    /// `MIN_PEG_PRICE_BPS..=MAX_PEG_PRICE_BPS` are rejected as implausible.
+    pub fn pegged_coverage(&self, peg: &FixedPrice) -> Result<u64> {
+        let peg_bps = peg.normalize_to(-4)?.mantissa;
+        require!(
+            (MIN_PEG_PRICE_BPS..=MAX_PEG_PRICE_BPS).contains(&peg_bps),
+            InsuranceError::PegOutOfRange
+        );
+        let coverage = self.effective_coverage()?;
+        if peg_bps >= BPS_DENOMINATOR as i64 {
+            return Ok(coverage);
+        }
+        math::prorate(coverage, BPS_DENOMINATOR, peg_bps as u64, RoundingMode::Ceil)
+    }
+
+    /// Start paying out against a vault holding `available` tokens. Returns the
+    /// amount payable now; the rest is recorded as an outstanding claim.
+    pub fn begin_payout(&mut self, available: u64, now: i64, actor: Pubkey) -> Result<u64> {
+        self.begin_payout_of(self.effective_coverage()?, available, now, actor)
+    }
+
+    /// `begin_payout` for an `owed` amount already adjusted, e.g. by `pegged_coverage`
+    pub fn begin_payout_of(
+        &mut self,
+        owed: u64,
+        available: u64,
+        now: i64,
+        actor: Pubkey,
+    ) -> Result<u64> {
+        let payable = owed.min(available);
+        self.outstanding_claim = owed - payable;
+        let event = if self.outstanding_claim > 0 {
+            PolicyEvent::PartialPayout
+        } else {
+            PolicyEvent::Payout
+        };
+        self.apply_event(event, now, actor)?;
+        Ok(payable)
+    }
+
+    /// Start a vesting payout of the effective coverage and return its total
+    pub fn begin_vesting(&mut self, now: i64, actor: Pubkey) -> Result<u64> {
+        self.begin_vesting_of(self.effective_coverage()?, now, actor)
+    }
+
+    /// `begin_vesting` for a `total` already adjusted, e.g. by `pegged_coverage`
+    pub fn begin_vesting_of(&mut self, total: u64, now: i64, actor: Pubkey) -> Result<u64> {
+        self.apply_event(PolicyEvent::BeginVesting, now, actor)?;
+        self.vesting_total = total;
+        self.vested_claimed = 0;
+        self.payout_started_at = Some(now);
+        Ok(self.vesting_total)
+    }
+
+    /// Cumulative amount released by `now`. Rounds down, so the running total
+    /// never exceeds the schedule and reaches `vesting_total` exactly at the end.
+    pub fn vested_amount(&self, now: i64) -> Result<u64> {
+        let Some(started) = self.payout_started_at else {
+            return Ok(0);
+        };
+        let elapsed = now.saturating_sub(started).clamp(0, self.payout_vesting_seconds);
+        if self.payout_vesting_seconds == 0 || elapsed == self.payout_vesting_seconds {
+            return Ok(self.vesting_total);
+        }
+        math::prorate(
+            self.vesting_total,
+            elapsed as u64,
+            self.payout_vesting_seconds as u64,
+            RoundingMode::Floor,
+        )
+    }
+
+    /// Claim everything vested since the last claim, completing the payout once
+    /// the full total has been released
+    pub fn claim_vested(&mut self, now: i64, actor: Pubkey) -> Result<u64> {
+        require!(self.status == PolicyStatus::Vesting, InsuranceError::PayoutNotVesting);
+        let amount = self.vested_amount(now)?.saturating_sub(self.vested_claimed);
+        require!(amount > 0, InsuranceError::NothingVested);
+        self.vested_claimed += amount;
+        if self.vested_claimed == self.vesting_total {
+            self.apply_event(PolicyEvent::Payout, now, actor)?;
+        }
+        Ok(amount)
+    }
+
+    /// Pay down the outstanding claim from a vault holding `available` tokens
+    pub fn settle_claim(&mut self, available: u64, now: i64, actor: Pubkey) -> Result<u64> {
+        require!(
+            self.status == PolicyStatus::PartiallyPaid && self.outstanding_claim > 0,
+            InsuranceError::NoOutstandingClaim
+        );
+        let payable = self.outstanding_claim.min(available);
+        require!(payable > 0, InsuranceError::InsufficientPoolLiquidity);
+        self.outstanding_claim -= payable;
+        if self.outstanding_claim == 0 {
+            self.apply_event(PolicyEvent::Payout, now, actor)?;
+        }
+        Ok(payable)
+    }
+
+    /// Move to the status `event` leads to, rejecting illegal transitions, and
+    /// record the transition in the ring buffer over the oldest entry
+    pub fn apply_event(&mut self, event: PolicyEvent, now: i64, actor: Pubkey) -> Result<()> {
+        let from = self.status.clone();
+        self.status = transition(from.clone(), event)?;
+        let slot = (self.transition_count % TRANSITION_HISTORY_LEN as u64) as usize;
+        self.transition_log[slot] = TransitionRecord {
+            from,
+            to: self.status.clone(),
+            timestamp: now,
+            actor,
+        };
+        self.transition_count += 1;
+        Ok(())
+    }
+
+    /// Retained transitions, oldest first
+    pub fn transition_history(&self) -> Vec<TransitionRecord> {
+        let capacity = TRANSITION_HISTORY_LEN as u64;
+        let oldest = self.transition_count.saturating_sub(capacity);
+        (oldest..self.transition_count)
+            .map(|index| self.transition_log[(index % capacity) as usize].clone())
+            .collect()
+    }
+
+    /// Event announcing a freshly initialized policy and its off-chain references
+    pub fn initialized_event(&self, policy: Pubkey) -> PolicyInitialized {
+        PolicyInitialized {
+            policy,
+            policy_number: self.policy_number,
+            authority: self.authority,
+            policy_holder: self.policy_holder,
+            external_id: self.external_id,
+            metadata_uri: self.metadata_uri.clone(),
+        }
+    }
+
+    /// Event carrying the transition history, once the policy is in a terminal status
+    pub fn finalized_event(&self, policy: Pubkey) -> Option<PolicyFinalized> {
+        self.status.is_terminal().then(|| PolicyFinalized {
+            policy,
+            status: self.status.clone(),
+            transition_count: self.transition_count,
+            history: self.transition_history(),
+        })
+    }
+
+    /// Feed behind the latest reading: the account `last_oracle_source` points
+    /// at, or the reporter for signed reports
+    pub fn last_oracle_key(&self) -> Pubkey {
+        if let OracleKind::SignedReport { reporter } = self.oracle_kind {
+            return reporter;
+        }
+        match self.last_oracle_source {
+            OracleSource::Primary => self.oracle_address,
+            OracleSource::Fallback => self.fallback_oracle_address.unwrap_or_default(),
+            OracleSource::Backup(index) => self
+                .backup_oracles
+                .get(index as usize)
+                .copied()
+                .unwrap_or_default(),
+        }
+    }
+
+    pub fn evidence_count(&self, role: EvidenceRole) -> u8 {
+        self.evidence_counts[role as usize]
+    }
+
+    /// Count a new evidence submission and return its index. Only the party the
+    /// role names may submit, and only while the policy is disputed.
+    pub fn record_evidence(&mut self, role: EvidenceRole, submitter: &Pubkey) -> Result<u8> {
+        require!(self.status == PolicyStatus::Disputed, InsuranceError::PolicyNotDisputed);
+        let party = match role {
+            EvidenceRole::Authority => self.authority,
+            EvidenceRole::Holder => self.policy_holder,
+        };
+        require_keys_eq!(*submitter, party, InsuranceError::Unauthorized);
+        let index = self.evidence_count(role);
+        require!(index < MAX_EVIDENCE_PER_PARTY, InsuranceError::EvidenceLimitReached);
+        self.evidence_counts[role as usize] = index + 1;
+        Ok(index)
+    }
+
+    /// Check a ruling against the evidence on file before it is applied. The
+    /// disputing authority must have submitted evidence, and a cited winning
+    /// index must exist for the prevailing side: the holder when the trigger is
+    /// upheld, the authority otherwise.
+    pub fn record_dispute_ruling(
+        &mut self,
+        uphold_trigger: bool,
+        winning_evidence: Option<u8>,
+    ) -> Result<()> {
+        require!(
+            self.evidence_count(EvidenceRole::Authority) > 0,
+            InsuranceError::MissingDisputeEvidence
+        );
+        let winner = if uphold_trigger { EvidenceRole::Holder } else { EvidenceRole::Authority };
+        if let Some(index) = winning_evidence {
+            require!(
+                index < self.evidence_count(winner),
+                InsuranceError::MissingDisputeEvidence
+            );
+        }
+        self.winning_evidence_index = winning_evidence;
+        Ok(())
+    }
+
+    /// Settle a dispute raised on a triggered policy
+    pub fn resolve_dispute(&mut self, uphold_trigger: bool, now: i64, actor: Pubkey) -> Result<()> {
+        require!(self.status == PolicyStatus::Disputed, InsuranceError::PolicyNotDisputed);
+        if uphold_trigger {
+            self.apply_event(PolicyEvent::Trigger, now, actor)?;
+        } else {
+            self.apply_event(PolicyEvent::Forfeit, now, actor)?;
+            self.triggered_timestamp = None;
+            self.trigger_price = None;
+            self.trigger_source = None;
+            self.trigger_reference_price = None;
+            self.manually_triggered = false;
+            self.condition_met_since = None;
+        }
+        Ok(())
+    }
+
+    /// Whether `now` falls inside the cooling-off window. The window closes early
+    /// once a trigger-favorable observation has been recorded.
+    pub fn in_cooling_off(&self, now: i64) -> bool {
+        self.first_adverse_observation.is_none()
+            && self
+                .purchased_timestamp
+                .map_or(false, |purchased| now - purchased <= self.cooling_off_seconds)
+    }
+
+    /// Reject a payout until `payout_delay_seconds` have passed since the trigger
+    pub fn check_payout_releasable(&self, now: i64) -> Result<()> {
+        let triggered = self.triggered_timestamp.unwrap_or(i64::MIN);
+        require!(
+            now >= triggered.saturating_add(self.payout_delay_seconds),
+            InsuranceError::PayoutNotYetReleasable
+        );
+        Ok(())
+    }
+
+    /// Release the whole reserved keeper reward, returning the amount released
+    pub fn take_keeper_reward(&mut self) -> u64 {
+        std::mem::take(&mut self.keeper_reward_reserved)
+    }
+
+    /// Record the deposit a keeper escrowed when its check triggered the policy
+    pub fn escrow_keeper_deposit(&mut self, amount: u64, now: i64, window_seconds: i64) {
+        self.keeper_deposit = amount;
+        self.keeper_deposit_deadline = now.saturating_add(window_seconds);
+    }
+
+    /// Release the keeper deposit on payout as `(refunded, forfeited)`: refunded
+    /// within the window, forfeited to the pool after it
+    pub fn release_keeper_deposit(&mut self, now: i64) -> (u64, u64) {
+        let deposit = std::mem::take(&mut self.keeper_deposit);
+        if now <= self.keeper_deposit_deadline {
+            (deposit, 0)
+        } else {
+            (0, deposit)
+        }
+    }
+
+    /// Release the whole keeper deposit for forfeiture to the pool
+    pub fn forfeit_keeper_deposit(&mut self) -> u64 {
+        std::mem::take(&mut self.keeper_deposit)
+    }
+
+    /// Release one check's reward from the reserve, or whatever is left of it
+    pub fn take_check_reward(&mut self) -> u64 {
+        let reward = self.keeper_reward_per_check.min(self.keeper_reward_reserved);
+        self.keeper_reward_reserved -= reward;
+        reward
+    }
+
+    /// Whether a trigger check may run at `now`. The first check after purchase
+    /// always runs; later ones must be at least `min_check_interval_seconds` apart.
+    pub fn check_interval_elapsed(&self, now: i64) -> Result<()> {
+        if let Some(last) = self.last_checked_timestamp {
+            require!(
+                now.saturating_sub(last) >= self.min_check_interval_seconds,
+                InsuranceError::CheckTooSoon
+            );
+        }
+        Ok(())
+    }
+
+    /// Admit a trigger check at `now`, starting the next check interval
+    pub fn record_check(&mut self, now: i64) -> Result<()> {
+        self.check_interval_elapsed(now)?;
+        self.last_checked_timestamp = Some(now);
+        Ok(())
+    }
+
+    pub fn record_block(&mut self, reason: BlockReason) {
+        self.last_block_reason = reason as u8;
+    }
+
+    /// Record an oracle-side rejection as `last_block_reason` and
+    /// return `None`, so the instruction can succeed and keep the reason. Other
+    /// errors pass through unchanged.
+    pub fn note_block<T>(&mut self, result: Result<T>) -> Result<Option<T>> {
+        match result {
+            Ok(value) => Ok(Some(value)),
+            Err(error) => {
+                let reason = BlockReason::from_error(&error).ok_or(error)?;
+                debug_msg!("Blocked: {:?}", reason);
+                self.record_block(reason);
+                Ok(None)
+            }
+        }
+    }
+
+    /// Check a group member's proof against the policy's root and return their
+    /// slice of the coverage, `coverage * weight / total_weight` rounded down
+    pub fn verify_group_claim(
+        &self,
+        index: u32,
+        member: &Pubkey,
+        weight: u64,
+        proof: &[[u8; 32]],
+    ) -> Result<u64> {
+        let root = self.group_merkle_root.ok_or(InsuranceError::InvalidMerkleProof)?;
+        require!(
+            verify_merkle_proof(proof, root, group_leaf(index, member, weight)),
+            InsuranceError::InvalidMerkleProof
+        );
+        math::prorate(
+            self.effective_coverage()?,
+            weight,
+            self.group_total_weight,
+            RoundingMode::Floor,
+        )
+    }
+
+    /// Turn a reading of the policy's feed into the price the trigger compares.
+    /// Cross-rate policies divide it by a fresh reading of `denominator_oracle`;
+    /// other policies use it as is.
+    pub fn apply_cross_rate(
+        &self,
+        reading: Price,
+        denominator_account: Option<&AccountInfo>,
+        now: i64,
+    ) -> Result<Price> {
+        let Some(expected) = self.denominator_oracle else {
+            return Ok(reading);
+        };
+        let account = denominator_account.ok_or(InsuranceError::OracleMismatch)?;
+        require_keys_eq!(account.key(), expected, InsuranceError::OracleMismatch);
+        let denominator =
+            load_oracle_price(&self.oracle_kind, account, now, self.max_price_age_seconds)?;
+        cross_rate(&reading, &denominator)
+    }
+
+    /// Whether the claim is the coverage, paid in full to the holder in one
+    /// transfer: no split, vesting, reinsurance, peg adjustment, callback or group
+    pub fn has_plain_payout(&self) -> bool {
+        self.beneficiaries.is_empty()
+            && self.payout_vesting_seconds == 0
+            && self.retention_amount.is_none()
+            && self.payout_peg_oracle_address.is_none()
+            && self.payout_callback_program.is_none()
+            && self.group_merkle_root.is_none()
+    }
+
+    /// Amount `execute_payouts_batch` pays this policy for `authority` out of
+    /// `pool`, or why it is skipped. Only plain payouts to the holder qualify;
+    /// split, vested, reinsured, pegged, group and callback payouts keep their
+    /// own instructions.
+    pub fn batch_claim(
+        &self,
+        authority: &Pubkey,
+        pool: &Pubkey,
+        mint: &Pubkey,
+        now: i64,
+    ) -> std::result::Result<u64, BatchPayoutResult> {
+        if self.status != PolicyStatus::TriggeredPayout {
+            return Err(BatchPayoutResult::NotTriggered);
+        }
+        if !self.has_plain_payout()
+            || self.authority != *authority
+            || !self.is_backed_by(pool, mint)
+            || self.version != CURRENT_POLICY_VERSION
+            || self.check_payout_releasable(now).is_err()
+        {
+            return Err(BatchPayoutResult::Ineligible);
+        }
+        self.effective_coverage().map_err(|_| BatchPayoutResult::Ineligible)
+    }
+
+    /// Reject a check before the recheck interval after the last negative result
+    /// has passed. Unlike `record_check`, which throttles all checks against spam,
+    /// this only delays re-evaluation after the condition was found not to hold.
+    pub fn check_evaluation_due(&self, now: i64) -> Result<()> {
+        require!(
+            now >= self.next_eligible_check_timestamp,
+            InsuranceError::EvaluationTooSoon
+        );
+        Ok(())
+    }
+
+    /// Push the next evaluation out by the recheck interval after a negative result
+    pub fn schedule_recheck(&mut self, now: i64) {
+        self.next_eligible_check_timestamp = now.saturating_add(self.recheck_interval_seconds);
+    }
+
+    /// Whether `destination` may receive the holder's payout: their canonical ATA
+    /// for the payout mint, or any account once they have opted in
+    pub fn classify_payout_destination(&self, destination: &Pubkey) -> Result<PayoutDestination> {
+        if *destination == get_associated_token_address(&self.policy_holder, &self.payout_mint) {
+            return Ok(PayoutDestination::AssociatedTokenAccount);
+        }
+        require!(self.allow_custom_payout_account, InsuranceError::InvalidPayoutAccount);
+        Ok(PayoutDestination::Custom)
+    }
+
+    /// Record who funded the premium, so refunds go back to them. A holder
+    /// paying for themselves is not recorded as a sponsor.
+    pub fn record_premium_payer(&mut self, payer: Pubkey) {
+        self.premium_payer = (payer != self.policy_holder).then_some(payer);
+    }
+
+    /// Owner of the token account cancellation and reduction refunds are paid to
+    pub fn refund_recipient(&self) -> Pubkey {
+        self.premium_payer.unwrap_or(self.policy_holder)
+    }
+
+    /// Whether `now` falls inside the free-look window. Unlike cooling-off it
+    /// does not close early on adverse observations; 0 disables it.
+    pub fn in_free_look(&self, now: i64) -> bool {
+        self.free_look_seconds > 0
+            && self
+                .purchased_timestamp
+                .map_or(false, |purchased| now - purchased <= self.free_look_seconds)
+    }
+
+    /// Whether `now` is still inside the minimum holding period after purchase
+    pub fn is_cancellation_locked(&self, now: i64) -> bool {
+        self.purchased_timestamp
+            .map_or(false, |purchased| now < purchased.saturating_add(self.min_holding_period_seconds))
+    }
+
+    /// Premium returned to the holder if the policy is cancelled at `now`. The
+    /// protocol fee already left the pool, so only the pool's share comes back.
+    pub fn cancellation_refund(&self, now: i64) -> Result<u64> {
+        let paid = self.premium_paid().saturating_sub(self.protocol_fee_paid);
+        if self.in_free_look(now) {
+            return Ok(paid);
+        }
+        require!(!self.is_cancellation_locked(now), InsuranceError::CancellationLocked);
+        if self.in_cooling_off(now) {
+            return Ok(paid);
+        }
+        // Fees round in the pool's favor
+        let fee = apply_bps(paid, self.cancellation_fee_bps as u64, RoundingMode::Ceil)?;
+        Ok(paid - fee)
+    }
+
+    /// Due date of the installment following one due at `due`, if any remain
+    pub fn next_due_after(&self, due: i64) -> Option<i64> {
+        if self.installments_paid < self.installment_count {
+            Some(due + self.installment_interval_seconds)
+        } else {
+            None
+        }
+    }
+
+    /// Whether an outstanding installment is overdue beyond the grace period
+    pub fn is_installment_lapsed(&self, now: i64) -> bool {
+        let grace = self.lapse_grace_seconds.unwrap_or(INSTALLMENT_GRACE_PERIOD_SECONDS);
+        self.next_installment_due
+            .map_or(false, |due| now > due.saturating_add(grace))
+    }
+
+    /// Move an installment-delinquent policy to `Lapsed`; current policies are left alone
+    pub fn lapse(&mut self, now: i64, actor: Pubkey) -> Result<()> {
+        require!(self.is_installment_lapsed(now), InsuranceError::InstallmentNotOverdue);
+        self.apply_event(PolicyEvent::Lapse, now, actor)?;
+        Ok(())
+    }
+
+    /// Premium `process_renewal` pulls at `now` to roll the policy into another
+    /// term, or `None` when the holder's balance cannot cover it and the policy
+    /// should lapse instead. The whole premium is due up front on renewal.
+    pub fn renewal_premium(
+        &self,
+        now: i64,
+        delegate: Option<Pubkey>,
+        delegated_amount: u64,
+        balance: u64,
+        pool: &Pubkey,
+    ) -> Result<Option<u64>> {
+        require!(self.auto_renew, InsuranceError::AutoRenewDisabled);
+        require!(now >= self.expiry_timestamp, InsuranceError::PolicyNotExpired);
+
+        let amount = self.premium_amount;
+        require!(
+            delegate.as_ref() == Some(pool) && delegated_amount >= amount,
+            InsuranceError::DelegationRevoked
+        );
+        Ok((balance >= amount).then_some(amount))
+    }
+
+    /// Extend an expired, untriggered policy by one term. The new term starts
+    /// where the old one ended, and a coverage window that ran to expiry
+    /// follows it.
+    pub fn renew(&mut self, now: i64, actor: Pubkey) -> Result<()> {
+        let next_expiry = self
+            .expiry_timestamp
+            .checked_add(self.term_seconds)
+            .ok_or(InsuranceError::MathOverflow)?;
+        self.apply_event(PolicyEvent::Renew, now, actor)?;
+        if self.coverage_end_timestamp == self.expiry_timestamp {
+            self.coverage_end_timestamp = next_expiry;
+        }
+        self.expiry_timestamp = next_expiry;
+        self.installments_paid = self.installment_count.max(1);
+        self.next_installment_due = None;
+        // Renewal premiums are collected without a protocol fee
+        self.protocol_fee_paid = 0;
+        self.renewal_count = self.renewal_count.saturating_add(1);
+        Ok(())
+    }
+
+    /// Status as of `now`: an active or purchased policy past `expirable_at`
+    /// reads as `Expired` even before anything has recorded the transition
+    pub fn status_at(&self, now: i64) -> PolicyStatus {
+        match self.status {
+            PolicyStatus::Active | PolicyStatus::Purchased if now >= self.expirable_at() => {
+                PolicyStatus::Expired
+            }
+            _ => self.status.clone(),
+        }
+    }
+
+    /// Move a policy past `expirable_at` to `Expired`, returning whether it held
+    /// a reservation against the pool, which only purchased policies do
+    pub fn expire(&mut self, now: i64) -> Result<bool> {
+        require!(now >= self.expirable_at(), InsuranceError::PolicyNotExpired);
+        let reserved = self.status == PolicyStatus::Purchased;
+        // Expiry is permissionless and has no signer to attribute it to
+        self.apply_event(PolicyEvent::Expire, now, Pubkey::default())?;
+        Ok(reserved)
+    }
+
+    /// Reject once the policy has expired, whether or not `Expired` has been
+    /// recorded yet. A renewable policy inside its renewal window is not
+    /// expired, but its coverage has still ended. Failing reverts the
+    /// instruction, so paths that should record the expiry use
+    /// `PoolState::close_expired` and succeed instead.
+    pub fn require_unexpired(&self, now: i64) -> Result<()> {
+        require!(
+            self.status_at(now) != PolicyStatus::Expired && now < self.expiry_timestamp,
+            InsuranceError::PolicyExpired
+        );
+        Ok(())
+    }
+
+    /// Earliest time `expire_policy` may close the policy
+    pub fn expirable_at(&self) -> i64 {
+        if self.auto_renew && self.status == PolicyStatus::Purchased {
+            self.expiry_timestamp.saturating_add(RENEWAL_WINDOW_SECONDS)
+        } else {
+            self.expiry_timestamp
+        }
+    }
+
+    /// Amount `collect_premium` may pull at `now` from a holder account whose
+    /// delegation is (`delegate`, `delegated_amount`)
+    pub fn collectible_installment(
+        &self,
+        now: i64,
+        delegate: Option<Pubkey>,
+        delegated_amount: u64,
+        pool: &Pubkey,
+    ) -> Result<u64> {
+        require!(
+            self.installments_paid < self.installment_count,
+            InsuranceError::NoInstallmentDue
+        );
+        let due = self.next_installment_due.ok_or(InsuranceError::NoInstallmentDue)?;
+        require!(now >= due, InsuranceError::InstallmentNotDue);
+
+        let amount = self.installment_amount(self.installments_paid);
+        require!(
+            delegate.as_ref() == Some(pool) && delegated_amount >= amount,
+            InsuranceError::DelegationRevoked
+        );
+        Ok(amount)
+    }
+
+    /// Count an installment as paid, less `protocol_fee` skimmed from it, and
+    /// advance the due date. The next due date is anchored to the schedule, not
+    /// to when this payment landed.
+    pub fn record_installment_paid(&mut self, protocol_fee: u64) {
+        self.installments_paid += 1;
+        self.protocol_fee_paid = self.protocol_fee_paid.saturating_add(protocol_fee);
+        self.next_installment_due = self.next_installment_due.and_then(|due| self.next_due_after(due));
+    }
+}
+
+/// Read a price from the policy's oracle, rejecting feeds that have not published
+/// within `max_age_seconds`
+pub fn load_oracle_price(
+    oracle_kind: &OracleKind,
+    oracle_account: &AccountInfo,
+    now: i64,
+    max_age_seconds: u64,
+) -> Result<Price> {
+    match oracle_kind {
+        OracleKind::PythLegacy => {
+            let price_feed = load_price_feed_from_account_info(oracle_account)
+                .map_err(|_| error!(InsuranceError::InvalidOracleData))?;
+            price_feed
+                .get_price_no_older_than(now, max_age_seconds)
+                .ok_or_else(|| error!(InsuranceError::StaleOracleData))
+        }
+        OracleKind::PythPull { feed_id } => {
+            load_pyth_pull_price(oracle_account, feed_id, now, max_age_seconds)
+        }
+        // Signed reports carry their value in instruction data, not in an account
+        OracleKind::SignedReport { .. } => err!(InsuranceError::InvalidOracleData),
+    }
+}
+
+/// Exponent cross rates are computed at
+pub const CROSS_RATE_EXPO: i32 = -8;
+
+/// Price of `numerator`'s asset in units of `denominator`'s, both quoted in the
+/// same currency, at `CROSS_RATE_EXPO`. The rate rounds toward zero and its
+/// confidence adds the two feeds' relative confidences. The older publish time
+/// is kept so staleness checks apply to both legs.
+pub fn cross_rate(numerator: &Price, denominator: &Price) -> Result<Price> {
+    require!(
+        numerator.price > 0 && denominator.price > 0,
+        InsuranceError::InvalidOracleData
+    );
+    // value = (n * 10^ne) / (d * 10^de), expressed as mantissa * 10^CROSS_RATE_EXPO
+    let shift = numerator.expo - denominator.expo - CROSS_RATE_EXPO;
+    let factor = 10i128
+        .checked_pow(shift.unsigned_abs())
+        .ok_or(InsuranceError::PriceOverflow)?;
+    let (scaled_numerator, scaled_denominator) = if shift >= 0 {
+        (numerator.price as i128 * factor, denominator.price as i128)
+    } else {
+        (numerator.price as i128, denominator.price as i128 * factor)
+    };
+    let rate = scaled_numerator / scaled_denominator;
+    let price = i64::try_from(rate).map_err(|_| error!(InsuranceError::PriceOverflow))?;
+
+    let scale = RETURN_SCALE as u128;
+    let relative_conf = |leg: &Price| leg.conf as u128 * scale / leg.price as u128;
+    let conf = (rate as u128)
+        .checked_mul(relative_conf(numerator) + relative_conf(denominator))
+        .ok_or(InsuranceError::PriceOverflow)?
+        / scale;
+    Ok(Price {
+        price,
+        conf: u64::try_from(conf).map_err(|_| error!(InsuranceError::PriceOverflow))?,
+        expo: CROSS_RATE_EXPO,
+        publish_time: numerator.publish_time.min(denominator.publish_time),
+    })
+}
+
+/// Programs that own legacy Pyth push-feed accounts (mainnet-beta, devnet)
+pub const PYTH_LEGACY_PROGRAM_IDS: [Pubkey; 2] = [
+    anchor_lang::solana_program::pubkey!("FsJ3A3u2vn5cTVofAjvy6y5kwABJAqYWpe4975bi2epH"),
+    anchor_lang::solana_program::pubkey!("gSbePebfvPy7tRqimPoVecS2UsBvYv46ynrzWocc92s"),
+];
+
+/// Check that `oracle_account` is a feed of `oracle_kind`, owned by the program
+/// that publishes such feeds, and reads a fresh price now. Used before pointing
+/// a policy at an account, so a bad address cannot leave it untriggerable.
+pub fn validate_oracle_account(
+    oracle_kind: &OracleKind,
+    oracle_account: &AccountInfo,
+    now: i64,
+    max_age_seconds: u64,
+) -> Result<Price> {
+    let owner_ok = match oracle_kind {
+        OracleKind::PythLegacy => PYTH_LEGACY_PROGRAM_IDS.contains(oracle_account.owner),
+        OracleKind::PythPull { .. } => *oracle_account.owner == PYTH_RECEIVER_PROGRAM_ID,
+        // Reports are attested by the reporter key; there is no feed account to point at
+        OracleKind::SignedReport { .. } => false,
+    };
+    require!(owner_ok, InsuranceError::InvalidOracleData);
+    load_oracle_price(oracle_kind, oracle_account, now, max_age_seconds)
+        .map_err(|_| error!(InsuranceError::InvalidOracleData))
+}
+
+/// A decimal fixed-point value `mantissa * 10^expo`, as published by oracles.
+/// Oracle-derived math goes through this type so mantissas at different
+/// exponents are never compared or mixed directly.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct FixedPrice {
+    pub mantissa: i64,
+    pub expo: i32,
+}
+
+impl FixedPrice {
+    pub fn new(mantissa: i64, expo: i32) -> Self {
+        Self { mantissa, expo }
+    }
+
+    pub fn from_oracle(price: &Price) -> Self {
+        Self::new(price.price, price.expo)
+    }
+
+    /// Express the same value at `expo`. Moving to a coarser exponent
+    /// truncates toward zero; moving to a finer one fails on overflow.
+    pub fn normalize_to(&self, expo: i32) -> Result<FixedPrice> {
+        let mantissa = math::normalize_price(self.mantissa, self.expo, expo)?;
+        Ok(FixedPrice::new(mantissa, expo))
+    }
+
+    /// Exact comparison of the represented values, whatever their exponents
+    pub fn compare(&self, other: &FixedPrice) -> std::cmp::Ordering {
+        let (coarse, fine, flipped) = if self.expo >= other.expo {
+            (self, other, false)
+        } else {
+            (other, self, true)
+        };
+        let shift = (coarse.expo - fine.expo).unsigned_abs();
+        let ordering = match 10i128
+            .checked_pow(shift)
+            .and_then(|factor| (coarse.mantissa as i128).checked_mul(factor))
+        {
+            Some(scaled) => scaled.cmp(&(fine.mantissa as i128)),
+            // A nonzero coarse value scaled past i128 outweighs any i64 mantissa
+            None if coarse.mantissa != 0 => coarse.mantissa.cmp(&0),
+            None => 0.cmp(&fine.mantissa),
+        };
+        if flipped {
+            ordering.reverse()
+        } else {
+            ordering
+        }
+    }
+
+    /// The value in base units of a token with `decimals` decimals (e.g. $1.25
+    /// is 1_250_000 USDC units), truncated. Negative values are rejected.
+    pub fn to_token_amount(&self, decimals: u8) -> Result<u64> {
+        require!(self.mantissa >= 0, InsuranceError::InvalidOracleData);
+        let units = self.normalize_to(-(decimals as i32))?;
+        Ok(units.mantissa as u64)
+    }
+}
+
+/// Rescale a fixed-point price from `10^from_expo` to `10^to_expo` units.
+/// Scaling to a coarser exponent truncates toward zero.
+pub fn rescale_price(price: i64, from_expo: i32, to_expo: i32) -> Result<i64> {
+    Ok(FixedPrice::new(price, from_expo).normalize_to(to_expo)?.mantissa)
+}
+
+/// Load the second leg of a spread condition, applying the same staleness bound
+/// as the primary feed, and express it at `target_expo`
+pub fn load_spread_reference(
+    oracle_kind: &OracleKind,
+    oracle_account: &AccountInfo,
+    target_expo: i32,
+    now: i64,
+    max_age_seconds: u64,
+) -> Result<i64> {
+    let reference = load_oracle_price(oracle_kind, oracle_account, now, max_age_seconds)?;
+    Ok(FixedPrice::from_oracle(&reference).normalize_to(target_expo)?.mantissa)
+}
+
+/// Layout of a boolean condition feed account, as written by its publisher:
+///
+/// | offset | size | field                                   |
+/// |--------|------|-----------------------------------------|
+/// | 0      | 8    | `CONDITION_FEED_MAGIC`                  |
+/// | 8      | 1    | value: 0 = false, 1 = true              |
+/// | 9      | 8    | publish_time, unix seconds (i64, LE)    |
+///
+/// Trailing bytes are ignored so publishers may append their own metadata.
+pub struct ConditionFeed;
+
+impl ConditionFeed {
+    pub const LEN: usize = 17;
+}
+
+/// Leading bytes identifying a `ConditionFeed` account
+pub const CONDITION_FEED_MAGIC: [u8; 8] = *b"CONDFEED";
+
+/// Read a `ConditionFeed` account, rejecting malformed data and values
+/// published more than `max_age_seconds` ago
+pub fn load_condition_flag(account: &AccountInfo, now: i64, max_age_seconds: u64) -> Result<bool> {
+    let data = account.try_borrow_data()?;
+    require!(
+        data.len() >= ConditionFeed::LEN && data[..8] == CONDITION_FEED_MAGIC,
+        InsuranceError::InvalidOracleData
+    );
+    let value = match data[8] {
+        0 => false,
+        1 => true,
+        _ => return err!(InsuranceError::InvalidOracleData),
+    };
+    let publish_time = data[9..17]
+        .try_into()
+        .map(i64::from_le_bytes)
+        .map_err(|_| InsuranceError::InvalidOracleData)?;
+    require!(
+        publish_time <= now && (now - publish_time) as u64 <= max_age_seconds,
+        InsuranceError::StaleOracleData
+    );
+    Ok(value)
+}
+
+/// Entries an `AuditLog` keeps before overwriting the oldest
+#[cfg(not(test))]
+pub const AUDIT_LOG_CAPACITY: usize = 128;
+#[cfg(test)]
+pub const AUDIT_LOG_CAPACITY: usize = 4;
+
+/// Most entries `read_audit_log` returns, bounded by the return data limit
+pub const MAX_AUDIT_ENTRIES_PER_READ: usize = 10;
+
+/// Kind of privileged action an `AuditEntry` records
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+#[repr(u8)]
+pub enum AuditAction {
+    OracleSwap,
+    ConfigUpdate,
+    EmergencyWithdraw,
+    DisputeResolution,
+}
+
+/// One privileged action. Fields are ordered widest first so the layout has
+/// no implicit padding.
+#[zero_copy]
+#[derive(Default, Debug, PartialEq, AnchorSerialize, AnchorDeserialize)]
+pub struct AuditEntry {
+    pub sequence: u64,
+    pub amount: u64,
+    pub timestamp: i64,
+    pub actor: Pubkey,
+    /// What the action changed: a policy, mint, destination or the config
+    pub target: Pubkey,
+    /// `AuditAction` discriminant
+    pub action: u8,
+    pub padding: [u8; 7],
+}
+
+/// Append-only ring of privileged actions for a pool, or for the protocol
+/// when `scope` is the config. Entries are never rewritten except by the
+/// ring wrapping, and `sequence` only grows, so gaps show as missing numbers.
+#[account(zero_copy)]
+pub struct AuditLog {
+    /// Pool or config this log is registered on
+    pub scope: Pubkey,
+    /// Entries ever written; the next entry takes this sequence number
+    pub sequence: u64,
+    pub entries: [AuditEntry; AUDIT_LOG_CAPACITY],
+}
+
+impl AuditLog {
+    pub const LEN: usize = 8 + // discriminator
+        32 + // scope
+        8 + // sequence
+        96 * AUDIT_LOG_CAPACITY; // entries
+
+    pub fn record(
+        &mut self,
+        action: AuditAction,
+        actor: Pubkey,
+        target: Pubkey,
+        amount: u64,
+        now: i64,
+    ) {
+        let slot = (self.sequence % AUDIT_LOG_CAPACITY as u64) as usize;
+        self.entries[slot] = AuditEntry {
+            sequence: self.sequence,
+            amount,
+            timestamp: now,
+            actor,
+            target,
+            action: action as u8,
+            padding: [0; 7],
+        };
+        self.sequence += 1;
+    }
+
+    /// Newest `count` entries still in the ring, oldest first
+    pub fn latest(&self, count: usize) -> Vec<AuditEntry> {
+        let retained = self.sequence.min(AUDIT_LOG_CAPACITY as u64);
+        let first = self.sequence - retained.min(count as u64);
+        (first..self.sequence)
+            .map(|sequence| self.entries[(sequence % AUDIT_LOG_CAPACITY as u64) as usize])
+            .collect()
+    }
+}
+
+/// Largest group a group policy's claim bitmap is sized for
+pub const MAX_GROUP_MEMBERS: u32 = 4_096;
+
+/// Leaf committing to one group member's slice. Domain-separated from inner
+/// nodes so a node cannot be passed off as a leaf.
+pub fn group_leaf(index: u32, member: &Pubkey, weight: u64) -> [u8; 32] {
+    hashv(&[&[0], &index.to_le_bytes(), member.as_ref(), &weight.to_le_bytes()]).to_bytes()
+}
+
+/// Verify `leaf` against `root`. Pairs are hashed in sorted order, so proofs
+/// carry only sibling hashes and no left/right flags.
+pub fn verify_merkle_proof(proof: &[[u8; 32]], root: [u8; 32], leaf: [u8; 32]) -> bool {
+    let computed = proof.iter().fold(leaf, |node, sibling| merkle_node(&node, sibling));
+    computed == root
+}
+
+pub fn merkle_node(a: &[u8; 32], b: &[u8; 32]) -> [u8; 32] {
+    let (first, second) = if a <= b { (a, b) } else { (b, a) };
+    hashv(&[&[1], first, second]).to_bytes()
+}
+
+/// Which members of a group policy have claimed, stored at
+/// `[b"group_claims", insurance_policy]` with one bit per leaf index
+#[account]
+pub struct GroupClaims {
+    pub policy: Pubkey,
+    pub member_count: u32,
+    pub claimed_count: u32,
+    pub bitmap: Vec<u8>,
+    pub bump: u8,
+}
+
+impl GroupClaims {
+    pub fn bitmap_len(member_count: u32) -> usize {
+        (member_count as usize + 7) / 8
+    }
+
+    /// Account size for a group of `member_count`
+    pub fn space(member_count: u32) -> usize {
+        8 + // discriminator
+        32 + // policy
+        4 + // member_count
+        4 + // claimed_count
+        4 + Self::bitmap_len(member_count) + // bitmap
+        1 // bump
+    }
+
+    /// Record `index` as claimed, rejecting a second claim of the same leaf
+    pub fn mark_claimed(&mut self, index: u32) -> Result<()> {
+        require!(index < self.member_count, InsuranceError::InvalidMerkleProof);
+        let (byte, bit) = (index as usize / 8, 1u8 << (index % 8));
+        require!(self.bitmap[byte] & bit == 0, InsuranceError::AlreadyClaimed);
+        self.bitmap[byte] |= bit;
+        self.claimed_count += 1;
+        Ok(())
+    }
+
+    pub fn all_claimed(&self) -> bool {
+        self.claimed_count == self.member_count
+    }
+}
+
+/// Submissions each side of a dispute may anchor
+pub const MAX_EVIDENCE_PER_PARTY: u8 = 5;
+
+/// Longest evidence URI, in bytes
+pub const MAX_EVIDENCE_URI_LEN: usize = 200;
+
+/// Which side of a dispute an `Evidence` record was submitted for
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, Debug, InitSpace)]
+pub enum EvidenceRole {
+    Authority,
+    Holder,
+}
+
+/// Off-chain evidence anchored for a disputed trigger, stored at
+/// `[b"evidence", insurance_policy, submitter, index]`
+#[account]
+#[derive(InitSpace)]
+pub struct Evidence {
+    pub policy: Pubkey,
+    pub submitter: Pubkey,
+    pub role: EvidenceRole,
+    pub index: u8,
+    #[max_len(MAX_EVIDENCE_URI_LEN)]
+    pub uri: String,
+    /// Digest of the document behind `uri`
+    pub hash: [u8; 32],
+    pub submitted_timestamp: i64,
+    pub bump: u8,
+}
+
+/// Bytes a `SignedReport` reporter signs: policy key, observed value and
+/// observation timestamp (little-endian)
+pub fn signed_report_message(policy: &Pubkey, observed_value: i64, timestamp: i64) -> Vec<u8> {
+    let mut message = Vec::with_capacity(48);
+    message.extend_from_slice(policy.as_ref());
+    message.extend_from_slice(&observed_value.to_le_bytes());
+    message.extend_from_slice(&timestamp.to_le_bytes());
+    message
+}
+
+/// Size of the ed25519 program's instruction header with a single signature
+const ED25519_SINGLE_SIGNATURE_HEADER_LEN: usize = 16;
+
+/// Check that `ix` is an ed25519-program instruction verifying exactly one
+/// signature by `signer` over `message`, with all data embedded in `ix` itself.
+/// The ed25519 program has already rejected the transaction if the signature is bad.
+pub fn verify_ed25519_instruction(ix: &Instruction, signer: &Pubkey, message: &[u8]) -> Result<()> {
+    require_keys_eq!(ix.program_id, ed25519_program::ID, InsuranceError::InvalidSignedReport);
+    let data = &ix.data;
+    require!(
+        data.len() >= ED25519_SINGLE_SIGNATURE_HEADER_LEN && data[0] == 1,
+        InsuranceError::InvalidSignedReport
+    );
+
+    let read_u16 = |at: usize| u16::from_le_bytes([data[at], data[at + 1]]);
+    let signature_ix_index = read_u16(4);
+    let public_key_offset = read_u16(6) as usize;
+    let public_key_ix_index = read_u16(8);
+    let message_offset = read_u16(10) as usize;
+    let message_size = read_u16(12) as usize;
+    let message_ix_index = read_u16(14);
+
+    // Offsets pointing into other instructions could smuggle in unrelated data
+    require!(
+        signature_ix_index == u16::MAX
+            && public_key_ix_index == u16::MAX
+            && message_ix_index == u16::MAX,
+        InsuranceError::InvalidSignedReport
+    );
+    require!(
+        data.get(public_key_offset..public_key_offset + 32) == Some(signer.as_ref()),
+        InsuranceError::InvalidSignedReport
+    );
+    require!(
+        data.get(message_offset..message_offset + message_size) == Some(message),
+        InsuranceError::InvalidSignedReport
+    );
+    Ok(())
+}
+
+/// Number of backup feeds a policy may list after its fallback
+pub const MAX_BACKUP_ORACLES: usize = 3;
+
+/// Number of independent feeds a policy may require to agree
+pub const MAX_QUORUM_ORACLES: usize = 5;
+
+/// Which configured feed an observation was read from
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Default, PartialEq, Eq, Debug, InitSpace)]
+pub enum OracleSource {
+    #[default]
+    Primary,
+    Fallback,
+    /// Index into `backup_oracles`
+    Backup(u8),
+}
+
+/// Prefer the primary reading; use the fallback and then each backup in order
+/// only when everything before it is stale or unreadable. When all fail the
+/// primary's error is returned.
+pub fn select_oracle_price(
+    primary: Result<Price>,
+    fallback: Option<Result<Price>>,
+    backups: Vec<Result<Price>>,
+) -> Result<(Price, OracleSource)> {
+    let error = match primary {
+        Ok(price) => return Ok((price, OracleSource::Primary)),
+        Err(error) => error,
+    };
+    if let Some(Ok(price)) = fallback {
+        return Ok((price, OracleSource::Fallback));
+    }
+    backups
+        .into_iter()
+        .enumerate()
+        .find_map(|(index, reading)| {
+            reading.ok().map(|price| (price, OracleSource::Backup(index as u8)))
+        })
+        .ok_or(error)
+}
+
+/// Program that owns pull-oracle `PriceUpdateV2` accounts
+pub const PYTH_RECEIVER_PROGRAM_ID: Pubkey =
+    anchor_lang::solana_program::pubkey!("rec5EKMGg6MxZYaMdyBfgwp4d5rB9T1VQH5pJv5LtFJ");
+
+/// How many Wormhole guardian signatures backed a pull-oracle update
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, PartialEq, Eq, Debug)]
+pub enum VerificationLevel {
+    Partial { num_signatures: u8 },
+    Full,
+}
+
+/// Price payload of a `PriceUpdateV2` account
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug)]
+pub struct PriceFeedMessage {
+    pub feed_id: [u8; 32],
+    pub price: i64,
+    pub conf: u64,
+    pub exponent: i32,
+    pub publish_time: i64,
+    pub prev_publish_time: i64,
+    pub ema_price: i64,
+    pub ema_conf: u64,
+}
+
+/// Mirror of pyth-solana-receiver's `PriceUpdateV2` account layout, decoded by hand
+/// so this program does not depend on the receiver SDK's Anchor version
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug)]
+pub struct PriceUpdateV2 {
+    pub write_authority: Pubkey,
+    pub verification_level: VerificationLevel,
+    pub price_message: PriceFeedMessage,
+    pub posted_slot: u64,
+}
+
+impl PriceUpdateV2 {
+    pub fn discriminator() -> [u8; 8] {
+        let mut discriminator = [0u8; 8];
+        discriminator.copy_from_slice(&hash(b"account:PriceUpdateV2").to_bytes()[..8]);
+        discriminator
+    }
+}
+
+/// Read a pull-oracle price update, checking ownership, feed id, full guardian
+/// verification and staleness
+pub fn load_pyth_pull_price(
+    oracle_account: &AccountInfo,
+    feed_id: &[u8; 32],
+    now: i64,
+    max_age_seconds: u64,
+) -> Result<Price> {
+    require_keys_eq!(
+        *oracle_account.owner,
+        PYTH_RECEIVER_PROGRAM_ID,
+        InsuranceError::InvalidOracleData
+    );
+    let data = oracle_account.try_borrow_data()?;
+    require!(
+        data.len() >= 8 && data[..8] == PriceUpdateV2::discriminator(),
+        InsuranceError::InvalidOracleData
+    );
+    let update = PriceUpdateV2::deserialize(&mut &data[8..])
+        .map_err(|_| error!(InsuranceError::InvalidOracleData))?;
+
+    require!(
+        update.verification_level == VerificationLevel::Full,
+        InsuranceError::InvalidOracleData
+    );
+    let message = update.price_message;
+    require!(message.feed_id == *feed_id, InsuranceError::OracleMismatch);
+    require!(
+        message.publish_time.saturating_add(max_age_seconds as i64) >= now,
+        InsuranceError::StaleOracleData
+    );
+
+    Ok(Price {
+        price: message.price,
+        conf: message.conf,
+        expo: message.exponent,
+        publish_time: message.publish_time,
+    })
+}
+
+/// Most payout recipients a single policy may split between
+pub const MAX_BENEFICIARIES: usize = 5;
+
+/// One recipient of a syndicated payout
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Default, PartialEq, Eq, Debug, InitSpace)]
+pub struct Beneficiary {
+    pub beneficiary: Pubkey,
+    pub share_bps: u16,
+}
+
+/// A split must name at most `MAX_BENEFICIARIES` recipients whose shares sum to
+/// exactly 100%; an empty list pays the policy holder
+pub fn validate_beneficiaries(beneficiaries: &[Beneficiary]) -> Result<()> {
+    if beneficiaries.is_empty() {
+        return Ok(());
+    }
+    require!(
+        beneficiaries.len() <= MAX_BENEFICIARIES,
+        InsuranceError::InvalidBeneficiaryShares
+    );
+    let total: u64 = beneficiaries.iter().map(|b| b.share_bps as u64).sum();
+    require!(total == BPS_DENOMINATOR, InsuranceError::InvalidBeneficiaryShares);
+    Ok(())
+}
+
+/// Kind of token account a holder payout is sent to
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum PayoutDestination {
+    /// Canonical ATA for (holder, payout mint); created during payout if missing
+    AssociatedTokenAccount,
+    /// Holder-approved token account; must already exist
+    Custom,
+}
+
+/// Rough payout probability returned by `estimate_payout_odds`.
+///
+/// Model: the price is assumed normally distributed around the current oracle
+/// price with the oracle confidence interval as its standard deviation, and
+/// the odds are the chance of it reaching the trigger level. Time to expiry,
+/// sustained-duration requirements, quorum and circuit breakers are ignored,
+/// so the figure is an indication for underwriters, not a price.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, PartialEq, Eq, Debug)]
+pub struct PayoutOdds {
+    /// Estimated probability in bps; `10_000` when the condition already holds
+    pub odds_bps: u16,
+    /// Distance to the trigger level in bps of the confidence interval;
+    /// 0 when the condition holds, `u64::MAX` when the feed reports no uncertainty
+    pub distance_conf_bps: u64,
+    /// Reading the estimate was made from, at the policy's exponent
+    pub price: i64,
+    pub confidence: i64,
+    pub publish_time: i64,
+}
+
+/// Upper normal tail sampled at 0, 1, 2, 3 and 4 standard deviations (in bps)
+const ODDS_TAIL_KNOTS: [(u64, u64); 5] = [
+    (0, 5_000),
+    (10_000, 1_587),
+    (20_000, 228),
+    (30_000, 13),
+    (40_000, 0),
+];
+
+/// Piecewise-linear approximation of the chance of moving at least
+/// `distance_conf_bps` (in bps of one standard deviation) in one direction
+pub fn odds_from_distance(distance_conf_bps: u64) -> u16 {
+    for pair in ODDS_TAIL_KNOTS.windows(2) {
+        let ((x0, y0), (x1, y1)) = (pair[0], pair[1]);
+        if distance_conf_bps <= x1 {
+            let offset = distance_conf_bps.saturating_sub(x0);
+            return (y0 - (y0 - y1) * offset / (x1 - x0)) as u16;
+        }
+    }
+    0
+}
+
+/// Premium rate, in bps of coverage, quoted for a trigger the price has
+/// already reached
+pub const MAX_QUOTE_RATE_BPS: u64 = 5_000;
+
+/// Distance to the trigger, in bps of price, at which the quoted rate halves
+pub const QUOTE_HALF_RATE_DISTANCE_BPS: u64 = 1_000;
+
+/// Suggested premium for `coverage_amount` under a simple inverse-distance
+/// model: the rate is `MAX_QUOTE_RATE_BPS` at the trigger and falls off as
+/// `half / (distance + half)` the further the price has to travel. `price` and
+/// `threshold` share an exponent. Like `PayoutOdds`, this is a starting point
+/// for underwriters rather than an actuarial price. Rounds up.
+pub fn suggested_premium(
+    coverage_amount: u64,
+    condition: TriggerConditionType,
+    threshold: i64,
+    price: i64,
+) -> Result<u64> {
+    let distance_bps = trigger_distance_bps(condition, threshold, price)?;
+    let half = QUOTE_HALF_RATE_DISTANCE_BPS;
+    let rate_bps = math::prorate(
+        MAX_QUOTE_RATE_BPS,
+        half,
+        distance_bps.saturating_add(half),
+        RoundingMode::Floor,
+    )?;
+    apply_bps(coverage_amount, rate_bps, RoundingMode::Ceil)
+}
+
+/// How far, in bps of `price`, the price must move before `condition` holds;
+/// 0 once it does. Relative conditions are quoted as if bought now, so the
+/// distance is the threshold itself.
+pub fn trigger_distance_bps(
+    condition: TriggerConditionType,
+    threshold: i64,
+    price: i64,
+) -> Result<u64> {
+    let gap = match condition {
+        TriggerConditionType::PriceAbove => threshold as i128 - price as i128,
+        TriggerConditionType::PriceBelow => price as i128 - threshold as i128,
+        TriggerConditionType::RelativeBelow | TriggerConditionType::RelativeAbove => {
+            return Ok(threshold.max(0) as u64)
+        }
+        TriggerConditionType::VolatilityAbove | TriggerConditionType::SpreadAbove => {
+            return err!(InsuranceError::OddsUnavailable)
+        }
+    };
+    require!(price != 0, InsuranceError::InvalidOracleData);
+    let distance = gap.max(0) * BPS_DENOMINATOR as i128 / price.unsigned_abs() as i128;
+    Ok(u64::try_from(distance).unwrap_or(u64::MAX))
+}
+
+/// Lifecycle summary returned by `get_policy_timeline`
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, PartialEq, Eq)]
+pub struct PolicyTimeline {
+    pub status: PolicyStatus,
+    pub created_timestamp: i64,
+    pub purchased_timestamp: Option<i64>,
+    pub triggered_timestamp: Option<i64>,
+    pub payout_timestamp: Option<i64>,
+    pub cancelled_timestamp: Option<i64>,
+    pub expiry_timestamp: i64,
+}
+
+/// Arguments passed to a payout callback program
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, PartialEq, Eq, Debug)]
+pub struct PayoutCallbackArgs {
+    pub policy: Pubkey,
+    pub amount: u64,
+}
+
+/// Build the `on_insurance_payout(policy, amount)` instruction invoked on the
+/// callback program. The discriminator follows Anchor's `global:<name>` convention
+/// so callback programs can be written as ordinary Anchor instructions.
+pub fn payout_callback_instruction(callback_program: Pubkey, policy: Pubkey, amount: u64) -> Instruction {
+    // Borsh layout of `PayoutCallbackArgs`, written out so this path cannot fail
+    let mut data = hash(b"global:on_insurance_payout").to_bytes()[..8].to_vec();
+    data.extend_from_slice(policy.as_ref());
+    data.extend_from_slice(&amount.to_le_bytes());
+    Instruction {
+        program_id: callback_program,
+        accounts: vec![AccountMeta::new_readonly(policy, false)],
+        data,
+    }
+}
+
+/// Source of the policy's price observations
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Default, PartialEq, Eq, Debug, InitSpace)]
+pub enum OracleKind {
+    /// Legacy Pyth push feed read with `load_price_feed_from_account_info`
+    #[default]
+    PythLegacy,
+    /// Pyth pull-oracle `PriceUpdateV2` account for the given feed
+    PythPull { feed_id: [u8; 32] },
+    /// Off-chain reporter attestations submitted via `submit_signed_observation`
+    SignedReport { reporter: Pubkey },
+}
+
+/// Capital backing every policy whose premiums are paid in `mint`, stored at
+/// `[b"pool", mint]`. `authority` administers the pool's capital and settings.
+#[account]
+#[derive(Default, InitSpace)]
+pub struct PoolState {
+    pub authority: Pubkey,
+    pub vault: Pubkey,
+    pub total_capital: u64,
+    pub total_committed_coverage: u64,
+    /// Maximum committed coverage as a fraction of capital
+    pub reserve_ratio_bps: u16,
+    pub bump: u8,
+    /// Rolling payout cap; a zero cap disables the circuit breaker
+    pub drawdown_window_seconds: i64,
+    pub max_drawdown_per_window: u64,
+    pub window_start_timestamp: i64,
+    pub window_paid_out: u64,
+    /// Same as `vault` unless payouts settle in a separate mint
+    pub payout_vault: Pubkey,
+    /// Coverage of policies triggered but not yet paid out
+    pub triggered_obligations: u64,
+    /// Shortfall owed to partially paid claims
+    pub outstanding_claims: u64,
+    /// Pending emergency withdrawal; `None` when no request is outstanding
+    pub emergency_withdraw_amount: u64,
+    pub emergency_withdraw_destination: Pubkey,
+    pub emergency_withdraw_executable_at: Option<i64>,
+    /// Premium multiplier curve over utilization: the multiplier rises by
+    /// `utilization_slope_bps` per 100% of utilization up to the kink and by
+    /// `utilization_steep_slope_bps` beyond it. All zero prices flat.
+    pub utilization_kink_bps: u16,
+    pub utilization_slope_bps: u32,
+    pub utilization_steep_slope_bps: u32,
+    /// Capital awaiting withdrawal; excluded from the pool's coverage capacity
+    pub queued_capital: u64,
+    /// Index of the oldest unprocessed `WithdrawalRequest` and of the next one to be queued
+    pub withdrawal_queue_head: u64,
+    pub withdrawal_queue_tail: u64,
+    /// Mint of `vault`, and the pool's address seed
+    pub mint: Pubkey,
+    /// Purchased policies not yet paid out, cancelled, lapsed or expired
+    pub active_policies: u64,
+    /// Stake, in `mint`, a keeper must hold to earn check rewards; 0 pays any keeper
+    pub keeper_stake_required: u64,
+    /// Wait between `deregister_keeper` and the stake's return
+    pub keeper_cooldown_seconds: i64,
+    /// `AuditLog` recording privileged actions on this pool
+    pub audit_log: Pubkey,
+    /// Capital in `payout_vault` when it is separate from `vault`, in the payout
+    /// mint; `total_capital` then counts only `vault`
+    pub payout_capital: u64,
+    /// Keeper deposit lamports forfeited to the pool, held on this account
+    /// above rent until the authority withdraws them
+    pub forfeited_deposits: u64,
+}
+
+/// Outcome for one policy of `execute_payouts_batch`, returned in input order
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BatchPayoutResult {
+    Paid,
+    /// Not awaiting payout, e.g. already paid, disputed or never triggered
+    NotTriggered,
+    /// Needs its own payout instruction, is still held, belongs to another
+    /// authority or pool, or was listed twice
+    Ineligible,
+    InvalidDestination,
+    /// The vault or drawdown cap could not cover this claim or one before it
+    InsufficientLiquidity,
+}
+
+/// Pool summary returned by `get_pool_metrics`
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug, PartialEq, Eq)]
+pub struct PoolMetrics {
+    pub total_capital: u64,
+    pub total_committed_coverage: u64,
+    pub active_policies: u64,
+    /// Committed coverage in bps of capital
+    pub utilization_bps: u64,
+}
+
+/// Address of the pool for premiums paid in `mint`
+pub fn pool_address(mint: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(&[b"pool", mint.as_ref()], &crate::ID).0
+}
+
+/// Delay between requesting and executing an emergency withdrawal
+pub const EMERGENCY_WITHDRAW_TIMELOCK_SECONDS: i64 = 7 * 24 * 60 * 60;
+
+impl PoolState {
+    /// Whether payouts settle from a vault, and mint, other than the premium vault's
+    pub fn has_separate_payout_vault(&self) -> bool {
+        self.payout_vault != self.vault
+    }
+
+    /// Credit `amount` deposited into `vault_key`, counting it against the
+    /// vault that holds it so the two mints never share a counter
+    pub fn credit_capital(&mut self, vault_key: Pubkey, amount: u64) -> Result<()> {
+        let capital = if self.has_separate_payout_vault() && vault_key == self.payout_vault {
+            &mut self.payout_capital
+        } else {
+            &mut self.total_capital
+        };
+        *capital = capital.checked_add(amount).ok_or(InsuranceError::MathOverflow)?;
+        Ok(())
+    }
+
+    /// Count `amount` lamports of a keeper deposit forfeited to the pool
+    pub fn record_forfeited_deposit(&mut self, amount: u64) -> Result<()> {
+        self.forfeited_deposits = self
+            .forfeited_deposits
+            .checked_add(amount)
+            .ok_or(InsuranceError::MathOverflow)?;
+        Ok(())
+    }
+
+    /// Release every forfeited deposit for withdrawal, returning the lamports
+    pub fn take_forfeited_deposits(&mut self) -> u64 {
+        std::mem::take(&mut self.forfeited_deposits)
+    }
+
+    /// Debit a claim paid out of `payout_vault`
+    pub fn debit_payout(&mut self, amount: u64) {
+        let capital = if self.has_separate_payout_vault() {
+            &mut self.payout_capital
+        } else {
+            &mut self.total_capital
+        };
+        *capital = capital.saturating_sub(amount);
+    }
+
+    /// Coverage the pool may have outstanding at its current capital, not
+    /// counting capital queued for withdrawal
+    pub fn coverage_capacity(&self) -> u64 {
+        let capital = self.total_capital.saturating_sub(self.queued_capital);
+        // Capacity rounds down so the pool never over-commits by a fractional token
+        apply_bps(capital, self.reserve_ratio_bps as u64, RoundingMode::Floor).unwrap_or(u64::MAX)
+    }
+
+    /// Reserve `coverage` against the pool, rejecting over-commitment
+    pub fn commit(&mut self, coverage: u64) -> Result<()> {
+        let committed = self
+            .total_committed_coverage
+            .checked_add(coverage)
+            .ok_or(InsuranceError::PoolCapacityExceeded)?;
+        require!(
+            committed <= self.coverage_capacity(),
+            InsuranceError::PoolCapacityExceeded
+        );
+        self.total_committed_coverage = committed;
+        Ok(())
+    }
+
+    /// Committed coverage plus `additional_coverage`, in bps of capital.
+    /// Coverage beyond the pool's capital is refused outright.
+    pub fn utilization_bps(&self, additional_coverage: u64) -> Result<u64> {
+        let outstanding = self.total_committed_coverage as i128 + additional_coverage as i128;
+        if outstanding == 0 {
+            return Ok(0);
+        }
+        require!(self.total_capital > 0, InsuranceError::PoolCapacityExceeded);
+        let utilization = math::mul_div(
+            outstanding as u128,
+            BPS_DENOMINATOR as u128,
+            self.total_capital as u128,
+            RoundingMode::Floor,
+        )?;
+        require!(utilization <= BPS_DENOMINATOR as u128, InsuranceError::PoolCapacityExceeded);
+        Ok(utilization as u64)
+    }
+
+    /// Premium multiplier, in bps, for writing `additional_coverage` against the
+    /// pool; 10_000 (1x) until a utilization curve is configured
+    pub fn premium_multiplier_bps(&self, additional_coverage: u64) -> Result<u64> {
+        let utilization = self.utilization_bps(additional_coverage)? as i128;
+        let kink = self.utilization_kink_bps as i128;
+        let denominator = BPS_DENOMINATOR as i128;
+        let below_kink = utilization.min(kink) * self.utilization_slope_bps as i128 / denominator;
+        let above_kink =
+            (utilization - kink).max(0) * self.utilization_steep_slope_bps as i128 / denominator;
+        Ok((denominator + below_kink + above_kink) as u64)
+    }
+
+    /// Expire `policy` once it is past `expirable_at`, retiring its reservation.
+    /// Returns whether it expired; anything not yet expirable or no longer
+    /// active or purchased is left untouched.
+    pub fn close_expired(&mut self, policy: &mut InsurancePolicy, now: i64) -> bool {
+        match policy.expire(now) {
+            Ok(reserved) => {
+                if reserved {
+                    self.retire(policy.coverage_amount);
+                }
+                true
+            }
+            Err(_) => false,
+        }
+    }
+
+    /// Release a reservation made by `commit`
+    pub fn release(&mut self, coverage: u64) {
+        self.total_committed_coverage = self.total_committed_coverage.saturating_sub(coverage);
+    }
+
+    /// Count a newly purchased policy as active
+    pub fn open_policy(&mut self) {
+        self.active_policies = self.active_policies.saturating_add(1);
+    }
+
+    /// Release a settled or ended policy's whole reservation and stop counting it
+    pub fn retire(&mut self, coverage: u64) {
+        self.release(coverage);
+        self.active_policies = self.active_policies.saturating_sub(1);
+    }
+
+    /// Snapshot for capital providers; utilization saturates instead of failing
+    /// when withdrawals have left coverage above capital
+    pub fn metrics(&self) -> PoolMetrics {
+        let utilization_bps = match self.total_capital {
+            0 if self.total_committed_coverage == 0 => 0,
+            0 => u64::MAX,
+            capital => math::prorate(
+                self.total_committed_coverage,
+                BPS_DENOMINATOR,
+                capital,
+                RoundingMode::Floor,
+            )
+            .unwrap_or(u64::MAX),
+        };
+        PoolMetrics {
+            total_capital: self.total_capital,
+            total_committed_coverage: self.total_committed_coverage,
+            active_policies: self.active_policies,
+            utilization_bps,
+        }
+    }
+
+    /// Count `amount` against the current drawdown window, starting a new window
+    /// once the previous one has elapsed
+    pub fn record_payout(&mut self, amount: u64, now: i64) -> Result<()> {
+        if self.max_drawdown_per_window == 0 {
+            return Ok(());
+        }
+        if now >= self.window_start_timestamp.saturating_add(self.drawdown_window_seconds) {
+            self.window_start_timestamp = now;
+            self.window_paid_out = 0;
+        }
+        let paid_out = self
+            .window_paid_out
+            .checked_add(amount)
+            .ok_or(InsuranceError::DrawdownLimitExceeded)?;
+        require!(
+            paid_out <= self.max_drawdown_per_window,
+            InsuranceError::DrawdownLimitExceeded
+        );
+        self.window_paid_out = paid_out;
+        Ok(())
+    }
+
+    /// Decide which batch claims are paid, strictly in input order. Each `Ok`
+    /// claim is paid in full while the vault balance and the drawdown cap allow;
+    /// the first one that does not fit, and every claim after it, is left
+    /// unpaid. Paid amounts are recorded against the drawdown window.
+    pub fn allocate_batch_payouts(
+        &mut self,
+        claims: &[std::result::Result<u64, BatchPayoutResult>],
+        vault_balance: u64,
+        now: i64,
+    ) -> Vec<BatchPayoutResult> {
+        let mut available = vault_balance;
+        let mut exhausted = false;
+        claims
+            .iter()
+            .map(|claim| match *claim {
+                Err(skipped) => skipped,
+                Ok(owed) => {
+                    exhausted = exhausted
+                        || owed > available
+                        || self.record_payout(owed, now).is_err();
+                    if exhausted {
+                        return BatchPayoutResult::InsufficientLiquidity;
+                    }
+                    available -= owed;
+                    BatchPayoutResult::Paid
+                }
+            })
+            .collect()
+    }
+
+    /// Track coverage owed to a newly triggered policy
+    pub fn add_obligation(&mut self, coverage: u64) {
+        self.triggered_obligations = self.triggered_obligations.saturating_add(coverage);
+    }
+
+    /// Drop an obligation once it is paid or the trigger is overturned
+    pub fn settle_obligation(&mut self, coverage: u64) {
+        self.triggered_obligations = self.triggered_obligations.saturating_sub(coverage);
+    }
+
+    pub fn add_outstanding_claim(&mut self, amount: u64) {
+        self.outstanding_claims = self.outstanding_claims.saturating_add(amount);
+    }
+
+    pub fn settle_outstanding_claim(&mut self, amount: u64) {
+        self.outstanding_claims = self.outstanding_claims.saturating_sub(amount);
+    }
+
+    /// Balance that withdrawals must leave behind for triggered and unpaid claims
+    pub fn owed_to_claimants(&self) -> u64 {
+        self.triggered_obligations.saturating_add(self.outstanding_claims)
+    }
+
+    /// Capital not already owed to triggered or partially paid claims
+    pub fn withdrawable_capital(&self) -> u64 {
+        self.total_capital.saturating_sub(self.owed_to_claimants())
+    }
+
+    /// Record a withdrawal request and return when it becomes executable
+    pub fn request_emergency_withdraw(&mut self, amount: u64, destination: Pubkey, now: i64) -> Result<i64> {
+        require!(amount > 0, InsuranceError::InvalidPolicyParameters);
+        require!(
+            self.emergency_withdraw_executable_at.is_none(),
+            InsuranceError::EmergencyWithdrawPending
+        );
+        let executable_at = now
+            .checked_add(EMERGENCY_WITHDRAW_TIMELOCK_SECONDS)
+            .ok_or(InsuranceError::MathOverflow)?;
+        self.emergency_withdraw_amount = amount;
+        self.emergency_withdraw_destination = destination;
+        self.emergency_withdraw_executable_at = Some(executable_at);
+        Ok(executable_at)
+    }
+
+    /// Whether the pending request may run now for exactly `amount` to
+    /// `destination`, leaving `vault_balance` enough to cover triggered payouts
+    pub fn check_emergency_withdraw(
+        &self,
+        amount: u64,
+        destination: Pubkey,
+        vault_balance: u64,
+        now: i64,
+    ) -> Result<()> {
+        let executable_at = self
+            .emergency_withdraw_executable_at
+            .ok_or(InsuranceError::NoEmergencyWithdrawPending)?;
+        require!(now >= executable_at, InsuranceError::EmergencyWithdrawLocked);
+        require!(
+            amount == self.emergency_withdraw_amount
+                && destination == self.emergency_withdraw_destination,
+            InsuranceError::EmergencyWithdrawMismatch
+        );
+        let remaining = vault_balance
+            .checked_sub(amount)
+            .ok_or(InsuranceError::InsufficientPoolLiquidity)?;
+        require!(
+            remaining >= self.owed_to_claimants(),
+            InsuranceError::OutstandingPayoutObligations
+        );
+        Ok(())
+    }
+
+    pub fn clear_emergency_withdraw(&mut self) {
+        self.emergency_withdraw_amount = 0;
+        self.emergency_withdraw_destination = Pubkey::default();
+        self.emergency_withdraw_executable_at = None;
+    }
+
+    /// Capital that must stay in the pool: committed coverage grossed up by the
+    /// reserve ratio, rounding up, plus everything owed to claimants
+    pub fn required_backing(&self) -> u64 {
+        let ratio = (self.reserve_ratio_bps as u64).max(1);
+        math::prorate(self.total_committed_coverage, BPS_DENOMINATOR, ratio, RoundingMode::Ceil)
+            .unwrap_or(u64::MAX)
+            .saturating_add(self.owed_to_claimants())
+    }
+
+    /// Capital queued withdrawals may take without leaving coverage unbacked
+    pub fn free_liquidity(&self, vault_balance: u64) -> u64 {
+        self.total_capital
+            .min(vault_balance)
+            .saturating_sub(self.required_backing())
+    }
+
+    /// Queue `amount` of capital for withdrawal and return the request's index
+    pub fn enqueue_withdrawal(&mut self, amount: u64) -> Result<u64> {
+        require!(amount > 0, InsuranceError::InvalidPolicyParameters);
+        let queued = self
+            .queued_capital
+            .checked_add(amount)
+            .ok_or(InsuranceError::MathOverflow)?;
+        require!(queued <= self.total_capital, InsuranceError::InsufficientFunds);
+        self.queued_capital = queued;
+        let index = self.withdrawal_queue_tail;
+        self.withdrawal_queue_tail += 1;
+        Ok(index)
+    }
+
+    /// Pay the request at the head of the queue in full, or fail until enough
+    /// capital is free; later requests wait behind it
+    pub fn fill_withdrawal(&mut self, amount: u64, vault_balance: u64) -> Result<()> {
+        require!(
+            self.free_liquidity(vault_balance) >= amount,
+            InsuranceError::InsufficientPoolLiquidity
+        );
+        self.total_capital -= amount;
+        self.queued_capital = self.queued_capital.saturating_sub(amount);
+        self.withdrawal_queue_head += 1;
+        Ok(())
+    }
+
+    /// Return a cancelled request's capital to the pool's coverage capacity
+    pub fn cancel_withdrawal(&mut self, amount: u64) {
+        self.queued_capital = self.queued_capital.saturating_sub(amount);
+    }
+
+    /// Vault balance beyond the recorded capital, sweepable only while no
+    /// coverage is committed and no claim is owed
+    pub fn sweepable_dust(&self, vault_balance: u64) -> Result<u64> {
+        require!(self.total_committed_coverage == 0, InsuranceError::CoverageInForce);
+        require!(self.owed_to_claimants() == 0, InsuranceError::OutstandingPayoutObligations);
+        Ok(vault_balance.saturating_sub(self.total_capital))
+    }
+}
+
+/// Capital withdrawal waiting for free liquidity, stored at
+/// `[b"withdrawal_request", pool_state, index]` and processed in index order
+#[account]
+#[derive(Default, InitSpace)]
+pub struct WithdrawalRequest {
+    pub pool: Pubkey,
+    pub owner: Pubkey,
+    pub index: u64,
+    pub amount: u64,
+    pub requested_at: i64,
+    /// Cancelled requests keep their place until the queue reaches them and closes them unpaid
+    pub cancelled: bool,
+    pub bump: u8,
+}
+
+/// A keeper's stake in one pool, stored at `[b"keeper", pool_state, keeper]`.
+/// Staked keepers earn check rewards; misbehaving ones can be slashed.
+#[account]
+#[derive(Default, InitSpace)]
+pub struct KeeperRegistration {
+    pub keeper: Pubkey,
+    pub pool: Pubkey,
+    /// Token account owned by this PDA, in the pool's mint
+    pub vault: Pubkey,
+    pub stake: u64,
+    /// When the stake may be withdrawn; set by `deregister_keeper`
+    pub unbonding_at: Option<i64>,
+    pub bump: u8,
+}
+
+impl KeeperRegistration {
+    /// Whether a check signed with `registration` earns the reward in a pool
+    /// requiring `stake_required`
+    pub fn eligible_for_fee(
+        registration: Option<&KeeperRegistration>,
+        stake_required: u64,
+    ) -> bool {
+        if stake_required == 0 {
+            return true;
+        }
+        registration.map_or(false, |registration| {
+            registration.unbonding_at.is_none() && registration.stake >= stake_required
+        })
+    }
+
+    /// Start the cooldown, returning when the stake becomes withdrawable
+    pub fn begin_unbonding(&mut self, now: i64, cooldown_seconds: i64) -> Result<i64> {
+        require!(self.unbonding_at.is_none(), InsuranceError::KeeperStakeLocked);
+        let withdrawable_at = now.saturating_add(cooldown_seconds);
+        self.unbonding_at = Some(withdrawable_at);
+        Ok(withdrawable_at)
+    }
+
+    pub fn check_withdrawable(&self, now: i64) -> Result<()> {
+        require!(
+            self.unbonding_at.map_or(false, |at| now >= at),
+            InsuranceError::KeeperStakeLocked
+        );
+        Ok(())
+    }
+
+    /// Take up to `amount` of the stake, returning how much was taken
+    pub fn slash(&mut self, amount: u64) -> u64 {
+        let slashed = amount.min(self.stake);
+        self.stake -= slashed;
+        slashed
+    }
+}
+
+/// Excess-of-loss cover for one pool, stored at `[b"reinsurance_pool", pool_state]`.
+/// Pays the part of a reinsured policy's claim above its retention in exchange
+/// for a share of that policy's premium.
+#[account]
+#[derive(Default, InitSpace)]
+pub struct ReinsurancePool {
+    pub pool: Pubkey,
+    /// Only key that may deposit or withdraw capital; receives the premium share
+    pub reinsurer: Pubkey,
+    /// Token account owned by this PDA, in the pool's payout mint
+    pub vault: Pubkey,
+    pub total_capital: u64,
+    pub premium_share_bps: u16,
+    pub premiums_earned: u64,
+    pub claims_paid: u64,
+    pub bump: u8,
+}
+
+/// Largest share of a reinsured policy's premium the reinsurer may take
+pub const MAX_REINSURANCE_PREMIUM_SHARE_BPS: u16 = 5_000;
+
+impl ReinsurancePool {
+    /// Unlike the primary pool's, the reinsurer's leg never becomes an
+    /// outstanding claim: it is paid in full or the payout fails
+    pub fn check_cover(&self, vault_balance: u64, reinsured: u64) -> Result<()> {
+        require!(vault_balance >= reinsured, InsuranceError::ReinsuranceShortfall);
+        Ok(())
+    }
+
+    pub fn premium_share(&self, premium_paid: u64) -> Result<u64> {
+        apply_bps(premium_paid, self.premium_share_bps as u64, RoundingMode::Floor)
+    }
+
+    pub fn record_claim(&mut self, amount: u64) {
+        self.total_capital = self.total_capital.saturating_sub(amount);
+        self.claims_paid = self.claims_paid.saturating_add(amount);
+    }
+
+    pub fn withdraw(&mut self, amount: u64) -> Result<()> {
+        self.total_capital = self
+            .total_capital
+            .checked_sub(amount)
+            .ok_or(InsuranceError::InsufficientFunds)?;
+        Ok(())
+    }
+}
+
+/// Most observations a `PriceHistory` buffer may be created with; keeps the
+/// account under the 10 KiB limit for accounts created through CPI
+pub const MAX_PRICE_HISTORY_CAPACITY: usize = 512;
+
+/// Width of a reading's confidence interval in bps of its price, rounded down.
+/// The product is taken in u128 so `conf * 10_000` cannot overflow; a result
+/// beyond u64 saturates, which still compares above any valid threshold.
+pub fn confidence_bps(price: &Price) -> Result<u64> {
+    let magnitude = price.price.unsigned_abs();
+    require!(magnitude != 0, InsuranceError::InvalidOracleData);
+    Ok(math::prorate(price.conf, BPS_DENOMINATOR, magnitude, RoundingMode::Floor)
+        .unwrap_or(u64::MAX))
+}
+
+/// Fixed-point scale for per-sample returns in volatility math (1e-8)
+const RETURN_SCALE: i128 = 100_000_000;
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Default, Debug, PartialEq)]
+pub struct PriceSample {
+    pub timestamp: i64,
+    pub price: i64,
+}
+
+/// Ring buffer of oracle observations for a single policy. Kept out of
+/// `InsurancePolicy` so the history can grow without reallocating the policy.
+/// The number of slots is chosen when the buffer is created.
+#[account]
+pub struct PriceHistory {
+    pub policy: Pubkey,
+    pub min_interval_seconds: i64,
+    /// Slot the next sample is written to
+    pub head: u32,
+    pub len: u32,
+    /// Fixed-length ring; its length is the buffer's capacity
+    pub samples: Vec<PriceSample>,
+}
+
+impl PriceHistory {
+    /// Account size for a buffer holding `capacity` samples
+    pub const fn space(capacity: usize) -> usize {
+        8 + // discriminator
+        32 + // policy
+        8 + // min_interval_seconds
+        4 + // head
+        4 + // len
+        4 + 16 * capacity // samples
+    }
+
+    pub fn new(policy: Pubkey, min_interval_seconds: i64, capacity: usize) -> Result<Self> {
+        require!(
+            min_interval_seconds >= 0 && (2..=MAX_PRICE_HISTORY_CAPACITY).contains(&capacity),
+            InsuranceError::InvalidPolicyParameters
+        );
+        Ok(Self {
+            policy,
+            min_interval_seconds,
+            head: 0,
+            len: 0,
+            samples: vec![PriceSample::default(); capacity],
+        })
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.samples.len()
+    }
+
+    /// Most recent sample, if any
+    pub fn latest(&self) -> Option<PriceSample> {
+        if self.len == 0 {
+            return None;
+        }
+        let index = (self.head as usize + self.capacity() - 1) % self.capacity();
+        Some(self.samples[index])
+    }
+
+    /// Append a sample, overwriting the oldest once the buffer is full. Samples
+    /// must be at least `min_interval_seconds` apart and strictly increasing.
+    pub fn push(&mut self, timestamp: i64, price: i64) -> Result<()> {
+        if let Some(latest) = self.latest() {
+            require!(
+                timestamp > latest.timestamp
+                    && timestamp - latest.timestamp >= self.min_interval_seconds,
+                InsuranceError::ObservationTooFrequent
+            );
+        }
+        self.samples[self.head as usize] = PriceSample { timestamp, price };
+        self.head = ((self.head as usize + 1) % self.capacity()) as u32;
+        self.len = (self.len + 1).min(self.capacity() as u32);
+        Ok(())
+    }
+
+    /// Samples within `window_seconds` of the latest sample, oldest first
+    pub fn window(&self, window_seconds: i64) -> Vec<PriceSample> {
+        let latest = match self.latest() {
+            Some(latest) => latest,
+            None => return Vec::new(),
+        };
+        let oldest = self.head as usize + self.capacity() - self.len as usize;
+        (0..self.len as usize)
+            .map(|i| self.samples[(oldest + i) % self.capacity()])
+            .filter(|sample| sample.timestamp >= latest.timestamp.saturating_sub(window_seconds))
+            .collect()
+    }
+
+    /// Time-weighted average price over the window; each sample is weighted by
+    /// how long it stood before the next one
+    pub fn twap(&self, window_seconds: i64) -> Result<i64> {
+        let samples = self.window(window_seconds);
+        let (first, last) = match (samples.first(), samples.last()) {
+            (Some(first), Some(last)) => (first, last),
+            _ => return err!(InsuranceError::InsufficientHistory),
+        };
+        if samples.len() == 1 {
+            return Ok(first.price);
+        }
+
+        math::weighted_index(samples.windows(2).map(|pair| {
+            let duration = pair[1].timestamp.saturating_sub(pair[0].timestamp);
+            (pair[0].price, duration.max(0) as u64)
+        }))
+    }
+
+    /// Largest peak-to-trough decline over the window, in bps of the peak
+    pub fn max_drawdown(&self, window_seconds: i64) -> Result<u64> {
+        let samples = self.window(window_seconds);
+        require!(!samples.is_empty(), InsuranceError::InsufficientHistory);
+
+        let mut peak = i64::MIN;
+        let mut drawdown: i128 = 0;
+        for sample in samples {
+            peak = peak.max(sample.price);
+            if peak > 0 {
+                let decline = (peak as i128 - sample.price as i128) * BPS_DENOMINATOR as i128;
+                drawdown = drawdown.max(decline / peak as i128);
+            }
+        }
+        u64::try_from(drawdown).map_err(|_| error!(InsuranceError::MathOverflow))
+    }
+
+    /// Population standard deviation of per-sample returns over the window, in
+    /// bps. Simple returns `(p1 - p0) / |p0|` stand in for log returns; the two
+    /// agree to first order for the small moves between consecutive samples.
+    /// Returns are computed at 1e-8 precision and the square root is taken with
+    /// integer arithmetic. At least `min_samples` (and never fewer than two)
+    /// samples must fall inside the window.
+    pub fn realized_volatility_bps(&self, window_seconds: i64, min_samples: usize) -> Result<u64> {
+        let samples = self.window(window_seconds);
+        require!(
+            samples.len() >= min_samples.max(2),
+            InsuranceError::InsufficientHistory
+        );
+
+        let mut sum: i128 = 0;
+        let mut sum_squares: i128 = 0;
+        for pair in samples.windows(2) {
+            let previous = pair[0].price as i128;
+            require!(previous != 0, InsuranceError::MathOverflow);
+            let change = (pair[1].price as i128 - previous)
+                .checked_mul(RETURN_SCALE)
+                .ok_or(InsuranceError::MathOverflow)?;
+            let ret = change / previous.abs();
+            sum = sum.checked_add(ret).ok_or(InsuranceError::MathOverflow)?;
+            sum_squares = ret
+                .checked_mul(ret)
+                .and_then(|square| sum_squares.checked_add(square))
+                .ok_or(InsuranceError::MathOverflow)?;
+        }
+
+        // n^2 * variance = n * sum(r^2) - sum(r)^2
+        let n = (samples.len() - 1) as i128;
+        let scaled_variance = n
+            .checked_mul(sum_squares)
+            .and_then(|total| total.checked_sub(sum * sum))
+            .ok_or(InsuranceError::MathOverflow)?;
+        let std_dev = isqrt(scaled_variance.max(0) as u128) / n as u128;
+        let bps = std_dev / (RETURN_SCALE / BPS_DENOMINATOR as i128) as u128;
+        u64::try_from(bps).map_err(|_| error!(InsuranceError::MathOverflow))
+    }
+}
+
+/// Integer square root, rounded down
+pub fn isqrt(value: u128) -> u128 {
+    if value < 2 {
+        return value;
+    }
+    let mut x = value;
+    let mut y = (x >> 1) + (x & 1);
+    while y < x {
+        x = y;
+        y = (x + value / x) / 2;
+    }
+    x
+}
+
+/// Largest admin council `GlobalConfig` has room for
+pub const MAX_COUNCIL_MEMBERS: usize = 7;
+
+/// Protocol-wide settings shared by every pool and policy
+#[account]
+#[derive(Default, InitSpace)]
+pub struct GlobalConfig {
+    /// Bootstrap admin; may grant and revoke roles without holding a role account
+    pub admin: Pubkey,
+    /// Master switch: blocks purchases, trigger checks and payouts while set
+    pub paused: bool,
+    pub bump: u8,
+    /// Keys that approve `AdminAction` proposals
+    #[max_len(MAX_COUNCIL_MEMBERS)]
+    pub council: Vec<Pubkey>,
+    pub approval_threshold: u8,
+    pub proposal_ttl_seconds: i64,
+    pub next_proposal_id: u64,
+    /// Treasury account that receives protocol fees
+    pub fee_recipient: Pubkey,
+    /// Share of each premium skimmed to `fee_recipient`
+    pub protocol_fee_bps: u16,
+    /// Longest a new policy may run, measured from initialization; 0 disables the cap
+    pub max_policy_duration_seconds: i64,
+    /// Policies initialized so far; doubles as the last `policy_number` handed out
+    pub policy_count: u64,
+    /// Premium discounts for holders with a claim-free streak; unused tiers are zeroed
+    pub loyalty_schedule: [LoyaltyTier; MAX_LOYALTY_TIERS],
+    /// Share of the first premium payment paid to the referring integrator
+    pub referral_fee_bps: u16,
+    /// Lamports a keeper escrows on the policy when its check triggers a payout
+    pub keeper_deposit_lamports: u64,
+    /// How long after the trigger a successful payout still refunds the deposit
+    pub keeper_deposit_window_seconds: i64,
+    /// Whether purchases and trigger checks may be reached through CPI
+    pub allow_cpi: bool,
+    /// `AuditLog` recording protocol-wide privileged actions
+    pub audit_log: Pubkey,
+}
+
+/// Whether this program is running as a transaction-level instruction: the
+/// instruction being executed targets it and nothing sits above it on the stack
+pub fn is_direct_invocation(top_level_program: &Pubkey, stack_height: usize) -> bool {
+    *top_level_program == crate::ID && stack_height == TRANSACTION_LEVEL_STACK_HEIGHT
+}
+
+/// Highest protocol fee the admin may configure
+pub const MAX_PROTOCOL_FEE_BPS: u16 = 2_000;
+
+/// Highest referral fee the admin may configure
+pub const MAX_REFERRAL_FEE_BPS: u16 = 500;
+
+/// Number of loyalty tiers the config has room for
+pub const MAX_LOYALTY_TIERS: usize = 4;
+
+/// Largest loyalty discount the admin may configure
+pub const MAX_LOYALTY_DISCOUNT_BPS: u16 = 5_000;
+
+/// Discount granted once a holder has bought `min_claim_free_policies` policies
+/// in a row without a claim
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Default, PartialEq, Eq, Debug, InitSpace)]
+pub struct LoyaltyTier {
+    pub min_claim_free_policies: u16,
+    pub discount_bps: u16,
+}
+
+impl GlobalConfig {
+    pub fn require_not_paused(&self) -> Result<()> {
+        require!(!self.paused, InsuranceError::ProtocolPaused);
+        Ok(())
+    }
+
+    /// Reject calls made from another program unless `allow_cpi` is set, so
+    /// coverage cannot be bought or triggered from inside a composed flash-loan
+    /// or oracle-update transaction
+    pub fn require_direct_invocation(&self, instructions_sysvar: &AccountInfo) -> Result<()> {
+        if self.allow_cpi {
+            return Ok(());
+        }
+        let current_index = sysvar_instructions::load_current_index_checked(instructions_sysvar)?;
+        let current = sysvar_instructions::load_instruction_at_checked(
+            current_index as usize,
+            instructions_sysvar,
+        )?;
+        require!(
+            is_direct_invocation(&current.program_id, get_stack_height()),
+            InsuranceError::CpiNotAllowed
+        );
+        Ok(())
+    }
+
+    /// Discount owed to a holder with `claim_free_policies` in their streak: the
+    /// largest among the tiers they qualify for
+    pub fn loyalty_discount_bps(&self, claim_free_policies: u64) -> u16 {
+        self.loyalty_schedule
+            .iter()
+            .filter(|tier| claim_free_policies >= tier.min_claim_free_policies as u64)
+            .map(|tier| tier.discount_bps)
+            .max()
+            .unwrap_or(0)
+    }
+
+    /// Replace the loyalty schedule; slots past `tiers` are cleared
+    pub fn set_loyalty_schedule(&mut self, tiers: &[LoyaltyTier]) -> Result<()> {
+        require!(tiers.len() <= MAX_LOYALTY_TIERS, InsuranceError::InvalidPolicyParameters);
+        require!(
+            tiers.iter().all(|tier| tier.discount_bps <= MAX_LOYALTY_DISCOUNT_BPS),
+            InsuranceError::InvalidPolicyParameters
+        );
+        self.loyalty_schedule = [LoyaltyTier::default(); MAX_LOYALTY_TIERS];
+        self.loyalty_schedule[..tiers.len()].copy_from_slice(tiers);
+        Ok(())
+    }
+
+    /// Give `policy` the next policy number. Called in the same instruction that
+    /// creates the policy, so a failed initialization never consumes a number.
+    pub fn assign_policy_number(&mut self, policy: &mut InsurancePolicy) -> Result<u64> {
+        self.policy_count = self.policy_count.checked_add(1).ok_or(InsuranceError::MathOverflow)?;
+        policy.policy_number = self.policy_count;
+        Ok(self.policy_count)
+    }
+
+    /// Reject a new policy whose expiry lies further out than the protocol allows
+    pub fn check_policy_duration(&self, expiry_timestamp: i64, now: i64) -> Result<()> {
+        if self.max_policy_duration_seconds > 0 {
+            require!(
+                expiry_timestamp.saturating_sub(now) <= self.max_policy_duration_seconds,
+                InsuranceError::InvalidPolicyParameters
+            );
+        }
+        Ok(())
+    }
+
+    /// Fee taken from `premium`, rounded down in the payer's favor
+    pub fn protocol_fee(&self, premium: u64) -> Result<u64> {
+        apply_bps(premium, self.protocol_fee_bps as u64, RoundingMode::Floor)
+    }
+
+    pub fn is_council_member(&self, key: &Pubkey) -> bool {
+        self.council.contains(key)
+    }
+
+    /// Whether `granter` may change other keys' roles
+    pub fn can_grant(&self, granter: &Pubkey, granter_roles: Option<&Roles>) -> bool {
+        *granter == self.admin
+            || granter_roles.is_some_and(|roles| roles.has(Roles::ADMIN))
+    }
+}
+
+/// Privileged operations that require council approval
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, PartialEq, Eq, Debug, InitSpace)]
+pub enum AdminAction {
+    Pause { paused: bool },
+    UpdateTreasury { fee_recipient: Pubkey },
+    /// Return a disputed policy to `TriggeredPayout`, or revert it to `Purchased`.
+    /// `winning_evidence` indexes the prevailing party's `Evidence` records.
+    ResolveDispute { policy: Pubkey, uphold_trigger: bool, winning_evidence: Option<u8> },
+    /// Queue a withdrawal of free capital from a pool's premium vault to
+    /// `destination`, executable once the emergency timelock has elapsed
+    EmergencyWithdraw { pool: Pubkey, amount: u64, destination: Pubkey },
+}
+
+/// Pending `AdminAction`, stored at `[b"admin_action", id]` until executed
+#[account]
+#[derive(InitSpace)]
+pub struct AdminProposal {
+    pub id: u64,
+    pub proposer: Pubkey,
+    pub action: AdminAction,
+    #[max_len(MAX_COUNCIL_MEMBERS)]
+    pub approvals: Vec<Pubkey>,
+    pub created_timestamp: i64,
+    pub expires_timestamp: i64,
+    pub bump: u8,
+}
+
+impl AdminProposal {
+    pub fn approve(&mut self, config: &GlobalConfig, approver: Pubkey, now: i64) -> Result<()> {
+        require!(config.is_council_member(&approver), InsuranceError::Unauthorized);
+        require!(now < self.expires_timestamp, InsuranceError::ProposalExpired);
+        require!(!self.approvals.contains(&approver), InsuranceError::DuplicateApproval);
+        self.approvals.push(approver);
+        Ok(())
+    }
+
+    /// Approvals are counted against the council as currently seated
+    pub fn ensure_executable(&self, config: &GlobalConfig, now: i64) -> Result<()> {
+        require!(now < self.expires_timestamp, InsuranceError::ProposalExpired);
+        let approvals = self
+            .approvals
+            .iter()
+            .filter(|approver| config.is_council_member(approver))
+            .count();
+        require!(
+            config.approval_threshold > 0 && approvals >= config.approval_threshold as usize,
+            InsuranceError::ApprovalThresholdNotMet
+        );
+        Ok(())
+    }
+}
+
+/// Purchase and claim record of one holder across all their policies, stored
+/// at `[b"holder_stats", holder]` and created on their first `purchase_policy`
+#[account]
+#[derive(Default, InitSpace)]
+pub struct HolderStats {
+    pub holder: Pubkey,
+    pub policies_purchased: u64,
+    pub claims_paid: u64,
+    /// Premium paid at purchase, net of discounts and including the protocol fee
+    pub premiums_paid_total: u64,
+    /// Policies bought since the holder's last paid claim; selects the loyalty tier
+    pub claim_free_policies: u64,
+    pub bump: u8,
+}
+
+impl HolderStats {
+    pub fn record_purchase(&mut self, premium_paid: u64) {
+        self.policies_purchased = self.policies_purchased.saturating_add(1);
+        self.claim_free_policies = self.claim_free_policies.saturating_add(1);
+        self.premiums_paid_total = self.premiums_paid_total.saturating_add(premium_paid);
+    }
+
+    /// A paid claim drops the holder back to the bottom loyalty tier
+    pub fn record_claim(&mut self) {
+        self.claims_paid = self.claims_paid.saturating_add(1);
+        self.claim_free_policies = 0;
+    }
+}
+
+/// Smallest premium and coverage a policy may be written for in one mint,
+/// stored at `[b"mint_limits", mint]`. Amounts are in the mint's base units,
+/// since a token of one mint can be worth far more than a token of another.
+#[account]
+#[derive(Default, InitSpace)]
+pub struct MintLimits {
+    pub mint: Pubkey,
+    pub min_premium_amount: u64,
+    pub min_coverage_amount: u64,
+    pub bump: u8,
+}
+
+impl MintLimits {
+    pub fn address(mint: &Pubkey) -> Pubkey {
+        Pubkey::find_program_address(&[b"mint_limits", mint.as_ref()], &crate::ID).0
+    }
+
+    /// Read the limits for `mint` from `account`, which must sit at the mint's
+    /// limits address. `None` if no limits have been set for the mint.
+    pub fn load(account: &AccountInfo, mint: &Pubkey) -> Result<Option<Self>> {
+        require_keys_eq!(account.key(), Self::address(mint), InsuranceError::WrongMint);
+        if account.data_is_empty() {
+            return Ok(None);
+        }
+        Ok(Some(Account::<MintLimits>::try_from(account)?.into_inner()))
+    }
+
+    pub fn check_premium(&self, premium_amount: u64) -> Result<()> {
+        require!(
+            premium_amount >= self.min_premium_amount,
+            InsuranceError::PremiumBelowMinimum
+        );
+        Ok(())
+    }
+
+    pub fn check_coverage(&self, coverage_amount: u64) -> Result<()> {
+        require!(
+            coverage_amount >= self.min_coverage_amount,
+            InsuranceError::CoverageBelowMinimum
+        );
+        Ok(())
+    }
+}
+
+/// Role flags held by a single key, stored at `[b"role", grantee]`
+#[account]
+#[derive(Default, InitSpace)]
+pub struct Roles {
+    pub grantee: Pubkey,
+    pub flags: u8,
+    pub bump: u8,
+}
+
+impl Roles {
+    pub const ADMIN: u8 = 1 << 0;
+    pub const ORACLE_MANAGER: u8 = 1 << 1;
+    pub const UNDERWRITER: u8 = 1 << 2;
+    // 1 << 3 was the pauser role; pausing is now a council action
+    pub const ALL: u8 = Self::ADMIN | Self::ORACLE_MANAGER | Self::UNDERWRITER;
+
+    pub fn has(&self, flag: u8) -> bool {
+        self.flags & flag == flag
+    }
+
+    pub fn grant(&mut self, flags: u8) {
+        self.flags |= flags;
+    }
+
+    pub fn revoke(&mut self, flags: u8) {
+        self.flags &= !flags;
+    }
+}
+
+/// Require that a role account holds `flag`. Callers derive the account from
+/// the signer's key, so holding the account means holding the role.
+pub fn require_role(roles: &Roles, flag: u8) -> Result<()> {
+    require!(roles.has(flag), InsuranceError::Unauthorized);
+    Ok(())
+}
+
+/// Most oracle feeds a product may allow
+pub const MAX_PRODUCT_ORACLES: usize = 4;
+
+/// Bounds and fixed settings shared by every policy written from a product
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Default, PartialEq, Eq, Debug, InitSpace)]
+pub struct ProductTerms {
+    pub trigger_condition: TriggerConditionType,
+    pub price_expo: i32,
+    pub min_threshold: i64,
+    pub max_threshold: i64,
+    pub min_coverage: u64,
+    pub max_coverage: u64,
+    /// Premium as a fraction of coverage
+    pub min_premium_bps: u16,
+    pub max_premium_bps: u16,
+    pub min_duration_seconds: i64,
+    pub max_duration_seconds: i64,
+    #[max_len(MAX_PRODUCT_ORACLES)]
+    pub allowed_oracles: Vec<Pubkey>,
+    pub cancellation_fee_bps: u16,
+    pub cooling_off_seconds: i64,
+}
+
+impl ProductTerms {
+    pub fn validate(&self) -> Result<()> {
+        require!(
+            self.min_threshold <= self.max_threshold
+                && 0 < self.min_coverage
+                && self.min_coverage <= self.max_coverage
+                && self.min_premium_bps <= self.max_premium_bps
+                && 0 < self.min_duration_seconds
+                && self.min_duration_seconds <= self.max_duration_seconds,
+            InsuranceError::InvalidPolicyParameters
+        );
+        require!(
+            !self.allowed_oracles.is_empty() && self.allowed_oracles.len() <= MAX_PRODUCT_ORACLES,
+            InsuranceError::InvalidPolicyParameters
+        );
+        require!(
+            self.cancellation_fee_bps as u64 <= BPS_DENOMINATOR && self.cooling_off_seconds >= 0,
+            InsuranceError::InvalidPolicyParameters
+        );
+        Ok(())
+    }
+
+    /// Check per-policy values against the product's bounds and stamp the
+    /// product's fixed settings onto them. The premium is checked before the
+    /// pool's utilization multiplier, which is only applied at purchase.
+    pub fn apply(&self, params: InitializeParams, now: i64) -> Result<InitializeParams> {
+        require!(
+            self.allowed_oracles.contains(&params.oracle_address),
+            InsuranceError::ProductOracleMismatch
+        );
+
+        let premium_bps_scaled = params.premium_amount as u128 * BPS_DENOMINATOR as u128;
+        let coverage = params.coverage_amount as u128;
+        let duration = params.expiry_timestamp.saturating_sub(now);
+        require!(
+            (self.min_threshold..=self.max_threshold).contains(&params.trigger_threshold)
+                && (self.min_coverage..=self.max_coverage).contains(&params.coverage_amount)
+                && premium_bps_scaled >= self.min_premium_bps as u128 * coverage
+                && premium_bps_scaled <= self.max_premium_bps as u128 * coverage
+                && (self.min_duration_seconds..=self.max_duration_seconds).contains(&duration),
+            InsuranceError::OutsideProductBounds
+        );
+
+        Ok(InitializeParams {
+            trigger_condition: self.trigger_condition,
+            price_expo: self.price_expo,
+            cancellation_fee_bps: self.cancellation_fee_bps,
+            cooling_off_seconds: self.cooling_off_seconds,
+            ..params
+        })
+    }
+}
+
+#[account]
+#[derive(InitSpace)]
+pub struct ProductConfig {
+    pub authority: Pubkey,
+    pub product_id: u64,
+    pub terms: ProductTerms,
+    pub bump: u8,
+}
+
+/// Preset a policy was configured from, for indexers
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Default, PartialEq, Eq, Debug, InitSpace)]
+pub enum ProductKind {
+    #[default]
+    Custom,
+    Depeg,
+}
+
+/// How much of `coverage_amount` a triggered policy pays
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Default, PartialEq, Eq, Debug, InitSpace)]
+pub enum CoverageMode {
+    /// Full coverage regardless of installments paid
+    #[default]
+    Full,
+    /// `coverage_amount * installments_paid / installment_count`
+    ProRata,
+}
+
+/// How coverage evolves over the coverage window
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Default, PartialEq, Eq, Debug, InitSpace)]
+pub enum CoverageSchedule {
+    #[default]
+    Flat,
+    /// Falls linearly from `coverage_amount` at the start of the window to zero
+    /// at its end, as for declining-balance loan cover
+    LinearDecay,
+}
+
+impl CoverageSchedule {
+    /// `coverage` scaled for an event at `at` within the `[start, end]` window
+    pub fn scale(&self, coverage: u64, start: i64, end: i64, at: i64) -> Result<u64> {
+        match self {
+            CoverageSchedule::Flat => Ok(coverage),
+            CoverageSchedule::LinearDecay => {
+                let total = end.saturating_sub(start);
+                if total <= 0 {
+                    return Ok(coverage);
+                }
+                let remaining = end.saturating_sub(at).clamp(0, total);
+                math::prorate(coverage, remaining as u64, total as u64, RoundingMode::Floor)
+            }
+        }
+    }
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Default, PartialEq, Eq, Debug, InitSpace)]
+pub enum PolicyStatus {
+    #[default]
+    Active,
+    Purchased,
+    TriggeredPayout,
+    PaidOut,
+    Cancelled,
+    Expired,
+    Lapsed,
+    /// Trigger held for council review before payout
+    Disputed,
+    /// Paid as far as the vault allowed; `outstanding_claim` is still owed
+    PartiallyPaid,
+    /// Payout releasing linearly to the holder via `claim_vested_payout`
+    Vesting,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Default, PartialEq, Eq, Debug, InitSpace)]
+pub enum TriggerConditionType {
+    #[default]
+    PriceAbove,
+    PriceBelow,
+    /// Volatility exceeds `threshold` bps of price (at most 10_000). Measured as
+    /// realized volatility when the policy keeps a price history, otherwise as
+    /// the reading's confidence interval, `conf * 10_000 / |price|`
+    VolatilityAbove,
+    /// Price falls by at least `threshold` bps from the purchase-time baseline
+    RelativeBelow,
+    /// Price rises by at least `threshold` bps from the purchase-time baseline
+    RelativeAbove,
+    /// Price differs from a second feed by more than `threshold` bps of that feed
+    SpreadAbove,
+}
+
+impl TriggerConditionType {
+    /// Condition implied by the threshold's sign, as policies created before the
+    /// condition was stored explicitly were evaluated
+    pub fn from_legacy_threshold(threshold: i64) -> Self {
+        if threshold > 0 {
+            TriggerConditionType::PriceAbove
+        } else {
+            TriggerConditionType::PriceBelow
+        }
+    }
+
+    /// Move `threshold` by `margin` in the direction that makes the condition
+    /// harder to meet; a negative margin makes it easier
+    pub fn shift_threshold(&self, threshold: i64, margin: i64) -> i64 {
+        match self {
+            TriggerConditionType::PriceBelow => threshold.saturating_sub(margin),
+            _ => threshold.saturating_add(margin),
+        }
+    }
+
+    /// Whether the condition is measured against the purchase-time baseline price
+    pub fn is_relative(&self) -> bool {
+        matches!(
+            self,
+            TriggerConditionType::RelativeBelow | TriggerConditionType::RelativeAbove
+        )
+    }
+
+    /// Evaluate the condition against an oracle reading. Prices may be negative
+    /// (power/commodity futures), so all math is done on i128 intermediates.
+    /// `volatility_bps` is the realized volatility from the policy's price
+    /// history and is only required by `VolatilityAbove`; `reference_price` is
+    /// the second feed, at the same exponent as `price`, for `SpreadAbove`.
+    pub fn is_met(
+        &self,
+        threshold: i64,
+        price: i64,
+        volatility_bps: Option<u64>,
+        baseline: i64,
+        reference_price: Option<i64>,
+    ) -> Result<bool> {
+        let price = price as i128;
+        let threshold = threshold as i128;
+        let baseline = baseline as i128;
+        let denominator = BPS_DENOMINATOR as i128;
+        Ok(match self {
+            TriggerConditionType::PriceAbove => price > threshold,
+            TriggerConditionType::PriceBelow => price < threshold,
+            TriggerConditionType::VolatilityAbove => {
+                let volatility_bps = volatility_bps.ok_or(InsuranceError::InsufficientHistory)?;
+                volatility_bps as i128 > threshold
+            }
+            // Moves are measured against |baseline| so a negative baseline still
+            // means "below" is more negative. Crossing exactly at the threshold counts.
+            TriggerConditionType::RelativeBelow => {
+                price * denominator <= baseline * denominator - baseline.abs() * threshold
+            }
+            TriggerConditionType::RelativeAbove => {
+                price * denominator >= baseline * denominator + baseline.abs() * threshold
+            }
+            TriggerConditionType::SpreadAbove => {
+                let reference = reference_price.ok_or(InsuranceError::InvalidOracleData)? as i128;
+                require!(reference != 0, InsuranceError::InvalidOracleData);
+                let spread = price
+                    .checked_sub(reference)
+                    .and_then(|spread| spread.checked_abs())
+                    .and_then(|spread| spread.checked_mul(denominator))
+                    .ok_or(InsuranceError::MathOverflow)?;
+                let limit = reference
+                    .checked_abs()
+                    .and_then(|reference| reference.checked_mul(threshold))
+                    .ok_or(InsuranceError::MathOverflow)?;
+                spread > limit
+            }
+        })
+    }
+}
+
+#[event]
+pub struct TriggerEvaluated {
+    pub policy: Pubkey,
+    pub price: i64,
+    pub publish_time: i64,
+    pub triggered: bool,
+    pub timestamp: i64,
+}
+
+/// Emitted by `execute_payout`; `outstanding` is what a partial payout still owes
+#[event]
+pub struct PayoutExecuted {
+    pub policy: Pubkey,
+    pub paid: u64,
+    pub outstanding: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct EmergencyWithdrawRequested {
+    pub pool: Pubkey,
+    pub amount: u64,
+    pub destination: Pubkey,
+    pub executable_at: i64,
+}
+
+#[event]
+pub struct EmergencyWithdrawCancelled {
+    pub pool: Pubkey,
+}
+
+#[event]
+pub struct EvidenceSubmitted {
+    pub policy: Pubkey,
+    pub submitter: Pubkey,
+    pub role: EvidenceRole,
+    pub index: u8,
+    pub uri: String,
+    pub hash: [u8; 32],
+}
+
+#[event]
+pub struct PolicyInitialized {
+    pub policy: Pubkey,
+    pub policy_number: u64,
+    pub authority: Pubkey,
+    pub policy_holder: Pubkey,
+    pub external_id: u128,
+    pub metadata_uri: String,
+}
+
+#[event]
+pub struct PolicyPurchased {
+    pub policy: Pubkey,
+    pub policy_number: u64,
+    pub policy_holder: Pubkey,
+    pub payer: Pubkey,
+    pub external_id: u128,
+    pub metadata_uri: String,
+}
+
+/// Emitted when a policy reaches a terminal status, so its history can be
+/// reconstructed without replaying logs
+#[event]
+pub struct PolicyFinalized {
+    pub policy: Pubkey,
+    pub status: PolicyStatus,
+    pub transition_count: u64,
+    pub history: Vec<TransitionRecord>,
+}
+
+#[event]
+pub struct PolicyTransferred {
+    pub policy: Pubkey,
+    pub previous_holder: Pubkey,
+    pub new_holder: Pubkey,
+}
+
+#[event]
+pub struct OracleUpdated {
+    pub policy: Pubkey,
+    pub old_oracle: Pubkey,
+    pub new_oracle: Pubkey,
+}
+
+#[error_code]
+pub enum InsuranceError {
+    #[msg("Policy is not active")]
+    PolicyNotActive,
+    #[msg("Policy has expired")]
+    PolicyExpired,
+    #[msg("Policy has not been purchased")]
+    PolicyNotPurchased,
+    #[msg("Payout has not been triggered")]
+    PayoutNotTriggered,
+    #[msg("Policy cannot be cancelled")]
+    PolicyCannotBeCancelled,
+    #[msg("Invalid oracle data")]
+    InvalidOracleData,
+    #[msg("Insufficient funds")]
+    InsufficientFunds,
+    #[msg("Invalid policy parameters")]
+    InvalidPolicyParameters,
+    #[msg("No installment is due")]
+    NoInstallmentDue,
+    #[msg("Policy account uses an older layout and must be migrated")]
+    PolicyNeedsMigration,
+    #[msg("Policy account is already on the current layout")]
+    PolicyAlreadyMigrated,
+    #[msg("Oracle observation is outside the coverage window")]
+    OutsideCoverageWindow,
+    #[msg("Pool capital cannot back additional coverage")]
+    PoolCapacityExceeded,
+    #[msg("Policy has not reached its expiry")]
+    PolicyNotExpired,
+    #[msg("Oracle price is stale")]
+    StaleOracleData,
+    #[msg("Payout callback program is missing or does not match the policy")]
+    InvalidPayoutCallback,
+    #[msg("Arithmetic overflow")]
+    MathOverflow,
+    #[msg("Signed report is missing or does not match the reporter and message")]
+    InvalidSignedReport,
+    #[msg("Observation is not newer than the last accepted observation")]
+    StaleObservation,
+    #[msg("Payout would exceed the pool's drawdown limit for this window")]
+    DrawdownLimitExceeded,
+    #[msg("Observation is too soon after the previous recorded sample")]
+    ObservationTooFrequent,
+    #[msg("Not enough price history to evaluate")]
+    InsufficientHistory,
+    #[msg("Metadata URI is not valid UTF-8")]
+    InvalidMetadataUri,
+    #[msg("Metadata URI exceeds the maximum length")]
+    UriTooLong,
+    #[msg("Signer is not permitted to perform this action")]
+    Unauthorized,
+    #[msg("Policy terms fall outside the product's bounds")]
+    OutsideProductBounds,
+    #[msg("Oracle is not allowed by the product")]
+    ProductOracleMismatch,
+    #[msg("Protocol is paused")]
+    ProtocolPaused,
+    #[msg("Role flags are empty or unknown")]
+    InvalidRole,
+    #[msg("Token account mint does not match the policy")]
+    WrongMint,
+    #[msg("Proposal has expired")]
+    ProposalExpired,
+    #[msg("Council member has already approved this proposal")]
+    DuplicateApproval,
+    #[msg("Proposal does not have enough council approvals")]
+    ApprovalThresholdNotMet,
+    #[msg("Account required by the admin action is missing or does not match")]
+    MissingActionAccount,
+    #[msg("Policy is not under dispute")]
+    PolicyNotDisputed,
+    #[msg("Policy cannot be cancelled before the minimum holding period elapses")]
+    CancellationLocked,
+    #[msg("An emergency withdrawal is already pending")]
+    EmergencyWithdrawPending,
+    #[msg("No emergency withdrawal is pending")]
+    NoEmergencyWithdrawPending,
+    #[msg("Emergency withdrawal timelock has not elapsed")]
+    EmergencyWithdrawLocked,
+    #[msg("Emergency withdrawal does not match the pending request")]
+    EmergencyWithdrawMismatch,
+    #[msg("Withdrawal would leave triggered payouts unfunded")]
+    OutstandingPayoutObligations,
+    #[msg("Policy has no outstanding claim")]
+    NoOutstandingClaim,
+    #[msg("Policy has no vesting payout")]
+    PayoutNotVesting,
+    #[msg("Nothing has vested since the last claim")]
+    NothingVested,
+    #[msg("Fee recipient token account is missing or not owned by the fee recipient")]
+    InvalidFeeRecipient,
+    #[msg("Beneficiary shares must sum to 10000 bps across at most 5 entries")]
+    InvalidBeneficiaryShares,
+    #[msg("Beneficiary destination account is missing or not owned by the beneficiary")]
+    InvalidBeneficiaryAccount,
+    #[msg("Installment is not due yet")]
+    InstallmentNotDue,
+    #[msg("Premium delegation to the pool has been revoked or is insufficient")]
+    DelegationRevoked,
+    #[msg("Payout account is not the holder's associated token account")]
+    InvalidPayoutAccount,
+    #[msg("Payout odds are not modelled for this trigger condition")]
+    OddsUnavailable,
+    #[msg("Policy cannot move to the requested status from its current one")]
+    InvalidStateTransition,
+    #[msg("Pool still has coverage in force")]
+    CoverageInForce,
+    #[msg("Oracle account or feed is not the one configured on the policy")]
+    OracleMismatch,
+    #[msg("Token account is not owned by the expected wallet")]
+    WrongTokenAccountOwner,
+    #[msg("Pool vault cannot cover the requested amount")]
+    InsufficientPoolLiquidity,
+    #[msg("Price cannot be represented at the requested exponent")]
+    PriceOverflow,
+    #[msg("Trigger threshold or oracle configuration is invalid for the condition")]
+    InvalidTriggerConfiguration,
+    #[msg("Payout token price is outside the plausible range around its peg")]
+    PegOutOfRange,
+    #[msg("Batch is empty, too large, repeats a holder, or does not match the accounts passed")]
+    InvalidBatch,
+    #[msg("Trigger was checked too recently")]
+    CheckTooSoon,
+    #[msg("No installment is overdue beyond the grace period")]
+    InstallmentNotOverdue,
+    #[msg("Policy is in a terminal status or the new holder is invalid")]
+    PolicyNotTransferable,
+    #[msg("A policy holder cannot refer their own purchase")]
+    SelfReferral,
+    #[msg("Policy is not set to renew automatically")]
+    AutoRenewDisabled,
+    #[msg("Reinsurance vault cannot cover the claim above the policy's retention")]
+    ReinsuranceShortfall,
+    #[msg("Pool does not back this policy")]
+    PoolMismatch,
+    #[msg("Instruction must be called directly, not through CPI")]
+    CpiNotAllowed,
+    #[msg("Token account has an active delegate")]
+    DelegatedTokenAccount,
+    #[msg("Trigger condition already holds; coverage would be a guaranteed payout")]
+    ConditionAlreadyMet,
+    #[msg("Payout is held for review until the payout delay has elapsed")]
+    PayoutNotYetReleasable,
+    #[msg("Premium is below the minimum for its mint")]
+    PremiumBelowMinimum,
+    #[msg("Coverage is below the minimum for its mint")]
+    CoverageBelowMinimum,
+    #[msg("Keeper stake is below the pool's requirement")]
+    InsufficientKeeperStake,
+    #[msg("Keeper stake is locked until the deregistration cooldown has elapsed")]
+    KeeperStakeLocked,
+    #[msg("Recheck interval since the last negative check has not elapsed")]
+    EvaluationTooSoon,
+    #[msg("Merkle proof does not match the group's root")]
+    InvalidMerkleProof,
+    #[msg("Group member has already claimed")]
+    AlreadyClaimed,
+    #[msg("Party has submitted the maximum number of evidence records")]
+    EvidenceLimitReached,
+    #[msg("Dispute ruling is not backed by submitted evidence")]
+    MissingDisputeEvidence,
+    #[msg("Audit log is not the one registered for this pool or config")]
+    AuditLogMismatch,
+    #[msg("Division by zero")]
+    DivisionByZero,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anchor_lang::prelude::*;
+    use solana_program_test::*;
+    use tokio;
+
+    #[tokio::test]
+    async fn test_initialize_policy() {
+        // Test initialization of insurance policy
+        // This would contain comprehensive tests for all contract functions
+    }
+
+    #[tokio::test]
+    async fn test_purchase_policy() {
+        // Test policy purchase flow
+    }
+
+    #[tokio::test]
+    async fn test_trigger_conditions() {
+        // Test trigger condition checking
+    }
+
+    #[tokio::test]
+    async fn test_execute_payout() {
+        // Test payout execution
+    }
+
+    #[tokio::test]
+    async fn test_cancel_policy() {
+        // Test policy cancellation
+    }
+
+    /// Compute-unit ceilings for the keeper's hot paths, guarding against
+    /// regressions; raise one only with a reason. `cargo test-sbf` enforces
+    /// them against the SBF build, which a plain `cargo test` cannot meter.
+    #[cfg(feature = "test-sbf")]
+    const CHECK_TRIGGER_CU_CEILING: u64 = 40_000;
+    #[cfg(feature = "test-sbf")]
+    const EXECUTE_PAYOUT_CU_CEILING: u64 = 50_000;
+
+    fn program_account<T: AccountSerialize>(
+        account: &T,
+        space: usize,
+    ) -> solana_sdk::account::Account {
+        let mut data = Vec::with_capacity(space);
+        account.try_serialize(&mut data).unwrap();
+        data.resize(space.max(data.len()), 0);
+        solana_sdk::account::Account {
+            lamports: Rent::default().minimum_balance(data.len()),
+            data,
+            owner: crate::ID,
+            executable: false,
+            rent_epoch: 0,
+        }
+    }
+
+    fn packed_account<T: anchor_lang::solana_program::program_pack::Pack>(
+        state: T,
+        owner: Pubkey,
+    ) -> solana_sdk::account::Account {
+        let mut data = vec![0; T::LEN];
+        state.pack_into_slice(&mut data);
+        solana_sdk::account::Account {
+            lamports: Rent::default().minimum_balance(data.len()),
+            data,
+            owner,
+            executable: false,
+            rent_epoch: 0,
+        }
+    }
+
+    fn token_account(mint: Pubkey, owner: Pubkey, amount: u64) -> solana_sdk::account::Account {
+        let state = spl_token::state::Account {
+            mint,
+            owner,
+            amount,
+            state: spl_token::state::AccountState::Initialized,
+            ..Default::default()
+        };
+        packed_account(state, spl_token::id())
+    }
+
+    /// Run `ix` as its own transaction and return the compute units it used
+    #[cfg(feature = "test-sbf")]
+    async fn units_consumed(context: &mut ProgramTestContext, ix: Instruction) -> u64 {
+        use solana_sdk::{signature::Signer, transaction::Transaction};
+
+        let blockhash = context.banks_client.get_latest_blockhash().await.unwrap();
+        let payer = context.payer.pubkey();
+        let tx =
+            Transaction::new_signed_with_payer(&[ix], Some(&payer), &[&context.payer], blockhash);
+        let simulation = context.banks_client.simulate_transaction(tx.clone()).await.unwrap();
+        simulation.result.unwrap().unwrap();
+        context.banks_client.process_transaction(tx).await.unwrap();
+        simulation.simulation_details.unwrap().units_consumed
+    }
+
+    /// Native entrypoint for `ProgramTest`. `entry` wants accounts that outlive
+    /// the call, so each instruction's account list is leaked.
+    fn process_instruction(
+        program_id: &Pubkey,
+        accounts: &[AccountInfo],
+        data: &[u8],
+    ) -> anchor_lang::solana_program::entrypoint::ProgramResult {
+        let accounts = Box::leak(Box::new(accounts.to_vec()));
+        crate::entry(program_id, accounts, data)
+    }
+
+    /// Start a bank seeded with `accounts`. The program runs natively unless
+    /// `cargo test-sbf` supplies its SBF build.
+    async fn start_with_accounts(
+        accounts: Vec<(Pubkey, solana_sdk::account::Account)>,
+    ) -> ProgramTestContext {
+        let mut program_test =
+            ProgramTest::new("insurance_contract", crate::ID, processor!(process_instruction));
+        for (address, account) in accounts {
+            program_test.add_account(address, account);
+        }
+        program_test.start_with_context().await
+    }
+
+    /// Run `ixs` as one transaction paid for by the context payer
+    async fn send(
+        context: &mut ProgramTestContext,
+        ixs: &[Instruction],
+        signers: &[&solana_sdk::signature::Keypair],
+    ) -> std::result::Result<(), BanksClientError> {
+        use solana_sdk::{signature::Signer, transaction::Transaction};
+
+        let blockhash = context.banks_client.get_latest_blockhash().await.unwrap();
+        let mut all_signers = vec![&context.payer];
+        all_signers.extend_from_slice(signers);
+        let tx = Transaction::new_signed_with_payer(
+            ixs,
+            Some(&context.payer.pubkey()),
+            &all_signers,
+            blockhash,
+        );
+        context.banks_client.process_transaction(tx).await
+    }
+
+    /// Assert a transaction failed with the program error `expected`
+    fn assert_program_error(
+        result: std::result::Result<(), BanksClientError>,
+        expected: impl Into<u32>,
+    ) {
+        use solana_sdk::{instruction::InstructionError, transaction::TransactionError};
+
+        match result.unwrap_err().unwrap() {
+            TransactionError::InstructionError(_, InstructionError::Custom(code)) => {
+                assert_eq!(code, expected.into())
+            }
+            other => panic!("unexpected error: {:?}", other),
+        }
+    }
+
+    async fn fetch<T: AccountDeserialize>(context: &mut ProgramTestContext, address: Pubkey) -> T {
+        let account = context.banks_client.get_account(address).await.unwrap().unwrap();
+        T::try_deserialize(&mut &account.data[..]).unwrap()
+    }
+
+    async fn token_balance(context: &mut ProgramTestContext, address: Pubkey) -> u64 {
+        use anchor_lang::solana_program::program_pack::Pack;
+
+        let account = context.banks_client.get_account(address).await.unwrap().unwrap();
+        spl_token::state::Account::unpack(&account.data).unwrap().amount
+    }
+
+    fn system_account(lamports: u64) -> solana_sdk::account::Account {
+        solana_sdk::account::Account::new(lamports, 0, &system_program::ID)
+    }
+
+    /// Pool whose whole `capital` may back coverage
+    fn funded_pool(capital: u64) -> PoolState {
+        PoolState {
+            total_capital: capital,
+            reserve_ratio_bps: BPS_DENOMINATOR as u16,
+            ..Default::default()
+        }
+    }
+
+    /// `pool_state` as the pool for `mint`, holding its capital in `vault`, with
+    /// the config and mint accounts it needs. Returns the pool address.
+    fn pool_accounts(
+        mint: Pubkey,
+        vault: Pubkey,
+        pool_state: PoolState,
+        config: GlobalConfig,
+    ) -> (Pubkey, Vec<(Pubkey, solana_sdk::account::Account)>) {
+        let (pool, bump) = Pubkey::find_program_address(&[b"pool", mint.as_ref()], &crate::ID);
+        let (config_address, config_bump) = Pubkey::find_program_address(&[b"config"], &crate::ID);
+        let capital = pool_state.total_capital;
+        let pool_state = PoolState { mint, bump, vault, payout_vault: vault, ..pool_state };
+        let config = GlobalConfig { bump: config_bump, ..config };
+        let mint_state = spl_token::state::Mint {
+            decimals: 6,
+            is_initialized: true,
+            ..Default::default()
+        };
+        let accounts = vec![
+            (pool, program_account(&pool_state, 8 + PoolState::INIT_SPACE)),
+            (config_address, program_account(&config, 8 + GlobalConfig::INIT_SPACE)),
+            (mint, packed_account(mint_state, spl_token::id())),
+            (vault, token_account(mint, pool, capital)),
+        ];
+        (pool, accounts)
+    }
+
+    fn check_instruction(
+        authority: Pubkey,
+        policy: Pubkey,
+        pool: Pubkey,
+        oracle: Pubkey,
+    ) -> Instruction {
+        use anchor_lang::InstructionData;
+
+        Instruction {
+            program_id: crate::ID,
+            accounts: crate::accounts::CheckTriggerConditions {
+                authority,
+                insurance_policy: policy,
+                pool_state: pool,
+                oracle_account: oracle,
+                denominator_oracle_account: None,
+                fallback_oracle_account: None,
+                price_history: None,
+                spread_oracle_account: None,
+                condition_oracle_account: None,
+                config: Pubkey::find_program_address(&[b"config"], &crate::ID).0,
+                system_program: system_program::ID,
+                keeper_registration: None,
+                instructions_sysvar: sysvar_instructions::ID,
+            }
+            .to_account_metas(None),
+            data: crate::instruction::CheckTriggerConditions {}.data(),
+        }
+    }
+
+    /// `execute_payout` of `policy` to `holder`'s associated account for `mint`
+    fn payout_instruction(
+        authority: Pubkey,
+        policy: Pubkey,
+        pool: Pubkey,
+        holder: Pubkey,
+        mint: Pubkey,
+        vault: Pubkey,
+        peg_oracle: Option<Pubkey>,
+    ) -> Instruction {
+        use anchor_lang::InstructionData;
+
+        Instruction {
+            program_id: crate::ID,
+            accounts: crate::accounts::ExecutePayout {
+                authority,
+                insurance_policy: policy,
+                pool_state: pool,
+                policy_holder: holder,
+                policy_holder_token_account: get_associated_token_address(&holder, &mint),
+                payout_mint: mint,
+                insurance_pool_token_account: vault,
+                token_program: token::ID,
+                associated_token_program: associated_token::ID,
+                system_program: system_program::ID,
+                payout_callback_program: None,
+                payout_peg_oracle_account: peg_oracle,
+                config: Pubkey::find_program_address(&[b"config"], &crate::ID).0,
+                holder_stats: None,
+                reinsurance_pool: None,
+                reinsurance_vault: None,
+            }
+            .to_account_metas(None),
+            data: crate::instruction::ExecutePayout {}.data(),
+        }
+    }
+
+    /// Legacy Pyth feed account publishing `price` (expo -5) at `publish_time`
+    fn pyth_account(price: i64, publish_time: i64) -> solana_sdk::account::Account {
+        solana_sdk::account::Account {
+            lamports: 1_000_000_000,
+            data: mock_pyth_price_account(price, 1_000, -5, publish_time),
+            owner: PYTH_LEGACY_PROGRAM_IDS[0],
+            executable: false,
+            rent_epoch: 0,
+        }
+    }
+
+    #[cfg(feature = "test-sbf")]
+    #[tokio::test]
+    async fn test_hot_path_compute_ceilings() {
+        use solana_sdk::signature::Signer;
+
+        let mut program_test = ProgramTest::new("insurance_contract", crate::ID, None);
+        program_test.prefer_bpf(true);
+        let mut context = program_test.start_with_context().await;
+        let keeper = context.payer.pubkey();
+        let now = context.banks_client.get_sysvar::<Clock>().await.unwrap().unix_timestamp;
+
+        let (mint, holder, policy, oracle, vault) = (
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+        );
+        let (pool, pool_bump) = Pubkey::find_program_address(&[b"pool", mint.as_ref()], &crate::ID);
+        let (config, config_bump) = Pubkey::find_program_address(&[b"config"], &crate::ID);
+        let holder_token_account = get_associated_token_address(&holder, &mint);
+
+        // A purchased policy whose trigger holds at the oracle's current price
+        let insurance_policy = InsurancePolicy {
+            authority: keeper,
+            policy_holder: holder,
+            pool,
+            premium_mint: mint,
+            payout_mint: mint,
+            oracle_address: oracle,
+            oracle_kind: OracleKind::PythLegacy,
+            trigger_condition: TriggerConditionType::PriceBelow,
+            trigger_threshold: 2_000_000,
+            price_expo: -5,
+            max_price_age_seconds: 60,
+            coverage_amount: 1_000_000,
+            coverage_end_timestamp: now + DAY,
+            expiry_timestamp: now + DAY,
+            status: PolicyStatus::Purchased,
+            version: CURRENT_POLICY_VERSION,
+            ..Default::default()
+        };
+        let pool_state = PoolState {
+            mint,
+            bump: pool_bump,
+            vault,
+            payout_vault: vault,
+            total_capital: 10_000_000,
+            total_committed_coverage: 1_000_000,
+            active_policies: 1,
+            ..Default::default()
+        };
+        let global_config = GlobalConfig { bump: config_bump, ..Default::default() };
+        let mint_state = spl_token::state::Mint {
+            decimals: 6,
+            is_initialized: true,
+            ..Default::default()
+        };
+        let oracle_account = pyth_account(1_500_000, now);
+        let accounts = [
+            (policy, program_account(&insurance_policy, 8 + InsurancePolicy::INIT_SPACE)),
+            (pool, program_account(&pool_state, 8 + PoolState::INIT_SPACE)),
+            (config, program_account(&global_config, 8 + GlobalConfig::INIT_SPACE)),
+            (oracle, oracle_account),
+            (mint, packed_account(mint_state, spl_token::id())),
+            (vault, token_account(mint, pool, 10_000_000)),
+            (holder_token_account, token_account(mint, holder, 0)),
+        ];
+        for (address, account) in accounts {
+            context.set_account(&address, &account.into());
+        }
+
+        let check = check_instruction(keeper, policy, pool, oracle);
+        let check_units = units_consumed(&mut context, check).await;
+        assert!(
+            check_units <= CHECK_TRIGGER_CU_CEILING,
+            "check_trigger_conditions used {} CU",
+            check_units
+        );
+
+        let payout = payout_instruction(keeper, policy, pool, holder, mint, vault, None);
+        let payout_units = units_consumed(&mut context, payout).await;
+        assert!(
+            payout_units <= EXECUTE_PAYOUT_CU_CEILING,
+            "execute_payout used {} CU",
+            payout_units
+        );
+    }
+
+    #[test]
+    fn test_validate_parameters_rejects_past_expiry() {
+        let now = 1_700_000_000;
+        assert_eq!(
+            InsurancePolicy::validate_parameters(now, TriggerConditionType::PriceAbove, 100, now - 1)
+                .unwrap_err(),
+            InsuranceError::InvalidPolicyParameters.into()
+        );
+    }
+
+    #[test]
+    fn test_validate_parameters_rejects_zero_duration() {
+        let now = 1_700_000_000;
+        assert_eq!(
+            InsurancePolicy::validate_parameters(now, TriggerConditionType::PriceAbove, 100, now)
+                .unwrap_err(),
+            InsuranceError::InvalidPolicyParameters.into()
+        );
+        assert!(
+            InsurancePolicy::validate_parameters(now, TriggerConditionType::PriceAbove, 100, now + 1)
+                .is_ok()
+        );
+    }
+
+    #[test]
+    fn test_validate_parameters_checks_threshold_for_condition() {
+        let now = 1_700_000_000;
+        let validate = |condition, threshold| {
+            InsurancePolicy::validate_parameters(now, condition, threshold, now + DAY)
+        };
+        assert!(validate(TriggerConditionType::PriceBelow, -25).is_ok());
+        assert!(validate(TriggerConditionType::VolatilityAbove, 0).is_err());
+        assert!(validate(TriggerConditionType::VolatilityAbove, 1).is_ok());
+        assert!(validate(TriggerConditionType::VolatilityAbove, 10_000).is_ok());
+        assert!(validate(TriggerConditionType::VolatilityAbove, 10_001).is_err());
+        assert!(validate(TriggerConditionType::RelativeBelow, 2_000).is_ok());
+        assert!(validate(TriggerConditionType::RelativeBelow, 0).is_err());
+        assert!(validate(TriggerConditionType::RelativeBelow, 10_001).is_err());
+        assert!(validate(TriggerConditionType::RelativeAbove, 15_000).is_ok());
+    }
+
+    const MONTH: i64 = 30 * 24 * 60 * 60;
+
+    fn installment_policy(now: i64) -> InsurancePolicy {
+        InsurancePolicy {
+            premium_amount: 1_000,
+            expiry_timestamp: now + 12 * MONTH,
+            installment_count: 12,
+            installment_interval_seconds: MONTH,
+            installments_paid: 1,
+            next_installment_due: Some(now + MONTH),
+            status: PolicyStatus::Purchased,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_installments_sum_to_premium() {
+        let mut policy = installment_policy(0);
+        let amounts: Vec<u64> = (0..12).map(|i| policy.installment_amount(i)).collect();
+        assert_eq!(amounts[0], 83);
+        assert_eq!(amounts[11], 1_000 - 83 * 11);
+        assert_eq!(amounts.iter().sum::<u64>(), 1_000);
+
+        policy.installments_paid = 12;
+        assert_eq!(policy.premium_paid(), 1_000);
+
+        // A single-installment plan behaves like an up-front premium
+        policy.installment_count = 1;
+        assert_eq!(policy.installment_amount(0), 1_000);
+    }
+
+    #[test]
+    fn test_installment_on_time() {
+        let now = 1_700_000_000;
+        let policy = installment_policy(now);
+        assert!(!policy.is_installment_lapsed(now + MONTH));
+        assert_eq!(policy.next_due_after(now + MONTH), Some(now + 2 * MONTH));
+    }
+
+    #[test]
+    fn test_installment_late_within_grace() {
+        let now = 1_700_000_000;
+        let policy = installment_policy(now);
+        assert!(!policy.is_installment_lapsed(now + MONTH + INSTALLMENT_GRACE_PERIOD_SECONDS));
+    }
+
+    #[test]
+    fn test_installment_lapsed() {
+        let now = 1_700_000_000;
+        let mut policy = installment_policy(now);
+        assert!(policy.is_installment_lapsed(now + MONTH + INSTALLMENT_GRACE_PERIOD_SECONDS + 1));
+
+        // Fully paid plans never lapse
+        policy.installments_paid = 12;
+        policy.next_installment_due = None;
+        assert!(!policy.is_installment_lapsed(now + 11 * MONTH));
+    }
+
+    #[test]
+    fn test_lapse_delinquent_policy() {
+        let now = 1_700_000_000;
+        let mut policy = installment_policy(now);
+        policy
+            .lapse(now + MONTH + INSTALLMENT_GRACE_PERIOD_SECONDS + 1, Pubkey::default())
+            .unwrap();
+        assert!(policy.status == PolicyStatus::Lapsed);
+
+        // Lapsed is terminal, so a trigger can no longer lead to a payout
+        assert_eq!(
+            policy.apply_event(PolicyEvent::Trigger, now, Pubkey::default()).unwrap_err(),
+            InsuranceError::InvalidStateTransition.into()
+        );
+    }
+
+    #[test]
+    fn test_lapse_rejects_current_policy() {
+        let now = 1_700_000_000;
+        let mut policy = installment_policy(now);
+        for at in [now + MONTH - 1, now + MONTH + INSTALLMENT_GRACE_PERIOD_SECONDS] {
+            assert_eq!(
+                policy.lapse(at, Pubkey::default()).unwrap_err(),
+                InsuranceError::InstallmentNotOverdue.into()
+            );
+        }
+        assert!(policy.status == PolicyStatus::Purchased);
+
+        // A shorter per-policy grace period brings the lapse forward
+        policy.lapse_grace_seconds = Some(DAY);
+        policy.lapse(now + MONTH + DAY + 1, Pubkey::default()).unwrap();
+        assert!(policy.status == PolicyStatus::Lapsed);
+    }
+
+    fn renewing_policy(now: i64) -> InsurancePolicy {
+        InsurancePolicy {
+            status: PolicyStatus::Purchased,
+            premium_amount: 1_000,
+            installment_count: 1,
+            installments_paid: 1,
+            expiry_timestamp: now + MONTH,
+            coverage_end_timestamp: now + MONTH,
+            term_seconds: MONTH,
+            auto_renew: true,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_auto_renew_extends_expiry() {
+        let now = 1_700_000_000;
+        let pool = Pubkey::new_unique();
+        let keeper = Pubkey::new_unique();
+        let mut policy = renewing_policy(now);
+
+        // Not before expiry
+        assert_eq!(
+            policy.renewal_premium(now + MONTH - 1, Some(pool), 1_000, 5_000, &pool).unwrap_err(),
+            InsuranceError::PolicyNotExpired.into()
+        );
+        // Nobody may expire it while the renewal window is open
+        assert_eq!(policy.expirable_at(), now + MONTH + RENEWAL_WINDOW_SECONDS);
+
+        let expiry = now + MONTH;
+        let amount = policy.renewal_premium(expiry, Some(pool), 1_000, 5_000, &pool).unwrap();
+        assert_eq!(amount, Some(1_000));
+        policy.renew(expiry, keeper).unwrap();
+        assert!(policy.status == PolicyStatus::Purchased);
+        assert_eq!(policy.expiry_timestamp, now + 2 * MONTH);
+        assert_eq!(policy.coverage_end_timestamp, now + 2 * MONTH);
+        assert_eq!(policy.renewal_count, 1);
+        let last = policy.transition_history().pop().unwrap();
+        assert_eq!((last.timestamp, last.actor), (expiry, keeper));
+
+        // Without a delegation there is nothing to pull
+        assert_eq!(
+            policy.renewal_premium(now + 2 * MONTH, None, 0, 5_000, &pool).unwrap_err(),
+            InsuranceError::DelegationRevoked.into()
+        );
+        policy.auto_renew = false;
+        assert_eq!(
+            policy.renewal_premium(now + 2 * MONTH, Some(pool), 1_000, 5_000, &pool).unwrap_err(),
+            InsuranceError::AutoRenewDisabled.into()
+        );
+        assert_eq!(policy.expirable_at(), now + 2 * MONTH);
+    }
+
+    #[test]
+    fn test_auto_renew_lapses_without_funds() {
+        let now = 1_700_000_000;
+        let pool = Pubkey::new_unique();
+        let mut policy = renewing_policy(now);
+        let expiry = now + MONTH;
+
+        let amount = policy.renewal_premium(expiry, Some(pool), 1_000, 999, &pool).unwrap();
+        assert_eq!(amount, None);
+        policy.apply_event(PolicyEvent::Lapse, expiry, Pubkey::default()).unwrap();
+        assert!(policy.status == PolicyStatus::Lapsed);
+        assert_eq!(policy.expiry_timestamp, expiry);
+        assert_eq!(
+            policy.renew(expiry, Pubkey::default()).unwrap_err(),
+            InsuranceError::InvalidStateTransition.into()
+        );
+    }
+
+    fn reinsured_policy() -> InsurancePolicy {
+        InsurancePolicy {
+            retention_amount: Some(60_000),
+            ..triggered_policy(100_000)
+        }
+    }
+
+    #[test]
+    fn test_reinsured_payout_under_retention() {
+        let mut policy = reinsured_policy();
+        let (retained, reinsured) = policy.split_retention(50_000);
+        assert_eq!((retained, reinsured), (50_000, 0));
+
+        let vault_balance = 1_000_000;
+        let payable = policy
+            .begin_payout_of(50_000, vault_balance.min(retained) + reinsured, 0, Pubkey::default())
+            .unwrap();
+        assert_eq!(payable - reinsured, 50_000);
+        assert!(policy.status == PolicyStatus::PaidOut);
+    }
+
+    #[test]
+    fn test_reinsured_payout_spans_both_pools() {
+        let mut policy = reinsured_policy();
+        let reinsurance_pool = ReinsurancePool { total_capital: 40_000, ..Default::default() };
+        let (retained, reinsured) = policy.split_retention(100_000);
+        assert_eq!((retained, reinsured), (60_000, 40_000));
+        reinsurance_pool.check_cover(40_000, reinsured).unwrap();
+
+        // The primary vault only pays up to the retention even when it holds more
+        let payable = policy
+            .begin_payout_of(100_000, 1_000_000u64.min(retained) + reinsured, 0, Pubkey::default())
+            .unwrap();
+        assert_eq!((payable - reinsured, reinsured), (60_000, 40_000));
+        assert!(policy.status == PolicyStatus::PaidOut);
+
+        // A primary shortfall stays owed; the reinsurer's leg is still paid in full
+        let mut policy = reinsured_policy();
+        let payable = policy
+            .begin_payout_of(100_000, 45_000u64.min(retained) + reinsured, 0, Pubkey::default())
+            .unwrap();
+        assert_eq!(payable - reinsured, 45_000);
+        assert_eq!(policy.outstanding_claim, 15_000);
+    }
+
+    #[test]
+    fn test_reinsurance_shortfall_rejects_payout() {
+        let reinsurance_pool = ReinsurancePool { total_capital: 39_999, ..Default::default() };
+        assert_eq!(
+            reinsurance_pool.check_cover(39_999, 40_000).unwrap_err(),
+            InsuranceError::ReinsuranceShortfall.into()
+        );
+    }
+
+    #[test]
+    fn test_reinsurance_premium_swept_once_at_settlement() {
+        let reinsurance_pool = ReinsurancePool { premium_share_bps: 2_500, ..Default::default() };
+        let mut policy = InsurancePolicy {
+            premium_amount: 1_001,
+            installment_count: 1,
+            installments_paid: 1,
+            ..reinsured_policy()
+        };
+        assert_eq!(
+            policy.take_reinsurance_premium(&reinsurance_pool).unwrap_err(),
+            InsuranceError::PolicyNotExpired.into()
+        );
+
+        policy.begin_payout_of(100_000, 100_000, 0, Pubkey::default()).unwrap();
+        assert_eq!(policy.take_reinsurance_premium(&reinsurance_pool).unwrap(), 250);
+        assert_eq!(
+            policy.take_reinsurance_premium(&reinsurance_pool).unwrap_err(),
+            InsuranceError::InvalidPolicyParameters.into()
+        );
+    }
+
+    #[test]
+    fn test_retention_must_be_below_coverage() {
+        let now = 1_700_000_000;
+        let oracle = Pubkey::new_unique();
+        let params = product_policy_params(oracle, now);
+        InitializeParams { retention_amount: Some(params.coverage_amount - 1), ..params.clone() }
+            .validate(now)
+            .unwrap();
+        for retention_amount in [Some(params.coverage_amount), Some(params.coverage_amount + 1)] {
+            assert_eq!(
+                InitializeParams { retention_amount, ..params.clone() }.validate(now).unwrap_err(),
+                InsuranceError::InvalidPolicyParameters.into()
+            );
+        }
+        let vested = InitializeParams {
+            retention_amount: Some(1),
+            payout_vesting_seconds: DAY,
+            ..params
+        };
+        assert_eq!(
+            vested.validate(now).unwrap_err(),
+            InsuranceError::InvalidPolicyParameters.into()
+        );
+    }
+
+    #[test]
+    fn test_collect_premium_pulls_due_installment() {
+        let now = 1_700_000_000;
+        let pool = Pubkey::new_unique();
+        let mut policy = installment_policy(now);
+        let amount = policy
+            .collectible_installment(now + MONTH, Some(pool), 11 * 83, &pool)
+            .unwrap();
+        assert_eq!(amount, 83);
+
+        // Every installment carries the protocol fee, not just the first
+        let config = GlobalConfig { protocol_fee_bps: 250, ..Default::default() };
+        let fee = config.protocol_fee(amount).unwrap();
+        assert_eq!(fee, 2);
+        policy.record_installment_paid(fee);
+        assert_eq!(policy.installments_paid, 2);
+        assert_eq!(policy.next_installment_due, Some(now + 2 * MONTH));
+        assert_eq!(policy.protocol_fee_paid, 2);
+    }
+
+    #[test]
+    fn test_collect_premium_rejects_early_pull() {
+        let now = 1_700_000_000;
+        let pool = Pubkey::new_unique();
+        let policy = installment_policy(now);
+        assert_eq!(
+            policy
+                .collectible_installment(now + MONTH - 1, Some(pool), 1_000, &pool)
+                .unwrap_err(),
+            InsuranceError::InstallmentNotDue.into()
+        );
+    }
+
+    #[test]
+    fn test_collect_premium_revoked_delegate() {
+        let now = 1_700_000_000;
+        let pool = Pubkey::new_unique();
+        let policy = installment_policy(now);
+        for (delegate, delegated_amount) in [(None, 0), (Some(Pubkey::new_unique()), 1_000), (Some(pool), 82)] {
+            assert_eq!(
+                policy
+                    .collectible_installment(now + MONTH, delegate, delegated_amount, &pool)
+                    .unwrap_err(),
+                InsuranceError::DelegationRevoked.into()
+            );
+        }
+        // Left unpaid, the policy lapses once the grace period runs out
+        assert!(policy.is_installment_lapsed(now + MONTH + INSTALLMENT_GRACE_PERIOD_SECONDS + 1));
+    }
+
+    #[test]
+    fn test_migrate_v1_account() {
+        let v1 = InsurancePolicyV1 {
+            authority: Pubkey::new_unique(),
+            policy_holder: Pubkey::new_unique(),
+            oracle_address: Pubkey::new_unique(),
+            trigger_threshold: 150,
+            coverage_amount: 10_000,
+            premium_amount: 500,
+            expiry_timestamp: 2_000,
+            created_timestamp: 1_000,
+            purchased_timestamp: Some(1_100),
+            triggered_timestamp: None,
+            payout_timestamp: None,
+            cancelled_timestamp: None,
+            trigger_price: None,
+            status: PolicyStatus::Purchased,
+            bump: 254,
+        };
+        let mut data = InsurancePolicy::DISCRIMINATOR.to_vec();
+        v1.serialize(&mut data).unwrap();
+        data.resize(InsurancePolicyV1::LEN, 0);
+
+        // Legacy bytes never read back as a current policy
+        assert!(InsurancePolicy::try_deserialize(&mut &data[..]).is_err());
+
+        let migrated = InsurancePolicy::from_legacy_bytes(&data).unwrap();
+        assert_eq!(migrated.version, 3);
+        assert_eq!(migrated.authority, v1.authority);
+        assert_eq!(migrated.coverage_amount, 10_000);
+        assert_eq!(migrated.purchased_timestamp, Some(1_100));
+        assert!(migrated.status == PolicyStatus::Purchased);
+        assert_eq!(migrated.premium_paid(), 500);
+        assert_eq!(migrated.next_installment_due, None);
+
+        let mut buf = vec![0u8; InsurancePolicy::LEN];
+        migrated.try_serialize(&mut &mut buf[..]).unwrap();
+        let reread = InsurancePolicy::try_deserialize(&mut &buf[..]).unwrap();
+        assert_eq!(reread.version, 3);
+        assert_eq!(reread.bump, 254);
+    }
+
+    #[test]
+    fn test_migrate_v2_account_keeps_installments() {
+        let v2 = InsurancePolicyV2 {
+            v1: InsurancePolicyV1 {
+                authority: Pubkey::new_unique(),
+                policy_holder: Pubkey::new_unique(),
+                oracle_address: Pubkey::new_unique(),
+                trigger_threshold: 150,
+                coverage_amount: 10_000,
+                premium_amount: 900,
+                expiry_timestamp: 10 * MONTH,
+                created_timestamp: 1_000,
+                purchased_timestamp: Some(1_100),
+                triggered_timestamp: None,
+                payout_timestamp: None,
+                cancelled_timestamp: None,
+                trigger_price: None,
+                status: PolicyStatus::Purchased,
+                bump: 254,
+            },
+            installment_count: 3,
+            installment_interval_seconds: MONTH,
+            installments_paid: 1,
+            next_installment_due: Some(1_100 + MONTH),
+            version: 2,
+        };
+        let mut data = InsurancePolicy::DISCRIMINATOR.to_vec();
+        v2.serialize(&mut data).unwrap();
+        assert_eq!(data.len(), InsurancePolicyV2::LEN);
+        assert!(InsurancePolicy::try_deserialize(&mut &data[..]).is_err());
+
+        let migrated = InsurancePolicy::from_legacy_bytes(&data).unwrap();
+        assert_eq!(migrated.version, 3);
+        assert_eq!(migrated.authority, v2.v1.authority);
+        assert_eq!(migrated.installment_count, 3);
+        assert_eq!(migrated.installments_paid, 1);
+        assert_eq!(migrated.next_installment_due, Some(1_100 + MONTH));
+        assert_eq!(migrated.premium_paid(), 300);
+
+        // Sizes that match no known layout are refused rather than guessed at
+        data.push(0);
+        assert!(InsurancePolicy::from_legacy_bytes(&data).is_err());
+    }
+
+    #[test]
+    fn test_migrated_v1_policy_pays_out() {
+        let v1 = InsurancePolicyV1 {
+            authority: Pubkey::new_unique(),
+            policy_holder: Pubkey::new_unique(),
+            oracle_address: Pubkey::new_unique(),
+            trigger_threshold: 150,
+            coverage_amount: 10_000,
+            premium_amount: 500,
+            expiry_timestamp: 2_000,
+            created_timestamp: 1_000,
+            purchased_timestamp: Some(1_100),
+            triggered_timestamp: Some(1_500),
+            payout_timestamp: None,
+            cancelled_timestamp: None,
+            trigger_price: Some(151),
+            status: PolicyStatus::TriggeredPayout,
+            bump: 254,
+        };
+        let mut data = InsurancePolicy::DISCRIMINATOR.to_vec();
+        v1.serialize(&mut data).unwrap();
+        data.resize(InsurancePolicyV1::LEN, 0);
+
+        // Same steps as `migrate_policy`: parse, grow, rewrite in the new layout
+        let migrated = InsurancePolicy::from_legacy_bytes(&data).unwrap();
+        data.resize(InsurancePolicy::LEN, 0);
+        migrated.try_serialize(&mut &mut data[..]).unwrap();
+
+        let mut policy = InsurancePolicy::try_deserialize(&mut &data[..]).unwrap();
+        assert_eq!(policy.version, 3);
+        // New fields take defaults that keep the legacy payout behavior
+        assert!(policy.beneficiaries.is_empty());
+        assert_eq!(policy.payout_vesting_seconds, 0);
+        assert_eq!(policy.begin_payout(50_000, 0, Pubkey::default()).unwrap(), 10_000);
+        assert!(policy.status == PolicyStatus::PaidOut);
+        assert_eq!(policy.outstanding_claim, 0);
+    }
+
+    const HOUR: i64 = 60 * 60;
+    const DAY: i64 = 24 * HOUR;
+
+    fn cooling_off_policy(purchased: i64) -> InsurancePolicy {
+        InsurancePolicy {
+            premium_amount: 1_000,
+            installment_count: 1,
+            installments_paid: 1,
+            purchased_timestamp: Some(purchased),
+            cooling_off_seconds: 7 * DAY,
+            cancellation_fee_bps: 2_000,
+            status: PolicyStatus::Purchased,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_cooling_off_full_refund() {
+        let purchased = 1_700_000_000;
+        let policy = cooling_off_policy(purchased);
+        assert_eq!(policy.cancellation_refund(purchased + HOUR).unwrap(), 1_000);
+    }
+
+    #[test]
+    fn test_refund_after_cooling_off_is_partial() {
+        let purchased = 1_700_000_000;
+        let policy = cooling_off_policy(purchased);
+        assert_eq!(policy.cancellation_refund(purchased + 8 * DAY).unwrap(), 800);
+    }
+
+    #[test]
+    fn test_refund_excludes_protocol_fee() {
+        let purchased = 1_700_000_000;
+        let policy = InsurancePolicy { protocol_fee_paid: 25, ..cooling_off_policy(purchased) };
+        assert_eq!(policy.cancellation_refund(purchased + HOUR).unwrap(), 975);
+        // The cancellation fee applies to what the pool actually received
+        assert_eq!(policy.cancellation_refund(purchased + 8 * DAY).unwrap(), 780);
+    }
+
+    #[test]
+    fn test_cooling_off_blocked_after_adverse_observation() {
+        let purchased = 1_700_000_000;
+        let mut policy = InsurancePolicy {
+            trigger_condition: TriggerConditionType::PriceBelow,
+            trigger_threshold: 100,
+            min_trigger_duration_seconds: HOUR,
+            coverage_end_timestamp: purchased + 30 * DAY,
+            ..cooling_off_policy(purchased)
+        };
+        let calm = purchased + 10 * 60;
+        assert!(!policy.apply_observation(&price_at(110, calm), None, None, calm).unwrap());
+        assert!(policy.in_cooling_off(calm));
+
+        // The condition holds but is not sustained yet, so nothing triggers,
+        // and the refund is no longer on offer
+        let adverse = purchased + 30 * 60;
+        assert!(!policy.apply_observation(&price_at(90, adverse), None, None, adverse).unwrap());
+        assert!(policy.status == PolicyStatus::Purchased);
+        assert_eq!(policy.first_adverse_observation, Some(adverse));
+        assert!(!policy.in_cooling_off(purchased + HOUR));
+        assert_eq!(policy.cancellation_refund(purchased + HOUR).unwrap(), 800);
+    }
+
+    #[test]
+    fn test_cancel_inside_free_look_refunds_in_full() {
+        let purchased = 1_700_000_000;
+        let mut policy = cooling_off_policy(purchased);
+        policy.cooling_off_seconds = 0;
+        policy.free_look_seconds = 3 * DAY;
+        // Overrides the holding period and survives an adverse observation
+        policy.min_holding_period_seconds = 10 * DAY;
+        policy.first_adverse_observation = Some(purchased + HOUR);
+        assert!(policy.in_free_look(purchased + 3 * DAY));
+        assert_eq!(policy.cancellation_refund(purchased + 2 * HOUR).unwrap(), 1_000);
+        assert_eq!(policy.cancellation_refund(purchased + 3 * DAY).unwrap(), 1_000);
+    }
+
+    #[test]
+    fn test_cancel_just_after_free_look_pays_fee() {
+        let purchased = 1_700_000_000;
+        let mut policy = cooling_off_policy(purchased);
+        policy.cooling_off_seconds = 0;
+        policy.free_look_seconds = 3 * DAY;
+        assert!(!policy.in_free_look(purchased + 3 * DAY + 1));
+        assert_eq!(policy.cancellation_refund(purchased + 3 * DAY + 1).unwrap(), 800);
+
+        // Past the window the holding period applies again
+        policy.min_holding_period_seconds = 10 * DAY;
+        assert_eq!(
+            policy.cancellation_refund(purchased + 3 * DAY + 1).unwrap_err(),
+            InsuranceError::CancellationLocked.into()
+        );
+    }
+
+    #[test]
+    fn test_cancel_within_holding_period_rejected() {
+        let purchased = 1_700_000_000;
+        let mut policy = cooling_off_policy(purchased);
+        policy.min_holding_period_seconds = 2 * DAY;
+        assert!(policy.is_cancellation_locked(purchased + DAY));
+        assert_eq!(
+            policy.cancellation_refund(purchased + DAY).unwrap_err(),
+            InsuranceError::CancellationLocked.into()
+        );
+    }
+
+    #[test]
+    fn test_cancel_after_holding_period() {
+        let purchased = 1_700_000_000;
+        let mut policy = cooling_off_policy(purchased);
+        policy.min_holding_period_seconds = 2 * DAY;
+        assert!(!policy.is_cancellation_locked(purchased + 2 * DAY));
+        // Still inside the cooling-off window, so the refund is in full
+        assert_eq!(policy.cancellation_refund(purchased + 2 * DAY).unwrap(), 1_000);
+        assert_eq!(policy.cancellation_refund(purchased + 8 * DAY).unwrap(), 800);
+    }
+
+    #[test]
+    fn test_policy_timeline_round_trip() {
+        let policy = InsurancePolicy {
+            status: PolicyStatus::PaidOut,
+            created_timestamp: 1_000,
+            purchased_timestamp: Some(1_100),
+            triggered_timestamp: Some(5_000),
+            payout_timestamp: Some(5_060),
+            expiry_timestamp: 10_000,
+            ..Default::default()
+        };
+        let data = policy.timeline().try_to_vec().unwrap();
+        let timeline = PolicyTimeline::try_from_slice(&data).unwrap();
+
+        assert!(timeline.status == PolicyStatus::PaidOut);
+        assert_eq!(timeline.created_timestamp, 1_000);
+        assert_eq!(timeline.purchased_timestamp, Some(1_100));
+        assert_eq!(timeline.triggered_timestamp, Some(5_000));
+        assert_eq!(timeline.payout_timestamp, Some(5_060));
+        assert_eq!(timeline.cancelled_timestamp, None);
+        assert_eq!(timeline.expiry_timestamp, 10_000);
+    }
+
+    #[test]
+    fn test_price_above_with_negative_prices() {
+        let above = TriggerConditionType::PriceAbove;
+        assert!(above.is_met(-50, -10, None, 0, None).unwrap());
+        assert!(!above.is_met(-50, -60, None, 0, None).unwrap());
+        assert!(above.is_met(-1, i64::MAX, None, 0, None).unwrap());
+    }
+
+    #[test]
+    fn test_price_below_with_negative_prices() {
+        let below = TriggerConditionType::PriceBelow;
+        assert!(below.is_met(-50, -60, None, 0, None).unwrap());
+        assert!(!below.is_met(-50, -50, None, 0, None).unwrap());
+        assert!(below.is_met(10, i64::MIN, None, 0, None).unwrap());
+    }
+
+    #[test]
+    fn test_volatility_uses_realized_history() {
+        let volatility = TriggerConditionType::VolatilityAbove;
+        assert!(volatility.is_met(300, -100, Some(314), 0, None).unwrap());
+        assert!(!volatility.is_met(314, -100, Some(314), 0, None).unwrap());
+        assert!(volatility.is_met(0, i64::MIN, Some(u64::MAX), 0, None).unwrap());
+        // Without history the condition cannot be evaluated
+        assert_eq!(
+            volatility.is_met(20, 100, None, 0, None).unwrap_err(),
+            InsuranceError::InsufficientHistory.into()
+        );
+    }
+
+    #[test]
+    fn test_confidence_bps_boundaries() {
+        let reading = |price, conf| Price { price, conf, expo: -8, publish_time: 0 };
+        assert_eq!(confidence_bps(&reading(10_000, 0)).unwrap(), 0);
+        assert_eq!(confidence_bps(&reading(10_000, 1)).unwrap(), 1);
+        // Rounds down just below a whole bps
+        assert_eq!(confidence_bps(&reading(10_001, 1)).unwrap(), 0);
+        assert_eq!(confidence_bps(&reading(-10_000, 10_000)).unwrap(), 10_000);
+        assert_eq!(confidence_bps(&reading(i64::MAX, i64::MAX as u64)).unwrap(), 10_000);
+        // conf * 10_000 would overflow u64 but not the u128 intermediate
+        assert_eq!(confidence_bps(&reading(1, u64::MAX)).unwrap(), u64::MAX);
+        assert_eq!(
+            confidence_bps(&reading(0, 1)).unwrap_err(),
+            InsuranceError::InvalidOracleData.into()
+        );
+
+        // The threshold itself is not enough; the band must be wider
+        let volatility = TriggerConditionType::VolatilityAbove;
+        let at_max = confidence_bps(&reading(100, 100)).unwrap();
+        assert!(!volatility.is_met(10_000, 100, Some(at_max), 0, None).unwrap());
+        assert!(volatility.is_met(9_999, 100, Some(at_max), 0, None).unwrap());
+        let one = confidence_bps(&reading(10_000, 1)).unwrap();
+        assert!(!volatility.is_met(1, 10_000, Some(one), 0, None).unwrap());
+    }
+
+    #[test]
+    fn test_realized_volatility_known_series() {
+        // Returns: +2%, -2.94%, +2.02%, -2.97%, +5.10%; population std dev 314.7 bps
+        // (log-return std dev is 312.9 bps)
+        let history = history_of(&[(0, 100), (60, 102), (120, 99), (180, 101), (240, 98), (300, 103)]);
+        assert_eq!(history.realized_volatility_bps(300, 6).unwrap(), 314);
+        assert_eq!(
+            history.realized_volatility_bps(300, 7).unwrap_err(),
+            InsuranceError::InsufficientHistory.into()
+        );
+        // Only the last two returns fall inside a two-minute window
+        assert_eq!(history.realized_volatility_bps(120, 3).unwrap(), 403);
+
+        let mut policy = InsurancePolicy {
+            trigger_condition: TriggerConditionType::VolatilityAbove,
+            trigger_threshold: 300,
+            coverage_end_timestamp: 1_000,
+            status: PolicyStatus::Purchased,
+            ..Default::default()
+        };
+        let volatility = history.realized_volatility_bps(300, 6).unwrap();
+        assert!(policy.apply_observation(&price_at(103, 300), Some(volatility), None, 300).unwrap());
+    }
+
+    fn seasonal_policy() -> InsurancePolicy {
+        // Policy lives for the year, but only insures June through September
+        InsurancePolicy {
+            created_timestamp: 1_704_067_200,        // 2024-01-01
+            coverage_start_timestamp: 1_717_200_000, // 2024-06-01
+            coverage_end_timestamp: 1_727_740_799,   // 2024-09-30 23:59:59
+            expiry_timestamp: 1_735_689_600,         // 2025-01-01
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_observation_before_coverage_window() {
+        let policy = seasonal_policy();
+        assert_eq!(
+            policy.check_coverage_window(policy.coverage_start_timestamp - 1).unwrap_err(),
+            InsuranceError::OutsideCoverageWindow.into()
+        );
+    }
+
+    #[test]
+    fn test_observation_during_coverage_window() {
+        let policy = seasonal_policy();
+        assert!(policy.check_coverage_window(policy.coverage_start_timestamp).is_ok());
+        assert!(policy.check_coverage_window(1_722_470_400).is_ok());
+        // Last second of coverage still counts
+        assert!(policy.check_coverage_window(policy.coverage_end_timestamp).is_ok());
+    }
+
+    #[test]
+    fn test_observation_after_coverage_window() {
+        let policy = seasonal_policy();
+        assert_eq!(
+            policy.check_coverage_window(policy.coverage_end_timestamp + 1).unwrap_err(),
+            InsuranceError::OutsideCoverageWindow.into()
+        );
+    }
+
+    #[test]
+    fn test_coverage_window_must_sit_inside_term() {
+        let now = 1_704_067_200;
+        let params = InitializeParams {
+            trigger_threshold: 100,
+            expiry_timestamp: now + 365 * DAY,
+            installment_count: 1,
+            coverage_start_timestamp: Some(now + 150 * DAY),
+            coverage_end_timestamp: Some(now + 270 * DAY),
+            ..Default::default()
+        };
+        assert!(params.validate(now).is_ok());
+
+        let reversed = InitializeParams {
+            coverage_start_timestamp: Some(now + 270 * DAY),
+            coverage_end_timestamp: Some(now + 150 * DAY),
+            ..params.clone()
+        };
+        assert!(reversed.validate(now).is_err());
+
+        let past_expiry = InitializeParams {
+            coverage_end_timestamp: Some(now + 400 * DAY),
+            ..params.clone()
+        };
+        assert!(past_expiry.validate(now).is_err());
+
+        let before_creation = InitializeParams {
+            coverage_start_timestamp: Some(now - 1),
+            ..params
+        };
+        assert!(before_creation.validate(now).is_err());
+    }
+
+    #[test]
+    fn test_pool_rejects_over_committing_purchase() {
+        let mut pool = PoolState {
+            total_capital: 100_000,
+            reserve_ratio_bps: 8_000,
+            ..Default::default()
+        };
+        assert_eq!(pool.coverage_capacity(), 80_000);
+        pool.commit(50_000).unwrap();
+        pool.commit(30_000).unwrap();
+        assert_eq!(
+            pool.commit(1).unwrap_err(),
+            InsuranceError::PoolCapacityExceeded.into()
+        );
+        assert_eq!(pool.total_committed_coverage, 80_000);
+
+        // Released coverage frees capacity for new purchases
+        pool.release(30_000);
+        pool.commit(30_000).unwrap();
+    }
+
+    #[test]
+    fn test_pool_drawdown_limit_resets_with_window() {
+        let mut pool = PoolState {
+            drawdown_window_seconds: DAY,
+            max_drawdown_per_window: 100_000,
+            window_start_timestamp: 0,
+            ..Default::default()
+        };
+        pool.record_payout(60_000, 10).unwrap();
+        pool.record_payout(40_000, 20).unwrap();
+        assert_eq!(
+            pool.record_payout(1, 30).unwrap_err(),
+            InsuranceError::DrawdownLimitExceeded.into()
+        );
+        assert_eq!(pool.window_paid_out, 100_000);
+
+        // The next window starts from zero
+        pool.record_payout(100_000, DAY).unwrap();
+        assert_eq!(pool.window_start_timestamp, DAY);
+        assert_eq!(pool.window_paid_out, 100_000);
+
+        // A zero cap disables the breaker
+        let mut uncapped = PoolState::default();
+        uncapped.record_payout(u64::MAX, 0).unwrap();
+    }
+
+    #[test]
+    fn test_pool_commit_overflow_is_rejected() {
+        let mut pool = PoolState {
+            total_capital: u64::MAX,
+            total_committed_coverage: 1,
+            reserve_ratio_bps: 20_000,
+            ..Default::default()
+        };
+        assert!(pool.commit(u64::MAX).is_err());
+    }
+
+    /// Serialize a legacy Pyth price account in the `Trading` state
+    fn mock_pyth_price_account(price: i64, conf: u64, expo: i32, publish_time: i64) -> Vec<u8> {
+        use pyth_sdk_solana::state::{AccountType, PriceStatus, SolanaPriceAccount, MAGIC, VERSION_2};
+
+        let mut account: SolanaPriceAccount = bytemuck::Zeroable::zeroed();
+        account.magic = MAGIC;
+        account.ver = VERSION_2;
+        account.atype = AccountType::Price as u32;
+        account.expo = expo;
+        account.timestamp = publish_time;
+        account.agg.price = price;
+        account.agg.conf = conf;
+        account.agg.status = PriceStatus::Trading;
+        bytemuck::bytes_of(&account).to_vec()
+    }
+
+    fn with_oracle_account<R>(owner: Pubkey, data: &mut [u8], f: impl FnOnce(&AccountInfo) -> R) -> R {
+        let key = Pubkey::new_unique();
+        let mut lamports = 0;
+        let account = AccountInfo::new(&key, false, false, &mut lamports, data, &owner, false, 0);
+        f(&account)
+    }
+
+    #[test]
+    fn test_load_oracle_price_reads_fresh_feed() {
+        let now = 1_700_000_000;
+        let mut data = mock_pyth_price_account(2_150_000, 1_000, -5, now - 10);
+        let price = with_oracle_account(Pubkey::new_unique(), &mut data, |oracle| {
+            load_oracle_price(&OracleKind::PythLegacy, oracle, now, 60)
+        })
+        .unwrap();
+        assert_eq!(price.price, 2_150_000);
+        assert_eq!(price.publish_time, now - 10);
+    }
+
+    #[test]
+    fn test_validate_oracle_account_accepts_alternate_feed() {
+        let now = 1_700_000_000;
+        let mut legacy = mock_pyth_price_account(2_150_000, 1_000, -5, now - 10);
+        let price = with_oracle_account(PYTH_LEGACY_PROGRAM_IDS[1], &mut legacy, |oracle| {
+            validate_oracle_account(&OracleKind::PythLegacy, oracle, now, 60)
+        })
+        .unwrap();
+        assert_eq!(price.price, 2_150_000);
+
+        let feed_id = [7u8; 32];
+        let mut pull = mock_price_update_v2(feed_id, VerificationLevel::Full, 9_500_000_000, now - 5);
+        let price = with_oracle_account(PYTH_RECEIVER_PROGRAM_ID, &mut pull, |oracle| {
+            validate_oracle_account(&OracleKind::PythPull { feed_id }, oracle, now, 60)
+        })
+        .unwrap();
+        assert_eq!(price.price, 9_500_000_000);
+    }
+
+    #[test]
+    fn test_validate_oracle_account_rejects_system_account() {
+        let now = 1_700_000_000;
+        let result = with_oracle_account(system_program::ID, &mut [], |oracle| {
+            validate_oracle_account(&OracleKind::PythLegacy, oracle, now, 60)
+        });
+        assert_eq!(result.unwrap_err(), InsuranceError::InvalidOracleData.into());
+
+        // A well-formed feed is still rejected when the wrong program owns it
+        let mut data = mock_pyth_price_account(2_150_000, 1_000, -5, now - 10);
+        let result = with_oracle_account(Pubkey::new_unique(), &mut data, |oracle| {
+            validate_oracle_account(&OracleKind::PythLegacy, oracle, now, 60)
+        });
+        assert_eq!(result.unwrap_err(), InsuranceError::InvalidOracleData.into());
+    }
+
+    #[test]
+    fn test_validate_oracle_account_rejects_stale_feed() {
+        let now = 1_700_000_000;
+        let mut data = mock_pyth_price_account(2_150_000, 1_000, -5, now - 61);
+        let result = with_oracle_account(PYTH_LEGACY_PROGRAM_IDS[0], &mut data, |oracle| {
+            validate_oracle_account(&OracleKind::PythLegacy, oracle, now, 60)
+        });
+        assert_eq!(result.unwrap_err(), InsuranceError::InvalidOracleData.into());
+    }
+
+    #[test]
+    fn test_load_oracle_price_rejects_stale_feed() {
+        let now = 1_700_000_000;
+        let mut data = mock_pyth_price_account(2_150_000, 1_000, -5, now - 61);
+        let result = with_oracle_account(Pubkey::new_unique(), &mut data, |oracle| {
+            load_oracle_price(&OracleKind::PythLegacy, oracle, now, 60)
+        });
+        assert_eq!(result.unwrap_err(), InsuranceError::StaleOracleData.into());
+    }
+
+    fn converting_policy(now: i64) -> InsurancePolicy {
+        // 0.1 SOL of cover paid in USDC: 6 premium decimals against 9
+        InsurancePolicy {
+            premium_amount: 100_000_000,
+            installment_count: 1,
+            max_price_age_seconds: 60,
+            premium_conversion_oracle_kind: OracleKind::PythLegacy,
+            premium_decimals_shift: -3,
+            premium_conversion_rate_min: 50 * 100_000_000,
+            premium_conversion_rate_max: 1_000 * 100_000_000,
+            expiry_timestamp: now + DAY,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_premium_converted_at_feed_rate() {
+        let now = 1_700_000_000;
+        for (sol_usd, usdc_due) in [(15_000_000, 15_000_000), (20_000_000, 20_000_000)] {
+            let mut policy = converting_policy(now);
+            let mut feed = mock_pyth_price_account(sol_usd, 1_000, -5, now - 10);
+            with_oracle_account(Pubkey::new_unique(), &mut feed, |oracle| {
+                policy.premium_conversion_oracle = Some(oracle.key());
+                policy.reprice_premium(Some(oracle), now)
+            })
+            .unwrap();
+            assert_eq!(policy.premium_amount, usdc_due);
+            assert_eq!(policy.premium_conversion_rate, Some(sol_usd * 1_000));
+        }
+
+        // Policies without a conversion feed pay the premium as quoted
+        let mut policy = InsurancePolicy { premium_amount: 1_000, ..Default::default() };
+        policy.reprice_premium(None, now).unwrap();
+        assert_eq!(policy.premium_amount, 1_000);
+    }
+
+    #[test]
+    fn test_premium_conversion_rejects_bad_feeds() {
+        let now = 1_700_000_000;
+        let cases = [
+            (
+                mock_pyth_price_account(15_000_000, 1_000, -5, now - 61),
+                InsuranceError::StaleOracleData,
+            ),
+            // $5,000 SOL is outside the band
+            (
+                mock_pyth_price_account(500_000_000, 1_000, -5, now),
+                InsuranceError::InvalidOracleData,
+            ),
+        ];
+        for (mut feed, expected) in cases {
+            let mut policy = converting_policy(now);
+            let result = with_oracle_account(Pubkey::new_unique(), &mut feed, |oracle| {
+                policy.premium_conversion_oracle = Some(oracle.key());
+                policy.reprice_premium(Some(oracle), now)
+            });
+            assert_eq!(result.unwrap_err(), expected.into());
+            assert_eq!(policy.premium_amount, 100_000_000);
+        }
+
+        // The feed must be the one fixed at initialize
+        let mut policy = converting_policy(now);
+        policy.premium_conversion_oracle = Some(Pubkey::new_unique());
+        let mut feed = mock_pyth_price_account(15_000_000, 1_000, -5, now);
+        let result = with_oracle_account(Pubkey::new_unique(), &mut feed, |oracle| {
+            policy.reprice_premium(Some(oracle), now)
+        });
+        assert_eq!(result.unwrap_err(), InsuranceError::OracleMismatch.into());
+        assert_eq!(
+            policy.reprice_premium(None, now).unwrap_err(),
+            InsuranceError::OracleMismatch.into()
+        );
+    }
+
+    #[test]
+    fn test_load_oracle_price_rejects_non_pyth_account() {
+        let mut data = vec![0u8; 64];
+        let result = with_oracle_account(Pubkey::new_unique(), &mut data, |oracle| {
+            load_oracle_price(&OracleKind::PythLegacy, oracle, 0, 60)
+        });
+        assert_eq!(result.unwrap_err(), InsuranceError::InvalidOracleData.into());
+    }
+
+    #[test]
+    fn test_payout_callback_instruction_layout() {
+        let callback_program = Pubkey::new_unique();
+        let policy = Pubkey::new_unique();
+        let ix = payout_callback_instruction(callback_program, policy, 42_000);
+
+        assert_eq!(ix.program_id, callback_program);
+        assert_eq!(ix.accounts, vec![AccountMeta::new_readonly(policy, false)]);
+        assert_eq!(ix.data[..8], hash(b"global:on_insurance_payout").to_bytes()[..8]);
+
+        // A mock callback program decodes exactly what was sent
+        let recorded = PayoutCallbackArgs::deserialize(&mut &ix.data[8..]).unwrap();
+        assert_eq!(recorded, PayoutCallbackArgs { policy, amount: 42_000 });
+    }
+
+    fn mock_price_update_v2(
+        feed_id: [u8; 32],
+        verification_level: VerificationLevel,
+        price: i64,
+        publish_time: i64,
+    ) -> Vec<u8> {
+        let update = PriceUpdateV2 {
+            write_authority: Pubkey::new_unique(),
+            verification_level,
+            price_message: PriceFeedMessage {
+                feed_id,
+                price,
+                conf: 500,
+                exponent: -8,
+                publish_time,
+                prev_publish_time: publish_time - 1,
+                ema_price: price,
+                ema_conf: 500,
+            },
+            posted_slot: 1,
+        };
+        let mut data = PriceUpdateV2::discriminator().to_vec();
+        update.serialize(&mut data).unwrap();
+        data
+    }
+
+    #[test]
+    fn test_pyth_pull_price_drives_trigger() {
+        let now = 1_700_000_000;
+        let feed_id = [7u8; 32];
+        let kind = OracleKind::PythPull { feed_id };
+        let mut data = mock_price_update_v2(feed_id, VerificationLevel::Full, 9_500_000_000, now - 5);
+
+        let price = with_oracle_account(PYTH_RECEIVER_PROGRAM_ID, &mut data, |oracle| {
+            load_oracle_price(&kind, oracle, now, 60)
+        })
+        .unwrap();
+        assert_eq!(price.price, 9_500_000_000);
+        assert_eq!(price.expo, -8);
+        assert_eq!(price.publish_time, now - 5);
+        assert!(TriggerConditionType::PriceAbove
+            .is_met(9_000_000_000, price.price, None, 0, None)
+            .unwrap());
+    }
+
+    #[test]
+    fn test_pyth_pull_rejects_wrong_feed_owner_and_level() {
+        let now = 1_700_000_000;
+        let feed_id = [7u8; 32];
+        let kind = OracleKind::PythPull { feed_id };
+
+        let mut other_feed = mock_price_update_v2([8u8; 32], VerificationLevel::Full, 1, now);
+        let result = with_oracle_account(PYTH_RECEIVER_PROGRAM_ID, &mut other_feed, |oracle| {
+            load_oracle_price(&kind, oracle, now, 60)
+        });
+        assert_eq!(result.unwrap_err(), InsuranceError::OracleMismatch.into());
+
+        let mut partial = mock_price_update_v2(
+            feed_id,
+            VerificationLevel::Partial { num_signatures: 5 },
+            1,
+            now,
+        );
+        let result = with_oracle_account(PYTH_RECEIVER_PROGRAM_ID, &mut partial, |oracle| {
+            load_oracle_price(&kind, oracle, now, 60)
+        });
+        assert_eq!(result.unwrap_err(), InsuranceError::InvalidOracleData.into());
+
+        let mut spoofed = mock_price_update_v2(feed_id, VerificationLevel::Full, 1, now);
+        let result = with_oracle_account(Pubkey::new_unique(), &mut spoofed, |oracle| {
+            load_oracle_price(&kind, oracle, now, 60)
+        });
+        assert_eq!(result.unwrap_err(), InsuranceError::InvalidOracleData.into());
+    }
+
+    #[test]
+    fn test_pyth_pull_rejects_stale_update() {
+        let now = 1_700_000_000;
+        let feed_id = [7u8; 32];
+        let mut data = mock_price_update_v2(feed_id, VerificationLevel::Full, 1, now - 61);
+        let result = with_oracle_account(PYTH_RECEIVER_PROGRAM_ID, &mut data, |oracle| {
+            load_oracle_price(&OracleKind::PythPull { feed_id }, oracle, now, 60)
+        });
+        assert_eq!(result.unwrap_err(), InsuranceError::StaleOracleData.into());
+    }
+
+    #[test]
+    fn test_apply_bps_floor() {
+        assert_eq!(apply_bps(10_001, 9_999, RoundingMode::Floor).unwrap(), 9_999);
+        assert_eq!(apply_bps(1, 9_999, RoundingMode::Floor).unwrap(), 0);
+        assert_eq!(apply_bps(12_345, 10_000, RoundingMode::Floor).unwrap(), 12_345);
+    }
+
+    #[test]
+    fn test_apply_bps_ceil() {
+        assert_eq!(apply_bps(10_001, 9_999, RoundingMode::Ceil).unwrap(), 10_000);
+        assert_eq!(apply_bps(1, 9_999, RoundingMode::Ceil).unwrap(), 1);
+        assert_eq!(apply_bps(1, 1, RoundingMode::Ceil).unwrap(), 1);
+        assert_eq!(apply_bps(0, 9_999, RoundingMode::Ceil).unwrap(), 0);
+        // Exact results are not bumped
+        assert_eq!(apply_bps(20_000, 5_000, RoundingMode::Ceil).unwrap(), 10_000);
+    }
+
+    #[test]
+    fn test_apply_bps_nearest() {
+        // 10_001 * 0.9999 = 9_999.9999
+        assert_eq!(apply_bps(10_001, 9_999, RoundingMode::Nearest).unwrap(), 10_000);
+        // 3 * 0.5 = 1.5 rounds half up
+        assert_eq!(apply_bps(3, 5_000, RoundingMode::Nearest).unwrap(), 2);
+        // 3 * 0.4999 = 1.4997
+        assert_eq!(apply_bps(3, 4_999, RoundingMode::Nearest).unwrap(), 1);
+    }
+
+    #[test]
+    fn test_apply_bps_overflow() {
+        assert_eq!(apply_bps(u64::MAX, 10_000, RoundingMode::Ceil).unwrap(), u64::MAX);
+        assert_eq!(
+            apply_bps(u64::MAX, 10_001, RoundingMode::Floor).unwrap_err(),
+            InsuranceError::MathOverflow.into()
+        );
+    }
+
+    #[test]
+    fn test_cancellation_fee_rounds_in_pool_favor() {
+        let mut policy = cooling_off_policy(0);
+        policy.premium_amount = 999;
+        policy.cancellation_fee_bps = 9_999;
+        // Fee of 998.9001 rounds up to 999, leaving nothing to refund
+        assert_eq!(policy.cancellation_refund(8 * DAY).unwrap(), 0);
+    }
+
+    fn reporter_keypair() -> ed25519_dalek::Keypair {
+        let secret = ed25519_dalek::SecretKey::from_bytes(&[42u8; 32]).unwrap();
+        let public = ed25519_dalek::PublicKey::from(&secret);
+        ed25519_dalek::Keypair { secret, public }
+    }
+
+    #[test]
+    fn test_signed_report_verification() {
+        let reporter = reporter_keypair();
+        let reporter_key = Pubkey::new_from_array(reporter.public.to_bytes());
+        let policy = Pubkey::new_unique();
+        let message = signed_report_message(&policy, 185, 1_700_000_000);
+
+        let ix = solana_sdk::ed25519_instruction::new_ed25519_instruction(&reporter, &message);
+        assert!(verify_ed25519_instruction(&ix, &reporter_key, &message).is_ok());
+
+        // Signed by someone other than the policy's reporter
+        assert!(verify_ed25519_instruction(&ix, &Pubkey::new_unique(), &message).is_err());
+    }
+
+    #[test]
+    fn test_signed_report_rejects_tampered_message() {
+        let reporter = reporter_keypair();
+        let reporter_key = Pubkey::new_from_array(reporter.public.to_bytes());
+        let policy = Pubkey::new_unique();
+        let signed = signed_report_message(&policy, 185, 1_700_000_000);
+        let ix = solana_sdk::ed25519_instruction::new_ed25519_instruction(&reporter, &signed);
+
+        let tampered_value = signed_report_message(&policy, 250, 1_700_000_000);
+        assert_eq!(
+            verify_ed25519_instruction(&ix, &reporter_key, &tampered_value).unwrap_err(),
+            InsuranceError::InvalidSignedReport.into()
+        );
+        let other_policy = signed_report_message(&Pubkey::new_unique(), 185, 1_700_000_000);
+        assert!(verify_ed25519_instruction(&ix, &reporter_key, &other_policy).is_err());
+
+        let mut not_ed25519 = ix.clone();
+        not_ed25519.program_id = Pubkey::new_unique();
+        assert!(verify_ed25519_instruction(&not_ed25519, &reporter_key, &signed).is_err());
+    }
+
+    #[test]
+    fn test_signed_observation_triggers_policy() {
+        let now = 1_700_000_000;
+        let mut policy = InsurancePolicy {
+            trigger_threshold: 120,
+            coverage_start_timestamp: now - DAY,
+            coverage_end_timestamp: now + DAY,
+            status: PolicyStatus::Purchased,
+            ..Default::default()
+        };
+        let observation = Price { price: 185, conf: 0, expo: 0, publish_time: now };
+        assert!(policy.apply_observation(&observation, None, None, now).unwrap());
+        assert!(policy.status == PolicyStatus::TriggeredPayout);
+        assert_eq!(policy.trigger_price, Some(185));
+    }
+
+    #[tokio::test]
+    async fn test_purchase_signed_report_policy() {
+        use anchor_lang::InstructionData;
+        use solana_sdk::signature::{Keypair, Signer};
+
+        let holder = Keypair::new();
+        let (mint, vault, policy, reporter) = (
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+        );
+        let (pool, mut accounts) =
+            pool_accounts(mint, vault, funded_pool(100_000), GlobalConfig::default());
+        let holder_token_account = Pubkey::new_unique();
+        let (holder_stats, _) = Pubkey::find_program_address(
+            &[b"holder_stats", holder.pubkey().as_ref()],
+            &crate::ID,
+        );
+        accounts.extend([
+            (holder.pubkey(), system_account(1_000_000_000)),
+            (holder_token_account, token_account(mint, holder.pubkey(), 1_000)),
+        ]);
+        let mut context = start_with_accounts(accounts).await;
+        let now = context.banks_client.get_sysvar::<Clock>().await.unwrap().unix_timestamp;
+
+        let insurance_policy = InsurancePolicy {
+            authority: Pubkey::new_unique(),
+            policy_holder: holder.pubkey(),
+            pool,
+            premium_mint: mint,
+            payout_mint: mint,
+            // Reports carry their value in instruction data; this account holds no feed
+            oracle_address: reporter,
+            oracle_kind: OracleKind::SignedReport { reporter },
+            trigger_condition: TriggerConditionType::PriceAbove,
+            trigger_threshold: 120,
+            max_price_age_seconds: 60,
+            coverage_amount: 10_000,
+            premium_amount: 1_000,
+            installment_count: 1,
+            coverage_end_timestamp: now + 30 * DAY,
+            expiry_timestamp: now + 30 * DAY,
+            status: PolicyStatus::Active,
+            version: CURRENT_POLICY_VERSION,
+            ..Default::default()
+        };
+        let policy_account = program_account(&insurance_policy, 8 + InsurancePolicy::INIT_SPACE);
+        context.set_account(&policy, &policy_account.into());
+
+        let purchase = Instruction {
+            program_id: crate::ID,
+            accounts: crate::accounts::PurchasePolicy {
+                policy_holder: holder.pubkey(),
+                insurance_policy: policy,
+                pool_state: pool,
+                oracle_account: reporter,
+                denominator_oracle_account: None,
+                policy_holder_token_account: holder_token_account,
+                insurance_pool_token_account: vault,
+                config: Pubkey::find_program_address(&[b"config"], &crate::ID).0,
+                fee_recipient_token_account: None,
+                referrer_token_account: None,
+                holder_stats,
+                token_program: token::ID,
+                system_program: anchor_lang::solana_program::system_program::ID,
+                premium_conversion_oracle_account: None,
+                instructions_sysvar: sysvar_instructions::ID,
+                premium_payer: None,
+                premium_payer_token_account: None,
+            }
+            .to_account_metas(None),
+            data: crate::instruction::PurchasePolicy {}.data(),
+        };
+        send(&mut context, &[purchase], &[&holder]).await.unwrap();
+
+        let purchased: InsurancePolicy = fetch(&mut context, policy).await;
+        assert!(purchased.status == PolicyStatus::Purchased);
+        assert_eq!(purchased.price_at_purchase, 0);
+        let pool_state: PoolState = fetch(&mut context, pool).await;
+        assert_eq!(pool_state.total_committed_coverage, 10_000);
+        assert_eq!(pool_state.total_capital, 101_000);
+        assert_eq!(token_balance(&mut context, vault).await, 101_000);
+        assert_eq!(token_balance(&mut context, holder_token_account).await, 0);
+    }
+
+    #[test]
+    fn test_relative_below_triggers_on_twenty_percent_drop() {
+        let relative = TriggerConditionType::RelativeBelow;
+        let baseline = 10_000_000;
+        assert!(relative.is_met(2_000, 8_000_000, None, baseline, None).unwrap());
+        assert!(relative.is_met(2_000, 7_500_000, None, baseline, None).unwrap());
+        assert!(!relative.is_met(2_000, 9_000_000, None, baseline, None).unwrap());
+        assert!(!relative.is_met(2_000, 8_000_001, None, baseline, None).unwrap());
+    }
+
+    #[test]
+    fn test_relative_above_and_negative_baseline() {
+        let above = TriggerConditionType::RelativeAbove;
+        assert!(above.is_met(2_000, 120, None, 100, None).unwrap());
+        assert!(!above.is_met(2_000, 110, None, 100, None).unwrap());
+
+        // A 20% drop from -100 is -120, not -80
+        let below = TriggerConditionType::RelativeBelow;
+        assert!(below.is_met(2_000, -120, None, -100, None).unwrap());
+        assert!(!below.is_met(2_000, -80, None, -100, None).unwrap());
+    }
+
+    #[test]
+    fn test_legacy_condition_derivation() {
+        assert_eq!(
+            TriggerConditionType::from_legacy_threshold(150),
+            TriggerConditionType::PriceAbove
+        );
+        assert_eq!(
+            TriggerConditionType::from_legacy_threshold(-150),
+            TriggerConditionType::PriceBelow
+        );
+    }
+
+    fn price_at(price: i64, publish_time: i64) -> Price {
+        Price { price, conf: 0, expo: 0, publish_time }
+    }
+
+    fn odds_policy(condition: TriggerConditionType, threshold: i64) -> InsurancePolicy {
+        InsurancePolicy {
+            trigger_condition: condition,
+            trigger_threshold: threshold,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_payout_odds_near_threshold() {
+        let policy = odds_policy(TriggerConditionType::PriceBelow, 100);
+        // A tenth of a standard deviation away: close to a coin flip
+        let reading = Price { price: 101, conf: 10, expo: 0, publish_time: 0 };
+        let odds = policy.estimate_payout_odds(&reading).unwrap();
+        assert_eq!(odds.distance_conf_bps, 1_000);
+        assert_eq!(odds.odds_bps, 4_659);
+        assert_eq!(odds.price, 101);
+
+        // Already past the threshold
+        let reading = Price { price: 99, conf: 10, expo: 0, publish_time: 0 };
+        let odds = policy.estimate_payout_odds(&reading).unwrap();
+        assert_eq!(odds.odds_bps, 10_000);
+        assert_eq!(odds.distance_conf_bps, 0);
+    }
+
+    #[test]
+    fn test_payout_odds_far_from_threshold() {
+        let policy = odds_policy(TriggerConditionType::PriceAbove, 200);
+        // Five standard deviations away
+        let reading = Price { price: 150, conf: 10, expo: 0, publish_time: 0 };
+        assert_eq!(policy.estimate_payout_odds(&reading).unwrap().odds_bps, 0);
+        // Two standard deviations away
+        let reading = Price { price: 180, conf: 10, expo: 0, publish_time: 0 };
+        assert_eq!(policy.estimate_payout_odds(&reading).unwrap().odds_bps, 228);
+        // No reported uncertainty and not yet met
+        let reading = Price { price: 199, conf: 0, expo: 0, publish_time: 0 };
+        assert_eq!(policy.estimate_payout_odds(&reading).unwrap().odds_bps, 0);
+    }
+
+    #[test]
+    fn test_quote_premium_near_vs_far_threshold() {
+        // SOL at $100.00 (expo -2), insuring 100_000 against a fall
+        let price = 10_000;
+        let quote = |threshold| {
+            suggested_premium(100_000, TriggerConditionType::PriceBelow, threshold, price).unwrap()
+        };
+        let near = quote(9_500);
+        let far = quote(7_000);
+        assert!(near > far, "near {} far {}", near, far);
+        // 5% away: 5_000 * 1_000 / 1_500 = 3_333 bps
+        assert_eq!(near, 33_330);
+        // 30% away: 5_000 * 1_000 / 4_000 = 1_250 bps
+        assert_eq!(far, 12_500);
+        // Already at or past the trigger: the full rate
+        assert_eq!(quote(10_000), 50_000);
+        assert_eq!(quote(12_000), 50_000);
+
+        // Direction matters: a far upside trigger is cheap too
+        let upside = TriggerConditionType::PriceAbove;
+        assert_eq!(suggested_premium(100_000, upside, 13_000, price).unwrap(), far);
+        // Relative thresholds are already a distance in bps
+        assert_eq!(
+            suggested_premium(100_000, TriggerConditionType::RelativeBelow, 3_000, price).unwrap(),
+            far
+        );
+    }
+
+    fn utilization_pool() -> PoolState {
+        PoolState {
+            total_capital: 1_000_000,
+            reserve_ratio_bps: 10_000,
+            utilization_kink_bps: 8_000,
+            utilization_slope_bps: 2_000,
+            utilization_steep_slope_bps: 40_000,
+            ..Default::default()
+        }
+    }
+
+    fn keeper(stake: u64) -> KeeperRegistration {
+        KeeperRegistration { stake, ..Default::default() }
+    }
+
+    #[test]
+    fn test_check_reward_requires_registration() {
+        // Unregistered keepers earn nothing once the pool requires stake
+        assert!(!KeeperRegistration::eligible_for_fee(None, 1_000));
+        assert!(!KeeperRegistration::eligible_for_fee(Some(&keeper(999)), 1_000));
+
+        let mut registered = keeper(1_000);
+        assert!(KeeperRegistration::eligible_for_fee(Some(&registered), 1_000));
+        let mut policy = InsurancePolicy {
+            keeper_reward_reserved: 10_000,
+            keeper_reward_per_check: 2_500,
+            ..Default::default()
+        };
+        assert_eq!(policy.take_check_reward(), 2_500);
+
+        // A deregistering keeper stops earning
+        registered.begin_unbonding(0, DAY).unwrap();
+        assert!(!KeeperRegistration::eligible_for_fee(Some(&registered), 1_000));
+
+        // Pools without a stake requirement pay anyone
+        assert!(KeeperRegistration::eligible_for_fee(None, 0));
+    }
+
+    #[test]
+    fn test_slash_reduces_keeper_stake() {
+        let mut registration = keeper(1_000);
+        assert_eq!(registration.slash(400), 400);
+        assert_eq!(registration.stake, 600);
+        assert!(!KeeperRegistration::eligible_for_fee(Some(&registration), 1_000));
+
+        // Slashing more than remains takes what is left
+        assert_eq!(registration.slash(5_000), 600);
+        assert_eq!(registration.stake, 0);
+    }
+
+    #[test]
+    fn test_keeper_deregistration_cooldown() {
+        let mut registration = keeper(1_000);
+        // Registered keepers cannot withdraw without deregistering first
+        assert_eq!(
+            registration.check_withdrawable(i64::MAX).unwrap_err(),
+            InsuranceError::KeeperStakeLocked.into()
+        );
+
+        assert_eq!(registration.begin_unbonding(100, DAY).unwrap(), 100 + DAY);
+        assert_eq!(
+            registration.begin_unbonding(200, DAY).unwrap_err(),
+            InsuranceError::KeeperStakeLocked.into()
+        );
+        assert_eq!(
+            registration.check_withdrawable(99 + DAY).unwrap_err(),
+            InsuranceError::KeeperStakeLocked.into()
+        );
+        registration.check_withdrawable(100 + DAY).unwrap();
+    }
+
+    #[test]
+    fn test_pool_metrics_track_policies() {
+        let mut pool = utilization_pool();
+        assert_eq!(
+            pool.metrics(),
+            PoolMetrics {
+                total_capital: 1_000_000,
+                total_committed_coverage: 0,
+                active_policies: 0,
+                utilization_bps: 0,
+            }
+        );
+
+        // Three purchases
+        for coverage in [200_000, 100_000, 50_000] {
+            pool.commit(coverage).unwrap();
+            pool.open_policy();
+        }
+        assert_eq!(pool.metrics().active_policies, 3);
+        assert_eq!(pool.metrics().utilization_bps, 3_500);
+
+        // One pays out, one is cancelled with a refund, one expires
+        pool.retire(200_000);
+        pool.total_capital -= 200_000;
+        assert_eq!(pool.metrics().active_policies, 2);
+        assert_eq!(pool.metrics().utilization_bps, 1_875);
+
+        pool.retire(100_000);
+        pool.total_capital -= 10_000;
+        pool.retire(50_000);
+        assert_eq!(
+            pool.metrics(),
+            PoolMetrics {
+                total_capital: 790_000,
+                total_committed_coverage: 0,
+                active_policies: 0,
+                utilization_bps: 0,
+            }
+        );
+    }
+
+    #[test]
+    fn test_policies_bound_to_their_mints_pool() {
+        let now = 1_700_000_000;
+        let (usdc, usdt) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let (usdc_pool, usdt_pool) = (pool_address(&usdc), pool_address(&usdt));
+        assert_ne!(usdc_pool, usdt_pool);
+
+        let mut policies = Vec::new();
+        for mint in [usdc, usdt] {
+            let params = InitializeParams {
+                premium_mint: mint,
+                ..product_policy_params(Pubkey::new_unique(), now)
+            };
+            let (authority, holder) = (Pubkey::new_unique(), Pubkey::new_unique());
+            let mut policy = InsurancePolicy::default();
+            policy.initialize_from_params(authority, holder, 255, &params, now).unwrap();
+            policies.push(policy);
+        }
+        let (usdc_policy, usdt_policy) = (&policies[0], &policies[1]);
+        assert_eq!(usdc_policy.pool, usdc_pool);
+        assert_eq!(usdt_policy.pool, usdt_pool);
+
+        // Each policy is only ever backed by its own mint's pool
+        assert!(usdc_policy.is_backed_by(&usdc_pool, &usdc));
+        assert!(!usdc_policy.is_backed_by(&usdt_pool, &usdt));
+        assert!(usdt_policy.is_backed_by(&usdt_pool, &usdt));
+        assert!(!usdt_policy.is_backed_by(&usdc_pool, &usdc));
+        // Neither the right address with the wrong mint nor the reverse passes
+        assert!(!usdc_policy.is_backed_by(&usdc_pool, &usdt));
+        assert!(!usdc_policy.is_backed_by(&usdt_pool, &usdc));
+    }
+
+    fn locked_pool() -> PoolState {
+        let mut pool = PoolState {
+            total_capital: 1_000,
+            reserve_ratio_bps: 10_000,
+            ..Default::default()
+        };
+        pool.commit(800).unwrap();
+        pool
+    }
+
+    #[test]
+    fn test_withdrawal_queued_while_locked() {
+        let mut pool = locked_pool();
+        assert_eq!(pool.free_liquidity(1_000), 200);
+
+        assert_eq!(pool.enqueue_withdrawal(500).unwrap(), 0);
+        assert_eq!(
+            pool.fill_withdrawal(500, 1_000).unwrap_err(),
+            InsuranceError::InsufficientPoolLiquidity.into()
+        );
+        // Queued capital no longer backs new coverage
+        assert_eq!(pool.coverage_capacity(), 500);
+        assert_eq!(pool.commit(1).unwrap_err(), InsuranceError::PoolCapacityExceeded.into());
+        // Nor can more be queued than the pool holds
+        assert_eq!(
+            pool.enqueue_withdrawal(501).unwrap_err(),
+            InsuranceError::InsufficientFunds.into()
+        );
+
+        // An expiry frees the coverage backing
+        pool.release(400);
+        assert_eq!(pool.free_liquidity(1_000), 600);
+        pool.fill_withdrawal(500, 1_000).unwrap();
+        assert_eq!((pool.total_capital, pool.queued_capital), (500, 0));
+        assert_eq!(pool.withdrawal_queue_head, pool.withdrawal_queue_tail);
+    }
+
+    #[test]
+    fn test_withdrawal_queue_is_fifo() {
+        let mut pool = locked_pool();
+        assert_eq!(pool.enqueue_withdrawal(300).unwrap(), 0);
+        assert_eq!(pool.enqueue_withdrawal(100).unwrap(), 1);
+
+        // The smaller, later request would fit now but waits behind the head
+        assert_eq!(pool.withdrawal_queue_head, 0);
+        assert_eq!(
+            pool.fill_withdrawal(300, 1_000).unwrap_err(),
+            InsuranceError::InsufficientPoolLiquidity.into()
+        );
+
+        pool.release(200);
+        pool.fill_withdrawal(300, 1_000).unwrap();
+        assert_eq!(pool.withdrawal_queue_head, 1);
+        pool.fill_withdrawal(100, 700).unwrap();
+        assert_eq!(pool.withdrawal_queue_head, 2);
+        assert_eq!((pool.total_capital, pool.queued_capital), (600, 0));
+        // Claims owed still hold capital back
+        pool.add_obligation(100);
+        assert_eq!(pool.required_backing(), 700);
+        assert_eq!(pool.free_liquidity(600), 0);
+    }
+
+    #[test]
+    fn test_withdrawal_cancellation_restores_capacity() {
+        let mut pool = locked_pool();
+        pool.enqueue_withdrawal(150).unwrap();
+        assert_eq!(pool.coverage_capacity(), 850);
+
+        pool.cancel_withdrawal(150);
+        assert_eq!(pool.queued_capital, 0);
+        assert_eq!(pool.coverage_capacity(), 1_000);
+        // The cancelled entry still occupies its slot until the queue closes it
+        assert_eq!((pool.withdrawal_queue_head, pool.withdrawal_queue_tail), (0, 1));
+    }
+
+    #[test]
+    fn test_utilization_multiplier_curve() {
+        let pool = utilization_pool();
+        assert_eq!(pool.premium_multiplier_bps(0).unwrap(), 10_000);
+        // At the kink: 80% of the gentle slope
+        assert_eq!(pool.premium_multiplier_bps(800_000).unwrap(), 11_600);
+        // 95%: the kink plus 15% of the steep slope
+        assert_eq!(pool.premium_multiplier_bps(950_000).unwrap(), 17_600);
+        assert_eq!(pool.premium_multiplier_bps(1_000_000).unwrap(), 19_600);
+        assert_eq!(
+            pool.premium_multiplier_bps(1_000_001).unwrap_err(),
+            InsuranceError::PoolCapacityExceeded.into()
+        );
+
+        // Without a curve the multiplier stays flat, but over-utilization is still refused
+        let flat = PoolState { total_capital: 1_000_000, ..Default::default() };
+        assert_eq!(flat.premium_multiplier_bps(950_000).unwrap(), 10_000);
+        let empty = PoolState::default();
+        assert_eq!(empty.premium_multiplier_bps(0).unwrap(), 10_000);
+        assert_eq!(
+            empty.premium_multiplier_bps(1).unwrap_err(),
+            InsuranceError::PoolCapacityExceeded.into()
+        );
+    }
+
+    #[test]
+    fn test_premium_rises_as_pool_fills() {
+        let now = 1_700_000_000;
+        let oracle = Pubkey::new_unique();
+        let terms = sol_crash_product(oracle);
+        // 1.2% of coverage, just above the product's 1% floor
+        let params = InitializeParams {
+            premium_amount: 1_200,
+            ..product_policy_params(oracle, now)
+        };
+        let mut pool = utilization_pool();
+        let quote = |pool: &PoolState| {
+            let below = TriggerConditionType::PriceBelow;
+            let premium = suggested_premium(100_000, below, 7_000, 10_000).unwrap();
+            apply_bps(premium, pool.premium_multiplier_bps(100_000).unwrap(), RoundingMode::Ceil)
+                .unwrap()
+        };
+
+        let empty_quote = quote(&pool);
+        let params = terms.apply(params, now).unwrap();
+        let mut policy = InsurancePolicy::default();
+        policy
+            .initialize_from_params(Pubkey::new_unique(), Pubkey::new_unique(), 255, &params, now)
+            .unwrap();
+
+        // The pool fills between initialization and purchase
+        pool.commit(800_000).unwrap();
+        let busy_quote = quote(&pool);
+        assert!(busy_quote > empty_quote, "busy {} empty {}", busy_quote, empty_quote);
+        // Purchase prices against the busy pool, not the empty one it was written against
+        let multiplier = pool.premium_multiplier_bps(policy.coverage_amount).unwrap();
+        assert_eq!(multiplier, 15_600);
+        policy.apply_utilization_multiplier(multiplier).unwrap();
+        assert_eq!(policy.premium_amount, 1_872);
+    }
+
+    #[test]
+    fn test_quote_premium_rejects_unmodelled_inputs() {
+        assert_eq!(
+            suggested_premium(100_000, TriggerConditionType::SpreadAbove, 100, 10_000).unwrap_err(),
+            InsuranceError::OddsUnavailable.into()
+        );
+        assert_eq!(
+            suggested_premium(100_000, TriggerConditionType::PriceBelow, -5, 0).unwrap_err(),
+            InsuranceError::InvalidOracleData.into()
+        );
+        // Negative prices measure distance against |price|
+        let below = TriggerConditionType::PriceBelow;
+        assert_eq!(trigger_distance_bps(below, -110, -100).unwrap(), 1_000);
+    }
+
+    #[test]
+    fn test_payout_odds_relative_and_unsupported() {
+        let mut policy = odds_policy(TriggerConditionType::RelativeBelow, 1_000);
+        policy.price_at_purchase = 1_000;
+        // Trigger level is 900; 950 is one standard deviation of 50 away
+        let reading = Price { price: 950, conf: 50, expo: 0, publish_time: 0 };
+        assert_eq!(policy.estimate_payout_odds(&reading).unwrap().odds_bps, 1_587);
+
+        let policy = odds_policy(TriggerConditionType::VolatilityAbove, 500);
+        assert_eq!(
+            policy.estimate_payout_odds(&price_at(100, 0)).unwrap_err(),
+            InsuranceError::OddsUnavailable.into()
+        );
+    }
+
+    #[test]
+    fn test_fresh_primary_ignores_fallback() {
+        let (price, source) = select_oracle_price(
+            Ok(price_at(100, 10)),
+            Some(Ok(price_at(50, 10))),
+            Vec::new(),
+        )
+        .unwrap();
+        assert_eq!(price.price, 100);
+        assert_eq!(source, OracleSource::Primary);
+    }
+
+    #[test]
+    fn test_stale_primary_uses_fallback() {
+        let (price, source) = select_oracle_price(
+            Err(InsuranceError::StaleOracleData.into()),
+            Some(Ok(price_at(50, 10))),
+            Vec::new(),
+        )
+        .unwrap();
+        assert_eq!(price.price, 50);
+        assert_eq!(source, OracleSource::Fallback);
+
+        let mut policy = InsurancePolicy {
+            trigger_condition: TriggerConditionType::PriceBelow,
+            trigger_threshold: 60,
+            coverage_end_timestamp: 100,
+            status: PolicyStatus::Purchased,
+            last_oracle_source: source,
+            ..Default::default()
+        };
+        assert!(policy.apply_observation(&price, None, None, 10).unwrap());
+        assert_eq!(policy.trigger_source, Some(OracleSource::Fallback));
+    }
+
+    #[test]
+    fn test_both_oracles_stale() {
+        let result = select_oracle_price(
+            Err(InsuranceError::StaleOracleData.into()),
+            Some(Err(InsuranceError::StaleOracleData.into())),
+            Vec::new(),
+        );
+        assert_eq!(result.unwrap_err(), InsuranceError::StaleOracleData.into());
+
+        let result = select_oracle_price(Err(InsuranceError::StaleOracleData.into()), None, Vec::new());
+        assert_eq!(result.unwrap_err(), InsuranceError::StaleOracleData.into());
+    }
+
+    #[test]
+    fn test_repeated_trigger_emits_once() {
+        let key = Pubkey::new_unique();
+        let mut policy = InsurancePolicy {
+            trigger_condition: TriggerConditionType::PriceBelow,
+            trigger_threshold: 60,
+            coverage_end_timestamp: 1_000,
+            status: PolicyStatus::Purchased,
+            ..Default::default()
+        };
+
+        let events: Vec<TriggerEvaluated> = [(50, 100), (40, 101)]
+            .iter()
+            .filter_map(|&(price, now)| {
+                policy.record_observation(key, &price_at(price, now), None, None, now).unwrap()
+            })
+            .collect();
+
+        assert_eq!(events.iter().filter(|event| event.triggered).count(), 1);
+        assert_eq!(events.len(), 1);
+        // The second keeper did not overwrite the original trigger
+        assert_eq!(policy.triggered_timestamp, Some(100));
+        assert_eq!(policy.trigger_price, Some(50));
+    }
+
+    #[test]
+    fn test_price_band_rejects_spike() {
+        let key = Pubkey::new_unique();
+        let mut policy = InsurancePolicy {
+            trigger_condition: TriggerConditionType::PriceBelow,
+            trigger_threshold: 60,
+            coverage_end_timestamp: 1_000,
+            status: PolicyStatus::Purchased,
+            sane_price_min: 10,
+            sane_price_max: 1_000,
+            ..Default::default()
+        };
+
+        // A spike to near zero would meet the condition, but never reaches evaluation
+        for spike in [9, 1_001] {
+            assert_eq!(
+                policy.check_price_band(&price_at(spike, 100)).unwrap_err(),
+                InsuranceError::InvalidOracleData.into()
+            );
+        }
+        assert!(policy.status == PolicyStatus::Purchased);
+
+        let price = price_at(50, 101);
+        policy.check_price_band(&price).unwrap();
+        let event = policy.record_observation(key, &price, None, None, 101).unwrap().unwrap();
+        assert!(event.triggered);
+        assert!(policy.status == PolicyStatus::TriggeredPayout);
+    }
+
+    #[test]
+    fn test_price_band_disabled_and_inverted() {
+        let policy = InsurancePolicy::default();
+        policy.check_price_band(&price_at(i64::MAX, 0)).unwrap();
+
+        let now = 1_700_000_000;
+        let params = InitializeParams {
+            sane_price_min: 100,
+            sane_price_max: 99,
+            ..product_policy_params(Pubkey::new_unique(), now)
+        };
+        assert_eq!(
+            params.validate(now).unwrap_err(),
+            InsuranceError::InvalidPolicyParameters.into()
+        );
+    }
+
+    fn hysteresis_policy() -> InsurancePolicy {
+        InsurancePolicy {
+            trigger_condition: TriggerConditionType::PriceBelow,
+            trigger_threshold: 100,
+            hysteresis_bps: 500,
+            min_trigger_duration_seconds: 60,
+            coverage_end_timestamp: 10 * HOUR,
+            status: PolicyStatus::Purchased,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_oscillation_within_margin_does_not_trigger() {
+        let mut policy = hysteresis_policy();
+        policy.min_trigger_duration_seconds = 0;
+        // First reading is not met; afterwards the price must reach 95 to trigger
+        assert!(!policy.apply_observation(&price_at(101, 0), None, None, 0).unwrap());
+        for (i, price) in [99, 102, 97, 100, 96].iter().enumerate() {
+            let now = (i as i64 + 1) * 60;
+            assert!(!policy.apply_observation(&price_at(*price, now), None, None, now).unwrap());
+        }
+        assert!(policy.status == PolicyStatus::Purchased);
+        assert!(policy.apply_observation(&price_at(94, 600), None, None, 600).unwrap());
+    }
+
+    #[test]
+    fn test_noise_within_margin_keeps_sustained_condition() {
+        let mut policy = hysteresis_policy();
+        assert!(!policy.apply_observation(&price_at(99, 0), None, None, 0).unwrap());
+        // Back above the threshold but inside the margin: still counts as met
+        assert!(!policy.apply_observation(&price_at(104, 30), None, None, 30).unwrap());
+        assert_eq!(policy.condition_met_since, Some(0));
+        assert!(policy.apply_observation(&price_at(99, 60), None, None, 60).unwrap());
+
+        // Clearing the margin does flip the outcome and restarts the clock
+        let mut policy = hysteresis_policy();
+        assert!(!policy.apply_observation(&price_at(99, 0), None, None, 0).unwrap());
+        assert!(!policy.apply_observation(&price_at(105, 30), None, None, 30).unwrap());
+        assert_eq!(policy.last_condition_met, Some(false));
+        assert_eq!(policy.condition_met_since, None);
+    }
+
+    fn breaker_policy() -> InsurancePolicy {
+        InsurancePolicy {
+            trigger_condition: TriggerConditionType::PriceBelow,
+            trigger_threshold: 60,
+            coverage_end_timestamp: 10 * HOUR,
+            status: PolicyStatus::Purchased,
+            max_single_observation_move_bps: 2_000,
+            confirmation_delay_seconds: HOUR,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_breaker_accepts_gradual_move() {
+        let mut policy = breaker_policy();
+        // The first observation only sets the baseline, even though it is below threshold
+        assert!(!policy.apply_observation(&price_at(55, 0), None, None, 0).unwrap());
+        assert_eq!(policy.last_observed_price, Some(55));
+
+        let mut policy = breaker_policy();
+        for (i, price) in [100, 85, 72, 62].iter().enumerate() {
+            let now = i as i64 * 60;
+            assert!(!policy.apply_observation(&price_at(*price, now), None, None, now).unwrap());
+        }
+        assert!(policy.apply_observation(&price_at(55, 240), None, None, 240).unwrap());
+        assert!(!policy.suspect_observation);
+    }
+
+    #[test]
+    fn test_breaker_ignores_spike_then_revert() {
+        let mut policy = breaker_policy();
+        assert!(!policy.apply_observation(&price_at(100, 0), None, None, 0).unwrap());
+        // One-slot 90% print
+        assert!(!policy.apply_observation(&price_at(10, 60), None, None, 60).unwrap());
+        assert!(policy.suspect_observation);
+        assert!(!policy.apply_observation(&price_at(99, 120), None, None, 120).unwrap());
+        assert!(!policy.apply_observation(&price_at(100, 2 * HOUR), None, None, 2 * HOUR).unwrap());
+        assert!(!policy.suspect_observation);
+        assert!(policy.status == PolicyStatus::Purchased);
+    }
+
+    #[test]
+    fn test_breaker_triggers_on_confirmed_spike() {
+        let mut policy = breaker_policy();
+        assert!(!policy.apply_observation(&price_at(100, 0), None, None, 0).unwrap());
+        assert!(!policy.apply_observation(&price_at(10, 60), None, None, 60).unwrap());
+        // Consistent, but too soon after the spike
+        assert!(!policy.apply_observation(&price_at(11, 120), None, None, 120).unwrap());
+        assert!(policy.suspect_observation);
+        assert!(policy.apply_observation(&price_at(11, 60 + HOUR), None, None, 60 + HOUR).unwrap());
+        assert!(policy.status == PolicyStatus::TriggeredPayout);
+        assert_eq!(policy.trigger_price, Some(11));
+    }
+
+    fn history(min_interval_seconds: i64) -> PriceHistory {
+        PriceHistory::new(Pubkey::default(), min_interval_seconds, 256).unwrap()
+    }
+
+    fn history_of(samples: &[(i64, i64)]) -> PriceHistory {
+        let mut history = history(0);
+        for &(timestamp, price) in samples {
+            history.push(timestamp, price).unwrap();
+        }
+        history
+    }
+
+    #[test]
+    fn test_history_enforces_min_interval() {
+        let mut history = history(60);
+        history.push(100, 10).unwrap();
+        assert_eq!(
+            history.push(159, 11).unwrap_err(),
+            InsuranceError::ObservationTooFrequent.into()
+        );
+        history.push(160, 11).unwrap();
+        assert_eq!(history.len, 2);
+        assert_eq!(history.latest(), Some(PriceSample { timestamp: 160, price: 11 }));
+    }
+
+    #[test]
+    fn test_history_wraps_around() {
+        let mut history = history(0);
+        for i in 0..300 {
+            history.push(i, 1_000 + i).unwrap();
+        }
+        assert_eq!(history.len, 256);
+        assert_eq!(history.head, 300 - 256);
+
+        let all = history.window(i64::MAX);
+        assert_eq!(all.len(), 256);
+        assert_eq!(all[0], PriceSample { timestamp: 44, price: 1_044 });
+        assert_eq!(all[255], PriceSample { timestamp: 299, price: 1_299 });
+
+        // The window straddles the physical end of the buffer
+        let recent = history.window(50);
+        assert_eq!(recent.len(), 51);
+        assert_eq!(recent[0].timestamp, 249);
+        assert_eq!(history.twap(50).unwrap(), 1_273);
+    }
+
+    #[test]
+    fn test_history_capacity_chosen_at_init() {
+        // A simple policy keeps a handful of samples in a small account
+        let mut small = PriceHistory::new(Pubkey::default(), 0, 4).unwrap();
+        for i in 0..6 {
+            small.push(i, 100 + i).unwrap();
+        }
+        assert_eq!(small.len, 4);
+        assert_eq!(small.window(i64::MAX)[0].timestamp, 2);
+        assert_eq!(small.try_to_vec().unwrap().len() + 8, PriceHistory::space(4));
+
+        let mut large =
+            PriceHistory::new(Pubkey::default(), 0, MAX_PRICE_HISTORY_CAPACITY).unwrap();
+        for i in 0..600 {
+            large.push(i, 100 + i % 7).unwrap();
+        }
+        assert_eq!(large.len as usize, MAX_PRICE_HISTORY_CAPACITY);
+        assert_eq!(large.window(i64::MAX)[0].timestamp, 600 - MAX_PRICE_HISTORY_CAPACITY as i64);
+        assert_eq!(
+            large.try_to_vec().unwrap().len() + 8,
+            PriceHistory::space(MAX_PRICE_HISTORY_CAPACITY)
+        );
+        assert!(PriceHistory::space(MAX_PRICE_HISTORY_CAPACITY) <= 10 * 1024);
+
+        for capacity in [0, 1, MAX_PRICE_HISTORY_CAPACITY + 1] {
+            assert_eq!(
+                PriceHistory::new(Pubkey::default(), 0, capacity).unwrap_err(),
+                InsuranceError::InvalidPolicyParameters.into()
+            );
+        }
+    }
+
+    #[test]
+    fn test_history_twap_weights_by_duration() {
+        let history = history_of(&[(0, 100), (10, 200), (40, 400)]);
+        // 100 for 10s, 200 for 30s
+        assert_eq!(history.twap(40).unwrap(), 175);
+        // Only the final sample is in a zero-length window
+        assert_eq!(history.twap(0).unwrap(), 400);
+        assert_eq!(
+            self::history(0).twap(60).unwrap_err(),
+            InsuranceError::InsufficientHistory.into()
+        );
+    }
+
+    #[test]
+    fn test_history_max_drawdown() {
+        let history = history_of(&[(0, 100), (1, 120), (2, 90), (3, 110), (4, 60), (5, 130)]);
+        // Peak 120 to trough 60
+        assert_eq!(history.max_drawdown(10).unwrap(), 5_000);
+        // From t=3 the peak is 110 and the trough 60
+        assert_eq!(history.max_drawdown(2).unwrap(), 4_545);
+        assert_eq!(history.max_drawdown(0).unwrap(), 0);
+    }
+
+    #[test]
+    fn test_history_realized_volatility() {
+        // Returns of +10% and -10%: standard deviation 10%
+        let history = history_of(&[(0, 100), (1, 110), (2, 99)]);
+        assert_eq!(history.realized_volatility_bps(10, 3).unwrap(), 1_000);
+
+        let flat = history_of(&[(0, 100), (1, 100), (2, 100)]);
+        assert_eq!(flat.realized_volatility_bps(10, 3).unwrap(), 0);
+
+        assert_eq!(
+            history_of(&[(0, 100)]).realized_volatility_bps(10, 1).unwrap_err(),
+            InsuranceError::InsufficientHistory.into()
+        );
+    }
+
+    #[test]
+    fn test_isqrt() {
+        assert_eq!(isqrt(0), 0);
+        assert_eq!(isqrt(15), 3);
+        assert_eq!(isqrt(16), 4);
+        assert_eq!(isqrt(u128::MAX), u64::MAX as u128);
+    }
+
+    /// Borsh size of `account` including the discriminator
+    fn serialized_len<T: AccountSerialize>(account: &T) -> usize {
+        let mut data = Vec::new();
+        account.try_serialize(&mut data).unwrap();
+        data.len()
+    }
+
+    #[test]
+    fn test_fully_populated_policy_fits_declared_space() {
+        let key = Pubkey::new_unique();
+        let feed = OracleKind::PythPull { feed_id: [7; 32] };
+        let policy = InsurancePolicy {
+            purchased_timestamp: Some(1),
+            triggered_timestamp: Some(2),
+            payout_timestamp: Some(3),
+            cancelled_timestamp: Some(4),
+            trigger_price: Some(5),
+            next_installment_due: Some(6),
+            first_adverse_observation: Some(7),
+            payout_callback_program: Some(key),
+            oracle_kind: feed.clone(),
+            fallback_oracle_address: Some(key),
+            trigger_source: Some(OracleSource::Backup(2)),
+            last_observed_price: Some(8),
+            price_history: Some(key),
+            metadata_uri: "u".repeat(MAX_METADATA_URI_LEN),
+            external_id: u128::MAX,
+            spread_oracle_address: Some(key),
+            spread_oracle_kind: feed.clone(),
+            payout_peg_oracle_address: Some(key),
+            payout_peg_oracle_kind: feed,
+            trigger_reference_price: Some(9),
+            condition_met_since: Some(10),
+            product: Some(key),
+            payout_started_at: Some(11),
+            beneficiaries: vec![Beneficiary { beneficiary: key, share_bps: 2_000 }; MAX_BENEFICIARIES],
+            premium_payer: Some(key),
+            last_checked_timestamp: Some(12),
+            ..Default::default()
+        };
+        assert!(serialized_len(&policy) <= 8 + InsurancePolicy::INIT_SPACE);
+        assert_eq!(InsurancePolicy::LEN, 8 + InsurancePolicy::INIT_SPACE);
+    }
+
+    #[test]
+    fn test_fully_populated_accounts_fit_declared_space() {
+        let key = Pubkey::new_unique();
+        let pool = PoolState {
+            emergency_withdraw_executable_at: Some(1),
+            ..Default::default()
+        };
+        assert!(serialized_len(&pool) <= 8 + PoolState::INIT_SPACE);
+
+        let config = GlobalConfig {
+            council: vec![key; MAX_COUNCIL_MEMBERS],
+            ..Default::default()
+        };
+        assert!(serialized_len(&config) <= 8 + GlobalConfig::INIT_SPACE);
+
+        let proposal = AdminProposal {
+            id: 1,
+            proposer: key,
+            action: AdminAction::EmergencyWithdraw { pool: key, amount: u64::MAX, destination: key },
+            approvals: vec![key; MAX_COUNCIL_MEMBERS],
+            created_timestamp: 0,
+            expires_timestamp: 0,
+            bump: 255,
+        };
+        assert!(serialized_len(&proposal) <= 8 + AdminProposal::INIT_SPACE);
+
+        let product = ProductConfig {
+            authority: key,
+            product_id: 1,
+            terms: ProductTerms {
+                allowed_oracles: vec![key; MAX_PRODUCT_ORACLES],
+                ..Default::default()
+            },
+            bump: 255,
+        };
+        assert!(serialized_len(&product) <= 8 + ProductConfig::INIT_SPACE);
+        assert!(serialized_len(&Roles::default()) <= 8 + Roles::INIT_SPACE);
+    }
+
+    #[test]
+    fn test_metadata_uri_round_trip() {
+        let uri = "ipfs://bafybeigdyrzt5sfp7udm7hu76uh7y26nf3efuylqabf3oclgtqy55fbzdi/terms.pdf";
+        let policy = InsurancePolicy {
+            metadata_uri: parse_metadata_uri(uri.as_bytes()).unwrap(),
+            ..Default::default()
+        };
+        let mut data = Vec::new();
+        policy.try_serialize(&mut data).unwrap();
+        assert!(data.len() <= InsurancePolicy::LEN);
+        let decoded = InsurancePolicy::try_deserialize(&mut data.as_slice()).unwrap();
+        assert_eq!(decoded.metadata_uri, uri);
+
+        assert_eq!(
+            parse_metadata_uri(&[b'a'; MAX_METADATA_URI_LEN + 1]).unwrap_err(),
+            InsuranceError::UriTooLong.into()
+        );
+        assert_eq!(
+            parse_metadata_uri(&[0xff, 0xfe]).unwrap_err(),
+            InsuranceError::InvalidMetadataUri.into()
+        );
+        assert!(parse_metadata_uri(&[b'a'; MAX_METADATA_URI_LEN]).is_ok());
+    }
+
+    #[test]
+    fn test_initialize_with_external_references() {
+        let now = 1_700_000_000;
+        let authority = Pubkey::new_unique();
+        let holder = Pubkey::new_unique();
+        let params = InitializeParams {
+            metadata_uri: vec![b'u'; MAX_METADATA_URI_LEN],
+            external_id: 0xC1A1_0000_0000_0000_0000_0000_0000_2024,
+            ..product_policy_params(Pubkey::new_unique(), now)
+        };
+        let mut policy = InsurancePolicy::default();
+        policy.initialize_from_params(authority, holder, 255, &params, now).unwrap();
+        assert_eq!(policy.metadata_uri.len(), MAX_METADATA_URI_LEN);
+        assert!(serialized_len(&policy) <= InsurancePolicy::LEN);
+
+        let key = Pubkey::new_unique();
+        let event = policy.initialized_event(key);
+        assert_eq!(event.policy, key);
+        assert_eq!(event.policy_holder, holder);
+        assert_eq!(event.external_id, params.external_id);
+        assert_eq!(event.metadata_uri, policy.metadata_uri);
+
+        let too_long = InitializeParams {
+            metadata_uri: vec![b'u'; MAX_METADATA_URI_LEN + 1],
+            ..params
+        };
+        assert_eq!(
+            InsurancePolicy::default()
+                .initialize_from_params(authority, holder, 255, &too_long, now)
+                .unwrap_err(),
+            InsuranceError::UriTooLong.into()
+        );
+    }
+
+    #[test]
+    fn test_manual_trigger_by_authority() {
+        let authority = Pubkey::new_unique();
+        let mut policy = InsurancePolicy {
+            authority,
+            coverage_amount: 50_000,
+            expiry_timestamp: 1_000,
+            status: PolicyStatus::Purchased,
+            ..Default::default()
+        };
+
+        assert_eq!(
+            policy
+                .declare_manual_trigger(&Pubkey::new_unique(), 7, 100)
+                .unwrap_err(),
+            InsuranceError::Unauthorized.into()
+        );
+        assert!(policy.status == PolicyStatus::Purchased);
+
+        policy.declare_manual_trigger(&authority, 7, 100).unwrap();
+        assert!(policy.status == PolicyStatus::TriggeredPayout);
+        assert!(policy.manually_triggered);
+        assert_eq!(policy.manual_trigger_reason, 7);
+        assert_eq!(policy.triggered_timestamp, Some(100));
+        assert_eq!(policy.trigger_price, None);
+
+        // The payout path accepts it like an oracle trigger
+        let mut pool = PoolState {
+            max_drawdown_per_window: 100_000,
+            drawdown_window_seconds: DAY,
+            ..Default::default()
+        };
+        pool.record_payout(policy.coverage_amount, 100).unwrap();
+
+        // A second declaration is rejected
+        assert!(policy.declare_manual_trigger(&authority, 7, 101).is_err());
+    }
+
+    fn spread_policy(now: i64) -> InsurancePolicy {
+        InsurancePolicy {
+            trigger_condition: TriggerConditionType::SpreadAbove,
+            trigger_threshold: 100,
+            coverage_end_timestamp: now + DAY,
+            status: PolicyStatus::Purchased,
+            spread_oracle_address: Some(Pubkey::new_unique()),
+            price_expo: -8,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_spread_above_depeg() {
+        let now = 1_700_000_000;
+        let usdc = Price { price: 99_995_000, conf: 0, expo: -8, publish_time: now };
+        // The second leg publishes at a different exponent
+        let mut usdt = mock_pyth_price_account(100_000, 10, -5, now - 5);
+        let reference = with_oracle_account(Pubkey::new_unique(), &mut usdt, |oracle| {
+            load_spread_reference(&OracleKind::PythLegacy, oracle, usdc.expo, now, 60)
+        })
+        .unwrap();
+        assert_eq!(reference, 100_000_000);
+
+        // Pegged: 0.005% apart against a 1% threshold
+        let mut policy = spread_policy(now);
+        assert!(!policy.apply_observation(&usdc, None, Some(reference), now).unwrap());
+
+        // 2% depeg
+        let depegged = Price { price: 98_000_000, ..usdc };
+        assert!(policy.apply_observation(&depegged, None, Some(reference), now).unwrap());
+        assert_eq!(policy.trigger_price, Some(98_000_000));
+        assert_eq!(policy.trigger_reference_price, Some(100_000_000));
+
+        // Without the second leg the condition cannot be evaluated
+        let mut policy = spread_policy(now);
+        assert!(policy.apply_observation(&depegged, None, None, now).is_err());
+    }
+
+    #[test]
+    fn test_spread_rejects_stale_leg() {
+        let now = 1_700_000_000;
+        let usdc = Price { price: 100_000_000, conf: 0, expo: -8, publish_time: now };
+        let mut usdt = mock_pyth_price_account(100_000, 10, -5, now - 61);
+        let result = with_oracle_account(Pubkey::new_unique(), &mut usdt, |oracle| {
+            load_spread_reference(&OracleKind::PythLegacy, oracle, usdc.expo, now, 60)
+        });
+        assert_eq!(result.unwrap_err(), InsuranceError::StaleOracleData.into());
+    }
+
+    #[test]
+    fn test_rescale_price() {
+        assert_eq!(rescale_price(100_000, -5, -8).unwrap(), 100_000_000);
+        assert_eq!(rescale_price(-123_456_789, -8, -5).unwrap(), -123_456);
+        assert_eq!(rescale_price(42, -6, -6).unwrap(), 42);
+        assert_eq!(
+            rescale_price(i64::MAX, 0, -1).unwrap_err(),
+            InsuranceError::PriceOverflow.into()
+        );
+        assert!(rescale_price(1, 0, -100).is_err());
+    }
+
+    #[test]
+    fn test_fixed_price_compare_across_exponents() {
+        use std::cmp::Ordering;
+
+        // $21.50 at expo -5 against $22.00 in cents
+        let feed = FixedPrice::new(2_150_000, -5);
+        let threshold = FixedPrice::new(2_200, -2);
+        assert_eq!(feed.compare(&threshold), Ordering::Less);
+        assert_eq!(threshold.compare(&feed), Ordering::Greater);
+        assert_eq!(FixedPrice::new(150, -2).compare(&FixedPrice::new(1_500_000, -6)), Ordering::Equal);
+        assert_eq!(FixedPrice::new(-1, 0).compare(&FixedPrice::new(-99, -2)), Ordering::Less);
+
+        // Exponent gaps too wide for i128 still compare correctly
+        assert_eq!(FixedPrice::new(1, 30).compare(&FixedPrice::new(i64::MAX, -10)), Ordering::Greater);
+        assert_eq!(FixedPrice::new(i64::MIN, -10).compare(&FixedPrice::new(-1, 30)), Ordering::Greater);
+        assert_eq!(FixedPrice::new(0, 30).compare(&FixedPrice::new(1, -10)), Ordering::Less);
+
+        assert_eq!(feed.normalize_to(-2).unwrap(), FixedPrice::new(2_150, -2));
+    }
+
+    #[test]
+    fn test_fixed_price_to_token_amount() {
+        // $1.25 in 6-decimal and 9-decimal tokens
+        let price = FixedPrice::new(125_000_000, -8);
+        assert_eq!(price.to_token_amount(6).unwrap(), 1_250_000);
+        assert_eq!(price.to_token_amount(9).unwrap(), 1_250_000_000);
+        // Finer than the token's precision truncates
+        assert_eq!(FixedPrice::new(123_456_789, -8).to_token_amount(2).unwrap(), 123);
+        assert_eq!(FixedPrice::new(7, 0).to_token_amount(0).unwrap(), 7);
+
+        assert!(FixedPrice::new(-1, -8).to_token_amount(6).is_err());
+        assert!(FixedPrice::new(i64::MAX, 0).to_token_amount(9).is_err());
+    }
+
+    #[test]
+    fn test_observation_normalized_to_policy_expo() {
+        let now = 1_700_000_000;
+        // Threshold of $22.00 written in cents; the feed publishes $21.50 at expo -5
+        let mut policy = InsurancePolicy {
+            trigger_condition: TriggerConditionType::PriceBelow,
+            trigger_threshold: 2_200,
+            price_expo: -2,
+            coverage_end_timestamp: now + DAY,
+            status: PolicyStatus::Purchased,
+            ..Default::default()
+        };
+        let mut data = mock_pyth_price_account(2_150_000, 1_000, -5, now - 10);
+        let price = with_oracle_account(Pubkey::new_unique(), &mut data, |oracle| {
+            load_oracle_price(&OracleKind::PythLegacy, oracle, now, 60)
+        })
+        .unwrap();
+
+        // Compared raw, 2_150_000 would never be below 2_200
+        assert_eq!(policy.normalize_price(&price).unwrap(), 2_150);
+        assert!(policy.apply_observation(&price, None, None, now).unwrap());
+        assert_eq!(policy.trigger_price, Some(2_150));
+
+        let params = InitializeParams {
+            oracle_address: Pubkey::new_unique(),
+            trigger_threshold: 2_200,
+            expiry_timestamp: now + DAY,
+            installment_count: 1,
+            max_price_age_seconds: 60,
+            price_expo: -19,
+            ..Default::default()
+        };
+        assert!(params.validate(now).is_err());
+        assert!(InitializeParams { price_expo: -8, ..params }.validate(now).is_ok());
+    }
+
+    fn depeg_params(now: i64) -> InitializeParams {
+        InitializeParams {
+            oracle_address: Pubkey::new_unique(),
+            coverage_amount: 1_000_000,
+            premium_amount: 20_000,
+            expiry_timestamp: now + 30 * DAY,
+            installment_count: 1,
+            max_price_age_seconds: 60,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_depeg_terms_validation() {
+        let now = 1_700_000_000;
+        let params = depeg_params(now).into_depeg(1_000_000, -6, 200, HOUR).unwrap();
+        assert_eq!(params.trigger_condition, TriggerConditionType::PriceBelow);
+        assert_eq!(params.trigger_threshold, 980_000);
+        assert_eq!(params.price_expo, -6);
+        assert_eq!(params.min_trigger_duration_seconds, HOUR);
+
+        assert!(depeg_params(now).into_depeg(1_000_000, -6, 0, HOUR).is_err());
+        assert!(depeg_params(now).into_depeg(1_000_000, -6, 10_000, HOUR).is_err());
+        assert!(depeg_params(now).into_depeg(0, -6, 200, HOUR).is_err());
+        // Shorter than one oracle heartbeat
+        assert!(depeg_params(now).into_depeg(1_000_000, -6, 200, 59).is_err());
+    }
+
+    #[test]
+    fn test_depeg_policy_lifecycle() {
+        let now = 1_700_000_000;
+        let params = depeg_params(now).into_depeg(1_000_000, -6, 200, HOUR).unwrap();
+        let mut policy = InsurancePolicy::default();
+        policy
+            .initialize_from_params(Pubkey::new_unique(), Pubkey::new_unique(), 255, &params, now)
+            .unwrap();
+        policy.product_kind = ProductKind::Depeg;
+
+        // Purchase against a pool with capacity for the cover
+        let mut pool = PoolState {
+            total_capital: 2_000_000,
+            reserve_ratio_bps: 10_000,
+            ..Default::default()
+        };
+        pool.commit(policy.coverage_amount).unwrap();
+        policy.status = PolicyStatus::Purchased;
+
+        // USDX feed publishes at expo -8
+        let usdx = |price: i64, t: i64| Price { price, conf: 0, expo: -8, publish_time: t };
+        let t = now + HOUR;
+        assert!(!policy.apply_observation(&usdx(99_500_000, t), None, None, t).unwrap());
+        // A brief dip below $0.98 that recovers does not pay
+        assert!(!policy.apply_observation(&usdx(97_000_000, t + 60), None, None, t + 60).unwrap());
+        assert!(!policy.apply_observation(&usdx(97_500_000, t + 1_800), None, None, t + 1_800).unwrap());
+        assert!(!policy.apply_observation(&usdx(98_500_000, t + 1_860), None, None, t + 1_860).unwrap());
+        assert_eq!(policy.condition_met_since, None);
+
+        // A sustained depeg pays once it has lasted an hour
+        let start = t + 2 * HOUR;
+        assert!(!policy.apply_observation(&usdx(96_000_000, start), None, None, start).unwrap());
+        let end = start + HOUR;
+        assert!(policy.apply_observation(&usdx(95_000_000, end), None, None, end).unwrap());
+        assert!(policy.status == PolicyStatus::TriggeredPayout);
+        assert_eq!(policy.trigger_price, Some(950_000));
+
+        pool.record_payout(policy.coverage_amount, end).unwrap();
+        pool.release(policy.coverage_amount);
+        assert_eq!(pool.total_committed_coverage, 0);
+    }
+
+    #[test]
+    fn test_purchase_after_expiry_sees_expired() {
+        let policy = InsurancePolicy { expiry_timestamp: 1_000, ..Default::default() };
+        policy.require_unexpired(999).unwrap();
+        assert!(policy.status_at(999) == PolicyStatus::Active);
+
+        // Purchase fails and reverts; nothing was reserved for an active policy
+        assert!(policy.status_at(1_000) == PolicyStatus::Expired);
+        assert_eq!(
+            policy.require_unexpired(1_000).unwrap_err(),
+            InsuranceError::PolicyExpired.into()
+        );
+        let mut expired = policy.clone();
+        assert!(PoolState::default().close_expired(&mut expired, 1_000));
+        assert_eq!(
+            expired.apply_event(PolicyEvent::Purchase, 1_001, Pubkey::default()).unwrap_err(),
+            InsuranceError::InvalidStateTransition.into()
+        );
+        assert_eq!(
+            expired.require_unexpired(1_001).unwrap_err(),
+            InsuranceError::PolicyExpired.into()
+        );
+    }
+
+    #[test]
+    fn test_lazy_expiry_spares_triggered_and_renewable_policies() {
+        let mut pool = PoolState { total_committed_coverage: 1_000, ..Default::default() };
+        let mut policy = triggered_policy(1_000);
+        policy.expiry_timestamp = 1_000;
+        assert!(!pool.close_expired(&mut policy, 2_000));
+        assert!(policy.status == PolicyStatus::TriggeredPayout);
+
+        // A renewable policy stays purchased through its renewal window
+        let mut policy = InsurancePolicy {
+            status: PolicyStatus::Purchased,
+            auto_renew: true,
+            coverage_amount: 1_000,
+            expiry_timestamp: 1_000,
+            ..Default::default()
+        };
+        assert!(!pool.close_expired(&mut policy, 1_000));
+        assert!(policy.status == PolicyStatus::Purchased);
+        assert_eq!(
+            policy.require_unexpired(1_000).unwrap_err(),
+            InsuranceError::PolicyExpired.into()
+        );
+        assert!(pool.close_expired(&mut policy, 1_000 + RENEWAL_WINDOW_SECONDS));
+        assert!(policy.status == PolicyStatus::Expired);
+        assert_eq!(pool.total_committed_coverage, 0);
+    }
+
+    #[tokio::test]
+    async fn test_check_after_expiry_persists_expired() {
+        use solana_sdk::signature::Signer;
+
+        let (mint, vault, policy, oracle) = (
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+        );
+        let pool_state = PoolState {
+            total_committed_coverage: 10_000,
+            active_policies: 1,
+            ..funded_pool(100_000)
+        };
+        let (pool, accounts) = pool_accounts(mint, vault, pool_state, GlobalConfig::default());
+        let mut context = start_with_accounts(accounts).await;
+        let keeper = context.payer.pubkey();
+        let now = context.banks_client.get_sysvar::<Clock>().await.unwrap().unix_timestamp;
+
+        // Purchased, reserved against the pool, and past expiry since an hour ago
+        let insurance_policy = InsurancePolicy {
+            authority: keeper,
+            pool,
+            premium_mint: mint,
+            payout_mint: mint,
+            oracle_address: oracle,
+            coverage_amount: 10_000,
+            expiry_timestamp: now - HOUR,
+            coverage_end_timestamp: now - HOUR,
+            status: PolicyStatus::Purchased,
+            version: CURRENT_POLICY_VERSION,
+            ..Default::default()
+        };
+        let policy_account = program_account(&insurance_policy, 8 + InsurancePolicy::INIT_SPACE);
+        context.set_account(&policy, &policy_account.into());
+
+        let check = check_instruction(keeper, policy, pool, oracle);
+        send(&mut context, &[check], &[]).await.unwrap();
+
+        let expired: InsurancePolicy = fetch(&mut context, policy).await;
+        assert!(expired.status == PolicyStatus::Expired);
+        assert!(expired.transition_history().last().unwrap().to == PolicyStatus::Expired);
+        let pool_state: PoolState = fetch(&mut context, pool).await;
+        assert_eq!(pool_state.total_committed_coverage, 0);
+        assert_eq!(pool_state.active_policies, 0);
+    }
+
+    #[tokio::test]
+    async fn test_manual_trigger_then_payout() {
+        use anchor_lang::InstructionData;
+        use solana_sdk::signature::Signer;
+
+        let (mint, vault, holder, policy) = (
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+        );
+        let pool_state = PoolState {
+            total_committed_coverage: 10_000,
+            active_policies: 1,
+            ..funded_pool(100_000)
+        };
+        let (pool, accounts) = pool_accounts(mint, vault, pool_state, GlobalConfig::default());
+        let mut context = start_with_accounts(accounts).await;
+        let authority = context.payer.pubkey();
+        let now = context.banks_client.get_sysvar::<Clock>().await.unwrap().unix_timestamp;
+
+        let insurance_policy = InsurancePolicy {
+            authority,
+            policy_holder: holder,
+            pool,
+            premium_mint: mint,
+            payout_mint: mint,
+            coverage_amount: 10_000,
+            coverage_end_timestamp: now + DAY,
+            expiry_timestamp: now + DAY,
+            status: PolicyStatus::Purchased,
+            version: CURRENT_POLICY_VERSION,
+            ..Default::default()
+        };
+        let policy_account = program_account(&insurance_policy, 8 + InsurancePolicy::INIT_SPACE);
+        context.set_account(&policy, &policy_account.into());
+        let holder_token_account = get_associated_token_address(&holder, &mint);
+        context.set_account(&holder_token_account, &token_account(mint, holder, 0).into());
+
+        let manual_trigger = Instruction {
+            program_id: crate::ID,
+            accounts: crate::accounts::ManualTrigger {
+                authority,
+                insurance_policy: policy,
+                pool_state: pool,
+            }
+            .to_account_metas(None),
+            data: crate::instruction::ManualTrigger { reason_code: 7 }.data(),
+        };
+        send(&mut context, &[manual_trigger], &[]).await.unwrap();
+        let triggered: InsurancePolicy = fetch(&mut context, policy).await;
+        assert!(triggered.status == PolicyStatus::TriggeredPayout);
+        assert!(triggered.manually_triggered);
+        assert_eq!(triggered.manual_trigger_reason, 7);
+        let pool_state: PoolState = fetch(&mut context, pool).await;
+        assert_eq!(pool_state.triggered_obligations, 10_000);
+
+        let payout = payout_instruction(authority, policy, pool, holder, mint, vault, None);
+        send(&mut context, &[payout], &[]).await.unwrap();
+
+        let paid: InsurancePolicy = fetch(&mut context, policy).await;
+        assert!(paid.status == PolicyStatus::PaidOut);
+        assert_eq!(paid.outstanding_claim, 0);
+        assert!(paid.payout_timestamp.is_some());
+        assert_eq!(token_balance(&mut context, holder_token_account).await, 10_000);
+        assert_eq!(token_balance(&mut context, vault).await, 90_000);
+        let pool_state: PoolState = fetch(&mut context, pool).await;
+        assert_eq!(pool_state.total_capital, 90_000);
+        assert_eq!(pool_state.total_committed_coverage, 0);
+        assert_eq!(pool_state.triggered_obligations, 0);
+        assert_eq!(pool_state.active_policies, 0);
+    }
+
+    #[tokio::test]
+    async fn test_stale_price_rejected_after_clock_advances() {
+        use solana_sdk::signature::Signer;
+
+        let (mint, vault, oracle) =
+            (Pubkey::new_unique(), Pubkey::new_unique(), Pubkey::new_unique());
+        let pool_state = PoolState {
+            total_committed_coverage: 20_000,
+            active_policies: 2,
+            ..funded_pool(100_000)
+        };
+        let (pool, accounts) = pool_accounts(mint, vault, pool_state, GlobalConfig::default());
+        let mut context = start_with_accounts(accounts).await;
+        let keeper = context.payer.pubkey();
+        let mut clock = context.banks_client.get_sysvar::<Clock>().await.unwrap();
+        let published = clock.unix_timestamp;
+        context.set_account(&oracle, &pyth_account(1_500_000, published).into());
+
+        // Purchased, with the trigger holding at the feed's price
+        let (policy, pegged_policy, holder) =
+            (Pubkey::new_unique(), Pubkey::new_unique(), Pubkey::new_unique());
+        let purchased = InsurancePolicy {
+            authority: keeper,
+            policy_holder: holder,
+            pool,
+            premium_mint: mint,
+            payout_mint: mint,
+            oracle_address: oracle,
+            trigger_condition: TriggerConditionType::PriceBelow,
+            trigger_threshold: 2_000_000,
+            price_expo: -5,
+            max_price_age_seconds: 60,
+            coverage_amount: 10_000,
+            coverage_end_timestamp: published + DAY,
+            expiry_timestamp: published + DAY,
+            status: PolicyStatus::Purchased,
+            version: CURRENT_POLICY_VERSION,
+            ..Default::default()
+        };
+        // Triggered, paying out at the peg the same feed reports
+        let triggered = InsurancePolicy {
+            payout_peg_oracle_address: Some(oracle),
+            status: PolicyStatus::TriggeredPayout,
+            ..purchased.clone()
+        };
+        // Purchased with a fallback feed configured
+        let fallback_policy = Pubkey::new_unique();
+        let with_fallback = InsurancePolicy {
+            fallback_oracle_address: Some(Pubkey::new_unique()),
+            ..purchased.clone()
+        };
+        for (address, state) in [
+            (policy, &purchased),
+            (pegged_policy, &triggered),
+            (fallback_policy, &with_fallback),
+        ] {
+            let account = program_account(state, 8 + InsurancePolicy::INIT_SPACE);
+            context.set_account(&address, &account.into());
+        }
+        let holder_token_account = get_associated_token_address(&holder, &mint);
+        context.set_account(&holder_token_account, &token_account(mint, holder, 0).into());
+
+        // Two minutes on, the feed is older than the policies accept
+        clock.unix_timestamp = published + 2 * 60;
+        context.set_sysvar(&clock);
+
+        // The check succeeds without evaluating, keeping why for clients
+        let check = check_instruction(keeper, policy, pool, oracle);
+        send(&mut context, &[check], &[]).await.unwrap();
+        let payout =
+            payout_instruction(keeper, pegged_policy, pool, holder, mint, vault, Some(oracle));
+        let result = send(&mut context, &[payout], &[]).await;
+        assert_program_error(result, InsuranceError::StaleOracleData);
+
+        // A configured fallback cannot be left out to stand on the stale primary alone
+        let check = check_instruction(keeper, fallback_policy, pool, oracle);
+        let result = send(&mut context, &[check], &[]).await;
+        assert_program_error(result, InsuranceError::OracleMismatch);
+
+        // Nothing moved on either policy, and the stale check did not use up the interval
+        let unchecked: InsurancePolicy = fetch(&mut context, policy).await;
+        assert!(unchecked.status == PolicyStatus::Purchased);
+        assert_eq!(unchecked.last_block_reason, BlockReason::StaleOracle as u8);
+        assert_eq!(unchecked.last_checked_timestamp, None);
+        let unpaid: InsurancePolicy = fetch(&mut context, pegged_policy).await;
+        assert!(unpaid.status == PolicyStatus::TriggeredPayout);
+        assert_eq!(token_balance(&mut context, holder_token_account).await, 0);
+    }
+
+    #[test]
+    fn test_reduce_coverage_by_half() {
+        let mut policy = InsurancePolicy {
+            coverage_amount: 100_000,
+            premium_amount: 10_000,
+            installment_count: 1,
+            installments_paid: 1,
+            status: PolicyStatus::Purchased,
+            ..Default::default()
+        };
+        assert_eq!(policy.reduce_coverage(50_000).unwrap(), 5_000);
+        assert_eq!(policy.coverage_amount, 50_000);
+        assert_eq!(policy.premium_amount, 5_000);
+
+        // Mid-plan, only installments already paid are refunded
+        let mut policy = InsurancePolicy {
+            coverage_amount: 100_000,
+            premium_amount: 10_000,
+            installment_count: 4,
+            installments_paid: 2,
+            ..Default::default()
+        };
+        assert_eq!(policy.reduce_coverage(50_000).unwrap(), 2_500);
+        assert_eq!(policy.installment_amount(2), 1_250);
+    }
+
+    #[test]
+    fn test_increase_coverage_charges_prorated_premium() {
+        let mut policy = InsurancePolicy {
+            coverage_amount: 100_000,
+            premium_amount: 10_000,
+            installment_count: 1,
+            installments_paid: 1,
+            coverage_start_timestamp: 0,
+            coverage_end_timestamp: 30 * DAY,
+            expiry_timestamp: 30 * DAY,
+            status: PolicyStatus::Purchased,
+            ..Default::default()
+        };
+        let mut pool = PoolState {
+            total_capital: 150_000,
+            reserve_ratio_bps: 10_000,
+            total_committed_coverage: 100_000,
+            ..Default::default()
+        };
+
+        // Doubling coverage with a third of the window left costs a third of the premium
+        let additional = policy.increase_coverage(200_000, 20 * DAY).unwrap();
+        assert_eq!(additional, 3_334);
+        assert_eq!(policy.coverage_amount, 200_000);
+        assert_eq!(policy.premium_amount, 13_334);
+        assert_eq!(policy.premium_paid(), 13_334);
+        pool.total_capital += additional;
+        pool.commit(100_000).unwrap();
+        assert_eq!(pool.total_committed_coverage, 200_000);
+
+        for amount in [200_000, 150_000] {
+            assert_eq!(
+                policy.increase_coverage(amount, 20 * DAY).unwrap_err(),
+                InsuranceError::InvalidPolicyParameters.into()
+            );
+        }
+        assert_eq!(
+            policy.increase_coverage(300_000, 30 * DAY).unwrap_err(),
+            InsuranceError::PolicyExpired.into()
+        );
+    }
+
+    #[test]
+    fn test_increase_coverage_rejected_when_pool_cannot_back_it() {
+        let mut pool = PoolState {
+            total_capital: 150_000,
+            reserve_ratio_bps: 10_000,
+            total_committed_coverage: 100_000,
+            ..Default::default()
+        };
+        assert_eq!(pool.commit(50_001).unwrap_err(), InsuranceError::PoolCapacityExceeded.into());
+        assert_eq!(pool.total_committed_coverage, 100_000);
+
+        // Mid-plan policies settle their installments before growing
+        let mut policy = InsurancePolicy {
+            coverage_amount: 100_000,
+            premium_amount: 10_000,
+            installment_count: 4,
+            installments_paid: 2,
+            coverage_end_timestamp: 30 * DAY,
+            expiry_timestamp: 30 * DAY,
+            ..Default::default()
+        };
+        assert_eq!(
+            policy.increase_coverage(150_000, DAY).unwrap_err(),
+            InsuranceError::InvalidPolicyParameters.into()
+        );
+    }
+
+    #[test]
+    fn test_reduce_coverage_rejects_increase() {
+        let mut policy = InsurancePolicy {
+            coverage_amount: 100_000,
+            premium_amount: 10_000,
+            installment_count: 1,
+            installments_paid: 1,
+            ..Default::default()
+        };
+        for amount in [100_001, 100_000, 0] {
+            assert_eq!(
+                policy.reduce_coverage(amount).unwrap_err(),
+                InsuranceError::InvalidPolicyParameters.into()
+            );
+        }
+        assert_eq!(policy.coverage_amount, 100_000);
+        assert_eq!(policy.premium_amount, 10_000);
+    }
+
+    #[test]
+    fn test_transfer_purchased_policy() {
+        let holder = Pubkey::new_unique();
+        let buyer = Pubkey::new_unique();
+        let other = Pubkey::new_unique();
+        let mut policy = InsurancePolicy {
+            policy_holder: holder,
+            status: PolicyStatus::Purchased,
+            beneficiaries: vec![
+                Beneficiary { beneficiary: holder, share_bps: 6_000 },
+                Beneficiary { beneficiary: other, share_bps: 4_000 },
+            ],
+            ..Default::default()
+        };
+        assert_eq!(policy.transfer_to(buyer).unwrap(), holder);
+        assert_eq!(policy.policy_holder, buyer);
+        assert_eq!(policy.beneficiaries[0].beneficiary, buyer);
+        assert_eq!(policy.beneficiaries[1].beneficiary, other);
+        assert!(policy.status == PolicyStatus::Purchased);
+
+        // Transferring to the current holder or the default key is a mistake
+        for target in [buyer, Pubkey::default()] {
+            assert_eq!(
+                policy.transfer_to(target).unwrap_err(),
+                InsuranceError::PolicyNotTransferable.into()
+            );
+        }
+    }
+
+    #[test]
+    fn test_transfer_paid_out_policy_rejected() {
+        let holder = Pubkey::new_unique();
+        let mut policy = InsurancePolicy {
+            policy_holder: holder,
+            status: PolicyStatus::PaidOut,
+            ..Default::default()
+        };
+        assert_eq!(
+            policy.transfer_to(Pubkey::new_unique()).unwrap_err(),
+            InsuranceError::PolicyNotTransferable.into()
+        );
+        assert_eq!(policy.policy_holder, holder);
+    }
+
+    #[test]
+    fn test_split_policy_70_30() {
+        let now = 1_700_000_000;
+        let parent_key = Pubkey::new_unique();
+        let params = InitializeParams {
+            premium_amount: 1_001,
+            ..product_policy_params(Pubkey::new_unique(), now)
+        };
+        let mut policy = InsurancePolicy::default();
+        policy
+            .initialize_from_params(Pubkey::new_unique(), Pubkey::new_unique(), 255, &params, now)
+            .unwrap();
+        policy.apply_event(PolicyEvent::Purchase, now, Pubkey::default()).unwrap();
+        policy.installments_paid = 1;
+        policy.gross_premium_amount = 1_001;
+        policy.keeper_reward_reserved = 5_000;
+
+        let child = policy.split(parent_key, 70_000, 30_000).unwrap();
+        assert_eq!((policy.coverage_amount, child.coverage_amount), (70_000, 30_000));
+        // The child's premium rounds down; the parts still sum to the original
+        assert_eq!((policy.premium_amount, child.premium_amount), (701, 300));
+        assert_eq!(policy.premium_paid() + child.premium_paid(), 1_001);
+        assert_eq!(child.parent_policy, Some(parent_key));
+        assert_eq!((policy.split_count, child.split_count), (1, 0));
+        assert_eq!(child.keeper_reward_reserved, 0);
+
+        // Trigger terms carry over unchanged
+        assert!(child.status == PolicyStatus::Purchased);
+        assert_eq!(child.trigger_condition, policy.trigger_condition);
+        assert_eq!(child.trigger_threshold, policy.trigger_threshold);
+        assert_eq!(child.oracle_address, policy.oracle_address);
+        assert_eq!(child.expiry_timestamp, policy.expiry_timestamp);
+        assert_eq!(child.policy_holder, policy.policy_holder);
+    }
+
+    #[test]
+    fn test_split_policy_rejects_bad_amounts() {
+        let mut policy = InsurancePolicy {
+            status: PolicyStatus::Purchased,
+            coverage_amount: 100_000,
+            premium_amount: 1_000,
+            ..Default::default()
+        };
+        for (retained, split) in [(0, 100_000), (100_000, 0), (70_000, 20_000), (70_000, 40_000)] {
+            assert_eq!(
+                policy.split(Pubkey::new_unique(), retained, split).unwrap_err(),
+                InsuranceError::InvalidPolicyParameters.into()
+            );
+        }
+        // Overflowing sums are rejected rather than wrapping
+        assert_eq!(
+            policy.split(Pubkey::new_unique(), u64::MAX, 1).unwrap_err(),
+            InsuranceError::InvalidPolicyParameters.into()
+        );
+        assert_eq!(policy.coverage_amount, 100_000);
+
+        policy.status = PolicyStatus::TriggeredPayout;
+        assert_eq!(
+            policy.split(Pubkey::new_unique(), 70_000, 30_000).unwrap_err(),
+            InsuranceError::PolicyNotPurchased.into()
+        );
+    }
+
+    fn sol_crash_product(oracle: Pubkey) -> ProductTerms {
+        ProductTerms {
+            trigger_condition: TriggerConditionType::PriceBelow,
+            price_expo: -2,
+            min_threshold: 5_000,
+            max_threshold: 10_000,
+            min_coverage: 1_000,
+            max_coverage: 1_000_000,
+            min_premium_bps: 100,
+            max_premium_bps: 500,
+            min_duration_seconds: 7 * DAY,
+            max_duration_seconds: 90 * DAY,
+            allowed_oracles: vec![oracle],
+            cancellation_fee_bps: 200,
+            cooling_off_seconds: DAY,
+        }
+    }
+
+    fn batch_entry(coverage_amount: u64) -> BatchPolicyEntry {
+        BatchPolicyEntry {
+            policy_holder: Pubkey::new_unique(),
+            coverage_amount,
+            premium_amount: coverage_amount / 20,
+        }
+    }
+
+    #[test]
+    fn test_batch_creates_policy_per_holder() {
+        let now = 1_700_000_000;
+        let authority = Pubkey::new_unique();
+        let template = product_policy_params(Pubkey::new_unique(), now);
+        let entries = vec![batch_entry(100_000), batch_entry(200_000), batch_entry(300_000)];
+
+        let policies = build_policy_batch(&authority, &template, &entries, now, &crate::ID).unwrap();
+        assert_eq!(policies.len(), 3);
+        for ((address, policy), entry) in policies.iter().zip(&entries) {
+            let (expected, bump) = Pubkey::find_program_address(
+                &[b"insurance_policy", authority.as_ref(), entry.policy_holder.as_ref()],
+                &crate::ID,
+            );
+            assert_eq!(*address, expected);
+            assert_eq!(policy.bump, bump);
+            assert_eq!(policy.authority, authority);
+            assert_eq!(policy.policy_holder, entry.policy_holder);
+            assert_eq!(policy.coverage_amount, entry.coverage_amount);
+            assert_eq!(policy.premium_amount, entry.premium_amount);
+            assert_eq!(policy.trigger_threshold, template.trigger_threshold);
+            assert!(policy.status == PolicyStatus::Active);
+        }
+    }
+
+    #[test]
+    fn test_batch_is_all_or_nothing() {
+        let now = 1_700_000_000;
+        let authority = Pubkey::new_unique();
+        let template = product_policy_params(Pubkey::new_unique(), now);
+
+        // A holder repeated late in the batch sinks the entries before it too
+        let mut repeated = vec![batch_entry(100_000), batch_entry(200_000), batch_entry(300_000)];
+        repeated[2].policy_holder = repeated[0].policy_holder;
+        assert_eq!(
+            build_policy_batch(&authority, &template, &repeated, now, &crate::ID).unwrap_err(),
+            InsuranceError::InvalidBatch.into()
+        );
+
+        let oversized: Vec<BatchPolicyEntry> = (0..=MAX_BATCH_POLICIES).map(|_| batch_entry(100_000)).collect();
+        assert_eq!(
+            build_policy_batch(&authority, &template, &oversized, now, &crate::ID).unwrap_err(),
+            InsuranceError::InvalidBatch.into()
+        );
+        assert!(build_policy_batch(&authority, &template, &[], now, &crate::ID).is_err());
+
+        let expired = InitializeParams { expiry_timestamp: now - 1, ..template };
+        let entries = vec![batch_entry(100_000)];
+        assert!(build_policy_batch(&authority, &expired, &entries, now, &crate::ID).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_batch_rejects_entry_below_mint_minimum() {
+        use anchor_lang::InstructionData;
+        use solana_sdk::signature::Signer;
+
+        let mint = Pubkey::new_unique();
+        let (config, config_bump) = Pubkey::find_program_address(&[b"config"], &crate::ID);
+        let global_config = GlobalConfig { bump: config_bump, ..Default::default() };
+        let limits = MintLimits {
+            mint,
+            min_premium_amount: 5_000,
+            min_coverage_amount: 100_000,
+            ..Default::default()
+        };
+        let limits_address = MintLimits::address(&mint);
+        let mut context = start_with_accounts(vec![
+            (config, program_account(&global_config, 8 + GlobalConfig::INIT_SPACE)),
+            (limits_address, program_account(&limits, 8 + MintLimits::INIT_SPACE)),
+        ])
+        .await;
+        let authority = context.payer.pubkey();
+        let now = context.banks_client.get_sysvar::<Clock>().await.unwrap().unix_timestamp;
+
+        let template = InitializeParams {
+            premium_mint: mint,
+            ..product_policy_params(Pubkey::new_unique(), now)
+        };
+        // The second entry's premium is under the mint's minimum
+        let mut entries = vec![batch_entry(200_000), batch_entry(200_000)];
+        entries[1].premium_amount = 4_999;
+        let policies: Vec<Pubkey> = entries
+            .iter()
+            .map(|entry| {
+                let holder = entry.policy_holder;
+                let seeds = [b"insurance_policy".as_ref(), authority.as_ref(), holder.as_ref()];
+                Pubkey::find_program_address(&seeds, &crate::ID).0
+            })
+            .collect();
+        let mut accounts = crate::accounts::InitializeBatch {
+            authority,
+            config,
+            system_program: system_program::ID,
+            premium_mint_limits: limits_address,
+            payout_mint_limits: limits_address,
+        }
+        .to_account_metas(None);
+        accounts.extend(policies.iter().map(|policy| AccountMeta::new(*policy, false)));
+        let batch = Instruction {
+            program_id: crate::ID,
+            accounts,
+            data: crate::instruction::InitializeBatch { count: 2, template, entries }.data(),
+        };
+        let result = send(&mut context, &[batch], &[]).await;
+        assert_program_error(result, InsuranceError::PremiumBelowMinimum);
+
+        // Not even the valid first entry was created
+        let first = context.banks_client.get_account(policies[0]).await.unwrap();
+        assert!(first.is_none());
+    }
+
+    fn product_policy_params(oracle: Pubkey, now: i64) -> InitializeParams {
+        InitializeParams {
+            oracle_address: oracle,
+            trigger_threshold: 8_000,
+            coverage_amount: 100_000,
+            premium_amount: 2_000,
+            expiry_timestamp: now + 30 * DAY,
+            installment_count: 1,
+            max_price_age_seconds: 60,
+            // Overridden by the product
+            cancellation_fee_bps: 0,
+            trigger_condition: TriggerConditionType::PriceAbove,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_product_in_bounds_policy() {
+        let now = 1_700_000_000;
+        let oracle = Pubkey::new_unique();
+        let mut terms = sol_crash_product(oracle);
+        terms.validate().unwrap();
+
+        let params = terms.apply(product_policy_params(oracle, now), now).unwrap();
+        assert_eq!(params.trigger_condition, TriggerConditionType::PriceBelow);
+        assert_eq!(params.cancellation_fee_bps, 200);
+        assert_eq!(params.price_expo, -2);
+
+        let mut policy = InsurancePolicy::default();
+        policy
+            .initialize_from_params(Pubkey::new_unique(), Pubkey::new_unique(), 255, &params, now)
+            .unwrap();
+
+        // Later product changes do not reach the written policy
+        terms.cancellation_fee_bps = 900;
+        terms.max_threshold = 6_000;
+        assert_eq!(policy.cancellation_fee_bps, 200);
+        assert_eq!(policy.trigger_threshold, 8_000);
+    }
+
+    #[test]
+    fn test_product_rejects_out_of_bounds_values() {
+        let now = 1_700_000_000;
+        let oracle = Pubkey::new_unique();
+        let terms = sol_crash_product(oracle);
+        let base = product_policy_params(oracle, now);
+
+        let cases = [
+            InitializeParams { trigger_threshold: 4_999, ..base.clone() },
+            InitializeParams { trigger_threshold: 10_001, ..base.clone() },
+            InitializeParams { coverage_amount: 999, premium_amount: 20, ..base.clone() },
+            InitializeParams { coverage_amount: 1_000_001, premium_amount: 20_000, ..base.clone() },
+            // 0.99% and 5.01% of coverage
+            InitializeParams { premium_amount: 990, ..base.clone() },
+            InitializeParams { premium_amount: 5_010, ..base.clone() },
+            InitializeParams { expiry_timestamp: now + 7 * DAY - 1, ..base.clone() },
+            InitializeParams { expiry_timestamp: now + 90 * DAY + 1, ..base.clone() },
+        ];
+        for params in cases {
+            assert_eq!(
+                terms.apply(params, now).unwrap_err(),
+                InsuranceError::OutsideProductBounds.into()
+            );
+        }
+
+        // Bounds are inclusive
+        assert!(terms
+            .apply(InitializeParams { premium_amount: 5_000, ..base.clone() }, now)
+            .is_ok());
+        assert!(terms
+            .apply(InitializeParams { expiry_timestamp: now + 7 * DAY, ..base }, now)
+            .is_ok());
+    }
+
+    #[test]
+    fn test_product_rejects_unlisted_oracle() {
+        let now = 1_700_000_000;
+        let terms = sol_crash_product(Pubkey::new_unique());
+        let params = product_policy_params(Pubkey::new_unique(), now);
+        assert_eq!(
+            terms.apply(params, now).unwrap_err(),
+            InsuranceError::ProductOracleMismatch.into()
+        );
+
+        let too_many = ProductTerms {
+            allowed_oracles: vec![Pubkey::new_unique(); MAX_PRODUCT_ORACLES + 1],
+            ..sol_crash_product(Pubkey::new_unique())
+        };
+        assert!(too_many.validate().is_err());
+    }
+
+    #[test]
+    fn test_stale_primary_uses_first_healthy_backup() {
+        let now = 1_700_000_000;
+        let mut stale = mock_pyth_price_account(2_150_000, 1_000, -5, now - 120);
+        let mut also_stale = mock_pyth_price_account(2_140_000, 1_000, -5, now - 90);
+        let mut fresh = mock_pyth_price_account(2_100_000, 1_000, -5, now - 5);
+        let read = |data: &mut Vec<u8>| {
+            with_oracle_account(Pubkey::new_unique(), data, |oracle| {
+                load_oracle_price(&OracleKind::PythLegacy, oracle, now, 60)
+            })
+        };
+
+        let primary = read(&mut stale);
+        let backups = vec![read(&mut also_stale), read(&mut fresh)];
+        let (price, source) = select_oracle_price(primary, None, backups).unwrap();
+        assert_eq!(price.price, 2_100_000);
+        assert_eq!(source, OracleSource::Backup(1));
+
+        let (_, source) = select_oracle_price(
+            Err(InsuranceError::StaleOracleData.into()),
+            Some(Ok(price_at(50, 10))),
+            vec![Ok(price_at(40, 10))],
+        )
+        .unwrap();
+        assert_eq!(source, OracleSource::Fallback);
+
+        let result = select_oracle_price(
+            Err(InsuranceError::StaleOracleData.into()),
+            None,
+            vec![Err(InsuranceError::InvalidOracleData.into())],
+        );
+        assert_eq!(result.unwrap_err(), InsuranceError::StaleOracleData.into());
+    }
+
+    #[test]
+    fn test_backup_oracles_must_be_contiguous() {
+        let now = 1_700_000_000;
+        let params = InitializeParams {
+            oracle_address: Pubkey::new_unique(),
+            trigger_threshold: 100,
+            expiry_timestamp: now + DAY,
+            installment_count: 1,
+            max_price_age_seconds: 60,
+            backup_oracles: [Pubkey::new_unique(), Pubkey::default(), Pubkey::new_unique()],
+            ..Default::default()
+        };
+        assert!(params.validate(now).is_err());
+
+        let backup = Pubkey::new_unique();
+        let params = InitializeParams {
+            backup_oracles: [backup, Pubkey::default(), Pubkey::default()],
+            ..params
+        };
+        params.validate(now).unwrap();
+        let mut policy = InsurancePolicy::default();
+        policy
+            .initialize_from_params(Pubkey::new_unique(), Pubkey::new_unique(), 255, &params, now)
+            .unwrap();
+        assert_eq!(policy.configured_backup_oracles().collect::<Vec<_>>(), vec![backup]);
+    }
+
+    fn quorum_policy() -> InsurancePolicy {
+        InsurancePolicy {
+            trigger_condition: TriggerConditionType::PriceBelow,
+            trigger_threshold: 60,
+            coverage_end_timestamp: 10 * HOUR,
+            status: PolicyStatus::Purchased,
+            quorum_oracles: [
+                Pubkey::new_unique(),
+                Pubkey::new_unique(),
+                Pubkey::new_unique(),
+                Pubkey::default(),
+                Pubkey::default(),
+            ],
+            required_agreements: 2,
+            ..Default::default()
+        }
+    }
+
+    fn condition_feed(value: u8, publish_time: i64) -> Vec<u8> {
+        let mut data = CONDITION_FEED_MAGIC.to_vec();
+        data.push(value);
+        data.extend_from_slice(&publish_time.to_le_bytes());
+        data
+    }
+
+    #[test]
+    fn test_condition_feed_layout() {
+        let now = 1_700_000_000;
+        let cases = [(condition_feed(1, now - 5), true), (condition_feed(0, now), false)];
+        for (mut data, expected) in cases {
+            let value = with_oracle_account(Pubkey::new_unique(), &mut data, |feed| {
+                load_condition_flag(feed, now, 60)
+            });
+            assert_eq!(value.unwrap(), expected);
+        }
+
+        let mut bad_magic = condition_feed(1, now);
+        bad_magic[0] = b'X';
+        let cases = [
+            (bad_magic, InsuranceError::InvalidOracleData),
+            (condition_feed(2, now), InsuranceError::InvalidOracleData),
+            (condition_feed(1, now)[..16].to_vec(), InsuranceError::InvalidOracleData),
+            (condition_feed(1, now - 61), InsuranceError::StaleOracleData),
+        ];
+        for (mut data, expected) in cases {
+            let result = with_oracle_account(Pubkey::new_unique(), &mut data, |feed| {
+                load_condition_flag(feed, now, 60)
+            });
+            assert_eq!(result.unwrap_err(), expected.into());
+        }
+    }
+
+    #[test]
+    fn test_condition_feed_gates_price_trigger() {
+        let mut policy = InsurancePolicy {
+            condition_oracle: Some(Pubkey::new_unique()),
+            ..quorum_policy()
+        };
+        policy.required_agreements = 0;
+
+        // The price is through the threshold but the event has not happened
+        policy.last_condition_flag = false;
+        assert!(!policy.apply_observation(&price_at(55, 0), None, None, 0).unwrap());
+        assert!(policy.status == PolicyStatus::Purchased);
+
+        policy.last_condition_flag = true;
+        assert!(policy.apply_observation(&price_at(55, 1), None, None, 1).unwrap());
+        assert!(policy.status == PolicyStatus::TriggeredPayout);
+    }
+
+    #[test]
+    fn test_cross_rate_crosses_threshold() {
+        let now = 1_700_000_000;
+        // SOL/USD 150.00 over ETH/USD 3,000.0 at different exponents: SOL/ETH 0.05
+        let sol_usd = Price { price: 15_000, conf: 15, expo: -2, publish_time: now - 5 };
+        let eth_usd = Price { price: 30_000, conf: 60, expo: -1, publish_time: now - 10 };
+        let cross = cross_rate(&sol_usd, &eth_usd).unwrap();
+        assert_eq!((cross.price, cross.expo), (5_000_000, CROSS_RATE_EXPO));
+        // 0.1% + 0.2% of the rate, and the older leg's publish time
+        assert_eq!(cross.conf, 15_000);
+        assert_eq!(cross.publish_time, now - 10);
+        assert_eq!(
+            cross_rate(&sol_usd, &Price { price: 0, ..eth_usd }).unwrap_err(),
+            InsuranceError::InvalidOracleData.into()
+        );
+
+        // Trigger when SOL/ETH falls below 0.055
+        let mut policy = InsurancePolicy {
+            trigger_condition: TriggerConditionType::PriceBelow,
+            trigger_threshold: 5_500_000,
+            price_expo: CROSS_RATE_EXPO,
+            coverage_end_timestamp: now + DAY,
+            max_price_age_seconds: 60,
+            status: PolicyStatus::Purchased,
+            ..Default::default()
+        };
+        let mut data = mock_pyth_price_account(30_000, 60, -1, now - 10);
+        let reading = with_oracle_account(Pubkey::new_unique(), &mut data, |denominator| {
+            // Without the configured denominator feed the check cannot proceed
+            policy.denominator_oracle = Some(Pubkey::new_unique());
+            assert_eq!(
+                policy.apply_cross_rate(sol_usd, Some(denominator), now).unwrap_err(),
+                InsuranceError::OracleMismatch.into()
+            );
+            policy.denominator_oracle = Some(denominator.key());
+            policy.apply_cross_rate(sol_usd, Some(denominator), now)
+        })
+        .unwrap();
+        assert_eq!(reading.price, 5_000_000);
+        assert!(policy.apply_observation(&reading, None, None, now).unwrap());
+        assert!(policy.status == PolicyStatus::TriggeredPayout);
+        assert_eq!(policy.trigger_price, Some(5_000_000));
+
+        // Above the threshold the same pair does not trigger
+        let mut policy = InsurancePolicy { trigger_threshold: 4_500_000, ..quorum_policy() };
+        policy.required_agreements = 0;
+        policy.price_expo = CROSS_RATE_EXPO;
+        policy.coverage_end_timestamp = now + DAY;
+        assert!(!policy.apply_observation(&cross, None, None, now).unwrap());
+    }
+
+    #[test]
+    fn test_purchase_rejected_when_condition_already_met() {
+        let mut policy = InsurancePolicy {
+            trigger_condition: TriggerConditionType::PriceBelow,
+            trigger_threshold: 60,
+            ..Default::default()
+        };
+        policy.check_not_in_the_money(&price_at(61, 0)).unwrap();
+        assert_eq!(
+            policy.check_not_in_the_money(&price_at(59, 0)).unwrap_err(),
+            InsuranceError::ConditionAlreadyMet.into()
+        );
+
+        // Products that intend in-the-money cover opt out of the guard
+        policy.allow_in_the_money_purchase = true;
+        policy.check_not_in_the_money(&price_at(59, 0)).unwrap();
+    }
+
+    #[test]
+    fn test_trigger_requires_post_purchase_print() {
+        let policy = InsurancePolicy { purchased_timestamp: Some(1_000), ..Default::default() };
+        for publish_time in [999, 1_000] {
+            assert_eq!(
+                policy.check_published_after_purchase(&price_at(55, publish_time)).unwrap_err(),
+                InsuranceError::StaleOracleData.into()
+            );
+        }
+        policy.check_published_after_purchase(&price_at(55, 1_001)).unwrap();
+    }
+
+    #[test]
+    fn test_quorum_one_of_three_agreeing() {
+        let mut policy = quorum_policy();
+        let readings = [price_at(55, 0), price_at(70, 0), price_at(65, 0)];
+        policy.last_quorum_agreements = policy.count_agreements(&readings, None, None).unwrap();
+        assert_eq!(policy.last_quorum_agreements, 1);
+        assert!(!policy.apply_observation(&price_at(55, 0), None, None, 0).unwrap());
+        assert!(policy.status == PolicyStatus::Purchased);
+    }
+
+    #[test]
+    fn test_quorum_two_of_three_agreeing() {
+        let mut policy = quorum_policy();
+        let readings = [price_at(55, 0), price_at(58, 0), price_at(65, 0)];
+        policy.last_quorum_agreements = policy.count_agreements(&readings, None, None).unwrap();
+        assert_eq!(policy.last_quorum_agreements, 2);
+        assert!(policy.apply_observation(&price_at(55, 0), None, None, 0).unwrap());
+    }
+
+    #[test]
+    fn test_quorum_three_of_three_agreeing() {
+        let mut policy = quorum_policy();
+        let readings = [price_at(55, 0), price_at(58, 0), price_at(50, 0)];
+        policy.last_quorum_agreements = policy.count_agreements(&readings, None, None).unwrap();
+        assert_eq!(policy.last_quorum_agreements, 3);
+        assert!(policy.apply_observation(&price_at(55, 0), None, None, 0).unwrap());
+
+        // Requiring more agreements than configured feeds is rejected up front
+        let now = 1_700_000_000;
+        let params = InitializeParams {
+            oracle_address: Pubkey::new_unique(),
+            trigger_threshold: 100,
+            expiry_timestamp: now + DAY,
+            installment_count: 1,
+            max_price_age_seconds: 60,
+            quorum_oracles: policy.quorum_oracles,
+            required_agreements: 4,
+            ..Default::default()
+        };
+        assert!(params.validate(now).is_err());
+        InitializeParams { required_agreements: 3, ..params }.validate(now).unwrap();
+    }
+
+    #[test]
+    fn test_sponsored_purchase_refunds_payer() {
+        let holder = Pubkey::new_unique();
+        let sponsor = Pubkey::new_unique();
+        let mut policy = InsurancePolicy {
+            policy_holder: holder,
+            allow_third_party_purchase: true,
+            ..cooling_off_policy(1_700_000_000)
+        };
+        assert_eq!(policy.refund_recipient(), holder);
+
+        policy.premium_payer = Some(sponsor);
+        assert_eq!(policy.refund_recipient(), sponsor);
+        // The refund amount itself is unchanged by who paid
+        assert_eq!(policy.cancellation_refund(1_700_000_000 + HOUR).unwrap(), 1_000);
+    }
+
+    #[test]
+    fn test_lender_funded_purchase_refunds_lender() {
+        let holder = Pubkey::new_unique();
+        let lender = Pubkey::new_unique();
+        let mut policy = InsurancePolicy {
+            policy_holder: holder,
+            ..cooling_off_policy(1_700_000_000)
+        };
+
+        policy.record_premium_payer(lender);
+        assert_eq!(policy.premium_payer, Some(lender));
+        assert_eq!(policy.refund_recipient(), lender);
+        // Inside the cooling-off window the lender gets the whole premium back
+        assert_eq!(policy.cancellation_refund(1_700_000_000 + HOUR).unwrap(), 1_000);
+
+        // A holder funding their own premium is not recorded as a sponsor
+        policy.record_premium_payer(holder);
+        assert_eq!(policy.premium_payer, None);
+        assert_eq!(policy.refund_recipient(), holder);
+    }
+
+    #[test]
+    fn test_sponsored_purchase_pays_out_to_holder() {
+        let holder = Pubkey::new_unique();
+        let payout_mint = Pubkey::new_unique();
+        let mut policy = InsurancePolicy {
+            policy_holder: holder,
+            premium_payer: Some(Pubkey::new_unique()),
+            payout_mint,
+            ..triggered_policy(1_000)
+        };
+        // Without beneficiaries the whole payout goes to the holder's account
+        assert!(policy.split_payout(1_000).unwrap().is_empty());
+        assert_eq!(policy.begin_payout(1_000, 0, Pubkey::default()).unwrap(), 1_000);
+        assert_eq!(policy.policy_holder, holder);
+
+        // Beneficiary destinations are checked against the beneficiaries, never the sponsor
+        let sponsor = policy.premium_payer.unwrap();
+        policy.beneficiaries = vec![Beneficiary { beneficiary: holder, share_bps: 10_000 }];
+        policy.check_beneficiary_destination(0, &holder, &payout_mint).unwrap();
+        assert!(policy.check_beneficiary_destination(0, &sponsor, &payout_mint).is_err());
+    }
+
+    #[test]
+    fn test_unspent_keeper_reward_returned_on_expiry() {
+        let now = 1_700_000_000;
+        let params = InitializeParams {
+            oracle_address: Pubkey::new_unique(),
+            trigger_threshold: 100,
+            expiry_timestamp: now + DAY,
+            installment_count: 1,
+            max_price_age_seconds: 60,
+            keeper_reward_lamports: 5_000_000,
+            ..Default::default()
+        };
+        let mut policy = InsurancePolicy::default();
+        policy
+            .initialize_from_params(Pubkey::new_unique(), Pubkey::new_unique(), 255, &params, now)
+            .unwrap();
+        assert_eq!(policy.keeper_reward_reserved, 5_000_000);
+
+        // Expired without a trigger: the full reservation is released once
+        assert_eq!(policy.take_keeper_reward(), 5_000_000);
+        assert_eq!(policy.keeper_reward_reserved, 0);
+        assert_eq!(policy.take_keeper_reward(), 0);
+    }
+
+    fn rate_limited_policy() -> InsurancePolicy {
+        InsurancePolicy {
+            status: PolicyStatus::Purchased,
+            min_check_interval_seconds: 60,
+            keeper_reward_reserved: 2_500,
+            keeper_reward_per_check: 1_000,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_check_in_same_second_rejected() {
+        let now = 1_700_000_000;
+        let mut policy = rate_limited_policy();
+        policy.record_check(now).unwrap();
+        assert_eq!(policy.record_check(now).unwrap_err(), InsuranceError::CheckTooSoon.into());
+        assert_eq!(policy.record_check(now + 59).unwrap_err(), InsuranceError::CheckTooSoon.into());
+        assert_eq!(policy.last_checked_timestamp, Some(now));
+    }
+
+    #[test]
+    fn test_recheck_cooldown_after_negative_check() {
+        let now = 1_700_000_000;
+        let mut policy = InsurancePolicy {
+            recheck_interval_seconds: HOUR,
+            ..rate_limited_policy()
+        };
+        policy.check_evaluation_due(now).unwrap();
+        policy.record_check(now).unwrap();
+        policy.schedule_recheck(now);
+
+        // Past the anti-spam interval but still inside the recheck cooldown
+        policy.record_check(now + 60).unwrap();
+        assert_eq!(
+            policy.check_evaluation_due(now + 60).unwrap_err(),
+            InsuranceError::EvaluationTooSoon.into()
+        );
+        assert_eq!(
+            policy.check_evaluation_due(now + HOUR - 1).unwrap_err(),
+            InsuranceError::EvaluationTooSoon.into()
+        );
+        policy.check_evaluation_due(now + HOUR).unwrap();
+
+        // Without a recheck interval only the anti-spam interval applies
+        let mut policy = rate_limited_policy();
+        policy.schedule_recheck(now);
+        policy.check_evaluation_due(now).unwrap();
+    }
+
+    #[test]
+    fn test_block_reason_matches_cause() {
+        let now = 1_700_000_000;
+        let reason = |policy: &InsurancePolicy| policy.last_block_reason;
+
+        // Feed older than the staleness bound
+        let mut policy = rate_limited_policy();
+        let mut data = mock_pyth_price_account(2_150_000, 1_000, -5, now - 120);
+        let stale = with_oracle_account(Pubkey::new_unique(), &mut data, |oracle| {
+            load_oracle_price(&OracleKind::PythLegacy, oracle, now, 60)
+        });
+        assert!(policy.note_block(stale).unwrap().is_none());
+        assert_eq!(reason(&policy), BlockReason::StaleOracle as u8);
+
+        // Reading outside the sanity band
+        let banded = InsurancePolicy { sane_price_min: 50, sane_price_max: 150, ..quorum_policy() };
+        let mut policy = banded.clone();
+        let checked = banded.check_price_band(&price_at(10, now));
+        assert_eq!(policy.note_block(checked).unwrap(), None);
+        assert_eq!(reason(&policy), BlockReason::InvalidOracle as u8);
+
+        // Caller mistakes and calls made too early pass through and record nothing
+        let mut policy = rate_limited_policy();
+        let mismatch: Result<()> = err!(InsuranceError::OracleMismatch);
+        assert_eq!(
+            policy.note_block(mismatch).unwrap_err(),
+            InsuranceError::OracleMismatch.into()
+        );
+        policy.record_check(now).unwrap();
+        let admitted = policy.record_check(now + 59);
+        assert_eq!(policy.note_block(admitted).unwrap_err(), InsuranceError::CheckTooSoon.into());
+        let mut policy = InsurancePolicy {
+            triggered_timestamp: Some(now),
+            payout_delay_seconds: DAY,
+            ..triggered_policy(1_000)
+        };
+        let releasable = policy.check_payout_releasable(now + HOUR);
+        assert_eq!(
+            policy.note_block(releasable).unwrap_err(),
+            InsuranceError::PayoutNotYetReleasable.into()
+        );
+        assert_eq!(reason(&policy), BlockReason::Clear as u8);
+    }
+
+    #[test]
+    fn test_block_reason_follows_evaluation() {
+        let key = Pubkey::new_unique();
+        let mut policy = quorum_policy();
+        policy.required_agreements = 0;
+
+        policy.record_observation(key, &price_at(80, HOUR), None, None, HOUR).unwrap();
+        assert_eq!(policy.last_block_reason, BlockReason::ConditionNotMet as u8);
+
+        // A trigger clears the earlier reason
+        policy.record_observation(key, &price_at(40, 2 * HOUR), None, None, 2 * HOUR).unwrap();
+        assert_eq!(policy.status, PolicyStatus::TriggeredPayout);
+        assert_eq!(policy.last_block_reason, BlockReason::Clear as u8);
+    }
+
+    #[test]
+    fn test_check_after_interval_allowed() {
+        let now = 1_700_000_000;
+        let mut policy = InsurancePolicy {
+            min_check_interval_seconds: 30 * DAY,
+            ..rate_limited_policy()
+        };
+        // The first check after purchase never waits
+        policy.record_check(now).unwrap();
+        policy.record_check(now + 30 * DAY).unwrap();
+        assert_eq!(policy.last_checked_timestamp, Some(now + 30 * DAY));
+    }
+
+    #[test]
+    fn test_keeper_reward_paid_once_per_interval() {
+        let now = 1_700_000_000;
+        let mut policy = rate_limited_policy();
+        let mut paid = 0;
+
+        // Two keepers race in the same second; only the first lands a check
+        for _ in 0..2 {
+            if policy.record_check(now).is_ok() {
+                paid += policy.take_check_reward();
+            }
+        }
+        assert_eq!(paid, 1_000);
+        assert_eq!(policy.keeper_reward_reserved, 1_500);
+
+        // Later intervals are paid until the reserve runs dry
+        policy.record_check(now + 60).unwrap();
+        assert_eq!(policy.take_check_reward(), 1_000);
+        policy.record_check(now + 120).unwrap();
+        assert_eq!(policy.take_check_reward(), 500);
+        policy.record_check(now + 180).unwrap();
+        assert_eq!(policy.take_check_reward(), 0);
+    }
+
+    fn roles_with(flags: u8) -> Roles {
+        Roles {
+            grantee: Pubkey::new_unique(),
+            flags,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_require_role_gates() {
+        let gates = [Roles::ADMIN, Roles::ORACLE_MANAGER, Roles::UNDERWRITER];
+        for gate in gates {
+            assert!(require_role(&roles_with(gate), gate).is_ok());
+            // Every other role is refused
+            assert_eq!(
+                require_role(&roles_with(Roles::ALL & !gate), gate).unwrap_err(),
+                InsuranceError::Unauthorized.into()
+            );
+        }
+        assert!(require_role(&roles_with(Roles::ALL), Roles::UNDERWRITER).is_ok());
+    }
+
+    #[test]
+    fn test_config_admin_and_admin_role_can_grant() {
+        let admin = Pubkey::new_unique();
+        let config = GlobalConfig { admin, ..Default::default() };
+        assert!(config.can_grant(&admin, None));
+
+        let other = Pubkey::new_unique();
+        assert!(!config.can_grant(&other, None));
+        assert!(!config.can_grant(&other, Some(&roles_with(Roles::UNDERWRITER))));
+        assert!(config.can_grant(&other, Some(&roles_with(Roles::ADMIN))));
+    }
+
+    #[test]
+    fn test_protocol_fee_skimmed_from_premium() {
+        let config = GlobalConfig { protocol_fee_bps: 250, ..Default::default() };
+        let fee = config.protocol_fee(10_001).unwrap();
+        assert_eq!(fee, 250);
+        assert_eq!(10_001 - fee, 9_751);
+
+        let free = GlobalConfig::default();
+        assert_eq!(free.protocol_fee(10_001).unwrap(), 0);
+    }
+
+    #[test]
+    fn test_policy_duration_cap() {
+        let now = 1_700_000_000;
+        let config = GlobalConfig { max_policy_duration_seconds: 365 * DAY, ..Default::default() };
+        config.check_policy_duration(now + 30 * DAY, now).unwrap();
+        config.check_policy_duration(now + 365 * DAY, now).unwrap();
+        assert_eq!(
+            config.check_policy_duration(now + 365 * DAY + 1, now).unwrap_err(),
+            InsuranceError::InvalidPolicyParameters.into()
+        );
+        assert_eq!(
+            config.check_policy_duration(i64::MAX, now).unwrap_err(),
+            InsuranceError::InvalidPolicyParameters.into()
+        );
+
+        // An unset cap accepts any expiry
+        GlobalConfig::default().check_policy_duration(i64::MAX, now).unwrap();
+    }
+
+    #[test]
+    fn test_policy_numbers_strictly_increase() {
+        let now = 1_700_000_000;
+        let authority = Pubkey::new_unique();
+        let params = product_policy_params(Pubkey::new_unique(), now);
+        let mut config = GlobalConfig { policy_count: 1_041, ..Default::default() };
+
+        let mut numbers = Vec::new();
+        for _ in 0..4 {
+            let holder = Pubkey::new_unique();
+            let mut policy = InsurancePolicy::default();
+            policy.initialize_numbered(&mut config, authority, holder, 255, &params, now).unwrap();
+            let event = policy.initialized_event(Pubkey::default());
+            assert_eq!(event.policy_number, policy.policy_number);
+            numbers.push(policy.policy_number);
+        }
+        assert_eq!(numbers, vec![1_042, 1_043, 1_044, 1_045]);
+        assert_eq!(config.policy_count, 1_045);
+    }
+
+    #[test]
+    fn test_failed_initialize_keeps_policy_number() {
+        let now = 1_700_000_000;
+        let authority = Pubkey::new_unique();
+        let params = product_policy_params(Pubkey::new_unique(), now);
+        let mut config = GlobalConfig::default();
+
+        let bad = InitializeParams { expiry_timestamp: now - 1, ..params.clone() };
+        let mut policy = InsurancePolicy::default();
+        assert!(policy
+            .initialize_numbered(&mut config, authority, Pubkey::new_unique(), 255, &bad, now)
+            .is_err());
+        assert_eq!(config.policy_count, 0);
+        assert_eq!(policy.policy_number, 0);
+
+        policy
+            .initialize_numbered(&mut config, authority, Pubkey::new_unique(), 255, &params, now)
+            .unwrap();
+        assert_eq!(policy.policy_number, 1);
+    }
+
+    #[test]
+    fn test_loyalty_discount_after_claim_free_streak() {
+        let mut config = GlobalConfig::default();
+        config
+            .set_loyalty_schedule(&[LoyaltyTier { min_claim_free_policies: 3, discount_bps: 500 }])
+            .unwrap();
+        let mut stats = HolderStats::default();
+        let purchase = |stats: &mut HolderStats| {
+            let mut policy = InsurancePolicy {
+                premium_amount: 10_000,
+                installment_count: 1,
+                ..Default::default()
+            };
+            policy
+                .apply_loyalty_discount(config.loyalty_discount_bps(stats.claim_free_policies))
+                .unwrap();
+            stats.record_purchase(policy.installment_amount(0));
+            policy
+        };
+
+        // Three claim-free cycles at full price
+        for _ in 0..3 {
+            let policy = purchase(&mut stats);
+            assert_eq!(policy.premium_amount, 10_000);
+            assert_eq!(policy.gross_premium_amount, 10_000);
+        }
+
+        // The fourth purchase earns the 5% tier
+        let policy = purchase(&mut stats);
+        assert_eq!(policy.gross_premium_amount, 10_000);
+        assert_eq!(policy.premium_amount, 9_500);
+        assert_eq!(policy.loyalty_discount_bps, 500);
+
+        // A paid claim drops the holder back to full price
+        stats.record_claim();
+        let policy = purchase(&mut stats);
+        assert_eq!(policy.premium_amount, 10_000);
+        assert_eq!(policy.loyalty_discount_bps, 0);
+
+        assert_eq!(stats.policies_purchased, 5);
+        assert_eq!(stats.claims_paid, 1);
+        assert_eq!(stats.premiums_paid_total, 49_500);
+        assert_eq!(stats.claim_free_policies, 1);
+    }
+
+    #[test]
+    fn test_loyalty_schedule_bounds() {
+        let mut config = GlobalConfig::default();
+        let tier = |min_claim_free_policies, discount_bps| LoyaltyTier {
+            min_claim_free_policies,
+            discount_bps,
+        };
+        config.set_loyalty_schedule(&[tier(3, 500), tier(10, 1_000)]).unwrap();
+        assert_eq!(config.loyalty_discount_bps(2), 0);
+        assert_eq!(config.loyalty_discount_bps(9), 500);
+        assert_eq!(config.loyalty_discount_bps(10), 1_000);
+
+        assert_eq!(
+            config.set_loyalty_schedule(&[tier(1, MAX_LOYALTY_DISCOUNT_BPS + 1)]).unwrap_err(),
+            InsuranceError::InvalidPolicyParameters.into()
+        );
+        assert_eq!(
+            config.set_loyalty_schedule(&[tier(1, 100); MAX_LOYALTY_TIERS + 1]).unwrap_err(),
+            InsuranceError::InvalidPolicyParameters.into()
+        );
+        // A shorter schedule clears the tiers it does not mention
+        config.set_loyalty_schedule(&[tier(3, 500)]).unwrap();
+        assert_eq!(config.loyalty_discount_bps(10), 500);
+    }
+
+    #[test]
+    fn test_referral_fee_split() {
+        let holder = Pubkey::new_unique();
+        let integrator = Pubkey::new_unique();
+        let policy = InsurancePolicy { policy_holder: holder, ..Default::default() };
+        let config = GlobalConfig {
+            protocol_fee_bps: 250,
+            referral_fee_bps: MAX_REFERRAL_FEE_BPS,
+            ..Default::default()
+        };
+
+        // With a referrer: 2.5% protocol fee and 5% referral cut, the pool keeps the rest
+        let premium = 10_001;
+        let protocol_fee = config.protocol_fee(premium).unwrap();
+        let referral_fee = policy.referral_fee(&config, Some(integrator), premium).unwrap();
+        assert_eq!(referral_fee, 500);
+        assert_eq!(premium - protocol_fee - referral_fee, 9_251);
+
+        // Without a referrer the pool's share is untouched
+        assert_eq!(policy.referral_fee(&config, None, premium).unwrap(), 0);
+    }
+
+    #[test]
+    fn test_self_referral_rejected() {
+        let holder = Pubkey::new_unique();
+        let policy = InsurancePolicy { policy_holder: holder, ..Default::default() };
+        let config = GlobalConfig { referral_fee_bps: 100, ..Default::default() };
+        assert_eq!(
+            policy.referral_fee(&config, Some(holder), 10_000).unwrap_err(),
+            InsuranceError::SelfReferral.into()
+        );
+    }
+
+    #[test]
+    fn test_zero_referral_rate_pays_nothing() {
+        let policy = InsurancePolicy { policy_holder: Pubkey::new_unique(), ..Default::default() };
+        let config = GlobalConfig::default();
+        let referred = policy.referral_fee(&config, Some(Pubkey::new_unique()), 10_000).unwrap();
+        let unreferred = policy.referral_fee(&config, None, 10_000).unwrap();
+        assert_eq!(referred, 0);
+        assert_eq!(referred, unreferred);
+    }
+
+    #[test]
+    fn test_master_pause_enforced() {
+        let mut config = GlobalConfig::default();
+        config.require_not_paused().unwrap();
+        config.paused = true;
+        assert_eq!(
+            config.require_not_paused().unwrap_err(),
+            InsuranceError::ProtocolPaused.into()
+        );
+    }
+
+    #[test]
+    fn test_revoked_role_fails_next_check() {
+        let mut roles = roles_with(0);
+        roles.grant(Roles::ORACLE_MANAGER | Roles::UNDERWRITER);
+        require_role(&roles, Roles::ORACLE_MANAGER).unwrap();
+
+        roles.revoke(Roles::ORACLE_MANAGER);
+        assert_eq!(
+            require_role(&roles, Roles::ORACLE_MANAGER).unwrap_err(),
+            InsuranceError::Unauthorized.into()
+        );
+        // Other roles are untouched
+        require_role(&roles, Roles::UNDERWRITER).unwrap();
+    }
+
+    fn role_account(grantee: Pubkey, flags: u8) -> (Pubkey, solana_sdk::account::Account) {
+        let (address, bump) =
+            Pubkey::find_program_address(&[b"role", grantee.as_ref()], &crate::ID);
+        (address, program_account(&Roles { grantee, flags, bump }, 8 + Roles::INIT_SPACE))
+    }
+
+    #[tokio::test]
+    async fn test_update_product_rejects_revoked_and_foreign_callers() {
+        use anchor_lang::InstructionData;
+        use solana_sdk::signature::{Keypair, Signer};
+
+        let underwriter = Keypair::new();
+        let outsider = Keypair::new();
+        let product = Pubkey::new_unique();
+        let product_state = ProductConfig {
+            authority: underwriter.pubkey(),
+            product_id: 1,
+            terms: sol_crash_product(Pubkey::new_unique()),
+            bump: 255,
+        };
+        let mut context = start_with_accounts(vec![
+            (product, program_account(&product_state, 8 + ProductConfig::INIT_SPACE)),
+            role_account(underwriter.pubkey(), 0),
+            role_account(outsider.pubkey(), Roles::UNDERWRITER),
+        ])
+        .await;
+        let update = |authority: Pubkey, terms: ProductTerms| Instruction {
+            program_id: crate::ID,
+            accounts: crate::accounts::UpdateProduct {
+                authority,
+                product,
+                roles: role_account(authority, 0).0,
+            }
+            .to_account_metas(None),
+            data: crate::instruction::UpdateProduct { terms }.data(),
+        };
+        let terms = sol_crash_product(Pubkey::new_unique());
+
+        // The product's own authority, once its underwriter role is revoked
+        let ix = update(underwriter.pubkey(), terms.clone());
+        let result = send(&mut context, &[ix], &[&underwriter]).await;
+        assert_program_error(result, InsuranceError::Unauthorized);
+        // An underwriter that does not own the product
+        let ix = update(outsider.pubkey(), terms.clone());
+        let result = send(&mut context, &[ix], &[&outsider]).await;
+        assert_program_error(result, InsuranceError::Unauthorized);
+
+        let (role, account) = role_account(underwriter.pubkey(), Roles::UNDERWRITER);
+        context.set_account(&role, &account.into());
+        send(&mut context, &[update(underwriter.pubkey(), terms.clone())], &[&underwriter])
+            .await
+            .unwrap();
+        let updated: ProductConfig = fetch(&mut context, product).await;
+        assert_eq!(updated.terms.allowed_oracles, terms.allowed_oracles);
+    }
+
+    #[tokio::test]
+    async fn test_forfeited_deposit_withdrawn_by_pool_authority() {
+        use anchor_lang::InstructionData;
+        use solana_sdk::signature::{Keypair, Signer};
+
+        let pool_authority = Keypair::new();
+        let (mint, vault, policy) =
+            (Pubkey::new_unique(), Pubkey::new_unique(), Pubkey::new_unique());
+        let pool_state = PoolState {
+            authority: pool_authority.pubkey(),
+            ..funded_pool(100_000)
+        };
+        let (pool, mut accounts) = pool_accounts(mint, vault, pool_state, GlobalConfig::default());
+        accounts.push((pool_authority.pubkey(), system_account(1_000_000_000)));
+        let mut context = start_with_accounts(accounts).await;
+        let authority = context.payer.pubkey();
+
+        let mut triggered = InsurancePolicy {
+            authority,
+            pool,
+            premium_mint: mint,
+            payout_mint: mint,
+            coverage_amount: 10_000,
+            status: PolicyStatus::TriggeredPayout,
+            version: CURRENT_POLICY_VERSION,
+            ..Default::default()
+        };
+        triggered.escrow_keeper_deposit(50_000, 0, HOUR);
+        let mut policy_account = program_account(&triggered, 8 + InsurancePolicy::INIT_SPACE);
+        policy_account.lamports += 50_000;
+        context.set_account(&policy, &policy_account.into());
+        let pool_lamports = context.banks_client.get_balance(pool).await.unwrap();
+
+        let dispute = Instruction {
+            program_id: crate::ID,
+            accounts: crate::accounts::DisputeTrigger {
+                authority,
+                insurance_policy: policy,
+                pool_state: pool,
+            }
+            .to_account_metas(None),
+            data: crate::instruction::DisputeTrigger {}.data(),
+        };
+        send(&mut context, &[dispute], &[]).await.unwrap();
+        let pool_state: PoolState = fetch(&mut context, pool).await;
+        assert_eq!(pool_state.forfeited_deposits, 50_000);
+        let balance = context.banks_client.get_balance(pool).await.unwrap();
+        assert_eq!(balance, pool_lamports + 50_000);
+
+        let withdraw = Instruction {
+            program_id: crate::ID,
+            accounts: crate::accounts::WithdrawForfeitedDeposits {
+                authority: pool_authority.pubkey(),
+                pool_state: pool,
+            }
+            .to_account_metas(None),
+            data: crate::instruction::WithdrawForfeitedDeposits {}.data(),
+        };
+        send(&mut context, &[withdraw], &[&pool_authority]).await.unwrap();
+        let pool_state: PoolState = fetch(&mut context, pool).await;
+        assert_eq!(pool_state.forfeited_deposits, 0);
+        assert_eq!(context.banks_client.get_balance(pool).await.unwrap(), pool_lamports);
+        let balance = context.banks_client.get_balance(pool_authority.pubkey()).await.unwrap();
+        assert_eq!(balance, 1_000_000_000 + 50_000);
+    }
+
+    #[tokio::test]
+    async fn test_lapse_returns_unspent_keeper_reward() {
+        use anchor_lang::InstructionData;
+        use solana_sdk::signature::Signer;
+
+        let (mint, vault, policy, authority) = (
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+        );
+        let pool_state = PoolState {
+            total_committed_coverage: 1_000,
+            active_policies: 1,
+            ..funded_pool(100_000)
+        };
+        let (pool, mut accounts) = pool_accounts(mint, vault, pool_state, GlobalConfig::default());
+        accounts.push((authority, system_account(1_000_000)));
+        let mut context = start_with_accounts(accounts).await;
+        let now = context.banks_client.get_sysvar::<Clock>().await.unwrap().unix_timestamp;
+
+        let delinquent = InsurancePolicy {
+            authority,
+            pool,
+            premium_mint: mint,
+            payout_mint: mint,
+            coverage_amount: 1_000,
+            keeper_reward_reserved: 5_000,
+            version: CURRENT_POLICY_VERSION,
+            ..installment_policy(now - 2 * MONTH)
+        };
+        let mut policy_account = program_account(&delinquent, 8 + InsurancePolicy::INIT_SPACE);
+        policy_account.lamports += 5_000;
+        context.set_account(&policy, &policy_account.into());
+        let policy_lamports = context.banks_client.get_balance(policy).await.unwrap();
+
+        let lapse = Instruction {
+            program_id: crate::ID,
+            accounts: crate::accounts::LapsePolicy {
+                caller: context.payer.pubkey(),
+                insurance_policy: policy,
+                pool_state: pool,
+                authority,
+            }
+            .to_account_metas(None),
+            data: crate::instruction::LapsePolicy {}.data(),
+        };
+        send(&mut context, &[lapse], &[]).await.unwrap();
+
+        let lapsed: InsurancePolicy = fetch(&mut context, policy).await;
+        assert!(lapsed.status == PolicyStatus::Lapsed);
+        assert_eq!(lapsed.keeper_reward_reserved, 0);
+        let balance = context.banks_client.get_balance(policy).await.unwrap();
+        assert_eq!(balance, policy_lamports - 5_000);
+        let balance = context.banks_client.get_balance(authority).await.unwrap();
+        assert_eq!(balance, 1_000_000 + 5_000);
+        let pool_state: PoolState = fetch(&mut context, pool).await;
+        assert_eq!(pool_state.total_committed_coverage, 0);
+    }
+
+    #[tokio::test]
+    async fn test_initialize_config_requires_upgrade_authority() {
+        use anchor_lang::InstructionData;
+        use solana_sdk::bpf_loader_upgradeable::{self, UpgradeableLoaderState};
+        use solana_sdk::signature::{Keypair, Signer};
+
+        let deployer = Keypair::new();
+        let program_data =
+            Pubkey::find_program_address(&[crate::ID.as_ref()], &bpf_loader_upgradeable::ID).0;
+        let state = UpgradeableLoaderState::ProgramData {
+            slot: 0,
+            upgrade_authority_address: Some(deployer.pubkey()),
+        };
+        let program_data_account = solana_sdk::account::Account::new_data(
+            1_000_000_000,
+            &state,
+            &bpf_loader_upgradeable::ID,
+        )
+        .unwrap();
+        let mut context = start_with_accounts(vec![
+            (program_data, program_data_account),
+            (deployer.pubkey(), system_account(1_000_000_000)),
+        ])
+        .await;
+        let config = Pubkey::find_program_address(&[b"config"], &crate::ID).0;
+        let initialize = |admin: Pubkey| Instruction {
+            program_id: crate::ID,
+            accounts: crate::accounts::InitializeConfig {
+                admin,
+                config,
+                program_data,
+                system_program: system_program::ID,
+            }
+            .to_account_metas(None),
+            data: crate::instruction::InitializeConfig {}.data(),
+        };
+
+        // Whoever gets there first is refused unless they hold the upgrade key
+        let payer = context.payer.pubkey();
+        let result = send(&mut context, &[initialize(payer)], &[]).await;
+        assert_program_error(result, InsuranceError::Unauthorized);
+
+        send(&mut context, &[initialize(deployer.pubkey())], &[&deployer]).await.unwrap();
+        let config: GlobalConfig = fetch(&mut context, config).await;
+        assert_eq!(config.admin, deployer.pubkey());
+    }
+
+    #[test]
+    fn test_distinct_premium_and_payout_mints() {
+        let now = 1_700_000_000;
+        let usdc = Pubkey::new_unique();
+        let protocol_token = Pubkey::new_unique();
+        let params = InitializeParams {
+            oracle_address: Pubkey::new_unique(),
+            trigger_threshold: 100,
+            expiry_timestamp: now + DAY,
+            installment_count: 1,
+            max_price_age_seconds: 60,
+            premium_mint: usdc,
+            payout_mint: Some(protocol_token),
+            ..Default::default()
+        };
+        let mut policy = InsurancePolicy::default();
+        policy
+            .initialize_from_params(Pubkey::new_unique(), Pubkey::new_unique(), 255, &params, now)
+            .unwrap();
+        assert_eq!(policy.premium_mint, usdc);
+        assert_eq!(policy.payout_mint, protocol_token);
+
+        // Without an explicit payout mint, payouts settle in the premium mint
+        let params = InitializeParams { payout_mint: None, ..params };
+        let mut policy = InsurancePolicy::default();
+        policy
+            .initialize_from_params(Pubkey::new_unique(), Pubkey::new_unique(), 255, &params, now)
+            .unwrap();
+        assert_eq!(policy.payout_mint, usdc);
+    }
+
+    #[test]
+    fn test_separate_payout_vault_keeps_its_own_capital() {
+        let vault = Pubkey::new_unique();
+        let payout_vault = Pubkey::new_unique();
+        let mut pool = PoolState { vault, payout_vault, ..Default::default() };
+        pool.credit_capital(vault, 1_000).unwrap();
+        pool.credit_capital(payout_vault, 50_000).unwrap();
+        assert_eq!((pool.total_capital, pool.payout_capital), (1_000, 50_000));
+
+        // Claims come out of the payout mint's capital only
+        pool.debit_payout(20_000);
+        assert_eq!((pool.total_capital, pool.payout_capital), (1_000, 30_000));
+
+        // A pool settling in its premium mint keeps a single counter
+        let mut pool = PoolState { vault, payout_vault: vault, ..Default::default() };
+        pool.credit_capital(vault, 1_000).unwrap();
+        pool.debit_payout(400);
+        assert_eq!((pool.total_capital, pool.payout_capital), (600, 0));
+    }
+
+    fn council(threshold: u8) -> (GlobalConfig, Vec<Pubkey>) {
+        let members: Vec<Pubkey> = (0..3).map(|_| Pubkey::new_unique()).collect();
+        let config = GlobalConfig {
+            council: members.clone(),
+            approval_threshold: threshold,
+            proposal_ttl_seconds: DAY,
+            ..Default::default()
+        };
+        (config, members)
+    }
+
+    fn proposal(proposer: Pubkey, now: i64) -> AdminProposal {
+        AdminProposal {
+            id: 0,
+            proposer,
+            action: AdminAction::UpdateTreasury { fee_recipient: Pubkey::new_unique() },
+            approvals: vec![proposer],
+            created_timestamp: now,
+            expires_timestamp: now + DAY,
+            bump: 255,
+        }
+    }
+
+    #[test]
+    fn test_admin_action_threshold_met() {
+        let now = 1_700_000_000;
+        let (config, members) = council(2);
+        let mut proposal = proposal(members[0], now);
+        proposal.approve(&config, members[1], now + HOUR).unwrap();
+        proposal.ensure_executable(&config, now + HOUR).unwrap();
+    }
+
+    #[test]
+    fn test_admin_action_threshold_not_met() {
+        let now = 1_700_000_000;
+        let (config, members) = council(3);
+        let mut proposal = proposal(members[0], now);
+        proposal.approve(&config, members[1], now).unwrap();
+        assert_eq!(
+            proposal.ensure_executable(&config, now).unwrap_err(),
+            InsuranceError::ApprovalThresholdNotMet.into()
+        );
+
+        // Outsiders cannot add approvals
+        assert_eq!(
+            proposal.approve(&config, Pubkey::new_unique(), now).unwrap_err(),
+            InsuranceError::Unauthorized.into()
+        );
+
+        // Approvals from members since removed from the council no longer count
+        let (mut config, members) = council(2);
+        let mut proposal = self::proposal(members[0], now);
+        proposal.approve(&config, members[1], now).unwrap();
+        config.council.retain(|member| *member != members[1]);
+        assert!(proposal.ensure_executable(&config, now).is_err());
+    }
+
+    #[test]
+    fn test_admin_action_duplicate_approval_rejected() {
+        let now = 1_700_000_000;
+        let (config, members) = council(2);
+        let mut proposal = proposal(members[0], now);
+        assert_eq!(
+            proposal.approve(&config, members[0], now).unwrap_err(),
+            InsuranceError::DuplicateApproval.into()
+        );
+        assert_eq!(proposal.approvals.len(), 1);
+    }
+
+    #[test]
+    fn test_admin_action_expired_proposal_rejected() {
+        let now = 1_700_000_000;
+        let (config, members) = council(2);
+        let mut proposal = proposal(members[0], now);
+        assert_eq!(
+            proposal.approve(&config, members[1], now + DAY).unwrap_err(),
+            InsuranceError::ProposalExpired.into()
+        );
+
+        let mut proposal = self::proposal(members[0], now);
+        proposal.approve(&config, members[1], now).unwrap();
+        assert_eq!(
+            proposal.ensure_executable(&config, now + DAY).unwrap_err(),
+            InsuranceError::ProposalExpired.into()
+        );
+    }
+
+    #[test]
+    fn test_keeper_deposit_returned_on_valid_trigger() {
+        let mut policy = triggered_policy(1_000);
+        policy.escrow_keeper_deposit(50_000, 100, HOUR);
+        assert_eq!(policy.keeper_deposit, 50_000);
+
+        // Paid out within the window: the keeper gets the deposit back
+        assert_eq!(policy.release_keeper_deposit(100 + HOUR), (50_000, 0));
+        assert_eq!(policy.keeper_deposit, 0);
+        assert_eq!(policy.release_keeper_deposit(100 + HOUR), (0, 0));
+
+        // Paid out too late: the deposit goes to the pool
+        policy.escrow_keeper_deposit(50_000, 100, HOUR);
+        assert_eq!(policy.release_keeper_deposit(101 + HOUR), (0, 50_000));
+    }
+
+    #[test]
+    fn test_keeper_deposit_forfeited_on_dispute() {
+        let authority = Pubkey::new_unique();
+        let mut policy = triggered_policy(1_000);
+        policy.escrow_keeper_deposit(50_000, 100, HOUR);
+
+        policy.apply_event(PolicyEvent::Dispute, 200, authority).unwrap();
+        assert_eq!(policy.forfeit_keeper_deposit(), 50_000);
+        assert_eq!(policy.keeper_deposit, 0);
+
+        // Upholding the trigger afterwards leaves nothing for the payout to refund
+        policy.resolve_dispute(true, 300, authority).unwrap();
+        assert_eq!(policy.release_keeper_deposit(400), (0, 0));
+    }
+
+    #[test]
+    fn test_forfeited_deposits_held_for_authority() {
+        let mut pool = PoolState::default();
+        pool.record_forfeited_deposit(50_000).unwrap();
+        pool.record_forfeited_deposit(20_000).unwrap();
+        assert_eq!(pool.forfeited_deposits, 70_000);
+        assert_eq!(
+            pool.record_forfeited_deposit(u64::MAX).unwrap_err(),
+            InsuranceError::MathOverflow.into()
+        );
+
+        assert_eq!(pool.take_forfeited_deposits(), 70_000);
+        assert_eq!(pool.take_forfeited_deposits(), 0);
+    }
+
+    #[test]
+    fn test_cpi_rejected_unless_allowed() {
+        // A transaction-level call into this program
+        assert!(is_direct_invocation(&crate::ID, TRANSACTION_LEVEL_STACK_HEIGHT));
+        // Invoked by another program's top-level instruction
+        assert!(!is_direct_invocation(&Pubkey::new_unique(), TRANSACTION_LEVEL_STACK_HEIGHT + 1));
+        // Reached through a nested CPI, even under our own top-level instruction
+        assert!(!is_direct_invocation(&crate::ID, TRANSACTION_LEVEL_STACK_HEIGHT + 1));
+
+        // The flag skips introspection entirely
+        let config = GlobalConfig { allow_cpi: true, ..Default::default() };
+        let key = Pubkey::new_unique();
+        let mut lamports = 0;
+        let mut data = [];
+        let owner = Pubkey::default();
+        let sysvar =
+            AccountInfo::new(&key, false, false, &mut lamports, &mut data, &owner, false, 0);
+        config.require_direct_invocation(&sysvar).unwrap();
+        assert!(GlobalConfig::default().require_direct_invocation(&sysvar).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_check_through_cpi_rejected_unless_allowed() {
+        use solana_sdk::signature::Signer;
+
+        let (mint, vault, oracle) =
+            (Pubkey::new_unique(), Pubkey::new_unique(), Pubkey::new_unique());
+        let (pool, accounts) =
+            pool_accounts(mint, vault, funded_pool(100_000), GlobalConfig::default());
+        let mut program_test =
+            ProgramTest::new("insurance_contract", crate::ID, processor!(process_instruction));
+        let probe = processor!(cpi_probe::process_instruction);
+        program_test.add_program("cpi_probe", cpi_probe::ID, probe);
+        for (address, account) in accounts {
+            program_test.add_account(address, account);
+        }
+        let mut context = program_test.start_with_context().await;
+        let keeper = context.payer.pubkey();
+
+        // Already triggered, so a check that gets past the guards changes nothing
+        let triggered = InsurancePolicy {
+            authority: keeper,
+            pool,
+            premium_mint: mint,
+            payout_mint: mint,
+            oracle_address: oracle,
+            status: PolicyStatus::TriggeredPayout,
+            version: CURRENT_POLICY_VERSION,
+            ..Default::default()
+        };
+        let (policy, other_policy) = (Pubkey::new_unique(), Pubkey::new_unique());
+        for address in [policy, other_policy] {
+            let account = program_account(&triggered, 8 + InsurancePolicy::INIT_SPACE);
+            context.set_account(&address, &account.into());
+        }
+
+        let relayed = cpi_probe::relay(check_instruction(keeper, policy, pool, oracle));
+        let result = send(&mut context, &[relayed], &[]).await;
+        assert_program_error(result, InsuranceError::CpiNotAllowed);
+        send(&mut context, &[check_instruction(keeper, policy, pool, oracle)], &[]).await.unwrap();
+
+        // Opting in lets the same call through the probe
+        let (config, bump) = Pubkey::find_program_address(&[b"config"], &crate::ID);
+        let allowed = GlobalConfig { allow_cpi: true, bump, ..Default::default() };
+        let account = program_account(&allowed, 8 + GlobalConfig::INIT_SPACE);
+        context.set_account(&config, &account.into());
+        let relayed = cpi_probe::relay(check_instruction(keeper, other_policy, pool, oracle));
+        send(&mut context, &[relayed], &[]).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_purchase_for_through_cpi_rejected() {
+        use anchor_lang::InstructionData;
+        use solana_sdk::signature::Signer;
+
+        let (mint, vault, oracle) =
+            (Pubkey::new_unique(), Pubkey::new_unique(), Pubkey::new_unique());
+        let (pool, accounts) =
+            pool_accounts(mint, vault, funded_pool(100_000), GlobalConfig::default());
+        let mut program_test =
+            ProgramTest::new("insurance_contract", crate::ID, processor!(process_instruction));
+        let probe = processor!(cpi_probe::process_instruction);
+        program_test.add_program("cpi_probe", cpi_probe::ID, probe);
+        for (address, account) in accounts {
+            program_test.add_account(address, account);
+        }
+        let mut context = program_test.start_with_context().await;
+        let payer = context.payer.pubkey();
+        let (config, _) = Pubkey::find_program_address(&[b"config"], &crate::ID);
+
+        let (policy, holder) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let active = InsurancePolicy {
+            authority: Pubkey::new_unique(),
+            policy_holder: holder,
+            pool,
+            premium_mint: mint,
+            payout_mint: mint,
+            oracle_address: oracle,
+            premium_amount: 1_000,
+            allow_third_party_purchase: true,
+            status: PolicyStatus::Active,
+            version: CURRENT_POLICY_VERSION,
+            ..Default::default()
+        };
+        let account = program_account(&active, 8 + InsurancePolicy::INIT_SPACE);
+        context.set_account(&policy, &account.into());
+        // A payer account someone else may spend from, e.g. a flash loan's
+        let (funds, delegated_funds) = (Pubkey::new_unique(), Pubkey::new_unique());
+        context.set_account(&funds, &token_account(mint, payer, 10_000).into());
+        let delegated = spl_token::state::Account {
+            mint,
+            owner: payer,
+            amount: 10_000,
+            delegate: Some(Pubkey::new_unique()).into(),
+            delegated_amount: 10_000,
+            state: spl_token::state::AccountState::Initialized,
+            ..Default::default()
+        };
+        let delegated = packed_account(delegated, spl_token::id());
+        context.set_account(&delegated_funds, &delegated.into());
+
+        let purchase_for = |payer_token_account: Pubkey| Instruction {
+            program_id: crate::ID,
+            accounts: crate::accounts::PurchasePolicyFor {
+                payer,
+                policy_holder: holder,
+                insurance_policy: policy,
+                pool_state: pool,
+                oracle_account: oracle,
+                denominator_oracle_account: None,
+                payer_token_account,
+                insurance_pool_token_account: vault,
+                config,
+                fee_recipient_token_account: None,
+                token_program: spl_token::id(),
+                premium_conversion_oracle_account: None,
+                instructions_sysvar: sysvar_instructions::ID,
+            }
+            .to_account_metas(None),
+            data: crate::instruction::PurchasePolicyFor {}.data(),
+        };
+
+        let relayed = cpi_probe::relay(purchase_for(funds));
+        let result = send(&mut context, &[relayed], &[]).await;
+        assert_program_error(result, InsuranceError::CpiNotAllowed);
+        let result = send(&mut context, &[purchase_for(delegated_funds)], &[]).await;
+        assert_program_error(result, InsuranceError::DelegatedTokenAccount);
+
+        let unpaid: InsurancePolicy = fetch(&mut context, policy).await;
+        assert!(unpaid.status == PolicyStatus::Active);
+        assert_eq!(token_balance(&mut context, funds).await, 10_000);
+    }
+
+    #[test]
+    fn test_resolve_dispute() {
+        let mut policy = InsurancePolicy {
+            status: PolicyStatus::Disputed,
+            triggered_timestamp: Some(100),
+            trigger_price: Some(42),
+            ..Default::default()
+        };
+        policy.resolve_dispute(false, 0, Pubkey::default()).unwrap();
+        assert!(policy.status == PolicyStatus::Purchased);
+        assert_eq!(policy.triggered_timestamp, None);
+        assert_eq!(
+            policy.resolve_dispute(true, 0, Pubkey::default()).unwrap_err(),
+            InsuranceError::PolicyNotDisputed.into()
+        );
+
+        policy.status = PolicyStatus::Disputed;
+        policy.resolve_dispute(true, 0, Pubkey::default()).unwrap();
+        assert!(policy.status == PolicyStatus::TriggeredPayout);
+    }
+
+    fn audit_log() -> AuditLog {
+        AuditLog {
+            scope: Pubkey::new_unique(),
+            sequence: 0,
+            entries: [AuditEntry::default(); AUDIT_LOG_CAPACITY],
+        }
+    }
+
+    #[test]
+    fn test_audit_log_records_actions_in_order() {
+        let (admin, policy, destination) =
+            (Pubkey::new_unique(), Pubkey::new_unique(), Pubkey::new_unique());
+        let mut log = audit_log();
+        assert!(log.latest(MAX_AUDIT_ENTRIES_PER_READ).is_empty());
+
+        log.record(AuditAction::OracleSwap, admin, policy, 0, 100);
+        log.record(AuditAction::EmergencyWithdraw, admin, destination, 5_000, 200);
+        log.record(AuditAction::DisputeResolution, admin, policy, 1, 300);
+
+        let entries = log.latest(MAX_AUDIT_ENTRIES_PER_READ);
+        let summary: Vec<_> = entries
+            .iter()
+            .map(|e| (e.sequence, e.action, e.target, e.amount, e.timestamp))
+            .collect();
+        assert_eq!(
+            summary,
+            vec![
+                (0, AuditAction::OracleSwap as u8, policy, 0, 100),
+                (1, AuditAction::EmergencyWithdraw as u8, destination, 5_000, 200),
+                (2, AuditAction::DisputeResolution as u8, policy, 1, 300),
+            ]
+        );
+        assert!(entries.iter().all(|entry| entry.actor == admin));
+        assert_eq!(log.latest(1), entries[2..].to_vec());
+        assert_eq!(AuditLog::LEN, 8 + std::mem::size_of::<AuditLog>());
+    }
+
+    #[test]
+    fn test_audit_log_wraps_keeping_newest() {
+        let admin = Pubkey::new_unique();
+        let mut log = audit_log();
+        let total = AUDIT_LOG_CAPACITY as u64 + 2;
+        for index in 0..total {
+            log.record(AuditAction::ConfigUpdate, admin, log.scope, index, index as i64);
+        }
+        assert_eq!(log.sequence, total);
+
+        // The two oldest entries were overwritten; sequence numbers stay contiguous
+        let sequences: Vec<_> =
+            log.latest(MAX_AUDIT_ENTRIES_PER_READ).iter().map(|entry| entry.sequence).collect();
+        assert_eq!(sequences, (2..total).collect::<Vec<_>>());
+        let newest: Vec<_> = log.latest(2).iter().map(|entry| entry.amount).collect();
+        assert_eq!(newest, vec![total - 2, total - 1]);
+    }
+
+    #[test]
+    fn test_evidence_submission_limits() {
+        let (authority, holder) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let mut policy = InsurancePolicy {
+            authority,
+            policy_holder: holder,
+            status: PolicyStatus::Disputed,
+            ..Default::default()
+        };
+        for expected in 0..MAX_EVIDENCE_PER_PARTY {
+            let index = policy.record_evidence(EvidenceRole::Authority, &authority).unwrap();
+            assert_eq!(index, expected);
+        }
+        assert_eq!(
+            policy.record_evidence(EvidenceRole::Authority, &authority).unwrap_err(),
+            InsuranceError::EvidenceLimitReached.into()
+        );
+        // The other side keeps its own allowance
+        assert_eq!(policy.record_evidence(EvidenceRole::Holder, &holder).unwrap(), 0);
+
+        // Roles must match the signer
+        assert_eq!(
+            policy.record_evidence(EvidenceRole::Holder, &authority).unwrap_err(),
+            InsuranceError::Unauthorized.into()
+        );
+        assert_eq!(policy.evidence_counts, [MAX_EVIDENCE_PER_PARTY, 1]);
+    }
+
+    #[test]
+    fn test_evidence_rejected_outside_dispute() {
+        let holder = Pubkey::new_unique();
+        for status in [PolicyStatus::Purchased, PolicyStatus::TriggeredPayout] {
+            let mut policy =
+                InsurancePolicy { policy_holder: holder, status, ..Default::default() };
+            assert_eq!(
+                policy.record_evidence(EvidenceRole::Holder, &holder).unwrap_err(),
+                InsuranceError::PolicyNotDisputed.into()
+            );
+            assert_eq!(policy.evidence_counts, [0, 0]);
+        }
+    }
+
+    #[test]
+    fn test_dispute_ruling_cites_winning_evidence() {
+        let (authority, holder) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let mut policy = InsurancePolicy {
+            authority,
+            policy_holder: holder,
+            status: PolicyStatus::Disputed,
+            ..Default::default()
+        };
+        policy.record_evidence(EvidenceRole::Holder, &holder).unwrap();
+
+        // The disputing authority has not backed its dispute yet
+        assert_eq!(
+            policy.record_dispute_ruling(true, Some(0)).unwrap_err(),
+            InsuranceError::MissingDisputeEvidence.into()
+        );
+
+        policy.record_evidence(EvidenceRole::Authority, &authority).unwrap();
+        // Rejecting the trigger must cite the authority's evidence, which has one record
+        assert_eq!(
+            policy.record_dispute_ruling(false, Some(1)).unwrap_err(),
+            InsuranceError::MissingDisputeEvidence.into()
+        );
+        policy.record_dispute_ruling(true, Some(0)).unwrap();
+        policy.resolve_dispute(true, 0, Pubkey::default()).unwrap();
+        assert_eq!(policy.winning_evidence_index, Some(0));
+        assert!(policy.status == PolicyStatus::TriggeredPayout);
+    }
+
+    #[test]
+    fn test_transition_history_records_full_lifecycle() {
+        let now = 1_700_000_000;
+        let authority = Pubkey::new_unique();
+        let holder = Pubkey::new_unique();
+        let council = Pubkey::new_unique();
+        let params = InitializeParams {
+            oracle_address: Pubkey::new_unique(),
+            trigger_threshold: 100,
+            coverage_amount: 1_000,
+            expiry_timestamp: now + DAY,
+            installment_count: 1,
+            max_price_age_seconds: 60,
+            ..Default::default()
+        };
+        let mut policy = InsurancePolicy::default();
+        policy.initialize_from_params(authority, holder, 255, &params, now).unwrap();
+        policy.apply_event(PolicyEvent::Purchase, now + 1, holder).unwrap();
+        policy.declare_manual_trigger(&authority, 7, now + 2).unwrap();
+        policy.apply_event(PolicyEvent::Dispute, now + 3, authority).unwrap();
+        policy.resolve_dispute(true, now + 4, council).unwrap();
+        assert!(policy.finalized_event(Pubkey::default()).is_none());
+        policy.begin_payout(1_000, now + 5, authority).unwrap();
+
+        let record = |from, to, timestamp, actor| TransitionRecord { from, to, timestamp, actor };
+        let expected = vec![
+            record(PolicyStatus::Active, PolicyStatus::Active, now, authority),
+            record(PolicyStatus::Active, PolicyStatus::Purchased, now + 1, holder),
+            record(PolicyStatus::Purchased, PolicyStatus::TriggeredPayout, now + 2, authority),
+            record(PolicyStatus::TriggeredPayout, PolicyStatus::Disputed, now + 3, authority),
+            record(PolicyStatus::Disputed, PolicyStatus::TriggeredPayout, now + 4, council),
+            record(PolicyStatus::TriggeredPayout, PolicyStatus::PaidOut, now + 5, authority),
+        ];
+        assert_eq!(policy.transition_count, 6);
+        assert_eq!(policy.transition_history(), expected);
+
+        // Reaching a terminal status surfaces the same history in the event
+        let key = Pubkey::new_unique();
+        let event = policy.finalized_event(key).unwrap();
+        assert_eq!(event.policy, key);
+        assert!(event.status == PolicyStatus::PaidOut);
+        assert_eq!(event.transition_count, 6);
+        assert_eq!(event.history, expected);
+    }
+
+    #[test]
+    fn test_transition_history_overwrites_oldest() {
+        let actor = Pubkey::new_unique();
+        let mut policy = InsurancePolicy {
+            status: PolicyStatus::TriggeredPayout,
+            ..Default::default()
+        };
+        for step in 0..10 {
+            let event = if step % 2 == 0 { PolicyEvent::Dispute } else { PolicyEvent::Trigger };
+            policy.apply_event(event, step, actor).unwrap();
+        }
+        assert_eq!(policy.transition_count, 10);
+
+        let history = policy.transition_history();
+        assert_eq!(history.len(), TRANSITION_HISTORY_LEN);
+        let timestamps: Vec<i64> = history.iter().map(|record| record.timestamp).collect();
+        assert_eq!(timestamps, (2..10).collect::<Vec<i64>>());
+
+        // Rejected events leave the log untouched
+        policy.apply_event(PolicyEvent::Purchase, 10, actor).unwrap_err();
+        assert_eq!(policy.transition_count, 10);
+    }
+
+    #[tokio::test]
+    async fn test_sweep_expired_skips_unexpired_and_foreign() {
+        use anchor_lang::InstructionData;
+        use solana_sdk::signature::Signer;
+
+        let (mint, vault) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let pool_state = PoolState {
+            total_committed_coverage: 3_000,
+            active_policies: 3,
+            ..funded_pool(100_000)
+        };
+        let (pool, accounts) = pool_accounts(mint, vault, pool_state, GlobalConfig::default());
+        let mut context = start_with_accounts(accounts).await;
+        let keeper = context.payer.pubkey();
+        let now = context.banks_client.get_sysvar::<Clock>().await.unwrap().unix_timestamp;
+
+        let expired = InsurancePolicy {
+            authority: keeper,
+            pool,
+            premium_mint: mint,
+            payout_mint: mint,
+            coverage_amount: 1_000,
+            coverage_end_timestamp: now - HOUR,
+            expiry_timestamp: now - HOUR,
+            status: PolicyStatus::Purchased,
+            version: CURRENT_POLICY_VERSION,
+            ..Default::default()
+        };
+        let unexpired = InsurancePolicy {
+            coverage_end_timestamp: now + DAY,
+            expiry_timestamp: now + DAY,
+            ..expired.clone()
+        };
+        // Expired, but listed with an authority that is not its own
+        let foreign = InsurancePolicy { authority: Pubkey::new_unique(), ..expired.clone() };
+        let policies = [Pubkey::new_unique(), Pubkey::new_unique(), Pubkey::new_unique()];
+        let mut accounts = crate::accounts::SweepExpired { cranker: keeper, pool_state: pool }
+            .to_account_metas(None);
+        for (address, state) in policies.iter().zip([&expired, &unexpired, &foreign]) {
+            let account = program_account(state, 8 + InsurancePolicy::INIT_SPACE);
+            context.set_account(address, &account.into());
+            accounts.push(AccountMeta::new(*address, false));
+            accounts.push(AccountMeta::new(keeper, false));
+        }
+        let sweep = Instruction {
+            program_id: crate::ID,
+            accounts,
+            data: crate::instruction::SweepExpired {}.data(),
+        };
+        send(&mut context, &[sweep], &[]).await.unwrap();
+
+        let mut statuses = Vec::new();
+        for address in policies {
+            let policy: InsurancePolicy = fetch(&mut context, address).await;
+            statuses.push(policy.status);
+        }
+        assert!(
+            statuses == [PolicyStatus::Expired, PolicyStatus::Purchased, PolicyStatus::Purchased]
+        );
+        let pool_state: PoolState = fetch(&mut context, pool).await;
+        assert_eq!(pool_state.total_committed_coverage, 2_000);
+        assert_eq!(pool_state.active_policies, 2);
+    }
+
+    #[test]
+    fn test_sweep_dust_after_policies_settled() {
+        let mut pool = PoolState {
+            total_capital: 1_000,
+            total_committed_coverage: 500,
+            ..Default::default()
+        };
+        assert_eq!(
+            pool.sweepable_dust(1_003).unwrap_err(),
+            InsuranceError::CoverageInForce.into()
+        );
+
+        pool.release(500);
+        assert_eq!(pool.sweepable_dust(1_003).unwrap(), 3);
+        // Recorded capital is never swept
+        assert_eq!(pool.sweepable_dust(900).unwrap(), 0);
+    }
+
+    #[test]
+    fn test_sweep_dust_rejected_while_claims_owed() {
+        let mut pool = PoolState::default();
+        pool.add_obligation(200);
+        assert_eq!(
+            pool.sweepable_dust(10).unwrap_err(),
+            InsuranceError::OutstandingPayoutObligations.into()
+        );
+        pool.settle_obligation(200);
+        assert_eq!(pool.sweepable_dust(10).unwrap(), 10);
+    }
+
+    #[test]
+    fn test_emergency_withdraw_rejected_before_timelock() {
+        let now = 1_700_000_000;
+        let destination = Pubkey::new_unique();
+        let mut pool = PoolState::default();
+        let executable_at = pool.request_emergency_withdraw(500, destination, now).unwrap();
+        assert_eq!(executable_at, now + EMERGENCY_WITHDRAW_TIMELOCK_SECONDS);
+        assert_eq!(
+            pool.request_emergency_withdraw(100, destination, now).unwrap_err(),
+            InsuranceError::EmergencyWithdrawPending.into()
+        );
+
+        assert_eq!(
+            pool.check_emergency_withdraw(500, destination, 1_000, executable_at - 1)
+                .unwrap_err(),
+            InsuranceError::EmergencyWithdrawLocked.into()
+        );
+        pool.check_emergency_withdraw(500, destination, 1_000, executable_at).unwrap();
+
+        pool.clear_emergency_withdraw();
+        assert_eq!(
+            pool.check_emergency_withdraw(500, destination, 1_000, executable_at).unwrap_err(),
+            InsuranceError::NoEmergencyWithdrawPending.into()
+        );
+    }
+
+    #[test]
+    fn test_emergency_withdraw_parameter_mismatch() {
+        let now = 1_700_000_000;
+        let destination = Pubkey::new_unique();
+        let mut pool = PoolState::default();
+        let executable_at = pool.request_emergency_withdraw(500, destination, now).unwrap();
+
+        assert_eq!(
+            pool.check_emergency_withdraw(499, destination, 1_000, executable_at).unwrap_err(),
+            InsuranceError::EmergencyWithdrawMismatch.into()
+        );
+        assert_eq!(
+            pool.check_emergency_withdraw(500, Pubkey::new_unique(), 1_000, executable_at)
+                .unwrap_err(),
+            InsuranceError::EmergencyWithdrawMismatch.into()
+        );
+    }
+
+    #[test]
+    fn test_emergency_withdraw_exceeding_vault_rejected() {
+        let now = 1_700_000_000;
+        let destination = Pubkey::new_unique();
+        let mut pool = PoolState::default();
+        let executable_at = pool.request_emergency_withdraw(500, destination, now).unwrap();
+
+        assert_eq!(
+            pool.check_emergency_withdraw(500, destination, 499, executable_at).unwrap_err(),
+            InsuranceError::InsufficientPoolLiquidity.into()
+        );
+        pool.check_emergency_withdraw(500, destination, 500, executable_at).unwrap();
+    }
+
+    #[test]
+    fn test_council_withdraw_capped_by_claims() {
+        let mut pool = PoolState { total_capital: 1_000, ..Default::default() };
+        pool.add_obligation(300);
+        pool.add_outstanding_claim(200);
+        assert_eq!(pool.withdrawable_capital(), 500);
+
+        pool.add_obligation(600);
+        assert_eq!(pool.withdrawable_capital(), 0);
+    }
+
+    #[test]
+    fn test_emergency_withdraw_respects_triggered_obligations() {
+        let now = 1_700_000_000;
+        let destination = Pubkey::new_unique();
+        let mut pool = PoolState::default();
+        let executable_at = pool.request_emergency_withdraw(500, destination, now).unwrap();
+
+        pool.add_obligation(600);
+        assert_eq!(
+            pool.check_emergency_withdraw(500, destination, 1_000, executable_at).unwrap_err(),
+            InsuranceError::OutstandingPayoutObligations.into()
+        );
+
+        pool.settle_obligation(100);
+        pool.check_emergency_withdraw(500, destination, 1_000, executable_at).unwrap();
+    }
+
+    #[test]
+    fn test_sweep_expired_batch_skips_unexpired() {
+        let now = 1_700_000_000;
+        let mut pool = PoolState {
+            total_committed_coverage: 5_000,
+            active_policies: 5,
+            ..Default::default()
+        };
+        let mut policies: Vec<_> = [now - DAY, now + 1, now, now + DAY, now - HOUR]
+            .into_iter()
+            .map(|expiry_timestamp| InsurancePolicy {
+                expiry_timestamp,
+                coverage_amount: 1_000,
+                status: PolicyStatus::Purchased,
+                ..Default::default()
+            })
+            .collect();
+
+        let expired = policies
+            .iter_mut()
+            .filter(|policy| pool.close_expired(policy, now))
+            .count() as u64;
+        assert_eq!(expired, 3);
+        let statuses: Vec<_> = policies.iter().map(|policy| policy.status.clone()).collect();
+        assert_eq!(
+            statuses,
+            [
+                PolicyStatus::Expired,
+                PolicyStatus::Purchased,
+                PolicyStatus::Expired,
+                PolicyStatus::Purchased,
+                PolicyStatus::Expired,
+            ]
+        );
+        assert_eq!(pool.total_committed_coverage, 2_000);
+        assert_eq!(pool.active_policies, 2);
+        assert_eq!(sweep_fee(expired, 1_000_000_000), 3 * SWEEP_FEE_LAMPORTS);
+        // The fee never dips into the pool account's rent
+        assert_eq!(sweep_fee(expired, SWEEP_FEE_LAMPORTS), SWEEP_FEE_LAMPORTS);
+
+        // Sweeping again expires nothing and leaves the pool alone
+        assert!(!pool.close_expired(&mut policies[0], now));
+        assert_eq!(pool.total_committed_coverage, 2_000);
+    }
+
+    fn triggered_policy(coverage: u64) -> InsurancePolicy {
+        InsurancePolicy {
+            coverage_amount: coverage,
+            status: PolicyStatus::TriggeredPayout,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_mint_limits_per_mint() {
+        // 1 USDC and 1,000 BONK at their respective decimals
+        let usdc = MintLimits {
+            min_premium_amount: 1_000_000,
+            min_coverage_amount: 10_000_000,
+            ..Default::default()
+        };
+        let bonk = MintLimits {
+            min_premium_amount: 100_000_000,
+            min_coverage_amount: 1_000_000_000,
+            ..Default::default()
+        };
+
+        for limits in [&usdc, &bonk] {
+            assert_eq!(
+                limits.check_premium(limits.min_premium_amount - 1).unwrap_err(),
+                InsuranceError::PremiumBelowMinimum.into()
+            );
+            assert_eq!(
+                limits.check_coverage(limits.min_coverage_amount - 1).unwrap_err(),
+                InsuranceError::CoverageBelowMinimum.into()
+            );
+            // Exactly the minimum is accepted
+            limits.check_premium(limits.min_premium_amount).unwrap();
+            limits.check_coverage(limits.min_coverage_amount).unwrap();
+        }
+
+        // An amount that clears one mint's minimum can fall short of another's
+        usdc.check_premium(50_000_000).unwrap();
+        assert_eq!(
+            bonk.check_premium(50_000_000).unwrap_err(),
+            InsuranceError::PremiumBelowMinimum.into()
+        );
+    }
+
+    #[test]
+    fn test_group_members_claim_weighted_slices() {
+        let (alice, bob) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let alice_leaf = group_leaf(0, &alice, 3);
+        let bob_leaf = group_leaf(1, &bob, 1);
+        let mut policy = triggered_policy(1_000);
+        policy.group_merkle_root = Some(merkle_node(&alice_leaf, &bob_leaf));
+        policy.group_total_weight = 4;
+        let mut claims = GroupClaims {
+            policy: Pubkey::default(),
+            member_count: 2,
+            claimed_count: 0,
+            bitmap: vec![0; GroupClaims::bitmap_len(2)],
+            bump: 0,
+        };
+
+        assert_eq!(policy.verify_group_claim(0, &alice, 3, &[bob_leaf]).unwrap(), 750);
+        claims.mark_claimed(0).unwrap();
+        assert!(!claims.all_claimed());
+        assert_eq!(policy.verify_group_claim(1, &bob, 1, &[alice_leaf]).unwrap(), 250);
+        claims.mark_claimed(1).unwrap();
+        assert!(claims.all_claimed());
+
+        // A repeat claim of the same leaf is rejected
+        assert_eq!(claims.mark_claimed(0).unwrap_err(), InsuranceError::AlreadyClaimed.into());
+    }
+
+    #[test]
+    fn test_group_claim_rejects_invalid_proof() {
+        let (alice, bob) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let alice_leaf = group_leaf(0, &alice, 3);
+        let bob_leaf = group_leaf(1, &bob, 1);
+        let mut policy = triggered_policy(1_000);
+        policy.group_merkle_root = Some(merkle_node(&alice_leaf, &bob_leaf));
+        policy.group_total_weight = 4;
+
+        let invalid = [
+            // Inflated weight
+            (1, bob, 3, vec![alice_leaf]),
+            // Someone else's leaf
+            (0, bob, 3, vec![bob_leaf]),
+            // Missing sibling
+            (0, alice, 3, vec![]),
+        ];
+        for (index, member, weight, proof) in invalid {
+            assert_eq!(
+                policy.verify_group_claim(index, &member, weight, &proof).unwrap_err(),
+                InsuranceError::InvalidMerkleProof.into()
+            );
+        }
+        // Single-holder policies have no group to claim from
+        assert_eq!(
+            triggered_policy(1_000).verify_group_claim(0, &alice, 3, &[bob_leaf]).unwrap_err(),
+            InsuranceError::InvalidMerkleProof.into()
+        );
+    }
+
+    #[test]
+    fn test_batch_payouts_in_order_until_liquidity_runs_out() {
+        let authority = Pubkey::new_unique();
+        let mint = Pubkey::new_unique();
+        let pool_key = Pubkey::new_unique();
+        let claimable = |coverage| InsurancePolicy {
+            authority,
+            pool: pool_key,
+            premium_mint: mint,
+            version: CURRENT_POLICY_VERSION,
+            ..triggered_policy(coverage)
+        };
+        let already_paid = InsurancePolicy { status: PolicyStatus::PaidOut, ..claimable(1_000) };
+        let other_authority =
+            InsurancePolicy { authority: Pubkey::new_unique(), ..claimable(1_000) };
+        let policies = [
+            claimable(1_000),
+            already_paid,
+            claimable(1_000),
+            other_authority,
+            // The vault runs dry here; the smaller claim after it waits its turn
+            claimable(1_000),
+            claimable(100),
+        ];
+        let claims: Vec<_> = policies
+            .iter()
+            .map(|policy| policy.batch_claim(&authority, &pool_key, &mint, 0))
+            .collect();
+        assert_eq!(claims[0], Ok(1_000));
+        assert_eq!(claims[1], Err(BatchPayoutResult::NotTriggered));
+        assert_eq!(claims[3], Err(BatchPayoutResult::Ineligible));
+
+        let mut pool = PoolState { mint, ..Default::default() };
+        let results = pool.allocate_batch_payouts(&claims, 2_500, 0);
+        assert_eq!(
+            results,
+            vec![
+                BatchPayoutResult::Paid,
+                BatchPayoutResult::NotTriggered,
+                BatchPayoutResult::Paid,
+                BatchPayoutResult::Ineligible,
+                BatchPayoutResult::InsufficientLiquidity,
+                BatchPayoutResult::InsufficientLiquidity,
+            ]
+        );
+
+        // The drawdown cap ends a batch the same way the vault balance does
+        let mut pool = PoolState {
+            max_drawdown_per_window: 1_500,
+            drawdown_window_seconds: DAY,
+            ..Default::default()
+        };
+        let results = pool.allocate_batch_payouts(&[Ok(1_000), Ok(1_000), Ok(100)], 10_000, 0);
+        assert_eq!(results[0], BatchPayoutResult::Paid);
+        assert_eq!(results[1..], [BatchPayoutResult::InsufficientLiquidity; 2]);
+        assert_eq!(pool.window_paid_out, 1_000);
+    }
+
+    #[test]
+    fn test_payout_held_until_delay_elapses() {
+        let mut policy = triggered_policy(1_000);
+        policy.triggered_timestamp = Some(100);
+        policy.payout_delay_seconds = DAY;
+
+        assert_eq!(
+            policy.check_payout_releasable(99 + DAY).unwrap_err(),
+            InsuranceError::PayoutNotYetReleasable.into()
+        );
+        policy.check_payout_releasable(100 + DAY).unwrap();
+        assert_eq!(policy.begin_payout(1_000, 100 + DAY, Pubkey::default()).unwrap(), 1_000);
+
+        // Without a delay the payout is releasable as soon as it triggers
+        let mut policy = triggered_policy(1_000);
+        policy.triggered_timestamp = Some(100);
+        policy.check_payout_releasable(100).unwrap();
+    }
+
+    #[test]
+    fn test_payout_with_empty_vault() {
+        let mut policy = triggered_policy(1_000);
+        assert_eq!(policy.begin_payout(0, 0, Pubkey::default()).unwrap(), 0);
+        assert!(policy.status == PolicyStatus::PartiallyPaid);
+        assert_eq!(policy.outstanding_claim, 1_000);
+    }
+
+    #[test]
+    fn test_payout_partial_shortfall() {
+        let mut policy = triggered_policy(1_000);
+        assert_eq!(policy.begin_payout(600, 0, Pubkey::default()).unwrap(), 600);
+        assert!(policy.status == PolicyStatus::PartiallyPaid);
+        assert_eq!(policy.outstanding_claim, 400);
+
+        // The shortfall holds back withdrawals just like an unpaid trigger
+        let destination = Pubkey::new_unique();
+        let mut pool = PoolState::default();
+        pool.add_outstanding_claim(policy.outstanding_claim);
+        let executable_at = pool.request_emergency_withdraw(700, destination, 0).unwrap();
+        assert_eq!(
+            pool.check_emergency_withdraw(700, destination, 1_000, executable_at).unwrap_err(),
+            InsuranceError::OutstandingPayoutObligations.into()
+        );
+
+        let mut funded = triggered_policy(1_000);
+        assert_eq!(funded.begin_payout(5_000, 0, Pubkey::default()).unwrap(), 1_000);
+        assert!(funded.status == PolicyStatus::PaidOut);
+        assert_eq!(funded.outstanding_claim, 0);
+    }
+
+    #[test]
+    fn test_outstanding_claim_settled_later() {
+        let mut policy = triggered_policy(1_000);
+        policy.begin_payout(200, 0, Pubkey::default()).unwrap();
+        assert_eq!(
+            policy.settle_claim(0, 0, Pubkey::default()).unwrap_err(),
+            InsuranceError::InsufficientPoolLiquidity.into()
+        );
+
+        assert_eq!(policy.settle_claim(300, 0, Pubkey::default()).unwrap(), 300);
+        assert!(policy.status == PolicyStatus::PartiallyPaid);
+        assert_eq!(policy.settle_claim(10_000, 0, Pubkey::default()).unwrap(), 500);
+        assert!(policy.status == PolicyStatus::PaidOut);
+        assert_eq!(policy.outstanding_claim, 0);
+        assert_eq!(
+            policy.settle_claim(10_000, 0, Pubkey::default()).unwrap_err(),
+            InsuranceError::NoOutstandingClaim.into()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_settle_outstanding_claim_rejects_foreign_destination() {
+        use anchor_lang::InstructionData;
+        use solana_sdk::signature::{Keypair, Signer};
+
+        let authority = Keypair::new();
+        let (mint, vault, policy, holder) = (
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+        );
+        let (pool, mut accounts) =
+            pool_accounts(mint, vault, funded_pool(10_000), GlobalConfig::default());
+        // Paid 6,000 of 10,000 when the vault ran short
+        let mut insurance_policy = InsurancePolicy {
+            authority: authority.pubkey(),
+            policy_holder: holder,
+            pool,
+            premium_mint: mint,
+            payout_mint: mint,
+            version: CURRENT_POLICY_VERSION,
+            ..triggered_policy(10_000)
+        };
+        insurance_policy.begin_payout(6_000, 0, Pubkey::default()).unwrap();
+        let (holder_account, stranger_account) = (Pubkey::new_unique(), Pubkey::new_unique());
+        accounts.extend([
+            (policy, program_account(&insurance_policy, 8 + InsurancePolicy::INIT_SPACE)),
+            (holder_account, token_account(mint, holder, 0)),
+            (stranger_account, token_account(mint, Pubkey::new_unique(), 0)),
+        ]);
+        let mut context = start_with_accounts(accounts).await;
+
+        let settle = |destination: Pubkey| Instruction {
+            program_id: crate::ID,
+            accounts: crate::accounts::SettleOutstandingClaim {
+                authority: authority.pubkey(),
+                insurance_policy: policy,
+                pool_state: pool,
+                policy_holder_token_account: destination,
+                insurance_pool_token_account: vault,
+                token_program: token::ID,
+            }
+            .to_account_metas(None),
+            data: crate::instruction::SettleOutstandingClaim {}.data(),
+        };
+        // The authority cannot redirect the holder's claim to another account
+        let result = send(&mut context, &[settle(stranger_account)], &[&authority]).await;
+        assert_program_error(result, InsuranceError::WrongTokenAccountOwner);
+        assert_eq!(token_balance(&mut context, stranger_account).await, 0);
+
+        send(&mut context, &[settle(holder_account)], &[&authority]).await.unwrap();
+        assert_eq!(token_balance(&mut context, holder_account).await, 4_000);
+        let settled: InsurancePolicy = fetch(&mut context, policy).await;
+        assert!(settled.status == PolicyStatus::PaidOut);
+        assert_eq!(settled.outstanding_claim, 0);
+    }
+
+    #[test]
+    fn test_pro_rata_coverage_half_paid() {
+        let mut policy = InsurancePolicy {
+            coverage_mode: CoverageMode::ProRata,
+            installment_count: 4,
+            installments_paid: 2,
+            ..triggered_policy(1_001)
+        };
+        assert_eq!(policy.effective_coverage().unwrap(), 500);
+        assert_eq!(policy.begin_payout(10_000, 0, Pubkey::default()).unwrap(), 500);
+        assert!(policy.status == PolicyStatus::PaidOut);
+        assert_eq!(policy.outstanding_claim, 0);
+
+        // Full mode ignores the installments paid
+        policy.coverage_mode = CoverageMode::Full;
+        assert_eq!(policy.effective_coverage().unwrap(), 1_001);
+    }
+
+    fn decaying_policy(triggered: i64) -> InsurancePolicy {
+        InsurancePolicy {
+            coverage_schedule: CoverageSchedule::LinearDecay,
+            coverage_start_timestamp: 0,
+            coverage_end_timestamp: 100 * DAY,
+            triggered_timestamp: Some(triggered),
+            ..triggered_policy(1_000_000)
+        }
+    }
 
-#[derive(Accounts)]
-pub struct PurchasePolicy<'info> {
-    #[account(mut)]
-    pub policy_holder: Signer<'info>,
-    
-    #[account(
-        mut,
-        has_one = policy_holder,
-        constraint = insurance_policy.status == PolicyStatus::Active
-    )]
-    pub insurance_policy: Account<'info, InsurancePolicy>,
-    
-    #[account(mut)]
-    pub policy_holder_token_account: Account<'info, TokenAccount>,
-    
-    #[account(mut)]
-    pub insurance_pool_token_account: Account<'info, TokenAccount>,
-    
-    pub token_program: Program<'info, Token>,
-}
+    #[test]
+    fn test_linear_decay_full_coverage_early() {
+        assert_eq!(decaying_policy(0).effective_coverage().unwrap(), 1_000_000);
+        assert_eq!(decaying_policy(DAY).effective_coverage().unwrap(), 990_000);
 
-#[derive(Accounts)]
-pub struct CheckTriggerConditions<'info> {
-    #[account(mut)]
-    pub authority: Signer<'info>,
-    
-    #[account(
-        mut,
-        has_one = authority,
-        constraint = insurance_policy.status == PolicyStatus::Purchased
-    )]
-    pub insurance_policy: Account<'info, InsurancePolicy>,
-    
-    /// CHECK: This is the oracle account that provides price data
-    pub oracle_account: AccountInfo<'info>,
-}
+        // Flat coverage ignores the trigger time
+        let flat = InsurancePolicy {
+            coverage_schedule: CoverageSchedule::Flat,
+            ..decaying_policy(99 * DAY)
+        };
+        assert_eq!(flat.effective_coverage().unwrap(), 1_000_000);
+    }
 
-#[derive(Accounts)]
-pub struct ExecutePayout<'info> {
-    #[account(mut)]
-    pub authority: Signer<'info>,
-    
-    #[account(
-        mut,
-        has_one = authority,
-        constraint = insurance_policy.status == PolicyStatus::TriggeredPayout
-    )]
-    pub insurance_policy: Account<'info, InsurancePolicy>,
-    
-    #[account(mut)]
-    pub policy_holder_token_account: Account<'info, TokenAccount>,
-    
-    #[account(mut)]
-    pub insurance_pool_token_account: Account<'info, TokenAccount>,
-    
-    pub token_program: Program<'info, Token>,
-}
+    #[test]
+    fn test_linear_decay_reduced_near_expiry() {
+        let mut policy = decaying_policy(99 * DAY);
+        assert_eq!(policy.effective_coverage().unwrap(), 10_000);
+        assert_eq!(policy.begin_payout(1_000_000, 99 * DAY, Pubkey::default()).unwrap(), 10_000);
+        assert!(policy.status == PolicyStatus::PaidOut);
 
-#[derive(Accounts)]
-pub struct CancelPolicy<'info> {
-    #[account(mut)]
-    pub policy_holder: Signer<'info>,
-    
-    #[account(
-        mut,
-        has_one = policy_holder,
-        constraint = insurance_policy.status == PolicyStatus::Purchased
-    )]
-    pub insurance_policy: Account<'info, InsurancePolicy>,
-    
-    #[account(mut)]
-    pub policy_holder_token_account: Account<'info, TokenAccount>,
-    
-    #[account(mut)]
-    pub insurance_pool_token_account: Account<'info, TokenAccount>,
-    
-    pub token_program: Program<'info, Token>,
-}
+        // Rounds down, and nothing is left at the very end of the window
+        assert_eq!(decaying_policy(100 * DAY - 1).effective_coverage().unwrap(), 0);
+        assert_eq!(decaying_policy(100 * DAY).effective_coverage().unwrap(), 0);
 
-#[derive(Accounts)]
-pub struct UpdateOracle<'info> {
-    #[account(mut)]
-    pub authority: Signer<'info>,
-    
-    #[account(
-        mut,
-        has_one = authority
-    )]
-    pub insurance_policy: Account<'info, InsurancePolicy>,
-}
+        // Stacks with pro-rata coverage
+        let pro_rata = InsurancePolicy {
+            coverage_mode: CoverageMode::ProRata,
+            installment_count: 2,
+            installments_paid: 1,
+            ..decaying_policy(50 * DAY)
+        };
+        assert_eq!(pro_rata.effective_coverage().unwrap(), 250_000);
+    }
 
-#[account]
-pub struct InsurancePolicy {
-    pub authority: Pubkey,
-    pub policy_holder: Pubkey,
-    pub oracle_address: Pubkey,
-    pub trigger_threshold: i64,
-    pub coverage_amount: u64,
-    pub premium_amount: u64,
-    pub expiry_timestamp: i64,
-    pub created_timestamp: i64,
-    pub purchased_timestamp: Option<i64>,
-    pub triggered_timestamp: Option<i64>,
-    pub payout_timestamp: Option<i64>,
-    pub cancelled_timestamp: Option<i64>,
-    pub trigger_price: Option<i64>,
-    pub status: PolicyStatus,
-    pub bump: u8,
-}
+    #[test]
+    fn test_pro_rata_single_installment_pays_full_coverage() {
+        let policy = InsurancePolicy {
+            coverage_mode: CoverageMode::ProRata,
+            installment_count: 1,
+            installments_paid: 1,
+            ..triggered_policy(1_000)
+        };
+        assert_eq!(policy.effective_coverage().unwrap(), 1_000);
+    }
 
-impl InsurancePolicy {
-    pub const LEN: usize = 8 + // discriminator
-        32 + // authority
-        32 + // policy_holder
-        32 + // oracle_address
-        8 + // trigger_threshold
-        8 + // coverage_amount
-        8 + // premium_amount
-        8 + // expiry_timestamp
-        8 + // created_timestamp
-        9 + // purchased_timestamp (Option<i64>)
-        9 + // triggered_timestamp (Option<i64>)
-        9 + // payout_timestamp (Option<i64>)
-        9 + // cancelled_timestamp (Option<i64>)
-        9 + // trigger_price (Option<i64>)
-        1 + // status
-        1; // bump
-
-    pub fn trigger_condition_type(&self) -> TriggerConditionType {
-        // Simplified logic - in production, this would be configurable
-        if self.trigger_threshold > 0 {
-            TriggerConditionType::PriceAbove
-        } else {
-            TriggerConditionType::PriceBelow
+    #[test]
+    fn test_vested_payout_claims_sum_to_coverage() {
+        let start = 1_700_000_000;
+        let vesting = 30 * DAY;
+        let mut policy = InsurancePolicy {
+            payout_vesting_seconds: vesting,
+            ..triggered_policy(1_000_003)
+        };
+        assert_eq!(policy.begin_vesting(start, Pubkey::default()).unwrap(), 1_000_003);
+        assert!(policy.status == PolicyStatus::Vesting);
+
+        let at_25 = policy.claim_vested(start + vesting / 4, Pubkey::default()).unwrap();
+        assert_eq!(at_25, 250_000);
+        assert_eq!(
+            policy.claim_vested(start + vesting / 4, Pubkey::default()).unwrap_err(),
+            InsuranceError::NothingVested.into()
+        );
+
+        let at_60 = policy.claim_vested(start + vesting * 6 / 10, Pubkey::default()).unwrap();
+        assert_eq!(at_25 + at_60, 600_001);
+        assert!(policy.status == PolicyStatus::Vesting);
+
+        let at_100 = policy.claim_vested(start + vesting, Pubkey::default()).unwrap();
+        assert_eq!(at_25 + at_60 + at_100, 1_000_003);
+        assert!(policy.status == PolicyStatus::PaidOut);
+        assert_eq!(
+            policy.claim_vested(start + 2 * vesting, Pubkey::default()).unwrap_err(),
+            InsuranceError::PayoutNotVesting.into()
+        );
+    }
+
+    fn beneficiary(share_bps: u16) -> Beneficiary {
+        Beneficiary { beneficiary: Pubkey::new_unique(), share_bps }
+    }
+
+    #[test]
+    fn test_three_way_payout_split() {
+        let beneficiaries = vec![beneficiary(7_000), beneficiary(2_000), beneficiary(1_000)];
+        validate_beneficiaries(&beneficiaries).unwrap();
+        let policy = InsurancePolicy { beneficiaries, ..Default::default() };
+
+        let parts = policy.split_payout(1_000_003).unwrap();
+        assert_eq!(parts, vec![700_002, 200_000, 100_001]);
+        assert_eq!(parts.iter().sum::<u64>(), 1_000_003);
+
+        assert_eq!(
+            validate_beneficiaries(&[beneficiary(7_000), beneficiary(2_000)]).unwrap_err(),
+            InsuranceError::InvalidBeneficiaryShares.into()
+        );
+        // Sums to 100% but exceeds the entry cap
+        let too_many: Vec<Beneficiary> =
+            [2_000, 2_000, 2_000, 2_000, 1_000, 1_000].into_iter().map(beneficiary).collect();
+        assert!(validate_beneficiaries(&too_many).is_err());
+    }
+
+    #[test]
+    fn test_reinsured_policy_rejects_beneficiaries() {
+        let mut policy = InsurancePolicy {
+            status: PolicyStatus::Purchased,
+            coverage_amount: 10_000,
+            retention_amount: Some(4_000),
+            ..Default::default()
+        };
+        assert_eq!(
+            policy.replace_beneficiaries(vec![beneficiary(10_000)]).unwrap_err(),
+            InsuranceError::InvalidPolicyParameters.into()
+        );
+        assert!(policy.beneficiaries.is_empty());
+        // Clearing the split stays allowed
+        policy.replace_beneficiaries(Vec::new()).unwrap();
+
+        policy.retention_amount = None;
+        policy.replace_beneficiaries(vec![beneficiary(10_000)]).unwrap();
+        assert_eq!(policy.beneficiaries.len(), 1);
+    }
+
+    #[test]
+    fn test_vesting_policy_rejects_beneficiaries() {
+        let mut policy = InsurancePolicy {
+            status: PolicyStatus::Purchased,
+            payout_vesting_seconds: DAY,
+            ..Default::default()
+        };
+        assert_eq!(
+            policy.replace_beneficiaries(vec![beneficiary(10_000)]).unwrap_err(),
+            InsuranceError::InvalidPolicyParameters.into()
+        );
+        policy.replace_beneficiaries(Vec::new()).unwrap();
+
+        let now = 1_700_000_000;
+        let params = InitializeParams {
+            payout_vesting_seconds: DAY,
+            beneficiaries: vec![beneficiary(10_000)],
+            ..product_policy_params(Pubkey::new_unique(), now)
+        };
+        assert_eq!(
+            params.validate(now).unwrap_err(),
+            InsuranceError::InvalidPolicyParameters.into()
+        );
+        InitializeParams { beneficiaries: Vec::new(), ..params }.validate(now).unwrap();
+    }
+
+    #[test]
+    fn test_beneficiary_destination_wrong_owner_rejected() {
+        let payout_mint = Pubkey::new_unique();
+        let policy = InsurancePolicy {
+            beneficiaries: vec![beneficiary(5_000), beneficiary(5_000)],
+            payout_mint,
+            ..Default::default()
+        };
+        let owner = policy.beneficiaries[1].beneficiary;
+        policy.check_beneficiary_destination(1, &owner, &payout_mint).unwrap();
+        assert_eq!(
+            policy
+                .check_beneficiary_destination(0, &owner, &payout_mint)
+                .unwrap_err(),
+            InsuranceError::InvalidBeneficiaryAccount.into()
+        );
+        assert_eq!(
+            policy.check_beneficiary_destination(2, &owner, &payout_mint).unwrap_err(),
+            InsuranceError::InvalidBeneficiaryAccount.into()
+        );
+    }
+
+    #[test]
+    fn test_beneficiary_destination_wrong_mint_rejected() {
+        let payout_mint = Pubkey::new_unique();
+        let policy = InsurancePolicy {
+            beneficiaries: vec![beneficiary(10_000)],
+            payout_mint,
+            ..Default::default()
+        };
+        let owner = policy.beneficiaries[0].beneficiary;
+        assert_eq!(
+            policy
+                .check_beneficiary_destination(0, &owner, &Pubkey::new_unique())
+                .unwrap_err(),
+            InsuranceError::WrongMint.into()
+        );
+    }
+
+    #[test]
+    fn test_depegged_payout_token_preserves_usd_value() {
+        let mut policy = triggered_policy(100_000);
+        // USDC at $0.95 with Pyth's usual exponent
+        let peg = FixedPrice::new(95_000_000, -8);
+        let owed = policy.pegged_coverage(&peg).unwrap();
+        assert_eq!(owed, 105_264);
+
+        let payable = policy.begin_payout_of(owed, 1_000_000, 0, Pubkey::default()).unwrap();
+        assert!(payable > policy.coverage_amount);
+        // $0.95 per token still adds up to the full USD coverage
+        assert!(payable as u128 * 95 >= policy.coverage_amount as u128 * 100);
+        assert!(policy.status == PolicyStatus::PaidOut);
+    }
+
+    #[test]
+    fn test_peg_at_or_above_par_pays_face_value() {
+        let policy = triggered_policy(100_000);
+        assert_eq!(policy.pegged_coverage(&FixedPrice::new(100_000_000, -8)).unwrap(), 100_000);
+        assert_eq!(policy.pegged_coverage(&FixedPrice::new(101_000_000, -8)).unwrap(), 100_000);
+    }
+
+    #[test]
+    fn test_implausible_peg_rejected() {
+        let policy = triggered_policy(100_000);
+        for peg in [FixedPrice::new(1, -2), FixedPrice::new(2, 0), FixedPrice::new(-1, 0)] {
+            assert_eq!(
+                policy.pegged_coverage(&peg).unwrap_err(),
+                InsuranceError::PegOutOfRange.into()
+            );
         }
     }
-}
 
-#[derive(AnchorSerialize, AnchorDeserialize, Clone, PartialEq, Eq)]
-pub enum PolicyStatus {
-    Active,
-    Purchased,
-    TriggeredPayout,
-    PaidOut,
-    Cancelled,
-    Expired,
-}
+    fn payout_policy() -> InsurancePolicy {
+        InsurancePolicy {
+            policy_holder: Pubkey::new_unique(),
+            payout_mint: Pubkey::new_unique(),
+            ..triggered_policy(1_000)
+        }
+    }
 
-#[derive(Clone)]
-pub enum TriggerConditionType {
-    PriceAbove,
-    PriceBelow,
-    VolatilityAbove,
-}
+    #[test]
+    fn test_payout_to_wallet_without_ata() {
+        let policy = payout_policy();
+        // The ATA address is derived up front and created during the payout
+        let ata = get_associated_token_address(&policy.policy_holder, &policy.payout_mint);
+        assert_eq!(
+            policy.classify_payout_destination(&ata).unwrap(),
+            PayoutDestination::AssociatedTokenAccount
+        );
+    }
 
-#[error_code]
-pub enum InsuranceError {
-    #[msg("Policy is not active")]
-    PolicyNotActive,
-    #[msg("Policy has expired")]
-    PolicyExpired,
-    #[msg("Policy has not been purchased")]
-    PolicyNotPurchased,
-    #[msg("Payout has not been triggered")]
-    PayoutNotTriggered,
-    #[msg("Policy cannot be cancelled")]
-    PolicyCannotBeCancelled,
-    #[msg("Invalid oracle data")]
-    InvalidOracleData,
-    #[msg("Insufficient funds")]
-    InsufficientFunds,
-}
+    #[test]
+    fn test_payout_to_existing_ata() {
+        let mut policy = payout_policy();
+        let ata = get_associated_token_address(&policy.policy_holder, &policy.payout_mint);
+        // Opting into custom accounts does not change how the ATA is treated
+        policy.allow_custom_payout_account = true;
+        assert_eq!(
+            policy.classify_payout_destination(&ata).unwrap(),
+            PayoutDestination::AssociatedTokenAccount
+        );
+    }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use anchor_lang::prelude::*;
-    use solana_program_test::*;
-    use tokio;
+    #[test]
+    fn test_non_canonical_payout_account_rejected() {
+        let mut policy = payout_policy();
+        let other = Pubkey::new_unique();
+        assert_eq!(
+            policy.classify_payout_destination(&other).unwrap_err(),
+            InsuranceError::InvalidPayoutAccount.into()
+        );
+        // Another mint's ATA is not canonical either
+        let wrong_mint = get_associated_token_address(&policy.policy_holder, &Pubkey::new_unique());
+        assert!(policy.classify_payout_destination(&wrong_mint).is_err());
 
-    #[tokio::test]
-    async fn test_initialize_policy() {
-        // Test initialization of insurance policy
-        // This would contain comprehensive tests for all contract functions
+        policy.allow_custom_payout_account = true;
+        assert_eq!(policy.classify_payout_destination(&other).unwrap(), PayoutDestination::Custom);
     }
 
-    #[tokio::test]
-    async fn test_purchase_policy() {
-        // Test policy purchase flow
+    const WSOL_RENT: u64 = 2_039_280;
+    const LAMPORTS_PER_SOL: u64 = 1_000_000_000;
+
+    #[test]
+    fn test_purchase_with_sol_lamport_balances() {
+        let policy = InsurancePolicy {
+            premium_amount: LAMPORTS_PER_SOL / 2,
+            installment_count: 1,
+            ..Default::default()
+        };
+        let holder_before = 3 * LAMPORTS_PER_SOL;
+        let premium = policy.installment_amount(0);
+
+        // Create the temporary account, then wrap the premium into it
+        let mut holder = holder_before - WSOL_RENT - premium;
+        let mut wrapped = WSOL_RENT + premium;
+        // Fee and pool transfers move the wrapped lamports out with the tokens
+        wrapped -= premium;
+        // Close: the holder gets back exactly the rent deposit
+        let refund = wrapped_sol_rent_refund(wrapped, 0).unwrap();
+        holder += wrapped;
+
+        assert_eq!(refund, WSOL_RENT);
+        assert_eq!(holder, holder_before - premium);
     }
 
-    #[tokio::test]
-    async fn test_trigger_conditions() {
-        // Test trigger condition checking
+    #[test]
+    fn test_payout_unwrap_lamport_balances() {
+        let payout = 2 * LAMPORTS_PER_SOL;
+        let (holder_before, authority_before, policy_before) = (10_000, LAMPORTS_PER_SOL, 5_000_000);
+
+        // The authority funds the temporary account, which then receives the payout
+        let authority = authority_before - WSOL_RENT;
+        let closed = WSOL_RENT + payout;
+        // Closed into the policy account, then forwarded
+        let policy = policy_before + closed;
+        let refund = wrapped_sol_rent_refund(closed, payout).unwrap();
+
+        assert_eq!(policy - closed, policy_before);
+        let holder = holder_before + payout;
+        assert_eq!(holder - holder_before, payout);
+        assert_eq!(authority + refund, authority_before);
+
+        // A closed account can never hold less than what was unwrapped into it
+        assert_eq!(
+            wrapped_sol_rent_refund(payout - 1, payout).unwrap_err(),
+            InsuranceError::MathOverflow.into()
+        );
     }
 
-    #[tokio::test]
-    async fn test_execute_payout() {
-        // Test payout execution
+    #[test]
+    fn test_pegged_payout_cannot_unwrap() {
+        let plain = triggered_policy(1_000);
+        assert!(plain.has_plain_payout());
+
+        // A pegged payout is resized against the peg feed, which only
+        // `execute_payout` reads
+        let pegged = InsurancePolicy {
+            payout_peg_oracle_address: Some(Pubkey::new_unique()),
+            ..triggered_policy(1_000)
+        };
+        assert!(!pegged.has_plain_payout());
     }
 
-    #[tokio::test]
-    async fn test_cancel_policy() {
-        // Test policy cancellation
+    #[test]
+    fn test_single_beneficiary_fast_path() {
+        validate_beneficiaries(&[]).unwrap();
+        let policy = InsurancePolicy::default();
+        assert!(policy.split_payout(1_000).unwrap().is_empty());
+
+        let sole = InsurancePolicy {
+            beneficiaries: vec![beneficiary(10_000)],
+            ..Default::default()
+        };
+        assert_eq!(sole.split_payout(1_001).unwrap(), vec![1_001]);
+    }
+
+    #[test]
+    fn test_update_threshold_on_active_policy() {
+        let now = 1_000;
+        let mut policy = InsurancePolicy {
+            trigger_condition: TriggerConditionType::RelativeBelow,
+            trigger_threshold: 2_000,
+            expiry_timestamp: now + DAY,
+            ..Default::default()
+        };
+        policy.update_threshold(1_500, now).unwrap();
+        assert_eq!(policy.trigger_threshold, 1_500);
+
+        // The new threshold must still make sense for the condition
+        assert_eq!(
+            policy.update_threshold(20_000, now).unwrap_err(),
+            InsuranceError::InvalidTriggerConfiguration.into()
+        );
+        assert_eq!(policy.trigger_threshold, 1_500);
+    }
+
+    #[test]
+    fn test_update_threshold_rejected_after_purchase() {
+        let now = 1_000;
+        let mut policy = installment_policy(now);
+        policy.trigger_threshold = 100;
+        assert_eq!(
+            policy.update_threshold(200, now).unwrap_err(),
+            InsuranceError::PolicyNotActive.into()
+        );
+        assert_eq!(policy.trigger_threshold, 100);
+    }
+
+    #[test]
+    fn test_installment_plan_must_fit_term() {
+        let now = 1_700_000_000;
+        assert!(InsurancePolicy::validate_installment_plan(now, now + 12 * MONTH, 12, MONTH).is_ok());
+        assert!(InsurancePolicy::validate_installment_plan(now, now + 11 * MONTH, 12, MONTH).is_err());
+        assert!(InsurancePolicy::validate_installment_plan(now, now + MONTH, 0, 0).is_err());
+        assert!(InsurancePolicy::validate_installment_plan(now, now + MONTH, 2, 0).is_err());
     }
 }
\ No newline at end of file
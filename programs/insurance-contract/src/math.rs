@@ -0,0 +1,208 @@
+//! Monetary and price arithmetic
+//!
+//! Token amounts, basis points and oracle prices are combined here instead of
+//! inline, so the rounding and overflow behaviour of each calculation can be
+//! audited in one place. Every function checks its intermediates and fails
+//! with `MathOverflow`, `PriceOverflow` or `DivisionByZero` rather than
+//! wrapping or panicking.
+
+use anchor_lang::prelude::*;
+
+use crate::InsuranceError;
+
+/// Basis-point denominator (100%)
+pub const BPS_DENOMINATOR: u64 = 10_000;
+
+/// Direction to round fractional token amounts in basis-point math
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum RoundingMode {
+    Floor,
+    Ceil,
+    /// Round half up
+    Nearest,
+}
+
+/// `a * b / denominator` in `u128` with the given rounding
+pub fn mul_div(a: u128, b: u128, denominator: u128, rounding: RoundingMode) -> Result<u128> {
+    require!(denominator > 0, InsuranceError::DivisionByZero);
+    let product = a.checked_mul(b).ok_or(InsuranceError::MathOverflow)?;
+    let quotient = product / denominator;
+    let remainder = product % denominator;
+    let round_up = match rounding {
+        RoundingMode::Floor => false,
+        RoundingMode::Ceil => remainder > 0,
+        RoundingMode::Nearest => remainder >= denominator - denominator / 2,
+    };
+    Ok(quotient + round_up as u128)
+}
+
+/// `amount * numerator / denominator`, e.g. a premium scaled to a changed
+/// coverage or a slice of coverage by weight
+pub fn prorate(
+    amount: u64,
+    numerator: u64,
+    denominator: u64,
+    rounding: RoundingMode,
+) -> Result<u64> {
+    let result = mul_div(amount as u128, numerator as u128, denominator as u128, rounding)?;
+    u64::try_from(result).map_err(|_| error!(InsuranceError::MathOverflow))
+}
+
+/// Compute `amount * bps / 10_000` with the given rounding. Fees and payouts both
+/// use `Ceil`, so the fractional token goes to the pool on fees and to the holder
+/// on payouts; limits on pool exposure use `Floor`.
+pub fn apply_bps(amount: u64, bps: u64, rounding: RoundingMode) -> Result<u64> {
+    prorate(amount, bps, BPS_DENOMINATOR, rounding)
+}
+
+/// Weighted mean of `(value, weight)` components, truncated toward zero, e.g.
+/// a time-weighted price with each sample weighted by how long it stood
+pub fn weighted_index(components: impl IntoIterator<Item = (i64, u64)>) -> Result<i64> {
+    let mut sum = 0i128;
+    let mut total_weight = 0i128;
+    for (value, weight) in components {
+        let term = (value as i128)
+            .checked_mul(weight as i128)
+            .ok_or(InsuranceError::MathOverflow)?;
+        sum = sum.checked_add(term).ok_or(InsuranceError::MathOverflow)?;
+        total_weight = total_weight
+            .checked_add(weight as i128)
+            .ok_or(InsuranceError::MathOverflow)?;
+    }
+    require!(total_weight > 0, InsuranceError::DivisionByZero);
+    i64::try_from(sum / total_weight).map_err(|_| error!(InsuranceError::MathOverflow))
+}
+
+/// Re-express the price `mantissa * 10^from_expo` at `to_expo`. Moving to a
+/// coarser exponent truncates toward zero; moving to a finer one fails on
+/// overflow.
+pub fn normalize_price(mantissa: i64, from_expo: i32, to_expo: i32) -> Result<i64> {
+    let shift = from_expo - to_expo;
+    let factor = 10i128
+        .checked_pow(shift.unsigned_abs())
+        .ok_or(InsuranceError::PriceOverflow)?;
+    let scaled = if shift >= 0 {
+        (mantissa as i128).checked_mul(factor).ok_or(InsuranceError::PriceOverflow)?
+    } else {
+        mantissa as i128 / factor
+    };
+    i64::try_from(scaled).map_err(|_| error!(InsuranceError::PriceOverflow))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_mul_div_rounding() {
+        assert_eq!(mul_div(7, 3, 2, RoundingMode::Floor).unwrap(), 10);
+        assert_eq!(mul_div(7, 3, 2, RoundingMode::Ceil).unwrap(), 11);
+        assert_eq!(mul_div(7, 3, 2, RoundingMode::Nearest).unwrap(), 11);
+        assert_eq!(mul_div(7, 1, 3, RoundingMode::Nearest).unwrap(), 2);
+        assert_eq!(mul_div(8, 1, 3, RoundingMode::Nearest).unwrap(), 3);
+        // Exact quotients never round
+        for rounding in [RoundingMode::Floor, RoundingMode::Ceil, RoundingMode::Nearest] {
+            assert_eq!(mul_div(6, 5, 3, rounding).unwrap(), 10);
+            assert_eq!(mul_div(0, 5, 3, rounding).unwrap(), 0);
+        }
+    }
+
+    #[test]
+    fn test_mul_div_overflow_and_zero_division() {
+        assert_eq!(
+            mul_div(u128::MAX, 2, 1, RoundingMode::Floor).unwrap_err(),
+            InsuranceError::MathOverflow.into()
+        );
+        assert_eq!(
+            mul_div(1, 1, 0, RoundingMode::Ceil).unwrap_err(),
+            InsuranceError::DivisionByZero.into()
+        );
+        // Intermediates past u64 are fine as long as the product fits u128
+        let max = u64::MAX as u128;
+        assert_eq!(mul_div(max, max, max, RoundingMode::Ceil).unwrap(), max);
+        assert_eq!(mul_div(u128::MAX, 1, 1, RoundingMode::Ceil).unwrap(), u128::MAX);
+    }
+
+    #[test]
+    fn test_prorate() {
+        assert_eq!(prorate(1_000, 1, 3, RoundingMode::Floor).unwrap(), 333);
+        assert_eq!(prorate(1_000, 1, 3, RoundingMode::Ceil).unwrap(), 334);
+        assert_eq!(prorate(1_000, 3, 3, RoundingMode::Floor).unwrap(), 1_000);
+        assert_eq!(prorate(u64::MAX, u64::MAX, u64::MAX, RoundingMode::Floor).unwrap(), u64::MAX);
+        assert_eq!(prorate(u64::MAX, 0, 1, RoundingMode::Ceil).unwrap(), 0);
+
+        assert_eq!(
+            prorate(1_000, 1, 0, RoundingMode::Floor).unwrap_err(),
+            InsuranceError::DivisionByZero.into()
+        );
+        // The result must fit back into a token amount
+        assert_eq!(
+            prorate(u64::MAX, 2, 1, RoundingMode::Floor).unwrap_err(),
+            InsuranceError::MathOverflow.into()
+        );
+    }
+
+    #[test]
+    fn test_apply_bps_boundaries() {
+        assert_eq!(apply_bps(12_345, 0, RoundingMode::Ceil).unwrap(), 0);
+        assert_eq!(apply_bps(12_345, BPS_DENOMINATOR, RoundingMode::Floor).unwrap(), 12_345);
+        assert_eq!(apply_bps(1, 1, RoundingMode::Floor).unwrap(), 0);
+        assert_eq!(apply_bps(1, 1, RoundingMode::Ceil).unwrap(), 1);
+        assert_eq!(apply_bps(1, 5_000, RoundingMode::Nearest).unwrap(), 1);
+        assert_eq!(apply_bps(1, 4_999, RoundingMode::Nearest).unwrap(), 0);
+        assert_eq!(apply_bps(u64::MAX, BPS_DENOMINATOR, RoundingMode::Ceil).unwrap(), u64::MAX);
+        assert_eq!(
+            apply_bps(u64::MAX, BPS_DENOMINATOR + 1, RoundingMode::Floor).unwrap_err(),
+            InsuranceError::MathOverflow.into()
+        );
+    }
+
+    #[test]
+    fn test_weighted_index() {
+        assert_eq!(weighted_index([(100, 1), (200, 3)]).unwrap(), 175);
+        // Truncates toward zero, for negative values too
+        assert_eq!(weighted_index([(1, 1), (2, 1)]).unwrap(), 1);
+        assert_eq!(weighted_index([(-1, 1), (-2, 1)]).unwrap(), -1);
+        // Zero-weight components do not count
+        assert_eq!(weighted_index([(100, 1), (1_000_000, 0)]).unwrap(), 100);
+        assert_eq!(weighted_index([(i64::MAX, u64::MAX)]).unwrap(), i64::MAX);
+        assert_eq!(weighted_index([(i64::MIN, 1), (i64::MIN, 1)]).unwrap(), i64::MIN);
+
+        assert_eq!(
+            weighted_index(std::iter::empty()).unwrap_err(),
+            InsuranceError::DivisionByZero.into()
+        );
+        assert_eq!(
+            weighted_index([(100, 0)]).unwrap_err(),
+            InsuranceError::DivisionByZero.into()
+        );
+        let huge = [(i64::MAX, u64::MAX); 3];
+        assert_eq!(weighted_index(huge).unwrap_err(), InsuranceError::MathOverflow.into());
+    }
+
+    #[test]
+    fn test_normalize_price() {
+        // $21.50 from 5 decimals to 3 and back
+        assert_eq!(normalize_price(2_150_000, -5, -3).unwrap(), 21_500);
+        assert_eq!(normalize_price(21_500, -3, -5).unwrap(), 2_150_000);
+        // Coarser exponents truncate toward zero
+        assert_eq!(normalize_price(2_150_999, -5, -3).unwrap(), 21_509);
+        assert_eq!(normalize_price(-2_150_999, -5, -3).unwrap(), -21_509);
+        assert_eq!(normalize_price(i64::MAX, 0, 0).unwrap(), i64::MAX);
+        assert_eq!(normalize_price(i64::MAX, -38, 0).unwrap(), 0);
+
+        assert_eq!(
+            normalize_price(i64::MAX, 0, -1).unwrap_err(),
+            InsuranceError::PriceOverflow.into()
+        );
+        assert_eq!(
+            normalize_price(1, 0, -40).unwrap_err(),
+            InsuranceError::PriceOverflow.into()
+        );
+        // No i128 power of ten is that large
+        assert_eq!(
+            normalize_price(1, i32::MAX, 0).unwrap_err(),
+            InsuranceError::PriceOverflow.into()
+        );
+    }
+}
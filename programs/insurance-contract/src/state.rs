@@ -0,0 +1,193 @@
+//! Policy lifecycle state machine
+//!
+//! Every status change goes through [`transition`], so the legal edges are
+//! listed in one place instead of being implied by scattered checks.
+
+use anchor_lang::prelude::*;
+
+use crate::{InsuranceError, PolicyStatus};
+
+/// Something that happens to a policy and may move it to a new status
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum PolicyEvent {
+    /// Policy account written by one of the initializers
+    Initialize,
+    /// Premium (or first installment) paid
+    Purchase,
+    /// Condition met or declared, or a disputed trigger upheld
+    Trigger,
+    /// Payout completed: in one go, after a shortfall, or at the end of vesting
+    Payout,
+    /// Vault could only cover part of the payout
+    PartialPayout,
+    /// Payout started releasing on a vesting schedule
+    BeginVesting,
+    Cancel,
+    Expire,
+    Dispute,
+    /// Installment overdue beyond the grace period
+    Lapse,
+    /// Disputed trigger struck down; the claim is forfeited and coverage resumes
+    Forfeit,
+    /// Untriggered policy rolled into a fresh term at expiry
+    Renew,
+}
+
+/// How many recent transitions a policy keeps on-chain
+pub const TRANSITION_HISTORY_LEN: usize = 8;
+
+/// One applied transition. `actor` is the signer that caused it, the oracle
+/// feed for observation-driven triggers, or the default key when nobody
+/// signed (expiry).
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Default, PartialEq, Eq, Debug, InitSpace)]
+pub struct TransitionRecord {
+    pub from: PolicyStatus,
+    pub to: PolicyStatus,
+    pub timestamp: i64,
+    pub actor: Pubkey,
+}
+
+impl PolicyStatus {
+    /// No event moves a policy out of these
+    pub fn is_terminal(&self) -> bool {
+        matches!(
+            self,
+            PolicyStatus::PaidOut
+                | PolicyStatus::Cancelled
+                | PolicyStatus::Expired
+                | PolicyStatus::Lapsed
+        )
+    }
+}
+
+/// Status a policy in `current` moves to on `event`. Anything not listed is
+/// rejected; `PaidOut`, `Cancelled`, `Expired` and `Lapsed` are terminal.
+pub fn transition(current: PolicyStatus, event: PolicyEvent) -> Result<PolicyStatus> {
+    let next = match (&current, event) {
+        (PolicyStatus::Active, PolicyEvent::Initialize) => PolicyStatus::Active,
+        (PolicyStatus::Active, PolicyEvent::Purchase) => PolicyStatus::Purchased,
+        (PolicyStatus::Active, PolicyEvent::Expire) => PolicyStatus::Expired,
+
+        (PolicyStatus::Purchased, PolicyEvent::Trigger) => PolicyStatus::TriggeredPayout,
+        (PolicyStatus::Purchased, PolicyEvent::Cancel) => PolicyStatus::Cancelled,
+        (PolicyStatus::Purchased, PolicyEvent::Expire) => PolicyStatus::Expired,
+        (PolicyStatus::Purchased, PolicyEvent::Lapse) => PolicyStatus::Lapsed,
+        (PolicyStatus::Purchased, PolicyEvent::Renew) => PolicyStatus::Purchased,
+
+        (PolicyStatus::TriggeredPayout, PolicyEvent::Payout) => PolicyStatus::PaidOut,
+        (PolicyStatus::TriggeredPayout, PolicyEvent::PartialPayout) => PolicyStatus::PartiallyPaid,
+        (PolicyStatus::TriggeredPayout, PolicyEvent::BeginVesting) => PolicyStatus::Vesting,
+        (PolicyStatus::TriggeredPayout, PolicyEvent::Dispute) => PolicyStatus::Disputed,
+
+        (PolicyStatus::Disputed, PolicyEvent::Trigger) => PolicyStatus::TriggeredPayout,
+        (PolicyStatus::Disputed, PolicyEvent::Forfeit) => PolicyStatus::Purchased,
+
+        (PolicyStatus::PartiallyPaid, PolicyEvent::Payout) => PolicyStatus::PaidOut,
+        (PolicyStatus::Vesting, PolicyEvent::Payout) => PolicyStatus::PaidOut,
+
+        _ => {
+            msg!("Invalid policy state transition: {:?} on {:?}", current, event);
+            return err!(InsuranceError::InvalidStateTransition);
+        }
+    };
+    Ok(next)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ALL_STATUSES: [PolicyStatus; 10] = [
+        PolicyStatus::Active,
+        PolicyStatus::Purchased,
+        PolicyStatus::TriggeredPayout,
+        PolicyStatus::PaidOut,
+        PolicyStatus::Cancelled,
+        PolicyStatus::Expired,
+        PolicyStatus::Lapsed,
+        PolicyStatus::Disputed,
+        PolicyStatus::PartiallyPaid,
+        PolicyStatus::Vesting,
+    ];
+
+    const ALL_EVENTS: [PolicyEvent; 12] = [
+        PolicyEvent::Initialize,
+        PolicyEvent::Purchase,
+        PolicyEvent::Trigger,
+        PolicyEvent::Payout,
+        PolicyEvent::PartialPayout,
+        PolicyEvent::BeginVesting,
+        PolicyEvent::Cancel,
+        PolicyEvent::Expire,
+        PolicyEvent::Dispute,
+        PolicyEvent::Lapse,
+        PolicyEvent::Forfeit,
+        PolicyEvent::Renew,
+    ];
+
+    /// The full table of legal edges, written out independently of `transition`
+    const EDGES: [(PolicyStatus, PolicyEvent, PolicyStatus); 16] = [
+        (PolicyStatus::Active, PolicyEvent::Initialize, PolicyStatus::Active),
+        (PolicyStatus::Active, PolicyEvent::Purchase, PolicyStatus::Purchased),
+        (PolicyStatus::Active, PolicyEvent::Expire, PolicyStatus::Expired),
+        (PolicyStatus::Purchased, PolicyEvent::Trigger, PolicyStatus::TriggeredPayout),
+        (PolicyStatus::Purchased, PolicyEvent::Cancel, PolicyStatus::Cancelled),
+        (PolicyStatus::Purchased, PolicyEvent::Expire, PolicyStatus::Expired),
+        (PolicyStatus::Purchased, PolicyEvent::Lapse, PolicyStatus::Lapsed),
+        (PolicyStatus::Purchased, PolicyEvent::Renew, PolicyStatus::Purchased),
+        (PolicyStatus::TriggeredPayout, PolicyEvent::Payout, PolicyStatus::PaidOut),
+        (PolicyStatus::TriggeredPayout, PolicyEvent::PartialPayout, PolicyStatus::PartiallyPaid),
+        (PolicyStatus::TriggeredPayout, PolicyEvent::BeginVesting, PolicyStatus::Vesting),
+        (PolicyStatus::TriggeredPayout, PolicyEvent::Dispute, PolicyStatus::Disputed),
+        (PolicyStatus::Disputed, PolicyEvent::Trigger, PolicyStatus::TriggeredPayout),
+        (PolicyStatus::Disputed, PolicyEvent::Forfeit, PolicyStatus::Purchased),
+        (PolicyStatus::PartiallyPaid, PolicyEvent::Payout, PolicyStatus::PaidOut),
+        (PolicyStatus::Vesting, PolicyEvent::Payout, PolicyStatus::PaidOut),
+    ];
+
+    #[test]
+    fn test_transition_table_is_exhaustive() {
+        for status in ALL_STATUSES {
+            for event in ALL_EVENTS {
+                let expected = EDGES
+                    .iter()
+                    .find(|(from, on, _)| *from == status && *on == event)
+                    .map(|(_, _, to)| to.clone());
+                match expected {
+                    Some(to) => assert_eq!(transition(status.clone(), event).unwrap(), to),
+                    None => assert_eq!(
+                        transition(status.clone(), event).unwrap_err(),
+                        InsuranceError::InvalidStateTransition.into(),
+                        "{:?} on {:?} should be rejected",
+                        status,
+                        event
+                    ),
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_terminal_states_accept_no_events() {
+        for status in ALL_STATUSES {
+            let accepts_any = ALL_EVENTS
+                .iter()
+                .any(|event| transition(status.clone(), *event).is_ok());
+            assert_eq!(status.is_terminal(), !accepts_any, "{:?}", status);
+        }
+    }
+
+    #[test]
+    fn test_every_status_is_reachable() {
+        for status in ALL_STATUSES {
+            assert!(EDGES.iter().any(|(_, _, to)| *to == status), "{:?} is unreachable", status);
+        }
+    }
+
+    #[test]
+    fn test_dispute_round_trip() {
+        let disputed = transition(PolicyStatus::TriggeredPayout, PolicyEvent::Dispute).unwrap();
+        assert_eq!(transition(disputed.clone(), PolicyEvent::Trigger).unwrap(), PolicyStatus::TriggeredPayout);
+        assert_eq!(transition(disputed, PolicyEvent::Forfeit).unwrap(), PolicyStatus::Purchased);
+    }
+}